@@ -0,0 +1,26 @@
+use std::fmt;
+
+/// `CREATE SEQUENCE name [START [WITH] n] [INCREMENT [BY] n]`
+#[derive(PartialEq, Clone)]
+pub struct CreateSequenceQuery {
+    pub name: String,
+    pub start: i64,
+    pub increment: i64
+}
+
+impl CreateSequenceQuery {
+    pub fn new<I: Into<String>>(name: I, start: i64, increment: i64) -> CreateSequenceQuery {
+        CreateSequenceQuery {
+            name: name.into(),
+            start,
+            increment
+        }
+    }
+}
+
+impl fmt::Debug for CreateSequenceQuery {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "statement: 'create sequence', name: '{}', start: {}, increment: {}", self.name, self.start, self.increment)
+    }
+}
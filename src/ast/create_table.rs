@@ -1,41 +1,114 @@
 use std::fmt;
 
-use super::Type;
+use super::{Type, Expression};
 
 #[derive(PartialEq, Clone)]
-pub struct CreateTableQuery {
+pub struct CreateTableQuery<T: fmt::Debug> {
     pub table_name: String,
-    pub table_columns: Vec<ColumnTable>
+    pub table_columns: Vec<ColumnTable<T>>,
+    pub keys: Vec<TableKey>,
+    /// visible only to the session that created the table
+    pub temporary: bool
 }
 
-impl CreateTableQuery {
-    pub fn new<I: Into<String>>(table_name: I, columns: Vec<ColumnTable>) -> CreateTableQuery {
+impl <T: fmt::Debug> CreateTableQuery<T> {
+    pub fn new<I: Into<String>>(table_name: I, columns: Vec<ColumnTable<T>>) -> CreateTableQuery<T> {
         CreateTableQuery {
             table_name: table_name.into(),
-            table_columns: columns
+            table_columns: columns,
+            keys: vec![],
+            temporary: false
         }
     }
 }
 
-impl fmt::Debug for CreateTableQuery {
+impl <T: fmt::Debug + fmt::Display> fmt::Debug for CreateTableQuery<T> {
 
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "statement: 'create table', table name: '{}', columns: {:?}", self.table_name, self.table_columns)
+        let statement = if self.temporary { "create temporary table" } else { "create table" };
+        write!(f, "statement: '{}', table name: '{}', columns: {:?}", statement, self.table_name, self.table_columns)?;
+        if self.keys.is_empty() {
+            Ok(())
+        } else {
+            write!(f, ", keys: {:?}", self.keys)
+        }
     }
 }
 
+/// `PRIMARY KEY (columns)` or `UNIQUE (columns)` declared after the columns
+/// of a table.
 #[derive(PartialEq, Clone)]
-pub struct ColumnTable {
+pub struct TableKey {
+    pub columns: Vec<String>,
+    pub primary: bool
+}
+
+impl TableKey {
+    pub fn new(columns: Vec<String>, primary: bool) -> TableKey {
+        TableKey {
+            columns,
+            primary
+        }
+    }
+}
+
+impl fmt::Debug for TableKey {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let key = if self.primary { "primary key" } else { "unique" };
+        let columns = self.columns.iter().map(|c| format!("'{}'", c)).collect::<Vec<String>>();
+        write!(f, "<{}: [{}]>", key, columns.join(", "))
+    }
+}
+
+#[derive(PartialEq, Clone)]
+pub struct ColumnTable<T: fmt::Debug> {
     pub column_name: String,
     pub column_type: Type,
     pub is_primary_key: bool,
     pub foreign_key: Option<(String, String)>,
     pub nullable: bool,
-    pub default_value: Option<String>
+    /// evaluated for every inserted row that doesn't set the column
+    pub default_value: Option<Expression<T>>,
+    pub is_unique: bool,
+    pub identity: Option<IdentityGeneration>,
+    pub generated: Option<Generation<T>>
+}
+
+/// `GENERATED ALWAYS AS (expr) [STORED | VIRTUAL]`, stored values are
+/// computed when a row is written and virtual ones when it is read.
+#[derive(PartialEq, Clone)]
+pub struct Generation<T: fmt::Debug> {
+    pub expr: Expression<T>,
+    pub stored: bool
+}
+
+impl <T: fmt::Debug> Generation<T> {
+    pub fn new(expr: Expression<T>, stored: bool) -> Generation<T> {
+        Generation {
+            expr,
+            stored
+        }
+    }
 }
 
-impl ColumnTable {
-    pub fn new<I>(name: I, column_type: Type, is_primary_key: bool, foreign_key: Option<(String, String)>, nullable: bool, default_value: Option<I>) -> ColumnTable
+impl <T: fmt::Debug + fmt::Display> fmt::Debug for Generation<T> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.expr, if self.stored { "stored" } else { "virtual" })
+    }
+}
+
+/// `GENERATED ALWAYS | BY DEFAULT AS IDENTITY`, `SERIAL` columns are
+/// generated by default.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum IdentityGeneration {
+    Always,
+    ByDefault
+}
+
+impl <T: fmt::Debug> ColumnTable<T> {
+    pub fn new<I>(name: I, column_type: Type, is_primary_key: bool, foreign_key: Option<(String, String)>, nullable: bool, default_value: Option<Expression<T>>) -> ColumnTable<T>
         where I: Into<String> {
         ColumnTable {
             column_name: name.into(),
@@ -43,17 +116,20 @@ impl ColumnTable {
             is_primary_key: is_primary_key,
             foreign_key: foreign_key,
             nullable: nullable,
-            default_value: default_value.map(Into::into)
+            default_value: default_value,
+            is_unique: false,
+            identity: None,
+            generated: None
         }
     }
 }
 
-impl fmt::Debug for ColumnTable {
+impl <T: fmt::Debug + fmt::Display> fmt::Debug for ColumnTable<T> {
 
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let default = match self.default_value {
-            Some(ref v) => v.as_str(),
-            None => "NULL"
+            Some(ref v) => v.to_string(),
+            None => "NULL".into()
         };
         let primary = if self.is_primary_key { "Yes" } else { "No" };
         let nullable = if self.nullable { "Yes" } else { "No" };
@@ -61,6 +137,18 @@ impl fmt::Debug for ColumnTable {
             Some((ref table, ref column)) => format!("{}->{}", table.as_str(), column.as_str()),
             _ => "No".into()
         };
-        write!(f, "<name: '{}', type: '{:?}', primary key: {}, foreign key: {}, nullable: {}, default value: {}>", self.column_name, self.column_type, primary, foreign, nullable, default)
+        write!(f, "<name: '{}', type: '{:?}', primary key: {}, foreign key: {}, nullable: {}, default value: {}", self.column_name, self.column_type, primary, foreign, nullable, default)?;
+        if self.is_unique {
+            write!(f, ", unique: Yes")?;
+        }
+        match self.identity {
+            Some(IdentityGeneration::Always) => write!(f, ", identity: always")?,
+            Some(IdentityGeneration::ByDefault) => write!(f, ", identity: by default")?,
+            None => {}
+        }
+        if let Some(ref generated) = self.generated {
+            write!(f, ", generated: {:?}", generated)?;
+        }
+        write!(f, ">")
     }
 }
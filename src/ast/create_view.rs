@@ -0,0 +1,89 @@
+use std::fmt;
+
+use super::RawColumn;
+use super::select_query::SelectQuery;
+
+/// `CREATE [MATERIALIZED] VIEW name [(columns)] AS select`, the select is
+/// kept in the catalog as it was written and typed again by every query
+/// that reads the view.
+#[derive(PartialEq, Clone)]
+pub struct CreateViewQuery<T: fmt::Debug> {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub query: SelectQuery<T>,
+    pub definition: SelectQuery<RawColumn>,
+    pub materialized: bool
+}
+
+impl <T: fmt::Debug> CreateViewQuery<T> {
+    pub fn new<I: Into<String>>(name: I, columns: Vec<String>, query: SelectQuery<T>, definition: SelectQuery<RawColumn>, materialized: bool) -> CreateViewQuery<T> {
+        CreateViewQuery {
+            name: name.into(),
+            columns,
+            query,
+            definition,
+            materialized
+        }
+    }
+}
+
+impl <T: fmt::Debug + fmt::Display> fmt::Debug for CreateViewQuery<T> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let statement = if self.materialized { "create materialized view" } else { "create view" };
+        let columns = self.columns.iter().map(|c| format!("'{}'", c)).collect::<Vec<String>>();
+        write!(f, "statement: '{}', name: '{}', columns: [{}], query: <{:?}>", statement, self.name, columns.join(", "), self.query)
+    }
+}
+
+/// `DROP [MATERIALIZED] VIEW name`
+#[derive(PartialEq, Clone)]
+pub struct DropViewQuery {
+    pub name: String,
+    pub materialized: bool
+}
+
+impl DropViewQuery {
+    pub fn new<I: Into<String>>(name: I, materialized: bool) -> DropViewQuery {
+        DropViewQuery {
+            name: name.into(),
+            materialized
+        }
+    }
+}
+
+impl fmt::Debug for DropViewQuery {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let statement = if self.materialized { "drop materialized view" } else { "drop view" };
+        write!(f, "statement: '{}', name: '{}'", statement, self.name)
+    }
+}
+
+/// `REFRESH MATERIALIZED VIEW name`, the query that recomputes the view is
+/// taken from the catalog when the statement is typed.
+#[derive(PartialEq, Clone)]
+pub struct RefreshViewQuery<T: fmt::Debug> {
+    pub name: String,
+    pub query: Option<SelectQuery<T>>
+}
+
+impl <T: fmt::Debug> RefreshViewQuery<T> {
+    pub fn new<I: Into<String>>(name: I) -> RefreshViewQuery<T> {
+        RefreshViewQuery {
+            name: name.into(),
+            query: None
+        }
+    }
+}
+
+impl <T: fmt::Debug + fmt::Display> fmt::Debug for RefreshViewQuery<T> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "statement: 'refresh materialized view', name: '{}'", self.name)?;
+        match self.query {
+            Some(ref query) => write!(f, ", query: <{:?}>", query),
+            None => Ok(())
+        }
+    }
+}
@@ -1,25 +1,29 @@
 use std::fmt;
 
-use super::{Condition, debug_predicates};
+use super::{Expression, debug_predicates, debug_returning};
+use super::select_query::Projection;
 
 #[derive(PartialEq, Clone)]
-pub struct DeleteQuery {
+pub struct DeleteQuery<T: fmt::Debug> {
     pub from: String,
-    pub predicates: Option<Condition>
+    pub predicates: Option<Expression<T>>,
+    pub returning: Vec<Projection<T>>
 }
 
-impl DeleteQuery {
-    pub fn new<I: Into<String>>(table: I, condition: Option<Condition>) -> DeleteQuery {
+impl <T: fmt::Debug> DeleteQuery<T> {
+    pub fn new<I: Into<String>>(table: I, condition: Option<Expression<T>>) -> DeleteQuery<T> {
         DeleteQuery {
             from: table.into(),
-            predicates: condition
+            predicates: condition,
+            returning: vec![]
         }
     }
 }
 
-impl fmt::Debug for DeleteQuery {
+impl <T: fmt::Debug + fmt::Display> fmt::Debug for DeleteQuery<T> {
 
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "statement: 'delete', table name: '{}', where: {}", self.from,  debug_predicates(&self.predicates))
+        write!(f, "statement: 'delete', table name: '{}', where: {}", self.from,  debug_predicates(&self.predicates))?;
+        debug_returning(f, &self.returning)
     }
 }
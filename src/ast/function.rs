@@ -0,0 +1,149 @@
+use std::fmt;
+
+use super::{Expression, Type};
+
+/// Built-in scalar functions.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Function {
+    Length,
+    Upper,
+    Lower,
+    /// `SUBSTRING(string FROM start [FOR count])`
+    Substring,
+    /// `TRIM([LEADING | TRAILING | BOTH] [characters] FROM string)`
+    Trim(TrimSide),
+    Replace,
+    /// `POSITION(substring IN string)`
+    Position,
+    /// `NEXTVAL('sequence')`, advances the sequence
+    NextVal,
+    /// `CURRVAL('sequence')`, value last returned by `NEXTVAL` of the sequence
+    CurrVal,
+    /// `CURRENT_TIMESTAMP`, start time of the statement as UTC text
+    CurrentTimestamp
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TrimSide {
+    Leading,
+    Trailing,
+    Both
+}
+
+impl Function {
+
+    pub fn from_name(name: &str) -> Option<Function> {
+        match name {
+            "length" | "char_length" => Some(Function::Length),
+            "upper" => Some(Function::Upper),
+            "lower" => Some(Function::Lower),
+            "substring" => Some(Function::Substring),
+            "trim" => Some(Function::Trim(TrimSide::Both)),
+            "replace" => Some(Function::Replace),
+            "position" => Some(Function::Position),
+            "nextval" => Some(Function::NextVal),
+            "currval" => Some(Function::CurrVal),
+            _ => None
+        }
+    }
+
+    /// Result type when called with arguments of `arg_types`, `None` if
+    /// there is no such signature.
+    pub fn result_type(&self, arg_types: &[Type]) -> Option<Type> {
+        let text = Type::Character(None);
+        match (*self, arg_types) {
+            (Function::Length, [Type::Character(_)]) => Some(Type::Integer),
+            (Function::Upper, [Type::Character(_)]) | (Function::Lower, [Type::Character(_)]) => Some(text),
+            (Function::Substring, [Type::Character(_), Type::Integer]) => Some(text),
+            (Function::Substring, [Type::Character(_), Type::Integer, Type::Integer]) => Some(text),
+            (Function::Trim(_), [Type::Character(_)]) | (Function::Trim(_), [Type::Character(_), Type::Character(_)]) => Some(text),
+            (Function::Replace, [Type::Character(_), Type::Character(_), Type::Character(_)]) => Some(text),
+            (Function::Position, [Type::Character(_), Type::Character(_)]) => Some(Type::Integer),
+            (Function::NextVal, [Type::Character(_)]) | (Function::CurrVal, [Type::Character(_)]) => Some(Type::Integer),
+            (Function::CurrentTimestamp, []) => Some(text),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for Function {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Function::Length => write!(f, "length"),
+            Function::Upper => write!(f, "upper"),
+            Function::Lower => write!(f, "lower"),
+            Function::Substring => write!(f, "substring"),
+            Function::Trim(TrimSide::Leading) => write!(f, "ltrim"),
+            Function::Trim(TrimSide::Trailing) => write!(f, "rtrim"),
+            Function::Trim(TrimSide::Both) => write!(f, "trim"),
+            Function::Replace => write!(f, "replace"),
+            Function::Position => write!(f, "position"),
+            Function::NextVal => write!(f, "nextval"),
+            Function::CurrVal => write!(f, "currval"),
+            Function::CurrentTimestamp => write!(f, "current_timestamp")
+        }
+    }
+}
+
+/// `expr [NOT] LIKE | ILIKE pattern [ESCAPE escape]`, negation is kept as
+/// a separate `NOT` node.
+#[derive(PartialEq, Clone)]
+pub struct PatternMatch<T: fmt::Debug> {
+    pub expr: Expression<T>,
+    pub pattern: Expression<T>,
+    pub escape: Option<Expression<T>>,
+    pub case_insensitive: bool
+}
+
+impl <T: fmt::Debug> PatternMatch<T> {
+    pub fn new(expr: Expression<T>, pattern: Expression<T>, escape: Option<Expression<T>>, case_insensitive: bool) -> PatternMatch<T> {
+        PatternMatch {
+            expr,
+            pattern,
+            escape,
+            case_insensitive
+        }
+    }
+}
+
+impl <T: fmt::Debug + fmt::Display> fmt::Display for PatternMatch<T> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let operator = if self.case_insensitive { "ilike" } else { "like" };
+        write!(f, "{} {} {}", self.expr, operator, self.pattern)?;
+        match self.escape {
+            Some(ref escape) => write!(f, " escape {}", escape),
+            None => Ok(())
+        }
+    }
+}
+
+/// Call of a function registered by the application, the typer fills in
+/// its result type and whether it aggregates rows.
+#[derive(PartialEq, Clone)]
+pub struct UserCall<T: fmt::Debug> {
+    pub name: String,
+    pub args: Vec<Expression<T>>,
+    pub return_type: Option<Type>,
+    pub aggregate: bool
+}
+
+impl <T: fmt::Debug> UserCall<T> {
+    pub fn new<I: Into<String>>(name: I, args: Vec<Expression<T>>) -> UserCall<T> {
+        UserCall {
+            name: name.into(),
+            args,
+            return_type: None,
+            aggregate: false
+        }
+    }
+}
+
+impl <T: fmt::Debug + fmt::Display> fmt::Display for UserCall<T> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let args = self.args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>();
+        write!(f, "{}({})", self.name, args.join(", "))
+    }
+}
@@ -1,13 +1,15 @@
 use std::fmt;
 
-use super::select_query::SelectQuery;
-use super::Type;
+use super::select_query::{SelectQuery, Projection};
+use super::{Expression, Type, debug_predicates, debug_returning};
 
 #[derive(PartialEq, Clone)]
 pub struct InsertQuery<T: fmt::Debug> {
     pub table_name: String,
     pub columns: Vec<T>,
-    pub values: ValueSource<T>
+    pub values: ValueSource<T>,
+    pub on_conflict: Option<OnConflict<T>>,
+    pub returning: Vec<Projection<T>>
 }
 
 impl <T: fmt::Debug> InsertQuery<T> {
@@ -15,29 +17,76 @@ impl <T: fmt::Debug> InsertQuery<T> {
         InsertQuery {
             table_name: table_name.into(),
             columns: columns,
-            values: values
+            values: values,
+            on_conflict: None,
+            returning: vec![]
         }
     }
 }
 
-impl <T: fmt::Debug> fmt::Debug for InsertQuery<T> {
+impl <T: fmt::Debug + fmt::Display> fmt::Debug for InsertQuery<T> {
 
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "statement: 'insert', table name: '{}', columns: {:?}, values: {:?}", self.table_name, self.columns, self.values)
+        write!(f, "statement: 'insert', table name: '{}', columns: {:?}, values: {:?}", self.table_name, self.columns, self.values)?;
+        if let Some(ref on_conflict) = self.on_conflict {
+            write!(f, ", on conflict: {:?}", on_conflict)?;
+        }
+        debug_returning(f, &self.returning)
     }
 }
 
+/// `ON CONFLICT [(columns)] DO NOTHING | DO UPDATE SET ... [WHERE ...]`,
+/// the update sees the row proposed for insertion as `excluded`.
+#[derive(PartialEq, Clone)]
+pub struct OnConflict<T: fmt::Debug> {
+    pub target: Vec<String>,
+    pub action: ConflictAction<T>
+}
+
+impl <T: fmt::Debug> OnConflict<T> {
+    pub fn new(target: Vec<String>, action: ConflictAction<T>) -> OnConflict<T> {
+        OnConflict {
+            target,
+            action
+        }
+    }
+}
+
+impl <T: fmt::Debug + fmt::Display> fmt::Debug for OnConflict<T> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let target = self.target.iter().map(|c| format!("'{}'", c)).collect::<Vec<String>>();
+        write!(f, "<target: [{}], ", target.join(", "))?;
+        match self.action {
+            ConflictAction::Nothing => write!(f, "do nothing>"),
+            ConflictAction::Update(ref assignments, ref predicates) => {
+                let assignments = assignments.iter().map(|(column, value)| format!("{:?} = {}", column, value)).collect::<Vec<String>>();
+                write!(f, "do update set: [{}], where: {}>", assignments.join(", "), debug_predicates(predicates))
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Clone)]
+pub enum ConflictAction<T: fmt::Debug> {
+    Nothing,
+    Update(Vec<(T, Expression<T>)>, Option<Expression<T>>)
+}
+
 #[derive(PartialEq, Clone)]
 pub enum ValueSource<T: fmt::Debug> {
-    Row(Vec<Value>),
+    Rows(Vec<Vec<Value>>),
     SubQuery(SelectQuery<T>)
 }
 
-impl <T: fmt::Debug> fmt::Debug for ValueSource<T> {
+impl <T: fmt::Debug + fmt::Display> fmt::Debug for ValueSource<T> {
 
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            ValueSource::Row(ref values) => write!(f, "{:?}", values),
+            ValueSource::Rows(ref rows) => {
+                let rows = rows.iter().map(|row| format!("{:?}", row)).collect::<Vec<String>>();
+                write!(f, "{}", rows.join(", "))
+            },
             ValueSource::SubQuery(ref subquery) => write!(f, "<sub{:?}>", subquery)
         }
     }
@@ -1,29 +1,53 @@
+pub mod create_sequence;
 pub mod create_table;
+pub mod create_view;
 pub mod delete_query;
+pub mod function;
 pub mod insert_query;
 pub mod select_query;
+pub mod transaction;
+pub mod update_query;
+pub mod window;
 
 use std::fmt;
 
+use self::create_sequence::CreateSequenceQuery;
 use self::create_table::CreateTableQuery;
+use self::create_view::{CreateViewQuery, DropViewQuery, RefreshViewQuery};
 use self::delete_query::DeleteQuery;
-use self::insert_query::InsertQuery;
-use self::select_query::SelectQuery;
+use self::function::{Function, PatternMatch, UserCall};
+use self::insert_query::{InsertQuery, Value};
+use self::select_query::{SelectQuery, Projection};
+use self::transaction::TransactionStatement;
+use self::update_query::UpdateQuery;
+use self::window::WindowCall;
 
 #[derive(Debug, PartialEq)]
 pub enum ValidatedStatement {
-    Create(CreateTableQuery),
+    Create(CreateTableQuery<TypedColumn>),
+    CreateSequence(CreateSequenceQuery),
+    CreateView(CreateViewQuery<TypedColumn>),
+    DropView(DropViewQuery),
+    RefreshView(RefreshViewQuery<TypedColumn>),
     Insert(InsertQuery<TypedColumn>),
     Select(SelectQuery<TypedColumn>),
-    Delete
+    Delete(DeleteQuery<TypedColumn>),
+    Update(UpdateQuery<TypedColumn>),
+    Transaction(TransactionStatement)
 }
 
 #[derive(PartialEq)]
 pub enum TypedStatement {
-    Create(CreateTableQuery),
+    Create(CreateTableQuery<TypedColumn>),
+    CreateSequence(CreateSequenceQuery),
+    CreateView(CreateViewQuery<TypedColumn>),
+    DropView(DropViewQuery),
+    RefreshView(RefreshViewQuery<TypedColumn>),
     Insert(InsertQuery<TypedColumn>),
     Select(SelectQuery<TypedColumn>),
-    Delelte
+    Delete(DeleteQuery<TypedColumn>),
+    Update(UpdateQuery<TypedColumn>),
+    Transaction(TransactionStatement)
 }
 
 impl fmt::Debug for TypedStatement {
@@ -31,9 +55,15 @@ impl fmt::Debug for TypedStatement {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             TypedStatement::Create(ref create_table_query) => write!(f, "{:?}", create_table_query),
+            TypedStatement::CreateSequence(ref create_sequence_query) => write!(f, "{:?}", create_sequence_query),
+            TypedStatement::CreateView(ref create_view_query) => write!(f, "{:?}", create_view_query),
+            TypedStatement::DropView(ref drop_view_query) => write!(f, "{:?}", drop_view_query),
+            TypedStatement::RefreshView(ref refresh_view_query) => write!(f, "{:?}", refresh_view_query),
             TypedStatement::Insert(ref insert_query) => write!(f, "{:?}", insert_query),
             TypedStatement::Select(ref select_query) => write!(f, "{:?}", select_query),
-            _ => panic!("unimplemented debug formatting")
+            TypedStatement::Delete(ref delete_query) => write!(f, "{:?}", delete_query),
+            TypedStatement::Update(ref update_query) => write!(f, "{:?}", update_query),
+            TypedStatement::Transaction(ref statement) => write!(f, "{:?}", statement)
         }
     }
 }
@@ -61,12 +91,25 @@ impl fmt::Debug for TypedColumn {
     }
 }
 
+impl fmt::Display for TypedColumn {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
 #[derive(PartialEq, Clone)]
 pub enum RawStatement {
-    Create(CreateTableQuery),
-    Delete(DeleteQuery),
+    Create(CreateTableQuery<RawColumn>),
+    CreateSequence(CreateSequenceQuery),
+    CreateView(CreateViewQuery<RawColumn>),
+    DropView(DropViewQuery),
+    RefreshView(RefreshViewQuery<RawColumn>),
+    Delete(DeleteQuery<RawColumn>),
     Insert(InsertQuery<RawColumn>),
-    Select(SelectQuery<RawColumn>)
+    Select(SelectQuery<RawColumn>),
+    Update(UpdateQuery<RawColumn>),
+    Transaction(TransactionStatement)
 }
 
 impl fmt::Debug for RawStatement {
@@ -74,9 +117,15 @@ impl fmt::Debug for RawStatement {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             RawStatement::Create(ref query) => write!(f, "{:?}", query),
+            RawStatement::CreateSequence(ref query) => write!(f, "{:?}", query),
+            RawStatement::CreateView(ref query) => write!(f, "{:?}", query),
+            RawStatement::DropView(ref query) => write!(f, "{:?}", query),
+            RawStatement::RefreshView(ref query) => write!(f, "{:?}", query),
             RawStatement::Delete(ref query) => write!(f, "{:?}", query),
             RawStatement::Insert(ref query) => write!(f, "{:?}", query),
             RawStatement::Select(ref query) => write!(f, "{:?}", query),
+            RawStatement::Update(ref query) => write!(f, "{:?}", query),
+            RawStatement::Transaction(ref statement) => write!(f, "{:?}", statement),
         }
     }
 }
@@ -102,10 +151,46 @@ impl fmt::Debug for RawColumn {
     }
 }
 
+impl fmt::Display for RawColumn {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
 #[derive(PartialEq, Clone, Copy, Hash, Eq)]
 pub enum Type {
     Integer,
     Character(Option<u8>),
+    Boolean,
+    /// type of `NULL` literal, it takes the type of values it is used with
+    Unknown
+}
+
+impl Type {
+
+    /// Common type of two values, `CHARACTER`s of different size are widened
+    /// to the longer one.
+    pub fn unify(self, other: Type) -> Option<Type> {
+        match (self, other) {
+            (Type::Integer, Type::Integer) => Some(Type::Integer),
+            (Type::Boolean, Type::Boolean) => Some(Type::Boolean),
+            (Type::Character(Some(l)), Type::Character(Some(r))) => Some(Type::Character(Some(l.max(r)))),
+            (Type::Character(_), Type::Character(_)) => Some(Type::Character(None)),
+            (Type::Unknown, other) | (other, Type::Unknown) => Some(other),
+            _ => None
+        }
+    }
+
+    /// Values of `source` type can be stored in a column of this type,
+    /// character columns take text of any size and check it when it is
+    /// written.
+    pub fn accepts(self, source: Type) -> bool {
+        match (self, source) {
+            (Type::Character(_), Type::Character(_)) | (_, Type::Unknown) => true,
+            (target, source) => target == source
+        }
+    }
 }
 
 impl fmt::Debug for Type {
@@ -114,80 +199,272 @@ impl fmt::Debug for Type {
         match *self {
             Type::Integer => write!(f, "integer"),
             Type::Character(Some(v)) => write!(f, "character[{}]", v),
-            Type::Character(None) => write!(f, "character")
+            Type::Character(None) => write!(f, "character"),
+            Type::Boolean => write!(f, "boolean"),
+            Type::Unknown => write!(f, "unknown")
         }
     }
 }
 
-pub fn debug_predicates(predicates: &Option<Condition>) -> String {
-    match *predicates {
-        Some(ref cond) => cond.to_string(),
-        None => "no predicate".into()
+#[derive(PartialEq, Clone)]
+pub enum Expression<T: fmt::Debug> {
+    Column(T),
+    Const(Value),
+    /// pseudo column of `WHERE LIMIT = n` predicate
+    Limit,
+    Binary(Box<Expression<T>>, Operator, Box<Expression<T>>),
+    Not(Box<Expression<T>>),
+    SubQuery(Box<SelectQuery<T>>),
+    Exists(Box<SelectQuery<T>>),
+    In(Box<Expression<T>>, Box<SelectQuery<T>>),
+    Window(Box<WindowCall<T>>),
+    Case(Box<CaseExpression<T>>),
+    Coalesce(Vec<Expression<T>>),
+    NullIf(Box<Expression<T>>, Box<Expression<T>>),
+    Cast(Box<Expression<T>>, Type),
+    Function(Function, Vec<Expression<T>>),
+    Like(Box<PatternMatch<T>>),
+    UserCall(Box<UserCall<T>>)
+}
+
+impl <T: fmt::Debug> Expression<T> {
+
+    pub fn binary(left: Expression<T>, operator: Operator, right: Expression<T>) -> Expression<T> {
+        Expression::Binary(Box::new(left), operator, Box::new(right))
+    }
+
+    pub fn references(&self, table_name: &str) -> bool {
+        match *self {
+            Expression::SubQuery(ref query) | Expression::Exists(ref query) => query.references(table_name),
+            Expression::In(ref expr, ref query) => expr.references(table_name) || query.references(table_name),
+            _ => self.operands().into_iter().any(|e| e.references(table_name))
+        }
+    }
+
+    /// Nested expressions evaluated in the scope of this one, i.e. everything
+    /// except sub queries.
+    pub fn operands(&self) -> Vec<&Expression<T>> {
+        match *self {
+            Expression::Column(_) | Expression::Const(_) | Expression::Limit
+                | Expression::SubQuery(_) | Expression::Exists(_) => vec![],
+            Expression::Binary(ref left, _, ref right) | Expression::NullIf(ref left, ref right) => vec![left, right],
+            Expression::Not(ref expr) | Expression::In(ref expr, _) | Expression::Cast(ref expr, _) => vec![expr],
+            Expression::Window(ref call) => call.args.iter()
+                .chain(call.partition_by.iter())
+                .chain(call.order_by.iter().map(|t| &t.key))
+                .collect(),
+            Expression::Case(ref case) => case.operand.iter()
+                .chain(case.branches.iter().flat_map(|(condition, result)| vec![condition, result]))
+                .chain(case.else_result.iter())
+                .collect(),
+            Expression::Coalesce(ref args) | Expression::Function(_, ref args) => args.iter().collect(),
+            Expression::Like(ref like) => vec![&like.expr, &like.pattern].into_iter().chain(like.escape.iter()).collect(),
+            Expression::UserCall(ref call) => call.args.iter().collect()
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Expression<T>> {
+        match *self {
+            Expression::Column(_) | Expression::Const(_) | Expression::Limit
+                | Expression::SubQuery(_) | Expression::Exists(_) => vec![],
+            Expression::Binary(ref mut left, _, ref mut right) | Expression::NullIf(ref mut left, ref mut right) => vec![&mut **left, &mut **right],
+            Expression::Not(ref mut expr) | Expression::In(ref mut expr, _) | Expression::Cast(ref mut expr, _) => vec![&mut **expr],
+            Expression::Window(ref mut call) => {
+                let call = &mut **call;
+                call.args.iter_mut()
+                    .chain(call.partition_by.iter_mut())
+                    .chain(call.order_by.iter_mut().map(|t| &mut t.key))
+                    .collect()
+            },
+            Expression::Case(ref mut case) => {
+                let case = &mut **case;
+                case.operand.iter_mut()
+                    .chain(case.branches.iter_mut().flat_map(|(condition, result)| vec![condition, result]))
+                    .chain(case.else_result.iter_mut())
+                    .collect()
+            },
+            Expression::Coalesce(ref mut args) | Expression::Function(_, ref mut args) => args.iter_mut().collect(),
+            Expression::Like(ref mut like) => {
+                let like = &mut **like;
+                vec![&mut like.expr, &mut like.pattern].into_iter().chain(like.escape.iter_mut()).collect()
+            },
+            Expression::UserCall(ref mut call) => call.args.iter_mut().collect()
+        }
+    }
+
+    /// Sub queries of the expression, not including the ones nested in them.
+    pub fn sub_queries_mut(&mut self) -> Vec<&mut SelectQuery<T>> {
+        match *self {
+            Expression::SubQuery(ref mut query) | Expression::Exists(ref mut query) => vec![&mut **query],
+            Expression::In(ref mut expr, ref mut query) => {
+                let mut queries = expr.sub_queries_mut();
+                queries.push(&mut **query);
+                queries
+            },
+            _ => self.operands_mut().into_iter().flat_map(|e| e.sub_queries_mut()).collect()
+        }
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct Condition {
-    pub left: CondArg,
-    pub right: CondArg,
-    pub cond_type: CondType
+impl Expression<TypedColumn> {
+
+    pub fn expr_type(&self) -> Type {
+        match *self {
+            Expression::Column(ref column) => column.col_type,
+            Expression::Const(ref value) => value.val_type,
+            Expression::Limit => Type::Integer,
+            Expression::SubQuery(ref query) => query.columns.first().map_or(Type::Integer, |p| p.expr.expr_type()),
+            Expression::Window(ref call) => call.result_type(),
+            Expression::Case(ref case) => case.branches.iter()
+                .map(|(_, result)| result)
+                .chain(case.else_result.iter())
+                .fold(None, |acc: Option<Type>, result| Some(acc.map_or(result.expr_type(), |t| t.unify(result.expr_type()).unwrap_or(t))))
+                .unwrap_or(Type::Integer),
+            Expression::Coalesce(ref args) => args.iter()
+                .fold(None, |acc: Option<Type>, arg| Some(acc.map_or(arg.expr_type(), |t| t.unify(arg.expr_type()).unwrap_or(t))))
+                .unwrap_or(Type::Integer),
+            Expression::NullIf(ref expr, _) => expr.expr_type(),
+            Expression::Cast(_, target) => target,
+            Expression::Binary(_, Operator::Concat, _) => Type::Character(None),
+            Expression::UserCall(ref call) => call.return_type.unwrap_or(Type::Character(None)),
+            Expression::Function(ref function, ref args) => {
+                let arg_types = args.iter().map(|arg| arg.expr_type()).collect::<Vec<Type>>();
+                function.result_type(&arg_types).unwrap_or(Type::Character(None))
+            },
+            Expression::Binary(..) | Expression::Not(_) | Expression::Exists(_) | Expression::In(..) | Expression::Like(_) => Type::Boolean
+        }
+    }
 }
 
-impl Condition {
-    pub fn new(left: CondArg, right: CondArg, cond_type: CondType) -> Condition {
-        Condition {
-            left: left,
-            right: right,
-            cond_type: cond_type
+impl <T: fmt::Debug + fmt::Display> fmt::Display for Expression<T> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Expression::Column(ref column) => write!(f, "{}", column),
+            Expression::Const(Value { ref val, val_type: Type::Character(_) }) => write!(f, "'{}'", val),
+            Expression::Const(ref value) => write!(f, "{}", value.val),
+            Expression::Limit => write!(f, "limit"),
+            Expression::Binary(ref left, ref operator, ref right) => write!(f, "{} {} {}", left, operator, right),
+            Expression::Not(ref expr) => write!(f, "not {}", expr),
+            Expression::SubQuery(ref query) => write!(f, "<sub{:?}>", query),
+            Expression::Exists(ref query) => write!(f, "exists <sub{:?}>", query),
+            Expression::In(ref expr, ref query) => write!(f, "{} in <sub{:?}>", expr, query),
+            Expression::Window(ref call) => write!(f, "{}", call),
+            Expression::Case(ref case) => write!(f, "{}", case),
+            Expression::Coalesce(ref args) => {
+                let args = args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>();
+                write!(f, "coalesce({})", args.join(", "))
+            },
+            Expression::NullIf(ref left, ref right) => write!(f, "nullif({}, {})", left, right),
+            Expression::Cast(ref expr, target) => write!(f, "cast({} as {:?})", expr, target),
+            Expression::Function(Function::CurrentTimestamp, _) => write!(f, "current_timestamp"),
+            Expression::Function(ref function, ref args) => {
+                let args = args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>();
+                write!(f, "{}({})", function, args.join(", "))
+            },
+            Expression::Like(ref like) => write!(f, "{}", like),
+            Expression::UserCall(ref call) => write!(f, "{}", call)
         }
     }
+}
 
-    pub fn equals(left: CondArg, right: CondArg) -> Condition {
-        Condition::new(left, right, CondType::Eq)
+impl <T: fmt::Debug + fmt::Display> fmt::Debug for Expression<T> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Expression::Column(ref column) => write!(f, "{:?}", column),
+            Expression::Const(ref value) => write!(f, "{:?}", value),
+            _ => write!(f, "<expression: {}>", self)
+        }
     }
+}
 
-    pub fn not_equals(left: CondArg, right: CondArg) -> Condition {
-        Condition::new(left, right, CondType::NotEq)
+/// Searched `CASE WHEN condition THEN result ... END` or, with an operand,
+/// simple `CASE operand WHEN value THEN result ... END`.
+#[derive(PartialEq, Clone)]
+pub struct CaseExpression<T: fmt::Debug> {
+    pub operand: Option<Expression<T>>,
+    pub branches: Vec<(Expression<T>, Expression<T>)>,
+    pub else_result: Option<Expression<T>>
+}
+
+impl <T: fmt::Debug> CaseExpression<T> {
+    pub fn new(operand: Option<Expression<T>>, branches: Vec<(Expression<T>, Expression<T>)>, else_result: Option<Expression<T>>) -> CaseExpression<T> {
+        CaseExpression {
+            operand,
+            branches,
+            else_result
+        }
     }
 }
 
-impl ToString for Condition {
+impl <T: fmt::Debug + fmt::Display> fmt::Display for CaseExpression<T> {
 
-    fn to_string(&self) -> String {
-        match (&self.left, &self.cond_type, &self.right) {
-            (&CondArg::ColumnName(ref name), &CondType::Eq, &CondArg::NumConst(ref c)) => format!("predicate <{} equals to {}>", name, c),
-            (&CondArg::StringConstant(ref c), &CondType::Eq, &CondArg::ColumnName(ref name)) => format!("predicate <'{}' equals to {}>", c, name),
-            (&CondArg::Limit, &CondType::Eq, &CondArg::NumConst(ref c)) => format!("predicate <limit equals to {}>", c),
-            (&CondArg::ColumnName(ref name), &CondType::NotEq, &CondArg::StringConstant(ref c)) => format!("predicate <{} not equals to '{}'>", name, c),
-            _ => "unimlemented condition formatting".into()
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "case")?;
+        if let Some(ref operand) = self.operand {
+            write!(f, " {}", operand)?;
+        }
+        for (condition, result) in self.branches.iter() {
+            write!(f, " when {} then {}", condition, result)?;
         }
+        if let Some(ref else_result) = self.else_result {
+            write!(f, " else {}", else_result)?;
+        }
+        write!(f, " end")
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
-pub enum CondType {
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Operator {
     Eq,
-    NotEq
+    NotEq,
+    Less,
+    LessEq,
+    Greater,
+    GreaterEq,
+    And,
+    Or,
+    Concat
 }
 
-#[derive(Debug, PartialEq, Clone)]
-pub enum CondArg {
-    ColumnName(String),
-    StringConstant(String),
-    NumConst(String),
-    Limit
+impl fmt::Display for Operator {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Operator::Eq => write!(f, "equals to"),
+            Operator::NotEq => write!(f, "not equals to"),
+            Operator::Less => write!(f, "less than"),
+            Operator::LessEq => write!(f, "less than or equal to"),
+            Operator::Greater => write!(f, "greater than"),
+            Operator::GreaterEq => write!(f, "greater than or equal to"),
+            Operator::And => write!(f, "and"),
+            Operator::Or => write!(f, "or"),
+            Operator::Concat => write!(f, "||")
+        }
+    }
 }
 
-impl CondArg {
-    pub fn column<I: Into<String>>(column_name: I) -> CondArg {
-        CondArg::ColumnName(column_name.into())
+/// Splits a column reference qualified as `table.column` into its parts.
+pub fn split_column_name(name: &str) -> (Option<&str>, &str) {
+    match name.rfind('.') {
+        Some(dot) => (Some(&name[..dot]), &name[dot + 1..]),
+        None => (None, name)
     }
+}
 
-    pub fn str<I: Into<String>>(const_str: I) -> CondArg {
-        CondArg::StringConstant(const_str.into())
+pub fn debug_predicates<T: fmt::Debug + fmt::Display>(predicates: &Option<Expression<T>>) -> String {
+    match *predicates {
+        Some(ref cond) => format!("predicate <{}>", cond),
+        None => "no predicate".into()
     }
+}
 
-    pub fn num<I: Into<String>>(const_num: I) -> CondArg {
-        CondArg::NumConst(const_num.into())
+/// Formats the `RETURNING` list of a statement when it has one.
+pub fn debug_returning<T: fmt::Debug + fmt::Display>(f: &mut fmt::Formatter, returning: &[Projection<T>]) -> fmt::Result {
+    if returning.is_empty() {
+        Ok(())
+    } else {
+        write!(f, ", returning: {:?}", returning)
     }
 }
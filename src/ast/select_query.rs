@@ -1,27 +1,283 @@
 use std::fmt;
 
-use super::{Condition, debug_predicates};
+use super::{Expression, debug_predicates};
 
 #[derive(PartialEq, Clone)]
 pub struct SelectQuery<T: fmt::Debug> {
     pub table_name: String,
-    pub columns: Vec<T>,
-    pub predicates: Option<Condition>
+    pub columns: Vec<Projection<T>>,
+    pub predicates: Option<Expression<T>>,
+    pub distinct: bool,
+    pub set_operations: Vec<SetOperation<T>>,
+    pub order_by: Vec<OrderingTerm<T>>,
+    pub limit: Option<usize>,
+    pub with: Option<WithClause<T>>,
+    pub locking: Option<Locking>
 }
 
 impl <T: fmt::Debug> SelectQuery<T> {
-    pub fn new<I: Into<String>>(table_name: I, columns: Vec<T>, predicates: Option<Condition>) -> SelectQuery<T> {
+    pub fn new<I: Into<String>>(table_name: I, columns: Vec<Projection<T>>, predicates: Option<Expression<T>>) -> SelectQuery<T> {
         SelectQuery {
             table_name: table_name.into(),
-            columns: columns,
-            predicates: predicates
+            columns,
+            predicates,
+            distinct: false,
+            set_operations: vec![],
+            order_by: vec![],
+            limit: None,
+            with: None,
+            locking: None
+        }
+    }
+
+    /// Checks whether the query or any of its sub queries reads `table_name`.
+    pub fn references(&self, table_name: &str) -> bool {
+        self.table_name == table_name
+            || self.columns.iter().any(|p| p.expr.references(table_name))
+            || self.predicates.as_ref().is_some_and(|p| p.references(table_name))
+            || self.set_operations.iter().any(|o| o.query.references(table_name))
+            || self.with.as_ref().is_some_and(|w| w.ctes.iter().any(|c| c.query.references(table_name)))
+    }
+}
+
+impl <T: fmt::Debug + fmt::Display> fmt::Debug for SelectQuery<T> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let statement = if self.distinct { "select distinct" } else { "select" };
+        write!(f, "statement: '{}', tables: [<name: '{}'>], columns: {:?}, where: {}", statement, self.table_name, self.columns, debug_predicates(&self.predicates))?;
+        for operation in self.set_operations.iter() {
+            write!(f, ", {:?}", operation)?;
+        }
+        if !self.order_by.is_empty() {
+            write!(f, ", order by: {:?}", self.order_by)?;
+        }
+        if let Some(limit) = self.limit {
+            write!(f, ", limit: {}", limit)?;
+        }
+        if let Some(ref locking) = self.locking {
+            write!(f, ", {:?}", locking)?;
+        }
+        match self.with {
+            Some(ref with) => write!(f, ", {:?}", with),
+            None => Ok(())
+        }
+    }
+}
+
+/// `FOR { UPDATE | SHARE } [NOWAIT | SKIP LOCKED]` locks rows returned by a
+/// select until the end of the transaction.
+#[derive(PartialEq, Clone, Copy)]
+pub struct Locking {
+    pub strength: LockStrength,
+    pub wait: LockWait
+}
+
+impl Locking {
+    pub fn new(strength: LockStrength, wait: LockWait) -> Locking {
+        Locking {
+            strength,
+            wait
+        }
+    }
+}
+
+impl fmt::Debug for Locking {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.wait {
+            LockWait::Wait => write!(f, "{:?}", self.strength),
+            LockWait::NoWait => write!(f, "{:?} nowait", self.strength),
+            LockWait::SkipLocked => write!(f, "{:?} skip locked", self.strength)
+        }
+    }
+}
+
+/// `FOR SHARE` locks of different transactions are compatible with each
+/// other, `FOR UPDATE` locks and writes of rows are compatible with nothing.
+#[derive(PartialEq, Clone, Copy)]
+pub enum LockStrength {
+    Update,
+    Share
+}
+
+impl LockStrength {
+
+    pub fn clause(&self) -> &'static str {
+        match *self {
+            LockStrength::Update => "FOR UPDATE",
+            LockStrength::Share => "FOR SHARE"
+        }
+    }
+}
+
+impl fmt::Debug for LockStrength {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LockStrength::Update => write!(f, "for update"),
+            LockStrength::Share => write!(f, "for share")
+        }
+    }
+}
+
+/// What to do with a row locked by another transaction.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LockWait {
+    Wait,
+    NoWait,
+    SkipLocked
+}
+
+/// `WITH [RECURSIVE] name [(columns)] AS (select), ...` preceding a select.
+#[derive(PartialEq, Clone)]
+pub struct WithClause<T: fmt::Debug> {
+    pub recursive: bool,
+    pub ctes: Vec<CommonTableExpression<T>>
+}
+
+impl <T: fmt::Debug> WithClause<T> {
+    pub fn new(recursive: bool, ctes: Vec<CommonTableExpression<T>>) -> WithClause<T> {
+        WithClause {
+            recursive,
+            ctes
+        }
+    }
+}
+
+impl <T: fmt::Debug + fmt::Display> fmt::Debug for WithClause<T> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let with = if self.recursive { "with recursive" } else { "with" };
+        write!(f, "{}: {:?}", with, self.ctes)
+    }
+}
+
+#[derive(PartialEq, Clone)]
+pub struct CommonTableExpression<T: fmt::Debug> {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub query: SelectQuery<T>
+}
+
+impl <T: fmt::Debug> CommonTableExpression<T> {
+    pub fn new<I: Into<String>>(name: I, columns: Vec<String>, query: SelectQuery<T>) -> CommonTableExpression<T> {
+        CommonTableExpression {
+            name: name.into(),
+            columns,
+            query
+        }
+    }
+}
+
+impl <T: fmt::Debug + fmt::Display> fmt::Debug for CommonTableExpression<T> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let columns = self.columns.iter().map(|c| format!("'{}'", c)).collect::<Vec<String>>();
+        write!(f, "<name: '{}', columns: [{}], query: <sub{:?}>>", self.name, columns.join(", "), self.query)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SetOperator {
+    Union,
+    UnionAll,
+    Intersect,
+    Except
+}
+
+#[derive(PartialEq, Clone)]
+pub struct SetOperation<T: fmt::Debug> {
+    pub operator: SetOperator,
+    pub query: SelectQuery<T>
+}
+
+impl <T: fmt::Debug> SetOperation<T> {
+    pub fn new(operator: SetOperator, query: SelectQuery<T>) -> SetOperation<T> {
+        SetOperation {
+            operator,
+            query
+        }
+    }
+}
+
+impl <T: fmt::Debug + fmt::Display> fmt::Debug for SetOperation<T> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let operator = match self.operator {
+            SetOperator::Union => "union",
+            SetOperator::UnionAll => "union all",
+            SetOperator::Intersect => "intersect",
+            SetOperator::Except => "except"
+        };
+        write!(f, "{}: <sub{:?}>", operator, self.query)
+    }
+}
+
+#[derive(PartialEq, Clone)]
+pub struct Projection<T: fmt::Debug> {
+    pub expr: Expression<T>,
+    pub alias: Option<String>
+}
+
+impl <T: fmt::Debug> Projection<T> {
+    pub fn new(expr: Expression<T>, alias: Option<String>) -> Projection<T> {
+        Projection {
+            expr,
+            alias
+        }
+    }
+}
+
+impl <T: fmt::Debug + fmt::Display> fmt::Debug for Projection<T> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.alias {
+            Some(ref alias) => write!(f, "{:?} as '{}'", self.expr, alias),
+            None => write!(f, "{:?}", self.expr)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SortOrder {
+    Asc,
+    Desc
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NullsOrder {
+    First,
+    Last
+}
+
+#[derive(PartialEq, Clone)]
+pub struct OrderingTerm<T: fmt::Debug> {
+    pub key: Expression<T>,
+    pub order: SortOrder,
+    pub nulls: NullsOrder
+}
+
+impl <T: fmt::Debug> OrderingTerm<T> {
+    pub fn new(key: Expression<T>, order: SortOrder, nulls: NullsOrder) -> OrderingTerm<T> {
+        OrderingTerm {
+            key,
+            order,
+            nulls
         }
     }
 }
 
-impl <T: fmt::Debug> fmt::Debug for SelectQuery<T> {
+impl <T: fmt::Debug + fmt::Display> fmt::Debug for OrderingTerm<T> {
 
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "statement: 'select', tables: [<name: '{}'>], columns: {:?}, where: {}", self.table_name, self.columns, debug_predicates(&self.predicates))
+        let order = match self.order {
+            SortOrder::Asc => "asc",
+            SortOrder::Desc => "desc"
+        };
+        let nulls = match self.nulls {
+            NullsOrder::First => "first",
+            NullsOrder::Last => "last"
+        };
+        write!(f, "<key: {:?}, order: {}, nulls: {}>", self.key, order, nulls)
     }
 }
@@ -0,0 +1,54 @@
+use std::fmt;
+
+/// Statements that control the transaction of a session.
+#[derive(PartialEq, Clone)]
+pub enum TransactionStatement {
+    Begin(Option<IsolationLevel>),
+    Commit,
+    Rollback,
+    Savepoint(String),
+    RollbackTo(String),
+    Release(String)
+}
+
+impl fmt::Debug for TransactionStatement {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TransactionStatement::Begin(None) => write!(f, "statement: 'begin'"),
+            TransactionStatement::Begin(Some(ref level)) => write!(f, "statement: 'begin', isolation level: '{:?}'", level),
+            TransactionStatement::Commit => write!(f, "statement: 'commit'"),
+            TransactionStatement::Rollback => write!(f, "statement: 'rollback'"),
+            TransactionStatement::Savepoint(ref name) => write!(f, "statement: 'savepoint', name: '{}'", name),
+            TransactionStatement::RollbackTo(ref name) => write!(f, "statement: 'rollback to savepoint', name: '{}'", name),
+            TransactionStatement::Release(ref name) => write!(f, "statement: 'release savepoint', name: '{}'", name)
+        }
+    }
+}
+
+/// What a transaction sees of changes made by concurrent transactions.
+/// `READ COMMITTED` reads rows committed before each of its statements
+/// started, `REPEATABLE READ` rows committed before its first statement and
+/// `SNAPSHOT` rows committed before the transaction began. `SERIALIZABLE`
+/// reads like `REPEATABLE READ` and fails to commit when the result could
+/// differ from running the transactions one after another.
+#[derive(PartialEq, Clone, Copy, Default)]
+pub enum IsolationLevel {
+    #[default]
+    ReadCommitted,
+    RepeatableRead,
+    Snapshot,
+    Serializable
+}
+
+impl fmt::Debug for IsolationLevel {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IsolationLevel::ReadCommitted => write!(f, "read committed"),
+            IsolationLevel::RepeatableRead => write!(f, "repeatable read"),
+            IsolationLevel::Snapshot => write!(f, "snapshot"),
+            IsolationLevel::Serializable => write!(f, "serializable")
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use std::fmt;
+
+use super::{Expression, debug_predicates, debug_returning};
+use super::select_query::Projection;
+
+/// `UPDATE table SET column = expr, ... [WHERE predicate] [RETURNING ...]`
+#[derive(PartialEq, Clone)]
+pub struct UpdateQuery<T: fmt::Debug> {
+    pub table_name: String,
+    pub assignments: Vec<(T, Expression<T>)>,
+    pub predicates: Option<Expression<T>>,
+    pub returning: Vec<Projection<T>>
+}
+
+impl <T: fmt::Debug> UpdateQuery<T> {
+    pub fn new<I: Into<String>>(table_name: I, assignments: Vec<(T, Expression<T>)>, predicates: Option<Expression<T>>) -> UpdateQuery<T> {
+        UpdateQuery {
+            table_name: table_name.into(),
+            assignments,
+            predicates,
+            returning: vec![]
+        }
+    }
+}
+
+impl <T: fmt::Debug + fmt::Display> fmt::Debug for UpdateQuery<T> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let assignments = self.assignments.iter().map(|(column, value)| format!("{:?} = {}", column, value)).collect::<Vec<String>>();
+        write!(f, "statement: 'update', table name: '{}', set: [{}], where: {}", self.table_name, assignments.join(", "), debug_predicates(&self.predicates))?;
+        debug_returning(f, &self.returning)
+    }
+}
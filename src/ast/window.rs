@@ -0,0 +1,173 @@
+use std::fmt;
+
+use super::{Expression, TypedColumn, Type};
+use super::select_query::OrderingTerm;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum WindowFunction {
+    RowNumber,
+    Rank,
+    DenseRank,
+    Lag,
+    Lead,
+    FirstValue,
+    Sum,
+    /// average of integers is truncated towards zero
+    Avg
+}
+
+impl WindowFunction {
+
+    pub fn from_name(name: &str) -> Option<WindowFunction> {
+        match name {
+            "row_number" => Some(WindowFunction::RowNumber),
+            "rank" => Some(WindowFunction::Rank),
+            "dense_rank" => Some(WindowFunction::DenseRank),
+            "lag" => Some(WindowFunction::Lag),
+            "lead" => Some(WindowFunction::Lead),
+            "first_value" => Some(WindowFunction::FirstValue),
+            "sum" => Some(WindowFunction::Sum),
+            "avg" => Some(WindowFunction::Avg),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for WindowFunction {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            WindowFunction::RowNumber => write!(f, "row_number"),
+            WindowFunction::Rank => write!(f, "rank"),
+            WindowFunction::DenseRank => write!(f, "dense_rank"),
+            WindowFunction::Lag => write!(f, "lag"),
+            WindowFunction::Lead => write!(f, "lead"),
+            WindowFunction::FirstValue => write!(f, "first_value"),
+            WindowFunction::Sum => write!(f, "sum"),
+            WindowFunction::Avg => write!(f, "avg")
+        }
+    }
+}
+
+/// `function(args) OVER (PARTITION BY ... ORDER BY ... frame)`
+#[derive(PartialEq, Clone)]
+pub struct WindowCall<T: fmt::Debug> {
+    pub function: WindowFunction,
+    pub args: Vec<Expression<T>>,
+    pub partition_by: Vec<Expression<T>>,
+    pub order_by: Vec<OrderingTerm<T>>,
+    pub frame: Option<WindowFrame>
+}
+
+impl <T: fmt::Debug> WindowCall<T> {
+    pub fn new(function: WindowFunction, args: Vec<Expression<T>>) -> WindowCall<T> {
+        WindowCall {
+            function,
+            args,
+            partition_by: vec![],
+            order_by: vec![],
+            frame: None
+        }
+    }
+
+    /// Without an explicit frame rows from the start of the partition up to
+    /// the last peer of the current row are used when the window is ordered,
+    /// and the whole partition otherwise.
+    pub fn effective_frame(&self) -> WindowFrame {
+        match self.frame {
+            Some(frame) => frame,
+            None if self.order_by.is_empty() => WindowFrame::new(FrameUnits::Rows, FrameBound::UnboundedPreceding, FrameBound::UnboundedFollowing),
+            None => WindowFrame::new(FrameUnits::Range, FrameBound::UnboundedPreceding, FrameBound::CurrentRow)
+        }
+    }
+}
+
+impl WindowCall<TypedColumn> {
+
+    /// There is no numeric type to hold a fraction, so `AVG` returns its
+    /// decimal value as text.
+    pub fn result_type(&self) -> Type {
+        match self.function {
+            WindowFunction::Lag | WindowFunction::Lead | WindowFunction::FirstValue =>
+                self.args.first().map_or(Type::Integer, |arg| arg.expr_type()),
+            WindowFunction::Avg => Type::Character(None),
+            _ => Type::Integer
+        }
+    }
+}
+
+impl <T: fmt::Debug + fmt::Display> fmt::Display for WindowCall<T> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let args = self.args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>();
+        write!(f, "{}({}) over (", self.function, args.join(", "))?;
+        let mut clauses = vec![];
+        if !self.partition_by.is_empty() {
+            let keys = self.partition_by.iter().map(|key| key.to_string()).collect::<Vec<String>>();
+            clauses.push(format!("partition by {}", keys.join(", ")));
+        }
+        if !self.order_by.is_empty() {
+            clauses.push(format!("order by {:?}", self.order_by));
+        }
+        if let Some(ref frame) = self.frame {
+            clauses.push(frame.to_string());
+        }
+        write!(f, "{})", clauses.join(" "))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FrameUnits {
+    Rows,
+    Range
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FrameBound {
+    UnboundedPreceding,
+    Preceding(u64),
+    CurrentRow,
+    Following(u64),
+    UnboundedFollowing
+}
+
+impl fmt::Display for FrameBound {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FrameBound::UnboundedPreceding => write!(f, "unbounded preceding"),
+            FrameBound::Preceding(n) => write!(f, "{} preceding", n),
+            FrameBound::CurrentRow => write!(f, "current row"),
+            FrameBound::Following(n) => write!(f, "{} following", n),
+            FrameBound::UnboundedFollowing => write!(f, "unbounded following")
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct WindowFrame {
+    pub units: FrameUnits,
+    pub start: FrameBound,
+    pub end: FrameBound
+}
+
+impl WindowFrame {
+    pub fn new(units: FrameUnits, start: FrameBound, end: FrameBound) -> WindowFrame {
+        WindowFrame {
+            units,
+            start,
+            end
+        }
+    }
+}
+
+impl fmt::Display for WindowFrame {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let units = match self.units {
+            FrameUnits::Rows => "rows",
+            FrameUnits::Range => "range"
+        };
+        write!(f, "{} between {} and {}", units, self.start, self.end)
+    }
+}
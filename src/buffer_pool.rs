@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use super::page::{Page, PAGE_SIZE};
+use super::transaction::TransactionId;
+use super::wal::Wal;
+
+pub type PageId = u32;
+
+/// Pages of a database file cached in a fixed number of frames. When all
+/// frames are taken a page is evicted by the clock algorithm: the hand
+/// sweeps over the frames clearing their reference bits and evicts the
+/// first page that was not used since the previous sweep. Dirty pages are
+/// written back to the file when they are evicted or at a checkpoint, always
+/// after their images are in the log.
+#[derive(Debug)]
+pub struct BufferPool {
+    file: File,
+    wal: Wal,
+    page_count: u32,
+    capacity: usize,
+    frames: Vec<Frame>,
+    frame_of: HashMap<PageId, usize>,
+    hand: usize
+}
+
+#[derive(Debug)]
+struct Frame {
+    page_id: PageId,
+    page: Page,
+    dirty: bool,
+    /// the log has the current image of the page
+    logged: bool,
+    referenced: bool
+}
+
+impl BufferPool {
+
+    pub fn new(file: File, wal: Wal, capacity: usize) -> Result<BufferPool, String> {
+        let length = file.metadata().map_err(|e| e.to_string())?.len();
+        if length % PAGE_SIZE as u64 != 0 {
+            return Err(format!("size of database file {} is not a multiple of page size {}", length, PAGE_SIZE));
+        }
+        Ok(BufferPool {
+            file,
+            wal,
+            page_count: (length / PAGE_SIZE as u64) as u32,
+            capacity: capacity.max(1),
+            frames: vec![],
+            frame_of: HashMap::default(),
+            hand: 0
+        })
+    }
+
+    pub fn page_count(&self) -> u32 {
+        self.page_count
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn wal_size(&self) -> u64 {
+        self.wal.size()
+    }
+
+    /// Number of pages that are cached at the moment.
+    pub fn cached(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Evicts pages that don't fit into the new number of frames.
+    pub fn resize(&mut self, capacity: usize) -> Result<(), String> {
+        self.capacity = capacity.max(1);
+        while self.frames.len() > self.capacity {
+            self.evict()?;
+        }
+        Ok(())
+    }
+
+    pub fn page(&mut self, page_id: PageId) -> Result<&Page, String> {
+        let frame = self.frame(page_id)?;
+        Ok(&self.frames[frame].page)
+    }
+
+    pub fn page_mut(&mut self, page_id: PageId) -> Result<&mut Page, String> {
+        let frame = self.frame(page_id)?;
+        self.frames[frame].dirty = true;
+        self.frames[frame].logged = false;
+        Ok(&mut self.frames[frame].page)
+    }
+
+    /// Adds an empty page of `owner` to the end of the file, the file is
+    /// extended right away so it never has holes.
+    pub fn allocate(&mut self, owner: u32) -> Result<PageId, String> {
+        let page_id = self.page_count;
+        let page = Page::new(owner);
+        write_page(&mut self.file, page_id, &page)?;
+        self.page_count += 1;
+        self.cache(page_id, page)?;
+        Ok(page_id)
+    }
+
+    /// Writes images of pages recovered from the log to the file.
+    pub fn restore(&mut self, pages: Vec<(PageId, Page)>) -> Result<(), String> {
+        if pages.is_empty() {
+            return Ok(());
+        }
+        for (page_id, page) in pages {
+            write_page(&mut self.file, page_id, &page)?;
+            self.page_count = self.page_count.max(page_id + 1);
+        }
+        self.file.sync_data().map_err(|e| e.to_string())
+    }
+
+    /// Logs images of pages changed since they were logged and the commit
+    /// record of the transaction, and waits until they reach the disk. A
+    /// transaction that changed nothing needs no record.
+    pub fn commit(&mut self, id: TransactionId, changed: bool) -> Result<(), String> {
+        if !changed && self.frames.iter().all(|frame| frame.logged) {
+            return Ok(());
+        }
+        self.log_changes()?;
+        self.wal.append_commit(id)?;
+        self.wal.sync()
+    }
+
+    /// Writes all dirty pages to the file and starts a new log, `next` and
+    /// `active` tell which transactions may have changed the pages without
+    /// committing.
+    pub fn checkpoint(&mut self, next: TransactionId, active: &[TransactionId]) -> Result<(), String> {
+        self.log_changes()?;
+        self.wal.sync()?;
+        for frame in 0..self.frames.len() {
+            if self.frames[frame].dirty {
+                self.write(frame)?;
+            }
+        }
+        self.file.sync_data().map_err(|e| e.to_string())?;
+        self.wal.reset(next, active)
+    }
+
+    fn log_changes(&mut self) -> Result<(), String> {
+        for frame in self.frames.iter_mut().filter(|frame| !frame.logged) {
+            self.wal.append_page(frame.page_id, &frame.page)?;
+            frame.logged = true;
+        }
+        Ok(())
+    }
+
+    fn frame(&mut self, page_id: PageId) -> Result<usize, String> {
+        if let Some(&frame) = self.frame_of.get(&page_id) {
+            self.frames[frame].referenced = true;
+            return Ok(frame);
+        }
+        if page_id >= self.page_count {
+            return Err(format!("page {} is out of database file", page_id));
+        }
+        let mut bytes = vec![0; PAGE_SIZE].into_boxed_slice();
+        self.file.seek(SeekFrom::Start(page_id as u64 * PAGE_SIZE as u64)).map_err(|e| e.to_string())?;
+        self.file.read_exact(&mut bytes).map_err(|e| format!("could not read page {}: {}", page_id, e))?;
+        let page = Page::from_bytes(bytes).map_err(|e| format!("could not read page {}: {}", page_id, e))?;
+        self.cache(page_id, page)
+    }
+
+    fn cache(&mut self, page_id: PageId, page: Page) -> Result<usize, String> {
+        if self.frames.len() >= self.capacity {
+            self.evict()?;
+        }
+        self.frames.push(Frame { page_id, page, dirty: false, logged: true, referenced: true });
+        self.frame_of.insert(page_id, self.frames.len() - 1);
+        Ok(self.frames.len() - 1)
+    }
+
+    fn evict(&mut self) -> Result<(), String> {
+        loop {
+            if self.hand >= self.frames.len() {
+                self.hand = 0;
+            }
+            if self.frames[self.hand].referenced {
+                self.frames[self.hand].referenced = false;
+                self.hand += 1;
+                continue;
+            }
+            let victim = self.hand;
+            if !self.frames[victim].logged {
+                self.wal.append_page(self.frames[victim].page_id, &self.frames[victim].page)?;
+                self.wal.sync()?;
+            }
+            if self.frames[victim].dirty {
+                self.write(victim)?;
+            }
+            let evicted = self.frames.swap_remove(victim);
+            self.frame_of.remove(&evicted.page_id);
+            if let Some(moved) = self.frames.get(victim) {
+                self.frame_of.insert(moved.page_id, victim);
+            }
+            return Ok(());
+        }
+    }
+
+    fn write(&mut self, frame: usize) -> Result<(), String> {
+        write_page(&mut self.file, self.frames[frame].page_id, &self.frames[frame].page)?;
+        self.frames[frame].dirty = false;
+        Ok(())
+    }
+}
+
+fn write_page(file: &mut File, page_id: PageId, page: &Page) -> Result<(), String> {
+    file.seek(SeekFrom::Start(page_id as u64 * PAGE_SIZE as u64)).map_err(|e| e.to_string())?;
+    file.write_all(page.bytes()).map_err(|e| format!("could not write page {}: {}", page_id, e))
+}
@@ -1,19 +1,105 @@
-use super::ast::Type;
+use super::ast::{Type, TypedColumn, RawColumn, Expression};
+use super::ast::create_table::Generation;
+use super::ast::select_query::SelectQuery;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct ColumnMetadata {
     pub name: String,
     pub col_type: Type,
-    pub default_val: Option<String>
+    pub default_val: Option<Expression<TypedColumn>>,
+    pub identity: Option<Identity>,
+    pub generated: Option<Generation<TypedColumn>>
 }
 
 impl ColumnMetadata {
 
-    pub fn new<I: Into<String>>(name: I, col_type: Type, default_val: Option<I>) -> ColumnMetadata {
+    pub fn new<I: Into<String>>(name: I, col_type: Type, default_val: Option<Expression<TypedColumn>>) -> ColumnMetadata {
         ColumnMetadata {
             name: name.into(),
             col_type: col_type,
-            default_val: default_val.and_then(|d| Some(d.into()))
+            default_val: default_val,
+            identity: None,
+            generated: None
         }
     }
 }
+
+/// Column that takes its values from a sequence when they are not given,
+/// `GENERATED ALWAYS` columns don't accept values at all.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Identity {
+    pub sequence: String,
+    pub always: bool
+}
+
+/// Generator of integer keys, `last_value` is `None` until the sequence is
+/// advanced for the first time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sequence {
+    pub start: i64,
+    pub increment: i64,
+    pub last_value: Option<i64>
+}
+
+impl Sequence {
+
+    pub fn new(start: i64, increment: i64) -> Sequence {
+        Sequence {
+            start,
+            increment,
+            last_value: None
+        }
+    }
+
+    pub fn next_value(&mut self) -> Option<i64> {
+        let next = match self.last_value {
+            Some(last) => last.checked_add(self.increment)?,
+            None => self.start
+        };
+        self.last_value = Some(next);
+        Some(next)
+    }
+}
+
+/// Query of a view as it was written, rows of a materialized view are
+/// stored as rows of a table with the same name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct View {
+    pub columns: Vec<String>,
+    pub definition: SelectQuery<RawColumn>,
+    pub materialized: bool
+}
+
+impl View {
+
+    pub fn new(columns: Vec<String>, definition: SelectQuery<RawColumn>, materialized: bool) -> View {
+        View {
+            columns,
+            definition,
+            materialized
+        }
+    }
+}
+
+/// Columns whose values can't repeat among the rows of a table, rows with
+/// `NULL` in any of them never conflict.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UniqueKey {
+    pub name: String,
+    pub columns: Vec<String>
+}
+
+impl UniqueKey {
+
+    pub fn new<I: Into<String>>(name: I, columns: Vec<String>) -> UniqueKey {
+        UniqueKey {
+            name: name.into(),
+            columns
+        }
+    }
+
+    /// Checks whether the key consists of exactly `columns` in any order.
+    pub fn matches(&self, columns: &[String]) -> bool {
+        self.columns.len() == columns.len() && columns.iter().all(|c| self.columns.contains(c))
+    }
+}
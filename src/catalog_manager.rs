@@ -1,85 +1,491 @@
 use std::sync::{Mutex, Arc};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use super::ast::Type;
-use super::catalog::ColumnMetadata;
+use super::ast::{Type, TypedColumn, Expression};
+use super::ast::create_table::Generation;
+use super::catalog::{ColumnMetadata, UniqueKey, Identity, Sequence, View};
+use super::functions::{UserFunctions, ScalarFunction, AggregateFunction};
+use super::transaction::{UndoLog, CONCURRENT_UPDATE};
 
 #[derive(Clone)]
 pub struct CatalogManager {
-    tables: Arc<Mutex<HashMap<String, Vec<ColumnMetadata>>>>
+    tables: Entries<Vec<ColumnMetadata>>,
+    keys: Entries<Vec<UniqueKey>>,
+    sequences: Entries<Sequence>,
+    views: Entries<View>,
+    functions: Arc<Mutex<UserFunctions>>,
+    /// temporary tables of a session and their keys and sequences
+    temporary_tables: Arc<Mutex<HashMap<String, Vec<ColumnMetadata>>>>,
+    temporary_keys: Arc<Mutex<HashMap<String, Vec<UniqueKey>>>>,
+    temporary_sequences: Arc<Mutex<HashMap<String, Sequence>>>,
+    /// catalog entries changed by the open transaction of a session
+    transaction: Arc<Mutex<Option<UndoLog<CatalogUndo>>>>,
+    /// names of relations created or dropped by transactions in progress
+    /// and the ones of them reserved by the transaction of a session
+    reserved: Arc<Mutex<HashSet<String>>>,
+    reservations: Arc<Mutex<HashSet<String>>>
+}
+
+/// Catalog entries of one kind shared by all sessions together with the
+/// ones changed by the open transaction of a session, other sessions see
+/// those after the transaction commits.
+#[derive(Clone)]
+struct Entries<V> {
+    shared: Arc<Mutex<HashMap<String, V>>>,
+    /// `None` for an entry removed by the transaction
+    changed: Arc<Mutex<HashMap<String, Option<V>>>>
+}
+
+/// Entry of the catalog as it was before a change made in a transaction.
+enum CatalogUndo {
+    Table { name: String, previous: Previous<Vec<ColumnMetadata>> },
+    Keys { table_name: String, previous: Previous<Vec<UniqueKey>> },
+    Sequence { name: String, previous: Previous<Sequence> },
+    View { name: String, previous: Option<Option<View>> }
+}
+
+enum Previous<V> {
+    /// entry of a temporary table, `None` when it didn't exist
+    Temporary(Option<V>),
+    /// change of a shared entry made earlier in the transaction, `None`
+    /// when the transaction had not changed the entry
+    Changed(Option<Option<V>>)
 }
 
 impl Default for CatalogManager {
     fn default() -> Self {
         CatalogManager {
-            tables: Arc::new(Mutex::new(HashMap::default()))
+            tables: Entries::default(),
+            keys: Entries::default(),
+            sequences: Entries::default(),
+            views: Entries::default(),
+            functions: Arc::new(Mutex::new(UserFunctions::default())),
+            temporary_tables: Arc::new(Mutex::new(HashMap::default())),
+            temporary_keys: Arc::new(Mutex::new(HashMap::default())),
+            temporary_sequences: Arc::new(Mutex::new(HashMap::default())),
+            transaction: Arc::new(Mutex::new(None)),
+            reserved: Arc::new(Mutex::new(HashSet::default())),
+            reservations: Arc::new(Mutex::new(HashSet::default()))
         }
     }
 }
 
 impl CatalogManager {
-    pub fn add_table<I: Into<String>>(&self, table_name: I) {
-        let mut guard = self.tables.lock().unwrap();
-        (*guard).entry(table_name.into()).or_insert_with(Vec::default);
-        drop(guard);
+    /// Makes `function` callable from queries as `name(args)`, arguments
+    /// are checked against `arg_types` when a query is typed.
+    pub fn register_function<F: ScalarFunction + 'static>(&self, name: &str, arg_types: Vec<Type>, return_type: Type, function: F) -> Result<(), String> {
+        let mut guard = self.functions.lock().unwrap();
+        (*guard).add_scalar(name, arg_types, return_type, function)
+    }
+
+    pub fn register_aggregate<A: AggregateFunction + 'static>(&self, name: &str, arg_types: Vec<Type>, return_type: Type, aggregate: A) -> Result<(), String> {
+        let mut guard = self.functions.lock().unwrap();
+        (*guard).add_aggregate(name, arg_types, return_type, aggregate)
+    }
+
+    /// Snapshot of the registered functions for a single query.
+    pub fn functions(&self) -> UserFunctions {
+        let guard = self.functions.lock().unwrap();
+        (*guard).clone()
+    }
+
+    /// Shares tables, views and sequences with `self` but has its own,
+    /// initially empty, set of temporary tables.
+    pub fn session(&self) -> CatalogManager {
+        CatalogManager {
+            tables: self.tables.session(),
+            keys: self.keys.session(),
+            sequences: self.sequences.session(),
+            views: self.views.session(),
+            temporary_tables: Arc::new(Mutex::new(HashMap::default())),
+            temporary_keys: Arc::new(Mutex::new(HashMap::default())),
+            temporary_sequences: Arc::new(Mutex::new(HashMap::default())),
+            transaction: Arc::new(Mutex::new(None)),
+            reservations: Arc::new(Mutex::new(HashSet::default())),
+            ..self.clone()
+        }
+    }
+
+    pub fn add_table<I: Into<String>>(&self, table_name: I) -> Result<(), String> {
+        let table_name = table_name.into();
+        self.reserve(table_name.as_str())?;
+        if self.tables.contains(table_name.as_str()) {
+            return Err(format!("relation \"{}\" already exists", table_name));
+        }
+        self.change_table(table_name.as_str(), false, |table| { table.get_or_insert_with(Vec::default); });
+        Ok(())
+    }
+
+    /// Temporary table hides a table or a view with the same name from the
+    /// session that created it.
+    pub fn add_temporary_table<I: Into<String>>(&self, table_name: I) {
+        let table_name = table_name.into();
+        self.change_table(table_name.as_str(), true, |table| { table.get_or_insert_with(Vec::default); });
     }
 
     pub fn contains_table(&self, table_name: &str) -> bool {
-        let guard = self.tables.lock().unwrap();
-        let r = (*guard).keys().any(|name| name == table_name);
+        self.contains_temporary_table(table_name) || self.tables.contains(table_name)
+    }
+
+    pub fn contains_temporary_table(&self, table_name: &str) -> bool {
+        let guard = self.temporary_tables.lock().unwrap();
+        let r = (*guard).contains_key(table_name);
         drop(guard);
         r
     }
 
-    pub fn add_column_to<I: Into<String>>(&self, table_name: &str, column: (I, Type, Option<I>)) {
-        let mut guard = self.tables.lock().unwrap();
-        if let Some(table) = (*guard).get_mut(table_name) {
-            (*table).push(ColumnMetadata { name: column.0.into(), col_type: column.1, default_val: column.2.and_then(|i| Some(i.into())) });
+    pub fn temporary_table_names(&self) -> Vec<String> {
+        let guard = self.temporary_tables.lock().unwrap();
+        let r = (*guard).keys().cloned().collect();
+        drop(guard);
+        r
+    }
+
+    fn table(&self, table_name: &str) -> Option<Vec<ColumnMetadata>> {
+        if self.contains_temporary_table(table_name) {
+            self.temporary_tables.lock().unwrap().get(table_name).cloned()
+        } else {
+            self.tables.get(table_name)
         }
+    }
+
+    fn is_temporary_sequence(&self, name: &str) -> bool {
+        self.temporary_sequences.lock().unwrap().contains_key(name)
+    }
+
+    /// Starts recording changes of the catalog so that they can be undone.
+    pub fn begin(&self) {
+        let mut guard = self.transaction.lock().unwrap();
+        *guard = Some(UndoLog::default());
         drop(guard);
     }
 
-    pub fn contains_column_in(&self, table_name: &str, column_name: &str) -> bool {
-        let mut guard = self.tables.lock().unwrap();
-        if let Some(table) = (*guard).get_mut(table_name) {
-            (*table).iter().any(|ref c| c.name == column_name)
+    pub fn in_transaction(&self) -> bool {
+        let guard = self.transaction.lock().unwrap();
+        let r = (*guard).is_some();
+        drop(guard);
+        r
+    }
+
+    /// Makes changes of the transaction visible to other sessions.
+    pub fn commit(&self) {
+        let mut guard = self.transaction.lock().unwrap();
+        *guard = None;
+        drop(guard);
+        self.tables.publish();
+        self.keys.publish();
+        self.sequences.publish();
+        self.views.publish();
+        self.release_reservations();
+    }
+
+    /// Undoes changes made by the transaction, values handed out by
+    /// sequences are not given back.
+    pub fn rollback(&self) {
+        let mut guard = self.transaction.lock().unwrap();
+        let undone = (*guard).take().map(|mut log| log.rollback()).unwrap_or_default();
+        drop(guard);
+        self.undo(undone);
+        self.tables.discard();
+        self.keys.discard();
+        self.sequences.discard();
+        self.views.discard();
+        self.release_reservations();
+    }
+
+    /// A relation can be created or dropped by one transaction at a time,
+    /// a concurrent one that tries to do it too can't be serialized.
+    fn reserve(&self, name: &str) -> Result<(), String> {
+        if !self.in_transaction() {
+            return Ok(());
+        }
+        let mut reservations = self.reservations.lock().unwrap();
+        if (*reservations).contains(name) {
+            return Ok(());
+        }
+        let mut reserved = self.reserved.lock().unwrap();
+        if !(*reserved).insert(name.to_owned()) {
+            return Err(CONCURRENT_UPDATE.into());
+        }
+        (*reservations).insert(name.to_owned());
+        Ok(())
+    }
+
+    fn release_reservations(&self) {
+        let mut reservations = self.reservations.lock().unwrap();
+        let mut reserved = self.reserved.lock().unwrap();
+        for name in (*reservations).drain() {
+            (*reserved).remove(name.as_str());
+        }
+    }
+
+    pub fn savepoint(&self, name: &str) -> Result<(), String> {
+        let mut guard = self.transaction.lock().unwrap();
+        match (*guard).as_mut() {
+            Some(log) => {
+                log.savepoint(name);
+                Ok(())
+            },
+            None => Err("SAVEPOINT can only be used in transaction blocks".into())
+        }
+    }
+
+    pub fn rollback_to(&self, name: &str) -> Result<(), String> {
+        let mut guard = self.transaction.lock().unwrap();
+        let undone = match (*guard).as_mut() {
+            Some(log) => log.rollback_to(name)?,
+            None => return Err("ROLLBACK TO SAVEPOINT can only be used in transaction blocks".into())
+        };
+        drop(guard);
+        self.undo(undone);
+        Ok(())
+    }
+
+    pub fn release(&self, name: &str) -> Result<(), String> {
+        let mut guard = self.transaction.lock().unwrap();
+        match (*guard).as_mut() {
+            Some(log) => log.release(name),
+            None => Err("RELEASE SAVEPOINT can only be used in transaction blocks".into())
+        }
+    }
+
+    fn undo(&self, undone: Vec<CatalogUndo>) {
+        for entry in undone {
+            match entry {
+                CatalogUndo::Table { name, previous } => self.tables.restore(&self.temporary_tables, name, previous),
+                CatalogUndo::Keys { table_name, previous } => self.keys.restore(&self.temporary_keys, table_name, previous),
+                CatalogUndo::Sequence { name, previous } => self.sequences.restore(&self.temporary_sequences, name, previous),
+                CatalogUndo::View { name, previous } => restore(&self.views.changed, name, previous)
+            }
+        }
+    }
+
+    fn record(&self, entry: CatalogUndo) {
+        let mut guard = self.transaction.lock().unwrap();
+        if let Some(log) = (*guard).as_mut() {
+            log.record(entry);
+        }
+        drop(guard);
+    }
+
+    fn change_table<F: FnOnce(&mut Option<Vec<ColumnMetadata>>)>(&self, table_name: &str, temporary: bool, change: F) {
+        let previous = self.change(&self.tables, &self.temporary_tables, temporary, table_name, change);
+        self.record(CatalogUndo::Table { name: table_name.to_owned(), previous });
+    }
+
+    fn change_keys<F: FnOnce(&mut Option<Vec<UniqueKey>>)>(&self, table_name: &str, temporary: bool, change: F) {
+        let previous = self.change(&self.keys, &self.temporary_keys, temporary, table_name, change);
+        self.record(CatalogUndo::Keys { table_name: table_name.to_owned(), previous });
+    }
+
+    fn change_sequence<F: FnOnce(&mut Option<Sequence>)>(&self, name: &str, temporary: bool, change: F) {
+        let previous = self.change(&self.sequences, &self.temporary_sequences, temporary, name, change);
+        self.record(CatalogUndo::Sequence { name: name.to_owned(), previous });
+    }
+
+    fn change_view<F: FnOnce(&mut Option<View>)>(&self, name: &str, change: F) {
+        let previous = self.views.change(name, self.in_transaction(), change);
+        self.record(CatalogUndo::View { name: name.to_owned(), previous });
+    }
+
+    /// Temporary entries are changed in place, they are seen by the session
+    /// alone.
+    fn change<V: Clone, F: FnOnce(&mut Option<V>)>(&self, entries: &Entries<V>, temporary_map: &Mutex<HashMap<String, V>>, temporary: bool, name: &str, change: F) -> Previous<V> {
+        if temporary {
+            let mut guard = temporary_map.lock().unwrap();
+            let previous = (*guard).get(name).cloned();
+            apply(&mut guard, name, change);
+            drop(guard);
+            Previous::Temporary(previous)
         } else {
-            false
+            Previous::Changed(entries.change(name, self.in_transaction(), change))
         }
     }
 
-    pub fn match_type(&self, table_name: &str, column_index: usize, column_type: Type) -> bool {
-        let mut guard = self.tables.lock().unwrap();
-        if let Some(table) = (*guard).get_mut(table_name) {
-            match (*table).get(column_index) {
-                Some(ref c) => c.col_type == column_type,
-                None => false
+    pub fn add_column_to<I: Into<String>>(&self, table_name: &str, column: (I, Type, Option<Expression<TypedColumn>>)) {
+        let column = ColumnMetadata { name: column.0.into(), col_type: column.1, default_val: column.2, identity: None, generated: None };
+        self.change_table(table_name, self.contains_temporary_table(table_name), |table| {
+            if let Some(table) = table.as_mut() {
+                table.push(column);
+            }
+        });
+    }
+
+    pub fn set_identity_of(&self, table_name: &str, column_name: &str, identity: Identity) {
+        self.change_table(table_name, self.contains_temporary_table(table_name), |table| {
+            if let Some(column) = table.as_mut().and_then(|table| table.iter_mut().find(|c| c.name == column_name)) {
+                column.identity = Some(identity);
             }
+        });
+    }
+
+    pub fn set_generation_of(&self, table_name: &str, column_name: &str, generation: Generation<TypedColumn>) {
+        self.change_table(table_name, self.contains_temporary_table(table_name), |table| {
+            if let Some(column) = table.as_mut().and_then(|table| table.iter_mut().find(|c| c.name == column_name)) {
+                column.generated = Some(generation);
+            }
+        });
+    }
+
+    /// Sequences share names with tables and views.
+    pub fn create_sequence(&self, name: &str, sequence: Sequence) -> Result<(), String> {
+        self.reserve(name)?;
+        if self.contains_table(name) || self.contains_sequence(name) || self.contains_view(name) {
+            return Err(format!("relation \"{}\" already exists", name));
+        }
+        self.change_sequence(name, false, |entry| *entry = Some(sequence));
+        Ok(())
+    }
+
+    /// Sequence of an identity column of a temporary table, it is dropped
+    /// together with the table.
+    pub fn create_temporary_sequence(&self, name: &str, sequence: Sequence) -> Result<(), String> {
+        if self.contains_temporary_table(name) || self.is_temporary_sequence(name) {
+            return Err(format!("relation \"{}\" already exists", name));
+        }
+        self.change_sequence(name, true, |entry| *entry = Some(sequence));
+        Ok(())
+    }
+
+    pub fn contains_sequence(&self, name: &str) -> bool {
+        self.is_temporary_sequence(name) || self.sequences.contains(name)
+    }
+
+    /// Advances the sequence, values that were handed out are never given
+    /// again even if the statement that took them fails.
+    pub fn next_value(&self, name: &str) -> Result<i64, String> {
+        self.with_sequence(name, |sequence| match sequence {
+            Some(sequence) => {
+                let bound = if sequence.increment > 0 { "maximum" } else { "minimum" };
+                sequence.next_value().ok_or_else(|| format!("nextval: reached {} value of sequence \"{}\"", bound, name))
+            },
+            None => Err(format!("relation \"{}\" does not exist", name))
+        })
+    }
+
+    /// Last values handed out by sequences that are not temporary.
+    pub fn sequence_values(&self) -> Vec<(String, i64)> {
+        let mut values = self.sequences.all().into_iter()
+            .filter_map(|(name, sequence)| sequence.last_value.map(|value| (name, value)))
+            .collect::<Vec<(String, i64)>>();
+        values.sort();
+        values
+    }
+
+    /// Continues the sequence after a value handed out before.
+    pub fn set_last_value_of(&self, name: &str, last_value: i64) {
+        self.sequences.with(name, |sequence| {
+            if let Some(sequence) = sequence {
+                sequence.last_value = Some(last_value);
+            }
+        });
+    }
+
+    pub fn current_value(&self, name: &str) -> Result<i64, String> {
+        self.with_sequence(name, |sequence| match sequence {
+            Some(sequence) => sequence.last_value.ok_or_else(|| format!("currval of sequence \"{}\" is not yet defined", name)),
+            None => Err(format!("relation \"{}\" does not exist", name))
+        })
+    }
+
+    /// Sequences are not transactional, a shared one is changed in place
+    /// unless the transaction of the session created it.
+    fn with_sequence<R, F: FnOnce(Option<&mut Sequence>) -> R>(&self, name: &str, f: F) -> R {
+        let mut guard = self.temporary_sequences.lock().unwrap();
+        match (*guard).get_mut(name) {
+            Some(sequence) => f(Some(sequence)),
+            None => {
+                drop(guard);
+                self.sequences.with(name, f)
+            }
+        }
+    }
+
+    /// Columns of a materialized view are added as columns of a table with
+    /// the view name after the view is created.
+    pub fn create_view(&self, name: &str, view: View) -> Result<(), String> {
+        self.reserve(name)?;
+        if self.contains_table(name) || self.contains_sequence(name) || self.contains_view(name) {
+            return Err(format!("relation \"{}\" already exists", name));
+        }
+        self.change_view(name, |entry| *entry = Some(view));
+        Ok(())
+    }
+
+    pub fn contains_view(&self, name: &str) -> bool {
+        self.views.contains(name)
+    }
+
+    pub fn get_view(&self, name: &str) -> Option<View> {
+        if self.contains_temporary_table(name) {
+            return None;
+        }
+        self.views.get(name)
+    }
+
+    /// Removes the view together with the table of a materialized view, views
+    /// that read it have to be dropped first.
+    pub fn drop_view(&self, name: &str, materialized: bool) -> Result<(), String> {
+        let kind = if materialized { "materialized view" } else { "view" };
+        self.reserve(name)?;
+        let views = self.views.all();
+        match views.get(name) {
+            Some(view) if view.materialized != materialized => return Err(format!("\"{}\" is not a {}", name, kind)),
+            Some(_) => {},
+            None => return Err(format!("{} \"{}\" does not exist", kind, name))
+        }
+        if views.values().any(|view| view.definition.references(name)) {
+            return Err(format!("cannot drop {} {} because other objects depend on it", kind, name));
+        }
+        self.change_view(name, |view| *view = None);
+        if materialized {
+            self.change_table(name, false, |table| *table = None);
+            self.change_keys(name, false, |keys| *keys = None);
+        }
+        Ok(())
+    }
+
+    pub fn add_unique_key(&self, table_name: &str, key: UniqueKey) {
+        self.change_keys(table_name, self.contains_temporary_table(table_name), |keys| keys.get_or_insert_with(Vec::default).push(key));
+    }
+
+    /// Primary key, when the table has one, comes first.
+    pub fn get_unique_keys(&self, table_name: &str) -> Vec<UniqueKey> {
+        let keys = if self.contains_temporary_table(table_name) {
+            self.temporary_keys.lock().unwrap().get(table_name).cloned()
         } else {
-            false
+            self.keys.get(table_name)
+        };
+        keys.unwrap_or_default()
+    }
+
+    pub fn contains_column_in(&self, table_name: &str, column_name: &str) -> bool {
+        match self.table(table_name) {
+            Some(table) => table.iter().any(|c| c.name == column_name),
+            None => false
+        }
+    }
+
+    pub fn match_type(&self, table_name: &str, column_index: usize, column_type: Type) -> bool {
+        match self.table(table_name) {
+            Some(table) => match table.get(column_index) {
+                Some(c) => c.col_type == column_type,
+                None => false
+            },
+            None => false
         }
     }
 
     pub fn get_column_index(&self, table_name: &str, column_name: &str) -> Option<usize> {
-        let guard = self.tables.lock().unwrap();
-        let r = (*guard).get(table_name).and_then(|v| v.iter().position(|ref c| c.name == column_name));
-        drop(guard);
-        r
+        self.table(table_name).and_then(|v| v.iter().position(|c| c.name == column_name))
     }
 
     pub fn get_table_columns(&self, table_name: &str) -> Vec<ColumnMetadata> {
-        let guard = self.tables.lock().unwrap();
-        let r = match (*guard).get(table_name) {
-            Some(table) => table.iter().cloned().collect::<Vec<ColumnMetadata>>(),
-            None => vec![] //panic!("table <{}> not found", table_name),
-        };
-        drop(guard);
-        r
+        self.table(table_name).unwrap_or_default()
     }
 
     pub fn get_column_type(&self, table_name: &str, column_name: &str) -> Type {
-        let guard = self.tables.lock().unwrap();
-        match (*guard).get(table_name) {
+        match self.table(table_name) {
             Some(table) => {
                 for c in table {
                     if c.name == column_name {
@@ -90,12 +496,10 @@ impl CatalogManager {
             }
             None => panic!("unimplemented if table <{}> does not exist", table_name)
         }
-        drop(guard);
     }
 
     pub fn get_column_type_by_index(&self, table_name: &str, index: usize) -> Type {
-        let guard = self.tables.lock().unwrap();
-        match (*guard).get(table_name) {
+        match self.table(table_name) {
             Some(table) => {
                 match table.into_iter().nth(index) {
                     Some(cm) => cm.col_type,
@@ -106,3 +510,117 @@ impl CatalogManager {
         }
     }
 }
+
+impl <V> Default for Entries<V> {
+    fn default() -> Self {
+        Entries {
+            shared: Arc::new(Mutex::new(HashMap::default())),
+            changed: Arc::new(Mutex::new(HashMap::default()))
+        }
+    }
+}
+
+impl <V: Clone> Entries<V> {
+
+    fn session(&self) -> Entries<V> {
+        Entries {
+            shared: self.shared.clone(),
+            changed: Arc::new(Mutex::new(HashMap::default()))
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<V> {
+        let changed = self.changed.lock().unwrap();
+        match (*changed).get(name) {
+            Some(entry) => entry.clone(),
+            None => self.shared.lock().unwrap().get(name).cloned()
+        }
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        let changed = self.changed.lock().unwrap();
+        match (*changed).get(name) {
+            Some(entry) => entry.is_some(),
+            None => self.shared.lock().unwrap().contains_key(name)
+        }
+    }
+
+    /// Entries as the session sees them.
+    fn all(&self) -> HashMap<String, V> {
+        let changed = self.changed.lock().unwrap();
+        let mut all = self.shared.lock().unwrap().clone();
+        for (name, entry) in (*changed).iter() {
+            match entry {
+                Some(value) => all.insert(name.clone(), value.clone()),
+                None => all.remove(name)
+            };
+        }
+        all
+    }
+
+    /// In a transaction a copy of the entry is changed, returns the change
+    /// of the entry made by the transaction before.
+    fn change<F: FnOnce(&mut Option<V>)>(&self, name: &str, in_transaction: bool, change: F) -> Option<Option<V>> {
+        if !in_transaction {
+            apply(&mut self.shared.lock().unwrap(), name, change);
+            return None;
+        }
+        let mut changed = self.changed.lock().unwrap();
+        let previous = (*changed).get(name).cloned();
+        let mut entry = match previous.clone() {
+            Some(entry) => entry,
+            None => self.shared.lock().unwrap().get(name).cloned()
+        };
+        change(&mut entry);
+        (*changed).insert(name.to_owned(), entry);
+        previous
+    }
+
+    /// Changes the entry in place where the session sees it.
+    fn with<R, F: FnOnce(Option<&mut V>) -> R>(&self, name: &str, f: F) -> R {
+        let mut changed = self.changed.lock().unwrap();
+        match (*changed).get_mut(name) {
+            Some(entry) => f(entry.as_mut()),
+            None => f(self.shared.lock().unwrap().get_mut(name))
+        }
+    }
+
+    fn restore(&self, temporary: &Mutex<HashMap<String, V>>, name: String, previous: Previous<V>) {
+        match previous {
+            Previous::Temporary(previous) => restore(temporary, name, previous),
+            Previous::Changed(previous) => restore(&self.changed, name, previous)
+        }
+    }
+
+    fn publish(&self) {
+        let mut changed = self.changed.lock().unwrap();
+        let mut shared = self.shared.lock().unwrap();
+        for (name, entry) in (*changed).drain() {
+            match entry {
+                Some(value) => (*shared).insert(name, value),
+                None => (*shared).remove(name.as_str())
+            };
+        }
+    }
+
+    fn discard(&self) {
+        self.changed.lock().unwrap().clear();
+    }
+}
+
+fn apply<V, F: FnOnce(&mut Option<V>)>(map: &mut HashMap<String, V>, name: &str, change: F) {
+    let mut entry = map.remove(name);
+    change(&mut entry);
+    if let Some(value) = entry {
+        map.insert(name.to_owned(), value);
+    }
+}
+
+fn restore<V>(map: &Mutex<HashMap<String, V>>, name: String, previous: Option<V>) {
+    let mut guard = map.lock().unwrap();
+    match previous {
+        Some(value) => (*guard).insert(name, value),
+        None => (*guard).remove(name.as_str())
+    };
+    drop(guard);
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+
+use super::ast::Type;
+use super::ast::insert_query::Value;
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+pub enum Datum {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Character(String)
+}
+
+impl Datum {
+
+    pub fn from_value(value: &Value) -> Result<Datum, String> {
+        Datum::parse(value.val.as_str(), value.val_type)
+    }
+
+    pub fn parse(val: &str, val_type: Type) -> Result<Datum, String> {
+        match val_type {
+            Type::Integer => match val.parse::<i64>() {
+                Ok(v) => Ok(Datum::Integer(v)),
+                Err(_) => Err(format!("invalid input for integer: '{}'", val))
+            },
+            Type::Character(_) => Ok(Datum::Character(val.into())),
+            Type::Boolean => match val {
+                "true" => Ok(Datum::Boolean(true)),
+                "false" => Ok(Datum::Boolean(false)),
+                _ => Err(format!("invalid input for boolean: '{}'", val))
+            },
+            Type::Unknown => Ok(Datum::Null)
+        }
+    }
+
+    /// Converts the value to `target`. Text is truncated to the size of a
+    /// sized `CHARACTER` type, as explicit casts do in SQL.
+    pub fn cast(self, target: Type) -> Result<Datum, String> {
+        match (self, target) {
+            (Datum::Null, _) => Ok(Datum::Null),
+            (Datum::Integer(v), Type::Integer) => Ok(Datum::Integer(v)),
+            (Datum::Integer(v), Type::Boolean) => Ok(Datum::Boolean(v != 0)),
+            (Datum::Boolean(v), Type::Boolean) => Ok(Datum::Boolean(v)),
+            (Datum::Boolean(v), Type::Integer) => Ok(Datum::Integer(v as i64)),
+            (Datum::Character(s), Type::Integer) => match s.trim().parse::<i64>() {
+                Ok(v) => Ok(Datum::Integer(v)),
+                Err(_) => Err(format!("invalid input syntax for type integer: '{}'", s))
+            },
+            (Datum::Character(s), Type::Boolean) => match s.trim().to_lowercase().as_str() {
+                "true" | "t" | "yes" | "y" | "on" | "1" => Ok(Datum::Boolean(true)),
+                "false" | "f" | "no" | "n" | "off" | "0" => Ok(Datum::Boolean(false)),
+                _ => Err(format!("invalid input syntax for type boolean: '{}'", s))
+            },
+            (value, Type::Character(size)) => {
+                let text = value.to_string();
+                Ok(Datum::Character(
+                    match size {
+                        Some(size) => text.chars().take(size as usize).collect(),
+                        None => text
+                    }
+                ))
+            },
+            (value, Type::Unknown) => Ok(value)
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == Datum::Null
+    }
+}
+
+impl fmt::Display for Datum {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Datum::Null => write!(f, "NULL"),
+            Datum::Boolean(v) => write!(f, "{}", v),
+            Datum::Integer(v) => write!(f, "{}", v),
+            Datum::Character(ref s) => write!(f, "{}", s)
+        }
+    }
+}
+
+impl fmt::Debug for Datum {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Datum::Null => write!(f, "NULL"),
+            _ => write!(f, "{:?}", self.to_string())
+        }
+    }
+}
+
+impl From<String> for Datum {
+    fn from(val: String) -> Datum {
+        Datum::Character(val)
+    }
+}
+
+impl<'s> PartialEq<&'s str> for Datum {
+    fn eq(&self, other: &&'s str) -> bool {
+        match *self {
+            Datum::Null => false,
+            Datum::Boolean(v) => other.parse::<bool>() == Ok(v),
+            Datum::Integer(v) => other.parse::<i64>() == Ok(v),
+            Datum::Character(ref s) => s == other
+        }
+    }
+}
+
+impl PartialEq<String> for Datum {
+    fn eq(&self, other: &String) -> bool {
+        *self == other.as_str()
+    }
+}
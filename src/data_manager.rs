@@ -1,99 +1,1212 @@
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use std::vec;
 
+use super::ast::select_query::{LockStrength, LockWait};
+use super::ast::transaction::IsolationLevel;
+use super::data::Datum;
+use super::lock_manager::{LockManager, DEADLOCK};
+use super::storage::{Storage, TupleId};
+use super::transaction::{UndoLog, Transactions, TransactionId, Snapshot, FROZEN, CONCURRENT_UPDATE};
+use super::wal::DEFAULT_WAL_SIZE_LIMIT;
+
+const SCAN_BATCH_SIZE: usize = 1024;
+
+/// Statements that defined the catalog of a database file, tables and
+/// views are defined again by running them when the file is opened.
+const DEFINITIONS: &str = "$definitions";
+
+/// created, expired and row id of a version stored in a page
+const TUPLE_HEADER_SIZE: usize = 24;
+const NOT_EXPIRED: u64 = u64::MAX;
+
+/// Versions of a row from the oldest one, a row keeps its position in the
+/// table even when none of its versions is visible.
+type Versions = Vec<Version>;
+
+/// Position of a row in its table, it doesn't change while the row lives.
+pub type RowId = usize;
+
+/// Rows of a scanned batch and the position to continue the scan from.
+type Batch = (Vec<(RowId, Vec<Datum>)>, usize);
+
+/// Indexes of unique keys of tables by table and key name.
+type KeyIndexes = HashMap<String, HashMap<String, KeyIndex>>;
+
+/// Rows of tables are kept in memory unless the data manager is opened on a
+/// database file, rows of temporary tables are always kept in memory.
 #[derive(Debug, Clone)]
 pub struct DataManager {
-    data: Arc<Mutex<HashMap<String, Vec<Vec<String>>>>>
+    data: Arc<RwLock<HashMap<String, Vec<Versions>>>>,
+    /// rows of temporary tables of a session
+    temporary: Arc<RwLock<HashMap<String, Vec<Versions>>>>,
+    keys: Arc<RwLock<KeyIndexes>>,
+    /// indexes of unique keys of temporary tables of a session
+    temporary_keys: Arc<RwLock<KeyIndexes>>,
+    /// memory limit of statements of a session
+    query_memory_limit: Arc<Mutex<Option<usize>>>,
+    /// sorted runs written to temporary files by queries of a session
+    spilled_runs: Arc<Mutex<usize>>,
+    lock_timeout: Option<Duration>,
+    /// size of the log that triggers a checkpoint
+    wal_size_limit: u64,
+    /// transactions of all sessions
+    transactions: Arc<Mutex<Transactions>>,
+    locks: Arc<LockManager>,
+    storage: Option<Arc<Storage>>,
+    /// open transaction of a session
+    transaction: Arc<Mutex<Option<OpenTransaction>>>
+}
+
+#[derive(Debug, Clone)]
+struct Version {
+    created: TransactionId,
+    /// transaction that updated or deleted the row
+    expired: Option<TransactionId>,
+    tuple: Tuple
+}
+
+/// Rows of a table with versions that had the hash of values of a unique
+/// key. It is built on the first lookup of the key and kept up to date by
+/// writes, rows found in it are checked against their versions.
+#[derive(Debug)]
+struct KeyIndex {
+    columns: Vec<usize>,
+    rows: HashMap<u64, Vec<RowId>>
+}
+
+impl KeyIndex {
+
+    fn add(&mut self, row_id: RowId, row: &[Datum]) {
+        if let Some(values) = key_values(row, &self.columns) {
+            let row_ids = self.rows.entry(hash_of(&values)).or_default();
+            if !row_ids.contains(&row_id) {
+                row_ids.push(row_id);
+            }
+        }
+    }
+}
+
+/// Values of a version, versions stored in pages of a database file are
+/// read from them every time they are needed.
+#[derive(Debug, Clone)]
+enum Tuple {
+    InMemory(Vec<Datum>),
+    OnPage(TupleId)
+}
+
+#[derive(Debug)]
+struct OpenTransaction {
+    id: TransactionId,
+    isolation: IsolationLevel,
+    snapshot: Option<Snapshot>,
+    /// a write conflicted with a concurrent transaction, the transaction
+    /// can only be rolled back
+    failed: bool,
+    /// some of its changes could not be undone by a rollback to a
+    /// savepoint, the transaction can't commit anymore
+    undo_failed: bool,
+    undo: UndoLog<DataUndo>,
+    /// tables that are not temporary, reads are tracked for serializable
+    /// transactions only
+    reads: HashSet<String>,
+    writes: HashSet<String>
+}
+
+/// How to bring rows of a table back to the state before a change made in
+/// a transaction.
+#[derive(Debug)]
+enum DataUndo {
+    Inserted { table_name: String, row_id: usize },
+    Updated { table_name: String, row_ids: Vec<usize> },
+    Deleted { table_name: String, row_ids: Vec<usize> },
+    Table { table_name: String, temporary: bool, previous: Option<Vec<Versions>> }
 }
 
 impl Default for DataManager {
     fn default() -> Self {
         DataManager {
-            data: Arc::new(Mutex::new(HashMap::default()))
+            data: Arc::new(RwLock::new(HashMap::default())),
+            temporary: Arc::new(RwLock::new(HashMap::default())),
+            keys: Arc::new(RwLock::new(HashMap::default())),
+            temporary_keys: Arc::new(RwLock::new(HashMap::default())),
+            query_memory_limit: Arc::new(Mutex::new(None)),
+            spilled_runs: Arc::new(Mutex::new(0)),
+            lock_timeout: None,
+            wal_size_limit: DEFAULT_WAL_SIZE_LIMIT,
+            transactions: Arc::new(Mutex::new(Transactions::default())),
+            locks: Arc::new(LockManager::default()),
+            storage: None,
+            transaction: Arc::new(Mutex::new(None))
         }
     }
 }
 
 impl DataManager {
-    pub fn save_to<I, D>(&self, table_name: I, data: D)
-        where I: Into<String>,
-              D: IntoIterator<Item = String> {
-        let mut guard = self.data.lock().unwrap();
-        (*guard).entry(table_name.into())
-            .or_insert_with(Vec::default)
-            .push(
-                data.into_iter().collect::<Vec<String>>()
-            );
+    /// Keeps rows of tables in the database file, it is created when it
+    /// doesn't exist. Versions left in the file by transactions that didn't
+    /// commit before a crash are removed, the rest become frozen.
+    pub fn open<P: AsRef<Path>>(path: P, buffer_pool_pages: usize) -> Result<DataManager, String> {
+        let storage = Storage::open(path, buffer_pool_pages)?;
+        let mut data = HashMap::default();
+        for table_name in storage.tables() {
+            let mut live = vec![];
+            for (tuple_id, tuple) in storage.tuples(table_name.as_str())? {
+                let (created, expired, row_id) = decode_header(&tuple)?;
+                let removed = expired.is_some_and(|expired| storage.is_committed(expired));
+                if removed || !storage.is_committed(created) {
+                    storage.delete(tuple_id)?;
+                } else {
+                    live.push((row_id, created, expired, tuple_id));
+                }
+            }
+            live.sort_by_key(|&(row_id, _, _, _)| row_id);
+            let mut rows = vec![];
+            for (row_id, (stored_id, created, expired, tuple_id)) in live.into_iter().enumerate() {
+                if stored_id != row_id || created != FROZEN || expired.is_some() {
+                    storage.write(tuple_id, 0, &encode_header(FROZEN, None, row_id))?;
+                }
+                rows.push(vec![Version { created: FROZEN, expired: None, tuple: Tuple::OnPage(tuple_id) }]);
+            }
+            data.insert(table_name, rows);
+        }
+        storage.checkpoint(FROZEN + 1, &[])?;
+        Ok(DataManager {
+            data: Arc::new(RwLock::new(data)),
+            storage: Some(Arc::new(storage)),
+            ..DataManager::default()
+        })
+    }
+
+    pub fn is_durable(&self) -> bool {
+        self.storage.is_some()
+    }
+
+    /// Writes changed pages to the database file, so the log can start
+    /// over. It is done by commits when the log grows bigger than its limit.
+    pub fn checkpoint(&self) -> Result<(), String> {
+        let transactions = self.transactions.lock().unwrap();
+        let done = self.checkpoint_with(&transactions);
+        drop(transactions);
+        done
+    }
+
+    fn checkpoint_with(&self, transactions: &Transactions) -> Result<(), String> {
+        match self.storage {
+            Some(ref storage) => {
+                let (next, active) = transactions.in_progress();
+                storage.checkpoint(next, &active)
+            },
+            None => Ok(())
+        }
+    }
+
+    /// Limits how many bytes the log of the database file may take before a
+    /// checkpoint.
+    pub fn with_wal_size_limit(mut self, bytes: u64) -> DataManager {
+        self.wal_size_limit = bytes;
+        self
+    }
+
+    /// Changes how many pages of the database file are kept in memory.
+    pub fn resize_buffer_pool(&self, pages: usize) -> Result<(), String> {
+        match self.storage {
+            Some(ref storage) => storage.resize_buffer_pool(pages),
+            None => Ok(())
+        }
+    }
+
+    /// Keeps the statement that changed the catalog together with the rows
+    /// changed by the same transaction.
+    pub fn remember_definition(&self, definition: &str) -> Result<(), String> {
+        if self.is_durable() {
+            self.save_to(DEFINITIONS, vec![Datum::Character(definition.to_owned())])
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Statements that defined the catalog in the order they were run.
+    pub fn definitions(&self) -> Result<Vec<String>, String> {
+        let mut definitions = vec![];
+        for row in self.scan(DEFINITIONS) {
+            if let Some(Datum::Character(definition)) = row?.into_iter().next() {
+                definitions.push(definition);
+            }
+        }
+        Ok(definitions)
+    }
+
+    /// Sequences hand out values outside of transactions, their last values
+    /// are saved before rows that use them are committed.
+    pub fn save_sequences(&self, sequences: &[(String, i64)]) -> Result<(), String> {
+        if let Some(ref storage) = self.storage {
+            for &(ref name, last_value) in sequences {
+                storage.save_sequence(name.as_str(), last_value)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn sequences(&self) -> Vec<(String, i64)> {
+        self.storage.as_ref().map(|storage| storage.sequences()).unwrap_or_default()
+    }
+
+    /// Limits how many bytes of rows a single query may buffer for sorting
+    /// before it starts spilling them to temporary files. Sessions start
+    /// with this limit.
+    pub fn with_query_memory_limit(mut self, bytes: usize) -> DataManager {
+        self.query_memory_limit = Arc::new(Mutex::new(Some(bytes)));
+        self
+    }
+
+    /// Changes the limit for the following statements of the session.
+    pub fn set_query_memory_limit(&self, bytes: Option<usize>) {
+        *self.query_memory_limit.lock().unwrap() = bytes;
+    }
+
+    pub fn query_memory_limit(&self) -> Option<usize> {
+        *self.query_memory_limit.lock().unwrap()
+    }
+
+    /// Number of runs queries of the session spilled to disk so far.
+    pub fn spilled_runs(&self) -> usize {
+        *self.spilled_runs.lock().unwrap()
+    }
+
+    pub fn add_spilled_runs(&self, runs: usize) {
+        *self.spilled_runs.lock().unwrap() += runs;
+    }
+
+    /// Limits how long a statement waits for a lock of a row, by default it
+    /// waits until the lock is released or a deadlock is detected.
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> DataManager {
+        self.lock_timeout = Some(timeout);
+        self
+    }
+
+    /// Shares rows of tables with `self` but keeps rows of temporary tables
+    /// to itself, they are gone when the last clone of it is dropped.
+    pub fn session(&self) -> DataManager {
+        DataManager {
+            temporary: Arc::new(RwLock::new(HashMap::default())),
+            temporary_keys: Arc::new(RwLock::new(HashMap::default())),
+            transaction: Arc::new(Mutex::new(None)),
+            query_memory_limit: Arc::new(Mutex::new(self.query_memory_limit())),
+            spilled_runs: Arc::new(Mutex::new(0)),
+            ..self.clone()
+        }
+    }
+
+    pub fn create_temporary<I: Into<String>>(&self, table_name: I) {
+        let table_name = table_name.into();
+        let mut guard = self.temporary.write().unwrap();
+        let previous = (*guard).insert(table_name.clone(), vec![]);
+        self.forget_keys(true, table_name.as_str());
         drop(guard);
+        self.record(|| DataUndo::Table { table_name, temporary: true, previous });
     }
 
-    pub fn get_row_from(&self, table_name: &str, row_id: usize) -> Vec<String> {
-        let guard = self.data.lock().unwrap();
-        let result = match (*guard).get(table_name) {
-            None => vec![],
-            Some(table_data) => {
-                match table_data.into_iter().nth(row_id) {
-                    None => vec![],
-                    Some(vec) => vec.iter().cloned().collect::<Vec<String>>()
+    /// Starts recording changes of rows so that they can be undone, the
+    /// changes are seen by other sessions after the transaction commits.
+    pub fn begin(&self, isolation: IsolationLevel) {
+        let mut transactions = self.transactions.lock().unwrap();
+        let id = (*transactions).start(isolation == IsolationLevel::Serializable);
+        let snapshot = if isolation == IsolationLevel::Snapshot {
+            Some((*transactions).snapshot(id))
+        } else {
+            None
+        };
+        drop(transactions);
+        let mut guard = self.transaction.lock().unwrap();
+        *guard = Some(OpenTransaction { id, isolation, snapshot, failed: false, undo_failed: false, undo: UndoLog::default(), reads: HashSet::default(), writes: HashSet::default() });
+        drop(guard);
+    }
+
+    /// Takes the snapshot that a statement of the open transaction reads
+    /// rows from.
+    pub fn begin_statement(&self) {
+        let mut guard = self.transaction.lock().unwrap();
+        if let Some(transaction) = (*guard).as_mut() {
+            if transaction.isolation == IsolationLevel::ReadCommitted || transaction.snapshot.is_none() {
+                transaction.snapshot = Some(self.transactions.lock().unwrap().snapshot(transaction.id));
+            }
+        }
+        drop(guard);
+    }
+
+    pub fn in_transaction(&self) -> bool {
+        let guard = self.transaction.lock().unwrap();
+        let r = (*guard).is_some();
+        drop(guard);
+        r
+    }
+
+    /// The open transaction conflicted with a concurrent one and has to be
+    /// rolled back.
+    pub fn is_failed(&self) -> bool {
+        let guard = self.transaction.lock().unwrap();
+        let r = (*guard).as_ref().is_some_and(|transaction| transaction.failed);
+        drop(guard);
+        r
+    }
+
+    /// Rolls back a serializable transaction that can't be serialized.
+    /// Changes are logged before other transactions see them.
+    pub fn commit(&self) -> Result<(), String> {
+        let mut guard = self.transaction.lock().unwrap();
+        let transaction = (*guard).take();
+        drop(guard);
+        let transaction = match transaction {
+            Some(transaction) => transaction,
+            None => return Ok(())
+        };
+        let mut transactions = self.transactions.lock().unwrap();
+        let validated = match transaction.snapshot {
+            Some(ref snapshot) if transaction.isolation == IsolationLevel::Serializable && !transaction.writes.is_empty() =>
+                (*transactions).validate(snapshot, &transaction.reads),
+            _ => Ok(())
+        };
+        let id = transaction.id;
+        let validated = validated.and_then(|_| match self.storage {
+            Some(ref storage) => {
+                if storage.wal_size() > self.wal_size_limit {
+                    self.checkpoint_with(&transactions)?;
                 }
+                storage.commit(id, !transaction.writes.is_empty())
             },
-        };
+            None => Ok(())
+        });
+        if validated.is_ok() {
+            (*transactions).commit(id, transaction.writes);
+            drop(transactions);
+        } else {
+            drop(transactions);
+            let mut undo = transaction.undo;
+            let undone = self.undo(undo.rollback());
+            self.end_rolled_back(id, undone.is_ok() && !transaction.undo_failed);
+            return undone.and(validated);
+        }
+        self.locks.release_all(id);
+        validated
+    }
+
+    /// The transaction ends even when its changes could not be undone in
+    /// the database file, versions it left there are never visible and are
+    /// removed when the file is opened again.
+    pub fn rollback(&self) -> Result<(), String> {
+        let mut guard = self.transaction.lock().unwrap();
+        let transaction = (*guard).take();
         drop(guard);
-        result
+        match transaction {
+            Some(mut transaction) => {
+                let undone = self.undo(transaction.undo.rollback());
+                self.end_rolled_back(transaction.id, undone.is_ok() && !transaction.undo_failed);
+                undone
+            },
+            None => Ok(())
+        }
     }
 
-    pub fn get_range(&self, table_name: &str, start_from: usize, number_of_rows: usize) -> Vec<Vec<String>> {
-        let guard = self.data.lock().unwrap();
-        let result = match (*guard).get(table_name) {
-            None => vec![],
-            Some(table_data) =>
-                table_data.into_iter()
-                    .skip(start_from)
-                    .take(number_of_rows)
-                    .cloned()
-                    .collect::<Vec<Vec<String>>>(),
+    /// A transaction whose changes were not all undone is aborted instead
+    /// of finished, so that it is never seen as committed, not even by
+    /// recovery after a checkpoint.
+    fn end_rolled_back(&self, id: TransactionId, undone: bool) {
+        let mut transactions = self.transactions.lock().unwrap();
+        if undone {
+            (*transactions).finish(id);
+        } else {
+            (*transactions).abort(id);
+        }
+        drop(transactions);
+        self.locks.release_all(id);
+    }
+
+    pub fn savepoint(&self, name: &str) -> Result<(), String> {
+        let mut guard = self.transaction.lock().unwrap();
+        match (*guard).as_mut() {
+            Some(transaction) => {
+                transaction.undo.savepoint(name);
+                Ok(())
+            },
+            None => Err("SAVEPOINT can only be used in transaction blocks".into())
+        }
+    }
+
+    pub fn rollback_to(&self, name: &str) -> Result<(), String> {
+        let mut guard = self.transaction.lock().unwrap();
+        let undone = match (*guard).as_mut() {
+            Some(transaction) => {
+                let undone = transaction.undo.rollback_to(name)?;
+                transaction.failed = transaction.undo_failed;
+                undone
+            },
+            None => return Err("ROLLBACK TO SAVEPOINT can only be used in transaction blocks".into())
         };
         drop(guard);
+        self.undo(undone).inspect_err(|_| {
+            let mut guard = self.transaction.lock().unwrap();
+            if let Some(transaction) = (*guard).as_mut() {
+                transaction.failed = true;
+                transaction.undo_failed = true;
+            }
+            drop(guard);
+        })
+    }
+
+    pub fn release(&self, name: &str) -> Result<(), String> {
+        let mut guard = self.transaction.lock().unwrap();
+        match (*guard).as_mut() {
+            Some(transaction) => transaction.undo.release(name),
+            None => Err("RELEASE SAVEPOINT can only be used in transaction blocks".into())
+        }
+    }
+
+    /// Versions written by the transaction are always the newest ones of
+    /// their rows, concurrent writers of the same rows fail. Entries after
+    /// one that could not be undone are still undone.
+    fn undo(&self, undone: Vec<DataUndo>) -> Result<(), String> {
+        let mut result = Ok(());
+        for entry in undone {
+            if let Err(error) = self.undo_entry(entry) {
+                result = result.and(Err(format!("could not undo changes of rows in the database file: {}", error)));
+            }
+        }
         result
     }
 
-    pub fn get_range_till_end(&self, table_name: &str, start_from: usize) -> Vec<Vec<String>> {
-        let guard = self.data.lock().unwrap();
-        let result = match (*guard).get(table_name) {
-            None => unimplemented!(),
-            Some(table_data) =>
-                table_data.into_iter()
-                    .skip(start_from)
-                    .cloned()
-                    .collect::<Vec<Vec<String>>>(),
+    /// Rows in memory are always brought back, the first error of changing
+    /// their tuples in the database file is returned after that.
+    fn undo_entry(&self, entry: DataUndo) -> Result<(), String> {
+        let mut result = Ok(());
+        match entry {
+            DataUndo::Inserted { table_name, row_id } => {
+                let mut guard = self.data_of(table_name.as_str()).write().unwrap();
+                if let Some(version) = (*guard).get_mut(table_name.as_str()).and_then(|rows| rows.get_mut(row_id)).and_then(|versions| versions.pop()) {
+                    result = self.remove(&version.tuple);
+                }
+                drop(guard);
+            },
+            DataUndo::Updated { table_name, row_ids } => {
+                let mut guard = self.data_of(table_name.as_str()).write().unwrap();
+                if let Some(rows) = (*guard).get_mut(table_name.as_str()) {
+                    for row_id in row_ids {
+                        if let Some(version) = rows[row_id].pop() {
+                            result = result.and(self.remove(&version.tuple));
+                        }
+                        if let Some(version) = rows[row_id].last_mut() {
+                            result = result.and(self.expire(version, None));
+                        }
+                    }
+                }
+                drop(guard);
+            },
+            DataUndo::Deleted { table_name, row_ids } => {
+                let mut guard = self.data_of(table_name.as_str()).write().unwrap();
+                if let Some(rows) = (*guard).get_mut(table_name.as_str()) {
+                    for row_id in row_ids {
+                        if let Some(version) = rows[row_id].last_mut() {
+                            result = result.and(self.expire(version, None));
+                        }
+                    }
+                }
+                drop(guard);
+            },
+            DataUndo::Table { table_name, temporary, previous } => {
+                let map = if temporary { &self.temporary } else { &self.data };
+                let mut guard = map.write().unwrap();
+                self.forget_keys(temporary, table_name.as_str());
+                match previous {
+                    Some(rows) => {
+                        for version in rows.iter().flat_map(|versions| versions.iter()) {
+                            result = result.and(self.write_expired(&version.tuple, version.expired));
+                        }
+                        (*guard).insert(table_name, rows)
+                    },
+                    None => (*guard).remove(table_name.as_str())
+                };
+                drop(guard);
+            }
+        }
+        result
+    }
+
+    /// Stores values of a new version of a row.
+    fn store(&self, table_name: &str, created: TransactionId, row_id: RowId, row: Vec<Datum>) -> Result<Tuple, String> {
+        match self.storage {
+            Some(ref storage) if !self.is_temporary(table_name) => {
+                let mut tuple = encode_header(created, None, row_id);
+                encode_row(&row, &mut tuple);
+                storage.insert(table_name, &tuple).map(Tuple::OnPage)
+            },
+            _ => Ok(Tuple::InMemory(row))
+        }
+    }
+
+    fn values(&self, tuple: &Tuple) -> Result<Vec<Datum>, String> {
+        match *tuple {
+            Tuple::InMemory(ref row) => Ok(row.clone()),
+            Tuple::OnPage(tuple_id) => match self.storage {
+                Some(ref storage) => decode_row(&storage.read(tuple_id)?[TUPLE_HEADER_SIZE..]),
+                None => Err(format!("tuple {:?} is not in memory", tuple_id))
+            }
+        }
+    }
+
+    fn expire(&self, version: &mut Version, expired: Option<TransactionId>) -> Result<(), String> {
+        version.expired = expired;
+        self.write_expired(&version.tuple, expired)
+    }
+
+    fn write_expired(&self, tuple: &Tuple, expired: Option<TransactionId>) -> Result<(), String> {
+        match (tuple, self.storage.as_ref()) {
+            (&Tuple::OnPage(tuple_id), Some(storage)) => storage.write(tuple_id, 8, &expired.unwrap_or(NOT_EXPIRED).to_le_bytes()),
+            _ => Ok(())
+        }
+    }
+
+    fn remove(&self, tuple: &Tuple) -> Result<(), String> {
+        match (tuple, self.storage.as_ref()) {
+            (&Tuple::OnPage(tuple_id), Some(storage)) => storage.delete(tuple_id),
+            _ => Ok(())
+        }
+    }
+
+    fn record<F: FnOnce() -> DataUndo>(&self, entry: F) {
+        let mut guard = self.transaction.lock().unwrap();
+        if let Some(transaction) = (*guard).as_mut() {
+            transaction.undo.record(entry());
+        }
+        drop(guard);
+    }
+
+    /// Snapshot of the current statement, a session outside of a
+    /// transaction sees everything committed so far.
+    fn snapshot(&self) -> Snapshot {
+        let guard = self.transaction.lock().unwrap();
+        let (own, snapshot) = match (*guard).as_ref() {
+            Some(transaction) => (transaction.id, transaction.snapshot.clone()),
+            None => (FROZEN, None)
         };
-        println!("result - {:?}", result);
         drop(guard);
-        result
+        snapshot.unwrap_or_else(|| self.transactions.lock().unwrap().snapshot(own))
     }
 
-    pub fn get_range_till_end_for_column(&self, table_name: &str, column_index: usize, number_of_columns: usize) -> Vec<Vec<String>> {
-        let guard = self.data.lock().unwrap();
-        let result = match (*guard).get(table_name) {
-            None => unimplemented!(),
-            Some(table_data) => {
-                table_data.into_iter().map(|row| row.into_iter().skip(column_index).take(number_of_columns).cloned().collect::<Vec<String>>()).collect::<Vec<Vec<String>>>()
+    fn own_id(&self) -> TransactionId {
+        let guard = self.transaction.lock().unwrap();
+        let id = (*guard).as_ref().map_or(FROZEN, |transaction| transaction.id);
+        drop(guard);
+        id
+    }
+
+    /// Fails the open transaction, it conflicted with a concurrent one.
+    pub fn conflict(&self) -> String {
+        let mut guard = self.transaction.lock().unwrap();
+        if let Some(transaction) = (*guard).as_mut() {
+            transaction.failed = true;
+        }
+        drop(guard);
+        CONCURRENT_UPDATE.into()
+    }
+
+    fn track_read(&self, table_name: &str) {
+        if self.is_temporary(table_name) {
+            return;
+        }
+        let mut guard = self.transaction.lock().unwrap();
+        if let Some(transaction) = (*guard).as_mut() {
+            if transaction.isolation == IsolationLevel::Serializable {
+                transaction.reads.insert(table_name.to_owned());
+            }
+        }
+        drop(guard);
+    }
+
+    fn track_write(&self, table_name: &str) {
+        if self.is_temporary(table_name) {
+            return;
+        }
+        let mut guard = self.transaction.lock().unwrap();
+        if let Some(transaction) = (*guard).as_mut() {
+            transaction.writes.insert(table_name.to_owned());
+        }
+        drop(guard);
+    }
+
+    fn is_temporary(&self, table_name: &str) -> bool {
+        let guard = self.temporary.read().unwrap();
+        let temporary = (*guard).contains_key(table_name);
+        drop(guard);
+        temporary
+    }
+
+    /// Temporary table hides a table with the same name.
+    fn data_of(&self, table_name: &str) -> &RwLock<HashMap<String, Vec<Versions>>> {
+        if self.is_temporary(table_name) {
+            &self.temporary
+        } else {
+            &self.data
+        }
+    }
+
+    /// Iterates over rows of the table fetching them in batches, so the whole
+    /// table is never copied at once.
+    pub fn scan<I: Into<String>>(&self, table_name: I) -> TableScan {
+        TableScan { rows: self.scan_with_row_ids(table_name) }
+    }
+
+    /// Same as `scan` but every row comes with its id.
+    pub fn scan_with_row_ids<I: Into<String>>(&self, table_name: I) -> RowScan {
+        let table_name = table_name.into();
+        self.track_read(table_name.as_str());
+        RowScan {
+            data_manager: self.clone(),
+            table_name,
+            snapshot: self.snapshot(),
+            position: 0,
+            batch: vec![].into_iter()
+        }
+    }
+
+    /// Up to `limit` rows seen by the snapshot starting from the row at
+    /// `position` and the position of the row after the last of them.
+    fn scan_batch(&self, table_name: &str, snapshot: &Snapshot, position: usize, limit: usize) -> Result<Batch, String> {
+        let guard = self.data_of(table_name).read().unwrap();
+        let mut batch = vec![];
+        let mut next = position;
+        if let Some(rows) = (*guard).get(table_name) {
+            for versions in rows.iter().skip(position) {
+                if batch.len() == limit {
+                    break;
+                }
+                if let Some(version) = visible_version(versions, snapshot) {
+                    batch.push((next, self.values(&versions[version].tuple)?));
+                }
+                next += 1;
+            }
+        }
+        drop(guard);
+        Ok((batch, next))
+    }
+
+    /// Locks the row until the end of the transaction. Returns `false` when
+    /// the row is skipped because it is locked by another transaction or
+    /// was changed since the snapshot was taken.
+    pub fn lock_row(&self, table_name: &str, row_id: RowId, strength: LockStrength, wait: LockWait) -> Result<bool, String> {
+        let acquired = self.lock(table_name, row_id, strength == LockStrength::Update, wait == LockWait::Wait)?;
+        if !acquired {
+            return match wait {
+                LockWait::SkipLocked => Ok(false),
+                _ => Err(format!("could not obtain lock on row in relation \"{}\"", table_name))
+            };
+        }
+        let snapshot = self.snapshot();
+        let guard = self.data_of(table_name).read().unwrap();
+        let latest = (*guard).get(table_name)
+            .and_then(|rows| rows.get(row_id))
+            .map(|versions| visible_version(versions, &snapshot).map(|version| is_latest(versions, version)));
+        drop(guard);
+        match latest {
+            Some(Some(true)) => Ok(true),
+            Some(Some(false)) if wait != LockWait::SkipLocked => Err(self.conflict()),
+            _ => Ok(false)
+        }
+    }
+
+    /// Rows of temporary tables and rows changed outside of transactions
+    /// are never locked. A deadlock fails the open transaction.
+    fn lock(&self, table_name: &str, row_id: RowId, exclusive: bool, wait: bool) -> Result<bool, String> {
+        let id = self.own_id();
+        if id == FROZEN || self.is_temporary(table_name) {
+            return Ok(true);
+        }
+        match self.locks.acquire(id, (table_name.to_owned(), row_id), exclusive, wait, self.lock_timeout) {
+            Err(ref error) if error == DEADLOCK => {
+                self.conflict();
+                Err(DEADLOCK.into())
             },
+            acquired => acquired
+        }
+    }
+
+    /// Locks values of a unique key of the table until the end of the
+    /// transaction, so that one transaction at a time writes rows with them.
+    /// Values are locked by their hash, different values that share it only
+    /// wait for each other.
+    pub fn lock_key(&self, table_name: &str, key_name: &str, values: &[Datum]) -> Result<(), String> {
+        if self.is_temporary(table_name) {
+            return Ok(());
+        }
+        self.lock(format!("{}.{}", table_name, key_name).as_str(), hash_of(values) as RowId, true, true).map(|_| ())
+    }
+
+    /// Row that the current statement sees with `values` of the unique key
+    /// made of `columns`, it is looked up in the index of the key.
+    pub fn find_key(&self, table_name: &str, key_name: &str, columns: &[usize], values: &[Datum]) -> Result<Option<(RowId, Vec<Datum>)>, String> {
+        self.track_read(table_name);
+        let snapshot = self.snapshot();
+        self.find_by_key(table_name, key_name, columns, values, |versions| visible_version(versions, &snapshot))
+    }
+
+    /// Fails the open transaction when a row with `values` of the unique key
+    /// made of `columns` isn't seen by the current statement because a
+    /// transaction in progress wrote it or one that committed after the
+    /// snapshot was taken.
+    pub fn check_unseen_key(&self, table_name: &str, key_name: &str, columns: &[usize], values: &[Datum]) -> Result<(), String> {
+        let snapshot = self.snapshot();
+        let committed = self.transactions.lock().unwrap().snapshot(snapshot.own());
+        let unseen = self.find_by_key(
+            table_name,
+            key_name,
+            columns,
+            values,
+            |versions| {
+                let version = versions.last()?;
+                let deleted = version.expired.is_some_and(|expired| expired == version.created || committed.sees(expired));
+                if snapshot.sees(version.created) || deleted {
+                    None
+                } else {
+                    Some(versions.len() - 1)
+                }
+            }
+        )?;
+        match unseen {
+            Some(_) => Err(self.conflict()),
+            None => Ok(())
+        }
+    }
+
+    /// First row with `values` of the key in the version of the row that
+    /// `version` picks.
+    fn find_by_key<F>(&self, table_name: &str, key_name: &str, columns: &[usize], values: &[Datum], version: F) -> Result<Option<(RowId, Vec<Datum>)>, String>
+        where F: Fn(&[Version]) -> Option<usize> {
+        let keys = self.keys_of(table_name);
+        let guard = self.data_of(table_name).read().unwrap();
+        let found = match (*guard).get(table_name) {
+            Some(rows) => self.key_rows(keys, table_name, key_name, columns, values, rows).and_then(
+                |row_ids| {
+                    for row_id in row_ids {
+                        if let Some(version) = version(&rows[row_id]) {
+                            let row = self.values(&rows[row_id][version].tuple)?;
+                            if key_values(&row, columns).as_deref() == Some(values) {
+                                return Ok(Some((row_id, row)));
+                            }
+                        }
+                    }
+                    Ok(None)
+                }
+            ),
+            None => Ok(None)
         };
-        println!("result - {:?}", result);
         drop(guard);
-        result
+        found
+    }
+
+    /// Ids of rows that may have versions with `values` of the key. The
+    /// index of the key is built from all versions of `rows` when it is
+    /// missing, the caller holds the lock of the rows so no write misses it.
+    fn key_rows(&self, keys: &RwLock<KeyIndexes>, table_name: &str, key_name: &str, columns: &[usize], values: &[Datum], rows: &[Versions]) -> Result<Vec<RowId>, String> {
+        let guard = keys.read().unwrap();
+        let built = (*guard).get(table_name).and_then(|indexes| indexes.get(key_name)).is_some_and(|index| index.columns == columns);
+        drop(guard);
+        if !built {
+            let mut index = KeyIndex { columns: columns.to_vec(), rows: HashMap::new() };
+            for (row_id, versions) in rows.iter().enumerate() {
+                for version in versions {
+                    index.add(row_id, &self.values(&version.tuple)?);
+                }
+            }
+            let mut guard = keys.write().unwrap();
+            (*guard).entry(table_name.to_owned()).or_default().insert(key_name.to_owned(), index);
+            drop(guard);
+        }
+        let guard = keys.read().unwrap();
+        let row_ids = (*guard).get(table_name)
+            .and_then(|indexes| indexes.get(key_name))
+            .and_then(|index| index.rows.get(&hash_of(values)))
+            .cloned()
+            .unwrap_or_default();
+        drop(guard);
+        Ok(row_ids)
+    }
+
+    /// Indexes of unique keys of the table, a temporary table hides a table
+    /// with the same name.
+    fn keys_of(&self, table_name: &str) -> &RwLock<KeyIndexes> {
+        if self.is_temporary(table_name) {
+            &self.temporary_keys
+        } else {
+            &self.keys
+        }
+    }
+
+    /// Drops indexes of unique keys of a table whose rows were replaced.
+    fn forget_keys(&self, temporary: bool, table_name: &str) {
+        let keys = if temporary { &self.temporary_keys } else { &self.keys };
+        let mut guard = keys.write().unwrap();
+        (*guard).remove(table_name);
+        drop(guard);
     }
 
-    pub fn get_not_equal(&self, table_name: &str, column_index: usize, value: &String) -> Vec<Vec<String>> {
-        let guard = self.data.lock().unwrap();
+    /// Ids of rows at the given positions among the rows that the snapshot
+    /// sees, each of them is locked for writing.
+    fn lock_for_write(&self, table_name: &str, positions: &[usize], snapshot: &Snapshot) -> Result<Vec<RowId>, String> {
+        let guard = self.data_of(table_name).read().unwrap();
+        let visible = (*guard).get(table_name).map(|rows| visible_versions(rows, snapshot)).unwrap_or_default();
+        drop(guard);
+        let row_ids = positions.iter().map(|&position| visible[position].0).collect::<Vec<RowId>>();
+        for &row_id in &row_ids {
+            self.lock(table_name, row_id, true, true)?;
+        }
+        Ok(row_ids)
+    }
+
+    fn visible_rows(&self, table_name: &str) -> Result<Vec<Vec<Datum>>, String> {
+        self.track_read(table_name);
+        let snapshot = self.snapshot();
+        let guard = self.data_of(table_name).read().unwrap();
         let result = match (*guard).get(table_name) {
-            None => unimplemented!(),
-            Some(table_data) => {
-                table_data.into_iter().filter(|v| v.get(column_index) != Some(value)).cloned().collect::<Vec<Vec<String>>>()
-            },
+            None => Ok(vec![]),
+            Some(rows) => rows.iter()
+                .filter_map(|versions| visible_version(versions, &snapshot).map(|version| self.values(&versions[version].tuple)))
+                .collect::<Result<Vec<Vec<Datum>>, String>>()
         };
         drop(guard);
         result
     }
+
+    pub fn save_to<I, D, V>(&self, table_name: I, data: D) -> Result<(), String>
+        where I: Into<String>,
+              D: IntoIterator<Item = V>,
+              V: Into<Datum> {
+        let table_name = table_name.into();
+        let created = self.own_id();
+        let keys = self.keys_of(table_name.as_str());
+        let mut guard = self.data_of(table_name.as_str()).write().unwrap();
+        let rows = (*guard).entry(table_name.clone()).or_insert_with(Vec::default);
+        let row_id = rows.len();
+        let row = data.into_iter().map(Into::into).collect::<Vec<Datum>>();
+        index_version(keys, table_name.as_str(), row_id, &row);
+        let tuple = match self.store(table_name.as_str(), created, row_id, row) {
+            Ok(tuple) => tuple,
+            Err(error) => {
+                drop(guard);
+                return Err(error);
+            }
+        };
+        rows.push(vec![Version { created, expired: None, tuple }]);
+        drop(guard);
+        self.track_write(table_name.as_str());
+        self.record(|| DataUndo::Inserted { table_name, row_id });
+        Ok(())
+    }
+
+    /// Removes all rows of the table.
+    pub fn drop_table(&self, table_name: &str) -> Result<(), String> {
+        let temporary = self.is_temporary(table_name);
+        self.track_write(table_name);
+        let mut guard = self.data_of(table_name).write().unwrap();
+        let previous = (*guard).remove(table_name);
+        self.forget_keys(temporary, table_name);
+        drop(guard);
+        let own = self.own_id();
+        for version in previous.iter().flat_map(|rows| rows.iter()).filter_map(|versions| versions.last()) {
+            if version.expired.is_none() {
+                self.write_expired(&version.tuple, Some(own))?;
+            }
+        }
+        self.record(|| DataUndo::Table { table_name: table_name.to_owned(), temporary, previous });
+        Ok(())
+    }
+
+    /// Replaces rows of the table at the given positions among the rows the
+    /// current statement sees. Fails when one of them was changed by a
+    /// transaction the statement doesn't see.
+    pub fn update_rows(&self, table_name: &str, rows: Vec<(usize, Vec<Datum>)>) -> Result<(), String> {
+        let snapshot = self.snapshot();
+        let positions = rows.iter().map(|&(position, _)| position).collect::<Vec<usize>>();
+        let row_ids = self.lock_for_write(table_name, &positions, &snapshot)?;
+        self.replace_rows(table_name, row_ids.into_iter().zip(rows.into_iter().map(|(_, row)| row)).collect(), &snapshot)
+    }
+
+    /// Same as `update_rows` for rows given by their ids.
+    pub fn update_rows_by_id(&self, table_name: &str, rows: Vec<(RowId, Vec<Datum>)>) -> Result<(), String> {
+        let snapshot = self.snapshot();
+        for &(row_id, _) in rows.iter() {
+            self.lock(table_name, row_id, true, true)?;
+        }
+        self.replace_rows(table_name, rows, &snapshot)
+    }
+
+    /// Adds new versions of locked rows.
+    fn replace_rows(&self, table_name: &str, rows: Vec<(RowId, Vec<Datum>)>, snapshot: &Snapshot) -> Result<(), String> {
+        let row_ids = rows.iter().map(|&(row_id, _)| row_id).collect::<Vec<RowId>>();
+        let keys = self.keys_of(table_name);
+        let mut guard = self.data_of(table_name).write().unwrap();
+        if let Some(table_rows) = (*guard).get_mut(table_name) {
+            let targets = match latest_versions(table_rows, &row_ids, snapshot) {
+                Some(targets) => targets,
+                None => {
+                    drop(guard);
+                    return Err(self.conflict());
+                }
+            };
+            let stored = targets.into_iter().zip(rows).try_for_each(|((row_id, version), (_, row))| {
+                self.expire(&mut table_rows[row_id][version], Some(snapshot.own()))?;
+                index_version(keys, table_name, row_id, &row);
+                let tuple = self.store(table_name, snapshot.own(), row_id, row)?;
+                table_rows[row_id].push(Version { created: snapshot.own(), expired: None, tuple });
+                Ok(())
+            });
+            if let Err(error) = stored {
+                drop(guard);
+                return Err(error);
+            }
+        }
+        drop(guard);
+        if !row_ids.is_empty() {
+            self.track_write(table_name);
+        }
+        self.record(|| DataUndo::Updated { table_name: table_name.to_owned(), row_ids });
+        Ok(())
+    }
+
+    /// Removes rows of the table at the given ascending positions among the
+    /// rows the current statement sees.
+    pub fn delete_rows(&self, table_name: &str, positions: &[usize]) -> Result<(), String> {
+        let snapshot = self.snapshot();
+        let row_ids = self.lock_for_write(table_name, positions, &snapshot)?;
+        let mut guard = self.data_of(table_name).write().unwrap();
+        if let Some(table_rows) = (*guard).get_mut(table_name) {
+            let targets = match latest_versions(table_rows, &row_ids, &snapshot) {
+                Some(targets) => targets,
+                None => {
+                    drop(guard);
+                    return Err(self.conflict());
+                }
+            };
+            let expired = targets.into_iter()
+                .try_for_each(|(row_id, version)| self.expire(&mut table_rows[row_id][version], Some(snapshot.own())));
+            if let Err(error) = expired {
+                drop(guard);
+                return Err(error);
+            }
+        }
+        drop(guard);
+        if !row_ids.is_empty() {
+            self.track_write(table_name);
+        }
+        self.record(|| DataUndo::Deleted { table_name: table_name.to_owned(), row_ids });
+        Ok(())
+    }
+
+    pub fn get_row_from(&self, table_name: &str, row_id: usize) -> Result<Vec<Datum>, String> {
+        Ok(self.visible_rows(table_name)?.into_iter().nth(row_id).unwrap_or_default())
+    }
+
+    pub fn get_range(&self, table_name: &str, start_from: usize, number_of_rows: usize) -> Result<Vec<Vec<Datum>>, String> {
+        Ok(self.visible_rows(table_name)?.into_iter()
+            .skip(start_from)
+            .take(number_of_rows)
+            .collect::<Vec<Vec<Datum>>>())
+    }
+
+    pub fn get_range_till_end(&self, table_name: &str, start_from: usize) -> Result<Vec<Vec<Datum>>, String> {
+        Ok(self.visible_rows(table_name)?.into_iter()
+            .skip(start_from)
+            .collect::<Vec<Vec<Datum>>>())
+    }
+
+    pub fn get_range_till_end_for_column(&self, table_name: &str, column_index: usize, number_of_columns: usize) -> Result<Vec<Vec<Datum>>, String> {
+        Ok(self.visible_rows(table_name)?.into_iter()
+            .map(|row| row.into_iter().skip(column_index).take(number_of_columns).collect::<Vec<Datum>>())
+            .collect::<Vec<Vec<Datum>>>())
+    }
+
+    pub fn get_not_equal(&self, table_name: &str, column_index: usize, value: &String) -> Result<Vec<Vec<Datum>>, String> {
+        Ok(self.visible_rows(table_name)?.into_iter()
+            .filter(|v| v.get(column_index).map_or(true, |d| d != value))
+            .collect::<Vec<Vec<Datum>>>())
+    }
+}
+
+/// Values of the columns of a unique key in a row, fixed-width values are
+/// compared without their padding. `None` when one of them is NULL, rows
+/// stored before a column was added don't have its value.
+pub fn key_values(row: &[Datum], columns: &[usize]) -> Option<Vec<Datum>> {
+    let mut values = vec![];
+    for &index in columns {
+        match row.get(index) {
+            None | Some(Datum::Null) => return None,
+            Some(Datum::Character(ref s)) => values.push(Datum::Character(s.trim_end_matches(' ').to_owned())),
+            Some(value) => values.push(value.clone())
+        }
+    }
+    Some(values)
+}
+
+fn hash_of(values: &[Datum]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    values.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Adds a new version of a row to the indexes of unique keys of its table,
+/// the caller holds the lock of the rows of the table.
+fn index_version(keys: &RwLock<KeyIndexes>, table_name: &str, row_id: RowId, row: &[Datum]) {
+    let mut guard = keys.write().unwrap();
+    if let Some(indexes) = (*guard).get_mut(table_name) {
+        for index in indexes.values_mut() {
+            index.add(row_id, row);
+        }
+    }
+    drop(guard);
+}
+
+/// Index of the version of a row that the snapshot sees.
+fn visible_version(versions: &[Version], snapshot: &Snapshot) -> Option<usize> {
+    let version = versions.iter().rposition(|version| snapshot.sees(version.created))?;
+    match versions[version].expired {
+        Some(expired) if snapshot.sees(expired) => None,
+        _ => Some(version)
+    }
+}
+
+/// Positions and versions of rows that the snapshot sees.
+fn visible_versions(rows: &[Versions], snapshot: &Snapshot) -> Vec<(usize, usize)> {
+    rows.iter()
+        .enumerate()
+        .filter_map(|(row_id, versions)| visible_version(versions, snapshot).map(|version| (row_id, version)))
+        .collect()
+}
+
+/// Versions of the rows that the snapshot sees if they are still the newest
+/// ones, `None` when a transaction the snapshot doesn't see changed a row.
+fn latest_versions(rows: &[Versions], row_ids: &[RowId], snapshot: &Snapshot) -> Option<Vec<(RowId, usize)>> {
+    row_ids.iter()
+        .map(|&row_id| visible_version(&rows[row_id], snapshot).filter(|&version| is_latest(&rows[row_id], version)).map(|version| (row_id, version)))
+        .collect()
+}
+
+/// No transaction has updated or deleted the version yet.
+fn is_latest(versions: &[Version], version: usize) -> bool {
+    version + 1 == versions.len() && versions[version].expired.is_none()
+}
+
+pub struct TableScan {
+    rows: RowScan
+}
+
+impl Iterator for TableScan {
+    type Item = Result<Vec<Datum>, String>;
+
+    fn next(&mut self) -> Option<Result<Vec<Datum>, String>> {
+        self.rows.next().map(|row| row.map(|(_, row)| row))
+    }
+}
+
+pub struct RowScan {
+    data_manager: DataManager,
+    table_name: String,
+    snapshot: Snapshot,
+    position: usize,
+    batch: vec::IntoIter<(RowId, Vec<Datum>)>
+}
+
+impl Iterator for RowScan {
+    type Item = Result<(RowId, Vec<Datum>), String>;
+
+    fn next(&mut self) -> Option<Result<(RowId, Vec<Datum>), String>> {
+        if let Some(row) = self.batch.next() {
+            return Some(Ok(row));
+        }
+        match self.data_manager.scan_batch(self.table_name.as_str(), &self.snapshot, self.position, SCAN_BATCH_SIZE) {
+            Ok((batch, position)) => {
+                self.position = position;
+                self.batch = batch.into_iter();
+                self.batch.next().map(Ok)
+            },
+            Err(error) => Some(Err(error))
+        }
+    }
+}
+
+fn encode_header(created: TransactionId, expired: Option<TransactionId>, row_id: RowId) -> Vec<u8> {
+    let mut header = Vec::with_capacity(TUPLE_HEADER_SIZE);
+    header.extend_from_slice(&created.to_le_bytes());
+    header.extend_from_slice(&expired.unwrap_or(NOT_EXPIRED).to_le_bytes());
+    header.extend_from_slice(&(row_id as u64).to_le_bytes());
+    header
+}
+
+fn decode_header(tuple: &[u8]) -> Result<(TransactionId, Option<TransactionId>, RowId), String> {
+    if tuple.len() < TUPLE_HEADER_SIZE {
+        return Err("tuple is too short".into());
+    }
+    let created = read_u64(&tuple[0..8]);
+    let expired = match read_u64(&tuple[8..16]) {
+        NOT_EXPIRED => None,
+        expired => Some(expired)
+    };
+    Ok((created, expired, read_u64(&tuple[16..24]) as RowId))
+}
+
+const NULL: u8 = 0;
+const BOOL: u8 = 1;
+const INTEGER: u8 = 2;
+const CHARACTER: u8 = 3;
+
+fn encode_row(row: &[Datum], bytes: &mut Vec<u8>) {
+    for datum in row {
+        match *datum {
+            Datum::Null => bytes.push(NULL),
+            Datum::Boolean(value) => bytes.extend_from_slice(&[BOOL, value as u8]),
+            Datum::Integer(value) => {
+                bytes.push(INTEGER);
+                bytes.extend_from_slice(&value.to_le_bytes());
+            },
+            Datum::Character(ref value) => {
+                bytes.push(CHARACTER);
+                bytes.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                bytes.extend_from_slice(value.as_bytes());
+            }
+        }
+    }
+}
+
+fn decode_row(mut bytes: &[u8]) -> Result<Vec<Datum>, String> {
+    let corrupted = || "tuple is corrupted".to_owned();
+    let mut row = vec![];
+    while let Some((&tag, rest)) = bytes.split_first() {
+        let (datum, size) = match tag {
+            NULL => (Datum::Null, 0),
+            BOOL if !rest.is_empty() => (Datum::Boolean(rest[0] != 0), 1),
+            INTEGER if rest.len() >= 8 => (Datum::Integer(read_u64(&rest[0..8]) as i64), 8),
+            CHARACTER if rest.len() >= 4 => {
+                let length = read_u32(&rest[0..4]) as usize;
+                let value = rest.get(4..4 + length).ok_or_else(corrupted)?;
+                (Datum::Character(String::from_utf8(value.to_vec()).map_err(|_| corrupted())?), 4 + length)
+            },
+            _ => return Err(corrupted())
+        };
+        row.push(datum);
+        bytes = &rest[size..];
+    }
+    Ok(row)
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    let mut value = [0; 8];
+    value.copy_from_slice(bytes);
+    u64::from_le_bytes(value)
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    let mut value = [0; 4];
+    value.copy_from_slice(bytes);
+    u32::from_le_bytes(value)
 }
@@ -0,0 +1,22 @@
+use std::path::Path;
+
+use super::catalog_manager::CatalogManager;
+use super::data_manager::DataManager;
+use super::session::Session;
+
+/// Opens the database file creating it when it doesn't exist. The catalog
+/// is defined again by running the statements kept in the file, rows are
+/// not touched by them.
+pub fn open<P: AsRef<Path>>(path: P, buffer_pool_pages: usize) -> Result<(CatalogManager, DataManager), String> {
+    let data_manager = DataManager::open(path, buffer_pool_pages)?;
+    let catalog_manager = CatalogManager::default();
+    let replay = Session::new(&catalog_manager, &DataManager::default());
+    for definition in data_manager.definitions()? {
+        replay.execute(definition.as_str())
+            .map_err(|error| format!("could not define catalog by '{}': {}", definition, error))?;
+    }
+    for (name, last_value) in data_manager.sequences() {
+        catalog_manager.set_last_value_of(name.as_str(), last_value);
+    }
+    Ok((catalog_manager, data_manager))
+}
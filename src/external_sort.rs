@@ -0,0 +1,206 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Lines, Write};
+use std::mem;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::vec;
+
+use super::data::Datum;
+
+static RUN_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Sorts rows by the key produced by `key_of`. Once buffered rows exceed
+/// the memory limit they are sorted and spilled to a temporary file as a
+/// run; `finish` merges all runs back into a single sorted stream.
+pub struct ExternalSorter<K: Ord, F: Fn(&[Datum]) -> Result<K, String>> {
+    key_of: F,
+    memory_limit: Option<usize>,
+    buffer: Vec<(K, Vec<Datum>)>,
+    buffered_size: usize,
+    runs: Vec<Run>
+}
+
+impl <K: Ord, F: Fn(&[Datum]) -> Result<K, String>> ExternalSorter<K, F> {
+
+    pub fn new(key_of: F, memory_limit: Option<usize>) -> ExternalSorter<K, F> {
+        ExternalSorter {
+            key_of,
+            memory_limit,
+            buffer: vec![],
+            buffered_size: 0,
+            runs: vec![]
+        }
+    }
+
+    pub fn push(&mut self, row: Vec<Datum>) -> Result<(), String> {
+        let key = (self.key_of)(&row)?;
+        self.buffered_size += row_size(&row);
+        self.buffer.push((key, row));
+        match self.memory_limit {
+            Some(limit) if self.buffered_size > limit => self.spill(),
+            _ => Ok(())
+        }
+    }
+
+    pub fn spilled_runs(&self) -> usize {
+        self.runs.len()
+    }
+
+    pub fn finish(mut self) -> Result<SortedRows<K, F>, String> {
+        if self.runs.is_empty() {
+            let mut buffer = mem::take(&mut self.buffer);
+            buffer.sort_by(|a, b| a.0.cmp(&b.0));
+            return Ok(SortedRows::InMemory(buffer.into_iter()));
+        }
+        if !self.buffer.is_empty() {
+            self.spill()?;
+        }
+        let mut heap = BinaryHeap::with_capacity(self.runs.len());
+        for (index, run) in self.runs.iter_mut().enumerate() {
+            if let Some(row) = run.next_row()? {
+                heap.push(Reverse(((self.key_of)(&row)?, index, row)));
+            }
+        }
+        Ok(SortedRows::Merge { key_of: self.key_of, runs: self.runs, heap })
+    }
+
+    fn spill(&mut self) -> Result<(), String> {
+        let mut buffer = mem::take(&mut self.buffer);
+        self.buffered_size = 0;
+        buffer.sort_by(|a, b| a.0.cmp(&b.0));
+        let run = Run::write(buffer.into_iter().map(|(_, row)| row))?;
+        self.runs.push(run);
+        Ok(())
+    }
+}
+
+pub enum SortedRows<K: Ord, F: Fn(&[Datum]) -> Result<K, String>> {
+    InMemory(vec::IntoIter<(K, Vec<Datum>)>),
+    Merge {
+        key_of: F,
+        runs: Vec<Run>,
+        heap: BinaryHeap<Reverse<(K, usize, Vec<Datum>)>>
+    }
+}
+
+impl <K: Ord, F: Fn(&[Datum]) -> Result<K, String>> Iterator for SortedRows<K, F> {
+    type Item = Result<Vec<Datum>, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match *self {
+            SortedRows::InMemory(ref mut rows) => rows.next().map(|(_, row)| Ok(row)),
+            SortedRows::Merge { ref key_of, ref mut runs, ref mut heap } => {
+                let Reverse((_, index, row)) = heap.pop()?;
+                match runs[index].next_row() {
+                    Ok(Some(next)) => match key_of(&next) {
+                        Ok(key) => heap.push(Reverse((key, index, next))),
+                        Err(e) => return Some(Err(e))
+                    },
+                    Ok(None) => {},
+                    Err(e) => return Some(Err(e))
+                }
+                Some(Ok(row))
+            }
+        }
+    }
+}
+
+/// A sorted run spilled to a temporary file, one encoded row per line.
+/// The file is removed when the run is dropped.
+pub struct Run {
+    path: PathBuf,
+    lines: Option<Lines<BufReader<File>>>
+}
+
+impl Run {
+
+    fn write<I: Iterator<Item = Vec<Datum>>>(rows: I) -> Result<Run, String> {
+        let path = env::temp_dir().join(
+            format!("sql-sort-{}-{}.run", process::id(), RUN_COUNTER.fetch_add(1, Ordering::SeqCst))
+        );
+        let file = File::create(&path).map_err(|e| e.to_string())?;
+        let mut writer = BufWriter::new(file);
+        for row in rows {
+            writeln!(writer, "{}", encode_row(&row)).map_err(|e| e.to_string())?;
+        }
+        writer.flush().map_err(|e| e.to_string())?;
+        Ok(Run { path, lines: None })
+    }
+
+    fn next_row(&mut self) -> Result<Option<Vec<Datum>>, String> {
+        if self.lines.is_none() {
+            let file = File::open(&self.path).map_err(|e| e.to_string())?;
+            self.lines = Some(BufReader::new(file).lines());
+        }
+        match self.lines.as_mut().and_then(|lines| lines.next()) {
+            Some(Ok(line)) => decode_row(line.as_str()).map(Some),
+            Some(Err(e)) => Err(e.to_string()),
+            None => Ok(None)
+        }
+    }
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        drop(fs::remove_file(&self.path));
+    }
+}
+
+/// Rough number of bytes a row occupies in memory.
+pub fn row_size(row: &[Datum]) -> usize {
+    row.iter()
+        .map(|d| match *d {
+            Datum::Character(ref s) => mem::size_of::<Datum>() + s.capacity(),
+            _ => mem::size_of::<Datum>()
+        })
+        .sum::<usize>() + mem::size_of::<Vec<Datum>>()
+}
+
+fn encode_row(row: &[Datum]) -> String {
+    row.iter()
+        .map(|d| match *d {
+            Datum::Null => "N".to_owned(),
+            Datum::Boolean(v) => format!("B{}", v),
+            Datum::Integer(v) => format!("I{}", v),
+            Datum::Character(ref s) => format!("C{}", s.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n"))
+        })
+        .collect::<Vec<String>>()
+        .join("\t")
+}
+
+fn decode_row(line: &str) -> Result<Vec<Datum>, String> {
+    if line.is_empty() {
+        return Ok(vec![]);
+    }
+    line.split('\t').map(decode_datum).collect()
+}
+
+fn decode_datum(cell: &str) -> Result<Datum, String> {
+    let mut chars = cell.chars();
+    match chars.next() {
+        Some('N') => Ok(Datum::Null),
+        Some('B') => chars.as_str().parse::<bool>().map(Datum::Boolean).map_err(|e| e.to_string()),
+        Some('I') => chars.as_str().parse::<i64>().map(Datum::Integer).map_err(|e| e.to_string()),
+        Some('C') => {
+            let mut value = String::with_capacity(cell.len());
+            while let Some(c) = chars.next() {
+                if c != '\\' {
+                    value.push(c);
+                    continue;
+                }
+                match chars.next() {
+                    Some('t') => value.push('\t'),
+                    Some('n') => value.push('\n'),
+                    Some(other) => value.push(other),
+                    None => return Err(format!("corrupted sort run cell '{}'", cell))
+                }
+            }
+            Ok(Datum::Character(value))
+        },
+        _ => Err(format!("corrupted sort run cell '{}'", cell))
+    }
+}
@@ -0,0 +1,346 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use super::ast::Type;
+use super::ast::function::{Function, TrimSide};
+use super::ast::window::WindowFunction;
+use super::data::Datum;
+
+/// Calls a built-in scalar function, any `NULL` argument makes the result
+/// `NULL`. Fixed-width arguments are expected with their padding stripped.
+pub fn call(function: Function, args: &[Datum]) -> Result<Datum, String> {
+    if args.iter().any(|arg| arg.is_null()) {
+        return Ok(Datum::Null);
+    }
+    match (function, args) {
+        (Function::Length, [Datum::Character(s)]) => Ok(Datum::Integer(s.chars().count() as i64)),
+        (Function::Upper, [Datum::Character(s)]) => Ok(Datum::Character(s.to_uppercase())),
+        (Function::Lower, [Datum::Character(s)]) => Ok(Datum::Character(s.to_lowercase())),
+        (Function::Substring, [Datum::Character(s), Datum::Integer(start)]) => Ok(Datum::Character(substring(s, *start, None)?)),
+        (Function::Substring, [Datum::Character(s), Datum::Integer(start), Datum::Integer(count)]) =>
+            Ok(Datum::Character(substring(s, *start, Some(*count))?)),
+        (Function::Trim(side), [Datum::Character(s)]) => Ok(Datum::Character(trim(s, side, " "))),
+        (Function::Trim(side), [Datum::Character(s), Datum::Character(characters)]) => Ok(Datum::Character(trim(s, side, characters))),
+        (Function::Replace, [Datum::Character(s), Datum::Character(from), Datum::Character(to)]) =>
+            Ok(Datum::Character(if from.is_empty() { s.clone() } else { s.replace(from.as_str(), to) })),
+        (Function::Position, [Datum::Character(sub), Datum::Character(s)]) => Ok(Datum::Integer(position(s, sub))),
+        _ => {
+            let args = args.iter().map(|arg| format!("{:?}", arg)).collect::<Vec<String>>();
+            Err(format!("function {}({}) can't be applied", function, args.join(", ")))
+        }
+    }
+}
+
+/// `start` counts from 1 and may point before the string, then the
+/// characters before the first one are counted against `count`.
+fn substring(s: &str, start: i64, count: Option<i64>) -> Result<String, String> {
+    let end = match count {
+        Some(count) if count < 0 => return Err("negative substring length not allowed".into()),
+        Some(count) => Some(start.saturating_add(count)),
+        None => None
+    };
+    let skip = usize::try_from(start.max(1) - 1).unwrap_or(usize::MAX);
+    let take = match end {
+        Some(end) => usize::try_from((end - start.max(1)).max(0)).unwrap_or(usize::MAX),
+        None => usize::MAX
+    };
+    Ok(s.chars().skip(skip).take(take).collect())
+}
+
+fn trim(s: &str, side: TrimSide, characters: &str) -> String {
+    let is_trimmed = |c: char| characters.contains(c);
+    match side {
+        TrimSide::Leading => s.trim_start_matches(is_trimmed),
+        TrimSide::Trailing => s.trim_end_matches(is_trimmed),
+        TrimSide::Both => s.trim_matches(is_trimmed)
+    }.to_owned()
+}
+
+fn position(s: &str, sub: &str) -> i64 {
+    match s.find(sub) {
+        Some(index) => s[..index].chars().count() as i64 + 1,
+        None => 0
+    }
+}
+
+/// Formats seconds since the Unix epoch as `YYYY-MM-DD HH:MM:SS` in UTC.
+pub fn timestamp(seconds: u64) -> String {
+    let (days, time) = ((seconds / 86_400) as i64, seconds % 86_400);
+    // civil date of a day count, shifted to eras starting at March 1st
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, time / 3600, time % 3600 / 60, time % 60)
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum PatternPart {
+    Char(char),
+    AnyChar,
+    AnyString
+}
+
+/// Matches `value` against a `LIKE` pattern where `%` stands for any
+/// sequence of characters and `_` for a single one. Backslash is the escape
+/// character unless `escape` overrides it, an empty `escape` disables it.
+pub fn like(value: &str, pattern: &str, escape: Option<&str>, case_insensitive: bool) -> Result<bool, String> {
+    let escape = match escape {
+        None => Some('\\'),
+        Some(escape) => {
+            let mut chars = escape.chars();
+            match (chars.next(), chars.next()) {
+                (None, _) => None,
+                (Some(c), None) => Some(c),
+                _ => return Err("invalid escape string".into())
+            }
+        }
+    };
+    let (value, pattern) = if case_insensitive {
+        (value.to_lowercase(), pattern.to_lowercase())
+    } else {
+        (value.to_owned(), pattern.to_owned())
+    };
+    let mut parts = vec![];
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        parts.push(
+            if Some(c) == escape {
+                match chars.next() {
+                    Some(escaped) => PatternPart::Char(escaped),
+                    None => return Err("LIKE pattern must not end with escape character".into())
+                }
+            } else if c == '%' {
+                PatternPart::AnyString
+            } else if c == '_' {
+                PatternPart::AnyChar
+            } else {
+                PatternPart::Char(c)
+            }
+        );
+    }
+    Ok(matches(&value.chars().collect::<Vec<char>>(), &parts))
+}
+
+/// Greedy matching that backtracks only to the last `%` seen.
+fn matches(value: &[char], parts: &[PatternPart]) -> bool {
+    let (mut v, mut p) = (0, 0);
+    let mut backtrack = None;
+    while v < value.len() {
+        match parts.get(p) {
+            Some(&PatternPart::AnyString) => {
+                backtrack = Some((p, v));
+                p += 1;
+            },
+            Some(&PatternPart::AnyChar) => {
+                v += 1;
+                p += 1;
+            },
+            Some(&PatternPart::Char(c)) if c == value[v] => {
+                v += 1;
+                p += 1;
+            },
+            _ => match backtrack {
+                Some((star, from)) => {
+                    backtrack = Some((star, from + 1));
+                    p = star + 1;
+                    v = from + 1;
+                },
+                None => return false
+            }
+        }
+    }
+    parts[p..].iter().all(|part| *part == PatternPart::AnyString)
+}
+
+/// Scalar function implemented by the application. Closures over the
+/// argument values implement it, `NULL` arguments are passed as they are.
+pub trait ScalarFunction: Send + Sync {
+    fn call(&self, args: &[Datum]) -> Result<Datum, String>;
+}
+
+impl <F> ScalarFunction for F where F: Fn(&[Datum]) -> Result<Datum, String> + Send + Sync {
+
+    fn call(&self, args: &[Datum]) -> Result<Datum, String> {
+        self(args)
+    }
+}
+
+/// Aggregate function implemented by the application. Rows are accumulated
+/// into partial states which are merged before the result is finalized.
+pub trait AggregateFunction: Send + Sync {
+    type State: 'static;
+
+    fn init(&self) -> Self::State;
+
+    fn accumulate(&self, state: &mut Self::State, args: &[Datum]) -> Result<(), String>;
+
+    fn merge(&self, state: &mut Self::State, other: Self::State) -> Result<(), String>;
+
+    fn finalize(&self, state: Self::State) -> Result<Datum, String>;
+}
+
+/// `AggregateFunction` with its state type erased so that aggregates with
+/// different states can be kept in one registry.
+trait AnyAggregate: Send + Sync {
+    fn init(&self) -> Box<dyn Any>;
+
+    fn accumulate(&self, state: &mut dyn Any, args: &[Datum]) -> Result<(), String>;
+
+    fn merge(&self, state: &mut dyn Any, other: Box<dyn Any>) -> Result<(), String>;
+
+    fn finalize(&self, state: Box<dyn Any>) -> Result<Datum, String>;
+}
+
+impl <A: AggregateFunction> AnyAggregate for A {
+
+    fn init(&self) -> Box<dyn Any> {
+        Box::new(AggregateFunction::init(self))
+    }
+
+    fn accumulate(&self, state: &mut dyn Any, args: &[Datum]) -> Result<(), String> {
+        AggregateFunction::accumulate(self, state.downcast_mut().expect("state of another aggregate"), args)
+    }
+
+    fn merge(&self, state: &mut dyn Any, other: Box<dyn Any>) -> Result<(), String> {
+        let other = other.downcast::<A::State>().expect("state of another aggregate");
+        AggregateFunction::merge(self, state.downcast_mut().expect("state of another aggregate"), *other)
+    }
+
+    fn finalize(&self, state: Box<dyn Any>) -> Result<Datum, String> {
+        let state = state.downcast::<A::State>().expect("state of another aggregate");
+        AggregateFunction::finalize(self, *state)
+    }
+}
+
+/// Number of rows accumulated into one partial state of an aggregate.
+pub const AGGREGATE_BATCH_SIZE: usize = 1024;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Signature {
+    pub arg_types: Vec<Type>,
+    pub return_type: Type,
+    pub aggregate: bool
+}
+
+impl Signature {
+
+    /// Fixed-width and variable-width character arguments are accepted
+    /// by any character parameter.
+    pub fn accepts(&self, arg_types: &[Type]) -> bool {
+        self.arg_types.len() == arg_types.len()
+            && self.arg_types.iter().zip(arg_types.iter()).all(
+                |(param, arg)| match (*param, *arg) {
+                    (Type::Character(_), Type::Character(_)) => true,
+                    (param, arg) => param == arg
+                }
+            )
+    }
+}
+
+/// Functions registered by the application, looked up by name.
+#[derive(Clone, Default)]
+pub struct UserFunctions {
+    scalars: HashMap<String, (Signature, Arc<dyn ScalarFunction>)>,
+    aggregates: HashMap<String, (Signature, Arc<dyn AnyAggregate>)>
+}
+
+impl UserFunctions {
+
+    pub fn add_scalar<F: ScalarFunction + 'static>(&mut self, name: &str, arg_types: Vec<Type>, return_type: Type, function: F) -> Result<(), String> {
+        let name = self.free_name(name)?;
+        let signature = Signature { arg_types, return_type, aggregate: false };
+        self.scalars.insert(name, (signature, Arc::new(function)));
+        Ok(())
+    }
+
+    pub fn add_aggregate<A: AggregateFunction + 'static>(&mut self, name: &str, arg_types: Vec<Type>, return_type: Type, aggregate: A) -> Result<(), String> {
+        let name = self.free_name(name)?;
+        let signature = Signature { arg_types, return_type, aggregate: true };
+        self.aggregates.insert(name, (signature, Arc::new(aggregate)));
+        Ok(())
+    }
+
+    /// Names are case insensitive as identifiers in queries are lower cased.
+    fn free_name(&self, name: &str) -> Result<String, String> {
+        let name = name.to_lowercase();
+        let built_in = Function::from_name(name.as_str()).is_some()
+            || WindowFunction::from_name(name.as_str()).is_some()
+            || name == "coalesce" || name == "nullif";
+        if built_in || self.scalars.contains_key(&name) || self.aggregates.contains_key(&name) {
+            return Err(format!("function \"{}\" already exists", name));
+        }
+        Ok(name)
+    }
+
+    pub fn signature(&self, name: &str) -> Option<&Signature> {
+        match self.scalars.get(name) {
+            Some((signature, _)) => Some(signature),
+            None => self.aggregates.get(name).map(|(signature, _)| signature)
+        }
+    }
+
+    pub fn call(&self, name: &str, args: &[Datum]) -> Result<Datum, String> {
+        match self.scalars.get(name) {
+            Some((_, function)) => function.call(args),
+            None => Err(format!("function {}() does not exist", name))
+        }
+    }
+
+    /// Applies aggregate `name` to the argument values of every row.
+    pub fn aggregate(&self, name: &str, rows: &[Vec<Datum>]) -> Result<Datum, String> {
+        let mut accumulator = self.accumulator(name)?;
+        for args in rows {
+            accumulator.push(args)?;
+        }
+        accumulator.finish()
+    }
+
+    /// Computes aggregate `name` over rows pushed one by one, they are not
+    /// kept after they are accumulated.
+    pub fn accumulator(&self, name: &str) -> Result<Accumulator, String> {
+        match self.aggregates.get(name) {
+            Some((_, aggregate)) => Ok(Accumulator { aggregate: aggregate.clone(), state: aggregate.init(), partial: None }),
+            None => Err(format!("aggregate function {}() does not exist", name))
+        }
+    }
+}
+
+/// Running state of an aggregate, rows are accumulated into a partial
+/// state that is merged every `AGGREGATE_BATCH_SIZE` rows.
+pub struct Accumulator {
+    aggregate: Arc<dyn AnyAggregate>,
+    state: Box<dyn Any>,
+    partial: Option<(Box<dyn Any>, usize)>
+}
+
+impl Accumulator {
+
+    pub fn push(&mut self, args: &[Datum]) -> Result<(), String> {
+        let (mut partial, rows) = match self.partial.take() {
+            Some(partial) => partial,
+            None => (self.aggregate.init(), 0)
+        };
+        self.aggregate.accumulate(partial.as_mut(), args)?;
+        if rows + 1 == AGGREGATE_BATCH_SIZE {
+            self.aggregate.merge(self.state.as_mut(), partial)
+        } else {
+            self.partial = Some((partial, rows + 1));
+            Ok(())
+        }
+    }
+
+    pub fn finish(mut self) -> Result<Datum, String> {
+        if let Some((partial, _)) = self.partial.take() {
+            self.aggregate.merge(self.state.as_mut(), partial)?;
+        }
+        self.aggregate.finalize(self.state)
+    }
+}
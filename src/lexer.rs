@@ -25,6 +25,8 @@ pub enum Token {
     Minus,
     Asterisk,
     Slash,
+    DoubleColon,
+    Concat,
 
     Insert,
     Into,
@@ -33,22 +35,86 @@ pub enum Token {
     From,
     Where,
     Delete,
+    Update,
+    Set,
+    Returning,
+    On,
+    Conflict,
+    Do,
+    Nothing,
     Create,
     Table,
+    Sequence,
+    View,
+    Materialized,
+    Refresh,
+    Drop,
+    Begin,
+    Commit,
+    Rollback,
+    Savepoint,
+    Release,
     Columns,
     Limit,
     Primary,
     Key,
+    Unique,
+    Generated,
+    Always,
+    Identity,
     Default,
     Null,
     Foreign,
     References,
+    Order,
+    By,
+    Asc,
+    Desc,
+    Nulls,
+    First,
+    Last,
+    As,
+    Distinct,
+    Union,
+    All,
+    Intersect,
+    Except,
+    With,
+    Recursive,
+    Over,
+    Partition,
+    Rows,
+    Range,
+    Between,
+    Unbounded,
+    Preceding,
+    Following,
+    Current,
+    Row,
+    Case,
+    When,
+    Then,
+    Else,
+    End,
+    Cast,
+    Like,
+    ILike,
+    Escape,
+    Leading,
+    Trailing,
+    Both,
+    For,
 
     And,
+    Or,
     Not,
+    Exists,
+    In,
 
     Int,
-    Character
+    Serial,
+    Character,
+    Boolean
 }
 
 impl fmt::Debug for Token {
@@ -70,6 +136,7 @@ impl fmt::Debug for Token {
             Token::Plus => write!(f, "Symbol(+)"),
             Token::Asterisk => write!(f, "Symbol(*)"),
             Token::Slash => write!(f, "Symbol(/)"),
+            Token::Concat => write!(f, "Symbol(||)"),
             Token::LParent => write!(f, "Symbol('(')"),
             Token::RParent => write!(f, "Symbol(')')"),
             Token::Semicolon => write!(f, "Symbol(';')"),
@@ -77,6 +144,9 @@ impl fmt::Debug for Token {
 
             Token::Character => write!(f, "KeyWord('CHARACTER')"),
             Token::Int => write!(f, "KeyWord('INTEGER')"),
+            Token::Serial => write!(f, "KeyWord('SERIAL')"),
+            Token::Boolean => write!(f, "KeyWord('BOOLEAN')"),
+            Token::DoubleColon => write!(f, "DoubleColon"),
 
             Token::Insert => write!(f, "KeyWord('INSERT')"),
             Token::Into => write!(f, "KeyWord('INTO')"),
@@ -84,22 +154,206 @@ impl fmt::Debug for Token {
             Token::Select => write!(f, "KeyWord('SELECT')"),
             Token::From => write!(f, "KeyWord('FROM')"),
             Token::Where => write!(f, "KeyWord('WHERE')"),
+            Token::Delete => write!(f, "KeyWord('DELETE')"),
+            Token::Update => write!(f, "KeyWord('UPDATE')"),
+            Token::Set => write!(f, "KeyWord('SET')"),
+            Token::Returning => write!(f, "KeyWord('RETURNING')"),
+            Token::On => write!(f, "KeyWord('ON')"),
+            Token::Conflict => write!(f, "KeyWord('CONFLICT')"),
+            Token::Do => write!(f, "KeyWord('DO')"),
+            Token::Nothing => write!(f, "KeyWord('NOTHING')"),
             Token::Create => write!(f, "KeyWord('CREATE')"),
             Token::Table => write!(f, "KeyWord('TABLE')"),
+            Token::Sequence => write!(f, "KeyWord('SEQUENCE')"),
+            Token::View => write!(f, "KeyWord('VIEW')"),
+            Token::Materialized => write!(f, "KeyWord('MATERIALIZED')"),
+            Token::Refresh => write!(f, "KeyWord('REFRESH')"),
+            Token::Drop => write!(f, "KeyWord('DROP')"),
+            Token::Begin => write!(f, "KeyWord('BEGIN')"),
+            Token::Commit => write!(f, "KeyWord('COMMIT')"),
+            Token::Rollback => write!(f, "KeyWord('ROLLBACK')"),
+            Token::Savepoint => write!(f, "KeyWord('SAVEPOINT')"),
+            Token::Release => write!(f, "KeyWord('RELEASE')"),
             Token::Primary => write!(f, "KeyWord('PRIMARY')"),
             Token::Foreign => write!(f, "KeyWord('FOREIGN')"),
             Token::Key => write!(f, "KeyWord('KEY')"),
+            Token::Unique => write!(f, "KeyWord('UNIQUE')"),
+            Token::Generated => write!(f, "KeyWord('GENERATED')"),
+            Token::Always => write!(f, "KeyWord('ALWAYS')"),
+            Token::Identity => write!(f, "KeyWord('IDENTITY')"),
             Token::References => write!(f, "KeyWord('REFERENCES')"),
             Token::Null => write!(f, "KeyWord('NULL')"),
+            Token::Limit => write!(f, "KeyWord('LIMIT')"),
+            Token::Order => write!(f, "KeyWord('ORDER')"),
+            Token::By => write!(f, "KeyWord('BY')"),
+            Token::Asc => write!(f, "KeyWord('ASC')"),
+            Token::Desc => write!(f, "KeyWord('DESC')"),
+            Token::Nulls => write!(f, "KeyWord('NULLS')"),
+            Token::First => write!(f, "KeyWord('FIRST')"),
+            Token::Last => write!(f, "KeyWord('LAST')"),
+            Token::As => write!(f, "KeyWord('AS')"),
+            Token::Distinct => write!(f, "KeyWord('DISTINCT')"),
+            Token::Union => write!(f, "KeyWord('UNION')"),
+            Token::All => write!(f, "KeyWord('ALL')"),
+            Token::Intersect => write!(f, "KeyWord('INTERSECT')"),
+            Token::Except => write!(f, "KeyWord('EXCEPT')"),
+            Token::With => write!(f, "KeyWord('WITH')"),
+            Token::Recursive => write!(f, "KeyWord('RECURSIVE')"),
+            Token::Over => write!(f, "KeyWord('OVER')"),
+            Token::Partition => write!(f, "KeyWord('PARTITION')"),
+            Token::Rows => write!(f, "KeyWord('ROWS')"),
+            Token::Range => write!(f, "KeyWord('RANGE')"),
+            Token::Between => write!(f, "KeyWord('BETWEEN')"),
+            Token::Unbounded => write!(f, "KeyWord('UNBOUNDED')"),
+            Token::Preceding => write!(f, "KeyWord('PRECEDING')"),
+            Token::Following => write!(f, "KeyWord('FOLLOWING')"),
+            Token::Current => write!(f, "KeyWord('CURRENT')"),
+            Token::Row => write!(f, "KeyWord('ROW')"),
+            Token::Case => write!(f, "KeyWord('CASE')"),
+            Token::When => write!(f, "KeyWord('WHEN')"),
+            Token::Then => write!(f, "KeyWord('THEN')"),
+            Token::Else => write!(f, "KeyWord('ELSE')"),
+            Token::End => write!(f, "KeyWord('END')"),
+            Token::Cast => write!(f, "KeyWord('CAST')"),
+            Token::Like => write!(f, "KeyWord('LIKE')"),
+            Token::ILike => write!(f, "KeyWord('ILIKE')"),
+            Token::Escape => write!(f, "KeyWord('ESCAPE')"),
+            Token::Leading => write!(f, "KeyWord('LEADING')"),
+            Token::Trailing => write!(f, "KeyWord('TRAILING')"),
+            Token::Both => write!(f, "KeyWord('BOTH')"),
+            Token::For => write!(f, "KeyWord('FOR')"),
 
             Token::Not => write!(f, "KeyWord('NOT')"),
             Token::And => write!(f, "KeyWord('AND')"),
+            Token::Or => write!(f, "KeyWord('OR')"),
+            Token::Exists => write!(f, "KeyWord('EXISTS')"),
+            Token::In => write!(f, "KeyWord('IN')"),
 
             _ => write!(f, "unimplemented debug representation")
         }
     }
 }
 
+impl fmt::Display for Token {
+
+    /// Text that is read back as the same token.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Token::Ident(ref val) => write!(f, "{}", val),
+            Token::NumConst(ref val) => write!(f, "{}", val),
+            Token::CharsConst(ref val) => write!(f, "'{}'", val.replace('\'', "''")),
+
+            Token::LParent => write!(f, "("),
+            Token::RParent => write!(f, ")"),
+            Token::Less => write!(f, "<"),
+            Token::LessEqual => write!(f, "<="),
+            Token::Greater => write!(f, ">"),
+            Token::GreaterEqual => write!(f, ">="),
+            Token::EqualSign => write!(f, "="),
+            Token::NotEqualSign => write!(f, "<>"),
+            Token::Comma => write!(f, ","),
+            Token::SingleQuote => write!(f, "'"),
+            Token::Semicolon => write!(f, ";"),
+            Token::Plus => write!(f, "+"),
+            Token::Minus => write!(f, "-"),
+            Token::Asterisk => write!(f, "*"),
+            Token::Slash => write!(f, "/"),
+            Token::DoubleColon => write!(f, "::"),
+            Token::Concat => write!(f, "||"),
+            Token::Insert => write!(f, "insert"),
+            Token::Into => write!(f, "into"),
+            Token::Columns => write!(f, "columns"),
+            Token::Values => write!(f, "values"),
+            Token::Select => write!(f, "select"),
+            Token::From => write!(f, "from"),
+            Token::Where => write!(f, "where"),
+            Token::Delete => write!(f, "delete"),
+            Token::Update => write!(f, "update"),
+            Token::Set => write!(f, "set"),
+            Token::Returning => write!(f, "returning"),
+            Token::On => write!(f, "on"),
+            Token::Conflict => write!(f, "conflict"),
+            Token::Do => write!(f, "do"),
+            Token::Nothing => write!(f, "nothing"),
+            Token::Create => write!(f, "create"),
+            Token::Table => write!(f, "table"),
+            Token::Sequence => write!(f, "sequence"),
+            Token::View => write!(f, "view"),
+            Token::Materialized => write!(f, "materialized"),
+            Token::Refresh => write!(f, "refresh"),
+            Token::Drop => write!(f, "drop"),
+            Token::Begin => write!(f, "begin"),
+            Token::Commit => write!(f, "commit"),
+            Token::Rollback => write!(f, "rollback"),
+            Token::Savepoint => write!(f, "savepoint"),
+            Token::Release => write!(f, "release"),
+            Token::Primary => write!(f, "primary"),
+            Token::Foreign => write!(f, "foreign"),
+            Token::Key => write!(f, "key"),
+            Token::Unique => write!(f, "unique"),
+            Token::Generated => write!(f, "generated"),
+            Token::Always => write!(f, "always"),
+            Token::Identity => write!(f, "identity"),
+            Token::References => write!(f, "references"),
+            Token::Default => write!(f, "default"),
+            Token::Not => write!(f, "not"),
+            Token::Null => write!(f, "null"),
+            Token::Int => write!(f, "integer"),
+            Token::Serial => write!(f, "serial"),
+            Token::Character => write!(f, "char"),
+            Token::Limit => write!(f, "limit"),
+            Token::Order => write!(f, "order"),
+            Token::By => write!(f, "by"),
+            Token::Asc => write!(f, "asc"),
+            Token::Desc => write!(f, "desc"),
+            Token::Nulls => write!(f, "nulls"),
+            Token::First => write!(f, "first"),
+            Token::Last => write!(f, "last"),
+            Token::As => write!(f, "as"),
+            Token::Distinct => write!(f, "distinct"),
+            Token::Union => write!(f, "union"),
+            Token::All => write!(f, "all"),
+            Token::Intersect => write!(f, "intersect"),
+            Token::Except => write!(f, "except"),
+            Token::With => write!(f, "with"),
+            Token::Recursive => write!(f, "recursive"),
+            Token::Over => write!(f, "over"),
+            Token::Partition => write!(f, "partition"),
+            Token::Rows => write!(f, "rows"),
+            Token::Range => write!(f, "range"),
+            Token::Between => write!(f, "between"),
+            Token::Unbounded => write!(f, "unbounded"),
+            Token::Preceding => write!(f, "preceding"),
+            Token::Following => write!(f, "following"),
+            Token::Current => write!(f, "current"),
+            Token::Row => write!(f, "row"),
+            Token::Case => write!(f, "case"),
+            Token::When => write!(f, "when"),
+            Token::Then => write!(f, "then"),
+            Token::Else => write!(f, "else"),
+            Token::End => write!(f, "end"),
+            Token::Cast => write!(f, "cast"),
+            Token::Like => write!(f, "like"),
+            Token::ILike => write!(f, "ilike"),
+            Token::Escape => write!(f, "escape"),
+            Token::Leading => write!(f, "leading"),
+            Token::Trailing => write!(f, "trailing"),
+            Token::Both => write!(f, "both"),
+            Token::For => write!(f, "for"),
+            Token::Boolean => write!(f, "boolean"),
+            Token::And => write!(f, "and"),
+            Token::Or => write!(f, "or"),
+            Token::Exists => write!(f, "exists"),
+            Token::In => write!(f, "in")
+        }
+    }
+}
+
+/// Statement made of the tokens that is read back as the same tokens.
+pub fn render(tokens: &[Token]) -> String {
+    tokens.iter().map(ToString::to_string).collect::<Vec<String>>().join(" ")
+}
+
 impl<'s> From<&'s str> for Token {
     fn from(token: &'s str) -> Token {
         match token {
@@ -111,19 +365,83 @@ impl<'s> From<&'s str> for Token {
             "from" => Token::From,
             "where" => Token::Where,
             "delete" => Token::Delete,
+            "update" => Token::Update,
+            "set" => Token::Set,
+            "returning" => Token::Returning,
+            "on" => Token::On,
+            "conflict" => Token::Conflict,
+            "do" => Token::Do,
+            "nothing" => Token::Nothing,
             "create" => Token::Create,
             "table" => Token::Table,
+            "sequence" => Token::Sequence,
+            "view" => Token::View,
+            "materialized" => Token::Materialized,
+            "refresh" => Token::Refresh,
+            "drop" => Token::Drop,
+            "begin" => Token::Begin,
+            "commit" => Token::Commit,
+            "rollback" => Token::Rollback,
+            "savepoint" => Token::Savepoint,
+            "release" => Token::Release,
             "primary" => Token::Primary,
             "foreign" => Token::Foreign,
             "key" => Token::Key,
+            "unique" => Token::Unique,
+            "generated" => Token::Generated,
+            "always" => Token::Always,
+            "identity" => Token::Identity,
             "references" => Token::References,
             "default" => Token::Default,
             "not" => Token::Not,
             "null" => Token::Null,
             "integer" | "int" => Token::Int,
+            "serial" => Token::Serial,
             "char" | "character" => Token::Character,
             "limit" => Token::Limit,
+            "order" => Token::Order,
+            "by" => Token::By,
+            "asc" => Token::Asc,
+            "desc" => Token::Desc,
+            "nulls" => Token::Nulls,
+            "first" => Token::First,
+            "last" => Token::Last,
+            "as" => Token::As,
+            "distinct" => Token::Distinct,
+            "union" => Token::Union,
+            "all" => Token::All,
+            "intersect" => Token::Intersect,
+            "except" => Token::Except,
+            "with" => Token::With,
+            "recursive" => Token::Recursive,
+            "over" => Token::Over,
+            "partition" => Token::Partition,
+            "rows" => Token::Rows,
+            "range" => Token::Range,
+            "between" => Token::Between,
+            "unbounded" => Token::Unbounded,
+            "preceding" => Token::Preceding,
+            "following" => Token::Following,
+            "current" => Token::Current,
+            "row" => Token::Row,
+            "case" => Token::Case,
+            "when" => Token::When,
+            "then" => Token::Then,
+            "else" => Token::Else,
+            "end" => Token::End,
+            "cast" => Token::Cast,
+            "like" => Token::Like,
+            "ilike" => Token::ILike,
+            "escape" => Token::Escape,
+            "leading" => Token::Leading,
+            "trailing" => Token::Trailing,
+            "both" => Token::Both,
+            "for" => Token::For,
+            "boolean" | "bool" => Token::Boolean,
             "and" => Token::And,
+            "or" => Token::Or,
+            "exists" => Token::Exists,
+            "in" => Token::In,
             _ => Token::Ident(token.into()),
         }
     }
@@ -167,6 +485,20 @@ pub fn tokenize(src: &str) -> Result<Tokens, String> {
                     _ => tokens.push(Token::Slash)
                 }
             }
+            ':' => {
+                chars.next();
+                match chars.next() {
+                    Some(':') => tokens.push(Token::DoubleColon),
+                    c => return Err(format!("unexpected character {:?} after ':'", c))
+                }
+            }
+            '|' => {
+                chars.next();
+                match chars.next() {
+                    Some('|') => tokens.push(Token::Concat),
+                    c => return Err(format!("unexpected character {:?} after '|'", c))
+                }
+            }
             '-' => {
                 chars.next();
                 match chars.peek().cloned() {
@@ -224,7 +556,7 @@ fn ident_token<I: Iterator<Item = char>>(chars: &mut Peekable<I>) -> Token {
             'A' ... 'Z' |
             'a' ... 'z' |
             '0' ... '9' |
-            '_' => { token.push(c); chars.next(); },
+            '_' | '.' => { token.push(c); chars.next(); },
             _ => break,
         }
     }
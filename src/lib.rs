@@ -4,6 +4,17 @@ pub mod parser;
 pub mod query_typer;
 pub mod query_validator;
 pub mod query_executer;
+pub mod session;
 pub mod catalog_manager;
 pub mod catalog;
+pub mod data;
 pub mod data_manager;
+pub mod external_sort;
+pub mod functions;
+pub mod lock_manager;
+pub mod transaction;
+pub mod page;
+pub mod buffer_pool;
+pub mod storage;
+pub mod wal;
+pub mod database;
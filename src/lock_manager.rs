@@ -0,0 +1,133 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use super::transaction::TransactionId;
+
+pub const DEADLOCK: &str = "deadlock detected";
+
+/// Row of a table by its id, ids stay the same for the whole life of rows.
+pub type RowKey = (String, usize);
+
+/// Locks of rows held by transactions until they finish. A transaction
+/// that has to wait is woken up every time some transaction releases its
+/// locks.
+#[derive(Debug, Default)]
+pub struct LockManager {
+    locks: Mutex<Locks>,
+    released: Condvar
+}
+
+#[derive(Debug, Default)]
+struct Locks {
+    rows: HashMap<RowKey, Holders>,
+    held: HashMap<TransactionId, HashSet<RowKey>>,
+    /// transactions that a waiting transaction waits for
+    waits_for: HashMap<TransactionId, HashSet<TransactionId>>
+}
+
+#[derive(Debug, Default)]
+struct Holders {
+    exclusive: Option<TransactionId>,
+    shared: HashSet<TransactionId>
+}
+
+impl Locks {
+
+    fn blockers(&self, key: &RowKey, id: TransactionId, exclusive: bool) -> HashSet<TransactionId> {
+        let mut blockers = HashSet::new();
+        if let Some(holders) = self.rows.get(key) {
+            blockers.extend(holders.exclusive.iter().cloned());
+            if exclusive {
+                blockers.extend(holders.shared.iter().cloned());
+            }
+        }
+        blockers.remove(&id);
+        blockers
+    }
+
+    fn grant(&mut self, key: RowKey, id: TransactionId, exclusive: bool) {
+        let holders = self.rows.entry(key.clone()).or_default();
+        if exclusive {
+            holders.exclusive = Some(id);
+        } else {
+            holders.shared.insert(id);
+        }
+        self.held.entry(id).or_default().insert(key);
+    }
+
+    /// Whether transactions that `id` waits for wait for it in turn.
+    fn deadlocked(&self, id: TransactionId) -> bool {
+        let mut visited = HashSet::new();
+        let mut pending = self.waits_for.get(&id).map(|ids| ids.iter().cloned().collect::<Vec<TransactionId>>()).unwrap_or_default();
+        while let Some(next) = pending.pop() {
+            if next == id {
+                return true;
+            }
+            if visited.insert(next) {
+                pending.extend(self.waits_for.get(&next).iter().flat_map(|ids| ids.iter().cloned()));
+            }
+        }
+        false
+    }
+}
+
+impl LockManager {
+
+    /// Returns `false` when the row is locked by another transaction and
+    /// `wait` is not set. A transaction that would close a cycle of waiting
+    /// transactions fails instead of waiting.
+    pub fn acquire(&self, id: TransactionId, key: RowKey, exclusive: bool, wait: bool, timeout: Option<Duration>) -> Result<bool, String> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut locks = self.locks.lock().unwrap();
+        loop {
+            let blockers = locks.blockers(&key, id, exclusive);
+            if blockers.is_empty() {
+                locks.waits_for.remove(&id);
+                locks.grant(key, id, exclusive);
+                return Ok(true);
+            }
+            if !wait {
+                return Ok(false);
+            }
+            locks.waits_for.insert(id, blockers);
+            if locks.deadlocked(id) {
+                locks.waits_for.remove(&id);
+                return Err(DEADLOCK.into());
+            }
+            locks = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        locks.waits_for.remove(&id);
+                        return Err("canceling statement due to lock timeout".into());
+                    }
+                    self.released.wait_timeout(locks, deadline - now).unwrap().0
+                },
+                None => self.released.wait(locks).unwrap()
+            };
+        }
+    }
+
+    pub fn release_all(&self, id: TransactionId) {
+        let mut locks = self.locks.lock().unwrap();
+        for key in locks.held.remove(&id).unwrap_or_default() {
+            let empty = match locks.rows.get_mut(&key) {
+                Some(holders) => {
+                    if holders.exclusive == Some(id) {
+                        holders.exclusive = None;
+                    }
+                    holders.shared.remove(&id);
+                    holders.exclusive.is_none() && holders.shared.is_empty()
+                },
+                None => false
+            };
+            if empty {
+                locks.rows.remove(&key);
+            }
+        }
+        locks.waits_for.remove(&id);
+        drop(locks);
+        self.released.notify_all();
+    }
+}
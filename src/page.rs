@@ -0,0 +1,220 @@
+use std::fmt;
+
+pub const PAGE_SIZE: usize = 8192;
+
+/// owner, number of slots and the start of tuple data
+const HEADER_SIZE: usize = 8;
+/// offset and length of a tuple
+const SLOT_SIZE: usize = 4;
+
+const CORRUPTED: &str = "page is corrupted";
+
+/// Biggest tuple that fits into an empty page.
+pub const MAX_TUPLE_SIZE: usize = PAGE_SIZE - HEADER_SIZE - SLOT_SIZE;
+
+/// Fixed-size block of a database file in slotted layout: the slot
+/// directory grows from the header towards the end of the page and tuples
+/// are packed from the end of the page towards the directory. A tuple keeps
+/// its slot for its whole life, so it can be referenced by page and slot
+/// while the page moves tuples around to reclaim space of deleted ones.
+/// A slot of a deleted tuple has zero length and is reused by inserts.
+#[derive(Clone)]
+pub struct Page {
+    bytes: Box<[u8]>
+}
+
+impl Page {
+
+    /// Empty page that holds tuples of `owner`.
+    pub fn new(owner: u32) -> Page {
+        let mut page = Page { bytes: vec![0; PAGE_SIZE].into_boxed_slice() };
+        page.write_u32(0, owner);
+        page.write_u16(4, 0);
+        page.write_u16(6, PAGE_SIZE as u16);
+        page
+    }
+
+    /// Checks that the slot directory and tuples of the page fit into it.
+    pub fn from_bytes(bytes: Box<[u8]>) -> Result<Page, String> {
+        if bytes.len() != PAGE_SIZE {
+            return Err(format!("page has {} bytes instead of {}", bytes.len(), PAGE_SIZE));
+        }
+        let page = Page { bytes };
+        let directory_end = HEADER_SIZE + page.slot_count() * SLOT_SIZE;
+        if directory_end > page.data_start() || page.data_start() > PAGE_SIZE {
+            return Err(CORRUPTED.into());
+        }
+        let outside = page.slots()
+            .filter_map(|slot| page.live_slot(slot as u16))
+            .any(|(offset, length)| offset < directory_end || offset + length > PAGE_SIZE);
+        if outside {
+            return Err(CORRUPTED.into());
+        }
+        page.free_space()?;
+        Ok(page)
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn owner(&self) -> u32 {
+        self.read_u32(0)
+    }
+
+    /// Size of the biggest tuple that can be inserted into the page. A page
+    /// whose slots and tuples take more than its size is corrupted.
+    pub fn free_space(&self) -> Result<usize, String> {
+        let used = self.slots().map(|slot| self.slot(slot).1).sum::<usize>();
+        let slots = self.slot_count() * SLOT_SIZE;
+        let free = (PAGE_SIZE - HEADER_SIZE).checked_sub(slots)
+            .and_then(|free| free.checked_sub(used))
+            .ok_or_else(|| CORRUPTED.to_owned())?;
+        if self.empty_slot().is_some() {
+            Ok(free)
+        } else {
+            Ok(free.saturating_sub(SLOT_SIZE))
+        }
+    }
+
+    /// Slot of the inserted tuple or `None` when the page doesn't have
+    /// enough space for it.
+    pub fn insert(&mut self, tuple: &[u8]) -> Option<u16> {
+        match self.free_space() {
+            Ok(free) if !tuple.is_empty() && tuple.len() <= free => {},
+            _ => return None
+        }
+        let empty = self.empty_slot();
+        let directory_end = HEADER_SIZE + (self.slot_count() + empty.map_or(1, |_| 0)) * SLOT_SIZE;
+        if self.data_start() < directory_end + tuple.len() {
+            self.compact();
+        }
+        let slot = match empty {
+            Some(slot) => slot,
+            None => {
+                let slot = self.slot_count();
+                self.write_u16(4, (slot + 1) as u16);
+                slot
+            }
+        };
+        let offset = self.data_start() - tuple.len();
+        self.bytes[offset..offset + tuple.len()].copy_from_slice(tuple);
+        self.write_u16(6, offset as u16);
+        self.set_slot(slot, offset, tuple.len());
+        Some(slot as u16)
+    }
+
+    pub fn tuple(&self, slot: u16) -> Option<&[u8]> {
+        match self.live_slot(slot) {
+            Some((offset, length)) => Some(&self.bytes[offset..offset + length]),
+            None => None
+        }
+    }
+
+    /// Tuple to change in place, its size stays the same.
+    pub fn tuple_mut(&mut self, slot: u16) -> Option<&mut [u8]> {
+        match self.live_slot(slot) {
+            Some((offset, length)) => Some(&mut self.bytes[offset..offset + length]),
+            None => None
+        }
+    }
+
+    pub fn delete(&mut self, slot: u16) -> bool {
+        match self.live_slot(slot) {
+            Some(_) => {
+                self.set_slot(slot as usize, 0, 0);
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Live tuples of the page in the order of their slots.
+    pub fn tuples(&self) -> Vec<(u16, &[u8])> {
+        self.slots()
+            .filter_map(|slot| self.tuple(slot as u16).map(|tuple| (slot as u16, tuple)))
+            .collect()
+    }
+
+    /// Moves tuples to the end of the page so that the free space between
+    /// them and the slot directory is contiguous.
+    fn compact(&mut self) {
+        let mut tuples = self.slots()
+            .filter_map(|slot| self.live_slot(slot as u16).map(|(offset, length)| (slot, offset, length)))
+            .collect::<Vec<(usize, usize, usize)>>();
+        tuples.sort_by_key(|&(_, offset, _)| offset);
+        let mut end = PAGE_SIZE;
+        for &(slot, offset, length) in tuples.iter().rev() {
+            end -= length;
+            self.bytes.copy_within(offset..offset + length, end);
+            self.set_slot(slot, end, length);
+        }
+        self.write_u16(6, end as u16);
+    }
+
+    fn live_slot(&self, slot: u16) -> Option<(usize, usize)> {
+        let slot = slot as usize;
+        if slot >= self.slot_count() {
+            return None;
+        }
+        match self.slot(slot) {
+            (_, 0) => None,
+            live => Some(live)
+        }
+    }
+
+    fn empty_slot(&self) -> Option<usize> {
+        self.slots().find(|&slot| self.slot(slot).1 == 0)
+    }
+
+    fn slots(&self) -> ::std::ops::Range<usize> {
+        0..self.slot_count()
+    }
+
+    fn slot_count(&self) -> usize {
+        self.read_u16(4) as usize
+    }
+
+    fn data_start(&self) -> usize {
+        self.read_u16(6) as usize
+    }
+
+    fn slot(&self, slot: usize) -> (usize, usize) {
+        let position = HEADER_SIZE + slot * SLOT_SIZE;
+        (self.read_u16(position) as usize, self.read_u16(position + 2) as usize)
+    }
+
+    fn set_slot(&mut self, slot: usize, offset: usize, length: usize) {
+        let position = HEADER_SIZE + slot * SLOT_SIZE;
+        self.write_u16(position, offset as u16);
+        self.write_u16(position + 2, length as u16);
+    }
+
+    fn read_u16(&self, position: usize) -> u16 {
+        u16::from_le_bytes([self.bytes[position], self.bytes[position + 1]])
+    }
+
+    fn read_u32(&self, position: usize) -> u32 {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&self.bytes[position..position + 4]);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn write_u16(&mut self, position: usize, value: u16) {
+        self.bytes[position..position + 2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, position: usize, value: u32) {
+        self.bytes[position..position + 4].copy_from_slice(&value.to_le_bytes());
+    }
+}
+
+impl fmt::Debug for Page {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.free_space() {
+            Ok(free) => write!(f, "page of {}, tuples: {}, free space: {}", self.owner(), self.tuples().len(), free),
+            Err(error) => write!(f, "page of {}: {}", self.owner(), error)
+        }
+    }
+}
@@ -2,24 +2,233 @@ use std::iter::{self, Peekable};
 use std::error::Error;
 
 use super::lexer::{Token, Tokens};
-use super::ast::{Type, CondType, RawStatement, RawColumn, Condition, CondArg};
-use super::ast::create_table::{CreateTableQuery, ColumnTable};
+use super::ast::{Type, RawStatement, RawColumn, Expression, Operator, CaseExpression};
+use super::ast::create_sequence::CreateSequenceQuery;
+use super::ast::create_table::{CreateTableQuery, ColumnTable, TableKey, IdentityGeneration, Generation};
+use super::ast::create_view::{CreateViewQuery, DropViewQuery, RefreshViewQuery};
 use super::ast::delete_query::DeleteQuery;
-use super::ast::insert_query::{Value, ValueSource, InsertQuery};
-use super::ast::select_query::SelectQuery;
+use super::ast::insert_query::{Value, ValueSource, InsertQuery, OnConflict, ConflictAction};
+use super::ast::function::{Function, TrimSide, PatternMatch, UserCall};
+use super::ast::window::{WindowCall, WindowFunction, WindowFrame, FrameUnits, FrameBound};
+use super::ast::update_query::UpdateQuery;
+use super::ast::transaction::{TransactionStatement, IsolationLevel};
+use super::ast::select_query::{SelectQuery, Locking, LockStrength, LockWait, Projection, OrderingTerm, SortOrder, NullsOrder, SetOperation, SetOperator, WithClause, CommonTableExpression};
 
 pub fn parse(tokens: Tokens) -> Result<RawStatement, String> {
-    let mut iter = tokens.into_iter();
+    let mut iter = tokens.into_iter().peekable();
     match iter.next() {
-        Some(Token::Create) => Ok(RawStatement::Create(try!(parse_create_table(iter.by_ref())))),
-        Some(Token::Delete) => Ok(RawStatement::Delete(parse_delete_query(iter.by_ref()))),
-        Some(Token::Insert) => Ok(RawStatement::Insert(parse_insert_query(iter.by_ref()))),
-        Some(Token::Select) => Ok(RawStatement::Select(parse_select_query(iter.by_ref()))),
+        Some(Token::Create) => match iter.peek() {
+            Some(&Token::Sequence) => {
+                iter.next();
+                Ok(RawStatement::CreateSequence(parse_create_sequence(iter.by_ref())?))
+            },
+            Some(&Token::View) | Some(&Token::Materialized) => Ok(RawStatement::CreateView(parse_create_view(iter.by_ref())?)),
+            Some(Token::Ident(word)) if word == "temporary" || word == "temp" => {
+                iter.next();
+                let mut query = parse_create_table(iter.by_ref())?;
+                query.temporary = true;
+                Ok(RawStatement::Create(query))
+            },
+            _ => Ok(RawStatement::Create(try!(parse_create_table(iter.by_ref()))))
+        },
+        Some(Token::Drop) => Ok(RawStatement::DropView(parse_drop_view(iter.by_ref())?)),
+        Some(Token::Refresh) => {
+            parse_view_keyword(iter.by_ref(), true)?;
+            let name = parse_view_name(iter.by_ref())?;
+            parse_end_of_statement(iter.by_ref())?;
+            Ok(RawStatement::RefreshView(RefreshViewQuery::new(name)))
+        },
+        Some(Token::Begin) => {
+            parse_transaction_keyword(iter.by_ref());
+            let isolation = parse_isolation_level(iter.by_ref())?;
+            parse_end_of_statement(iter.by_ref())?;
+            Ok(RawStatement::Transaction(TransactionStatement::Begin(isolation)))
+        },
+        Some(Token::Ident(ref word)) if word == "start" => {
+            match iter.next() {
+                Some(Token::Ident(ref word)) if word == "transaction" => {},
+                token => return Err(format!("expected 'TRANSACTION' but found {:?}", token))
+            }
+            let isolation = parse_isolation_level(iter.by_ref())?;
+            parse_end_of_statement(iter.by_ref())?;
+            Ok(RawStatement::Transaction(TransactionStatement::Begin(isolation)))
+        },
+        Some(Token::Commit) | Some(Token::End) => {
+            parse_transaction_keyword(iter.by_ref());
+            parse_end_of_statement(iter.by_ref())?;
+            Ok(RawStatement::Transaction(TransactionStatement::Commit))
+        },
+        Some(Token::Rollback) => {
+            parse_transaction_keyword(iter.by_ref());
+            let statement = match iter.peek() {
+                Some(Token::Ident(word)) if word == "to" => {
+                    iter.next();
+                    if iter.peek() == Some(&Token::Savepoint) {
+                        iter.next();
+                    }
+                    TransactionStatement::RollbackTo(parse_savepoint_name(iter.by_ref())?)
+                },
+                _ => TransactionStatement::Rollback
+            };
+            parse_end_of_statement(iter.by_ref())?;
+            Ok(RawStatement::Transaction(statement))
+        },
+        Some(Token::Savepoint) => {
+            let name = parse_savepoint_name(iter.by_ref())?;
+            parse_end_of_statement(iter.by_ref())?;
+            Ok(RawStatement::Transaction(TransactionStatement::Savepoint(name)))
+        },
+        Some(Token::Release) => {
+            if iter.peek() == Some(&Token::Savepoint) {
+                iter.next();
+            }
+            let name = parse_savepoint_name(iter.by_ref())?;
+            parse_end_of_statement(iter.by_ref())?;
+            Ok(RawStatement::Transaction(TransactionStatement::Release(name)))
+        },
+        Some(Token::Delete) => Ok(RawStatement::Delete(parse_delete_query(iter.by_ref())?)),
+        Some(Token::Insert) => Ok(RawStatement::Insert(try!(parse_insert_query(iter.by_ref())))),
+        Some(Token::Update) => Ok(RawStatement::Update(parse_update_query(iter.by_ref())?)),
+        Some(Token::Select) => {
+            let query = parse_select_query(iter.by_ref())?;
+            parse_end_of_statement(iter.by_ref())?;
+            Ok(RawStatement::Select(query))
+        },
+        Some(Token::With) => {
+            let with = parse_with_clause(iter.by_ref())?;
+            parse_select_keyword(iter.by_ref())?;
+            let mut query = parse_select_query(iter.by_ref())?;
+            query.with = Some(with);
+            parse_end_of_statement(iter.by_ref())?;
+            Ok(RawStatement::Select(query))
+        },
         token => panic!("unimplemented parsing procedure for {:?}", token),
     }
 }
 
-fn parse_create_table<I: Iterator<Item = Token>>(tokens: &mut I) -> Result<CreateTableQuery, String> {
+/// Parses statements separated by semicolons, an error names the statement
+/// it was found in counting from 1.
+pub fn parse_script(tokens: Tokens) -> Result<Vec<RawStatement>, String> {
+    split_statements(tokens).into_iter()
+        .enumerate()
+        .map(|(index, statement)| parse(statement).map_err(|e| format!("statement {}: {}", index + 1, e)))
+        .collect()
+}
+
+/// Tokens of every statement of a script together with the semicolon that
+/// ends it, empty statements are skipped.
+pub fn split_statements(tokens: Tokens) -> Vec<Tokens> {
+    let mut statements = vec![];
+    let mut statement = vec![];
+    for token in tokens {
+        let end = token == Token::Semicolon;
+        statement.push(token);
+        if end {
+            if statement.len() > 1 {
+                statements.push(statement);
+            }
+            statement = vec![];
+        }
+    }
+    if !statement.is_empty() {
+        statements.push(statement);
+    }
+    statements
+}
+
+fn parse_create_view<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<CreateViewQuery<RawColumn>, String> {
+    let materialized = tokens.peek() == Some(&Token::Materialized);
+    parse_view_keyword(tokens.by_ref(), materialized)?;
+    let name = parse_view_name(tokens.by_ref())?;
+    let columns = if tokens.peek() == Some(&Token::LParent) {
+        tokens.next();
+        parse_column_names(tokens.by_ref())?
+    } else {
+        vec![]
+    };
+    if tokens.next() != Some(Token::As) {
+        return Err(format!("missed 'AS' after view '{}'", name));
+    }
+    let with = if tokens.peek() == Some(&Token::With) {
+        tokens.next();
+        Some(parse_with_clause(tokens.by_ref())?)
+    } else {
+        None
+    };
+    parse_select_keyword(tokens.by_ref())?;
+    let mut query = parse_select_query(tokens.by_ref())?;
+    query.with = with;
+    parse_end_of_statement(tokens.by_ref())?;
+    Ok(CreateViewQuery::new(name, columns, query.clone(), query, materialized))
+}
+
+fn parse_drop_view<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<DropViewQuery, String> {
+    let materialized = tokens.peek() == Some(&Token::Materialized);
+    parse_view_keyword(tokens.by_ref(), materialized)?;
+    let name = parse_view_name(tokens.by_ref())?;
+    parse_end_of_statement(tokens.by_ref())?;
+    Ok(DropViewQuery::new(name, materialized))
+}
+
+/// `VIEW` or `MATERIALIZED VIEW` when `materialized` is set.
+fn parse_view_keyword<I: Iterator<Item = Token>>(tokens: &mut I, materialized: bool) -> Result<(), String> {
+    if materialized {
+        match tokens.next() {
+            Some(Token::Materialized) => {},
+            token => return Err(format!("expected 'MATERIALIZED' but found {:?}", token))
+        }
+    }
+    match tokens.next() {
+        Some(Token::View) => Ok(()),
+        token => Err(format!("expected 'VIEW' but found {:?}", token))
+    }
+}
+
+fn parse_view_name<I: Iterator<Item = Token>>(tokens: &mut I) -> Result<String, String> {
+    match tokens.next() {
+        Some(Token::Ident(name)) => Ok(name),
+        token => Err(format!("expected name of view but found {:?}", token))
+    }
+}
+
+/// Skips optional `WORK` or `TRANSACTION` after a transaction keyword.
+fn parse_transaction_keyword<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) {
+    if let Some(Token::Ident(word)) = tokens.peek() {
+        if word == "work" || word == "transaction" {
+            tokens.next();
+        }
+    }
+}
+
+/// `ISOLATION LEVEL { READ COMMITTED | REPEATABLE READ | SNAPSHOT | SERIALIZABLE }`
+fn parse_isolation_level<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Option<IsolationLevel>, String> {
+    match tokens.peek() {
+        Some(Token::Ident(word)) if word == "isolation" => {},
+        _ => return Ok(None)
+    }
+    tokens.next();
+    let mut words = vec![];
+    while let Some(Token::Ident(word)) = tokens.peek() {
+        words.push(word.clone());
+        tokens.next();
+    }
+    match words.iter().map(String::as_str).collect::<Vec<&str>>().as_slice() {
+        ["level", "read", "committed"] => Ok(Some(IsolationLevel::ReadCommitted)),
+        ["level", "repeatable", "read"] => Ok(Some(IsolationLevel::RepeatableRead)),
+        ["level", "snapshot"] => Ok(Some(IsolationLevel::Snapshot)),
+        ["level", "serializable"] => Ok(Some(IsolationLevel::Serializable)),
+        _ => Err(format!("unrecognized isolation level '{}'", words.join(" ")))
+    }
+}
+
+fn parse_savepoint_name<I: Iterator<Item = Token>>(tokens: &mut I) -> Result<String, String> {
+    match tokens.next() {
+        Some(Token::Ident(name)) => Ok(name),
+        token => Err(format!("expected name of savepoint but found {:?}", token))
+    }
+}
+
+fn parse_create_table<I: Iterator<Item = Token>>(tokens: &mut I) -> Result<CreateTableQuery<RawColumn>, String> {
     if tokens.next() != Some(Token::Table) {
         unimplemented!();
     }
@@ -30,37 +239,107 @@ fn parse_create_table<I: Iterator<Item = Token>>(tokens: &mut I) -> Result<Creat
     };
 
     let mut columns = vec![];
+    let mut keys = vec![];
 
     let mut has_semicolon = false;
     while let Some(token) = tokens.next() {
         match token {
-            Token::LParent => {},
+            Token::LParent | Token::Comma | Token::RParent => {},
             Token::Semicolon => {
                 has_semicolon = true;
                 break
             },
             Token::Ident(name) => columns.push(try!(parse_table_column(tokens.by_ref(), name))),
+            Token::Primary => {
+                if tokens.next() != Some(Token::Key) {
+                    return Err("expected 'KEY' after 'PRIMARY'".into());
+                }
+                keys.push(TableKey::new(parse_key_columns(tokens.by_ref())?, true));
+            },
+            Token::Unique => keys.push(TableKey::new(parse_key_columns(tokens.by_ref())?, false)),
             token => panic!("unexpected token {:?}", token)
         }
     }
     if !has_semicolon {
         Err("missed ';' in the end of statement".into())
     } else {
-        Ok(CreateTableQuery::new(table_name, columns))
+        let mut query = CreateTableQuery::new(table_name, columns);
+        query.keys = keys;
+        Ok(query)
     }
 }
 
-fn parse_table_column<I: Iterator<Item = Token>>(tokens: &mut I, column_name: String) -> Result<ColumnTable, String> {
+/// `(column, ...)` of a table key.
+fn parse_key_columns<I: Iterator<Item = Token>>(tokens: &mut I) -> Result<Vec<String>, String> {
+    if tokens.next() != Some(Token::LParent) {
+        return Err("expected list of key columns".into());
+    }
+    Ok(parse_columns(tokens.by_ref()).into_iter().map(|column| column.name).collect())
+}
+
+fn parse_create_sequence<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<CreateSequenceQuery, String> {
+    let name = match tokens.next() {
+        Some(Token::Ident(name)) => name,
+        token => return Err(format!("unexpected token {:?}", token))
+    };
+    let mut query = CreateSequenceQuery::new(name, 1, 1);
+    loop {
+        match tokens.peek() {
+            Some(Token::Ident(option)) if option == "start" => {
+                tokens.next();
+                if tokens.peek() == Some(&Token::With) {
+                    tokens.next();
+                }
+                query.start = parse_signed_integer(tokens.by_ref())?;
+            },
+            Some(Token::Ident(option)) if option == "increment" => {
+                tokens.next();
+                if tokens.peek() == Some(&Token::By) {
+                    tokens.next();
+                }
+                query.increment = parse_signed_integer(tokens.by_ref())?;
+            },
+            _ => break
+        }
+    }
+    parse_end_of_statement(tokens.by_ref())?;
+    Ok(query)
+}
+
+fn parse_signed_integer<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<i64, String> {
+    let negative = tokens.peek() == Some(&Token::Minus);
+    if negative {
+        tokens.next();
+    }
+    match tokens.next() {
+        Some(Token::NumConst(num)) => {
+            let num = if negative { format!("-{}", num) } else { num };
+            num.parse::<i64>().map_err(|e| e.to_string())
+        },
+        token => Err(format!("unexpected token {:?}", token))
+    }
+}
+
+fn parse_table_column<I: Iterator<Item = Token>>(tokens: &mut I, column_name: String) -> Result<ColumnTable<RawColumn>, String> {
     let mut tokens = tokens.peekable();
+    let mut identity = None;
     let column_type = match tokens.next() {
         Some(Token::Int) => Type::Integer,
+        Some(Token::Serial) => {
+            identity = Some(IdentityGeneration::ByDefault);
+            Type::Integer
+        },
         Some(Token::Character) => try!(parse_char_type(tokens.by_ref())),
+        Some(Token::Boolean) => Type::Boolean,
         token => panic!("Unexpected token - {:?}", token),
     };
     let mut is_primary_key = false;
     let mut foreign_key = None;
     let mut is_nullable = true;
+    let mut is_unique = false;
     let mut default_value = None;
+    let mut generated = None;
+    let mut not_null = false;
     while let Some(token) = tokens.next() {
         match token {
             Token::Primary => {
@@ -69,6 +348,28 @@ fn parse_table_column<I: Iterator<Item = Token>>(tokens: &mut I, column_name: St
                 }
                 is_primary_key = true;
             },
+            Token::Unique => is_unique = true,
+            Token::Generated => {
+                let generation = match tokens.next() {
+                    Some(Token::Always) => IdentityGeneration::Always,
+                    Some(Token::By) => {
+                        if tokens.next() != Some(Token::Default) {
+                            return Err("expected 'DEFAULT' after 'BY'".into());
+                        }
+                        IdentityGeneration::ByDefault
+                    },
+                    token => return Err(format!("unexpected token {:?}", token))
+                };
+                if tokens.next() != Some(Token::As) {
+                    return Err("expected 'AS' after 'GENERATED'".into());
+                }
+                match tokens.next() {
+                    Some(Token::Identity) => identity = Some(generation),
+                    Some(Token::LParent) if generation == IdentityGeneration::Always => generated = Some(parse_generation(tokens.by_ref())?),
+                    Some(Token::LParent) => return Err("for a generated column, GENERATED ALWAYS must be specified".into()),
+                    token => return Err(format!("unexpected token {:?}", token))
+                }
+            },
             Token::Foreign => {
                 if Some(Token::Key) != tokens.next() {
                     unimplemented!()
@@ -94,26 +395,12 @@ fn parse_table_column<I: Iterator<Item = Token>>(tokens: &mut I, column_name: St
                     token => panic!("unexpected token {:?}", token)
                 }
             },
-            Token::Default => {
-                match tokens.next() {
-                    Some(Token::NumConst(const_val)) |
-                    Some(Token::CharsConst(const_val)) => { default_value = Option::from(const_val) },
-                    token => panic!("unexpected token {:?}", token)
-                }
-            },
+            Token::Default => default_value = Some(parse_concatenation(tokens.by_ref())?),
             Token::Not => {
                 match tokens.next() {
                     Some(Token::Null) => {
                         is_nullable = false;
-                        match column_type {
-                            Type::Integer => {
-                                default_value = Option::from(String::from("0"));
-                            },
-                            Type::Character(Some(len)) => {
-                                default_value = Option::from(iter::repeat(" ").take(len as usize).collect::<String>());
-                            },
-                            Type::Character(None) => {}
-                        }
+                        not_null = true;
                     },
                     t => panic!("unexpected token {:?}", t)
                 }
@@ -127,7 +414,41 @@ fn parse_table_column<I: Iterator<Item = Token>>(tokens: &mut I, column_name: St
             t => panic!("unexpected token {:?}", t)
         }
     };
-    Ok(ColumnTable::new(column_name, column_type, is_primary_key, foreign_key, is_nullable, default_value))
+    if not_null && default_value.is_none() && generated.is_none() {
+        match column_type {
+            Type::Integer => {
+                default_value = Some(Expression::Const(Value::new("0", column_type)));
+            },
+            Type::Character(Some(len)) => {
+                default_value = Some(Expression::Const(Value::new(iter::repeat(" ").take(len as usize).collect::<String>(), column_type)));
+            },
+            Type::Character(None) | Type::Unknown => {},
+            Type::Boolean => {
+                default_value = Some(Expression::Const(Value::new("false", column_type)));
+            }
+        }
+    }
+    let mut column = ColumnTable::new(column_name, column_type, is_primary_key, foreign_key, is_nullable, default_value);
+    column.is_unique = is_unique;
+    column.identity = identity;
+    column.generated = generated;
+    Ok(column)
+}
+
+/// `(expr) [STORED | VIRTUAL]` after `GENERATED ALWAYS AS`, columns are
+/// virtual unless stated otherwise.
+fn parse_generation<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Generation<RawColumn>, String> {
+    let expr = parse_expression(tokens.by_ref())?;
+    if tokens.next() != Some(Token::RParent) {
+        return Err("expected ')' after generation expression".into());
+    }
+    let stored = match tokens.peek() {
+        Some(Token::Ident(kind)) if kind == "stored" => true,
+        Some(Token::Ident(kind)) if kind == "virtual" => false,
+        _ => return Ok(Generation::new(expr, false))
+    };
+    tokens.next();
+    Ok(Generation::new(expr, stored))
 }
 
 fn parse_char_type<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Type, String> {
@@ -191,7 +512,17 @@ fn parse_values<I: Iterator<Item = Token>>(tokens: &mut I) -> Vec<Value> {
     values
 }
 
-fn parse_insert_query<I: Iterator<Item = Token>>(tokens: &mut I) -> InsertQuery<RawColumn> {
+/// `VALUES (...), (...), ...`
+fn parse_rows<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Vec<Vec<Value>> {
+    let mut rows = vec![parse_values(tokens.by_ref())];
+    while tokens.peek() == Some(&Token::Comma) {
+        tokens.next();
+        rows.push(parse_values(tokens.by_ref()));
+    }
+    rows
+}
+
+fn parse_insert_query<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<InsertQuery<RawColumn>, String> {
     if tokens.next() != Some(Token::Into) {
         unimplemented!();
     }
@@ -218,18 +549,19 @@ fn parse_insert_query<I: Iterator<Item = Token>>(tokens: &mut I) -> InsertQuery<
         }
     }
 
-    if sub_query {
-        InsertQuery::new(table_name, columns.into_iter().collect(), ValueSource::SubQuery(parse_select_query(tokens.by_ref())))
+    let values = if sub_query {
+        ValueSource::SubQuery(parse_select_query(tokens.by_ref())?)
     } else {
-        let query = InsertQuery::new(table_name, columns.into_iter().collect(), ValueSource::Row(parse_values(tokens.by_ref())));
-        if tokens.next() != Some(Token::Semicolon) {
-            unimplemented!();
-        }
-        query
-    }
+        ValueSource::Rows(parse_rows(tokens.by_ref()))
+    };
+    let mut query = InsertQuery::new(table_name, columns.into_iter().collect(), values);
+    query.on_conflict = parse_on_conflict(tokens.by_ref())?;
+    query.returning = parse_returning(tokens.by_ref())?;
+    parse_end_of_statement(tokens.by_ref())?;
+    Ok(query)
 }
 
-fn parse_delete_query<I: Iterator<Item = Token>>(tokens: &mut I) -> DeleteQuery {
+fn parse_delete_query<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<DeleteQuery<RawColumn>, String> {
     if tokens.next() != Some(Token::From) {
         unimplemented!();
     }
@@ -239,57 +571,720 @@ fn parse_delete_query<I: Iterator<Item = Token>>(tokens: &mut I) -> DeleteQuery
         _ => unimplemented!(),
     };
 
-    DeleteQuery::new(table_name, parse_where(tokens.by_ref()))
+    let mut query = DeleteQuery::new(table_name, parse_where(tokens.by_ref())?);
+    query.returning = parse_returning(tokens.by_ref())?;
+    parse_end_of_statement(tokens.by_ref())?;
+    Ok(query)
+}
+
+fn parse_update_query<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<UpdateQuery<RawColumn>, String> {
+    let table_name = match tokens.next() {
+        Some(Token::Ident(name)) => name,
+        token => return Err(format!("unexpected token {:?}", token))
+    };
+    if tokens.next() != Some(Token::Set) {
+        return Err("expected 'SET' after table name".into());
+    }
+    let assignments = parse_assignments(tokens.by_ref())?;
+    let mut query = UpdateQuery::new(table_name, assignments, parse_where(tokens.by_ref())?);
+    query.returning = parse_returning(tokens.by_ref())?;
+    parse_end_of_statement(tokens.by_ref())?;
+    Ok(query)
+}
+
+/// `column = expr, ...` following `SET`.
+fn parse_assignments<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Vec<(RawColumn, Expression<RawColumn>)>, String> {
+    let mut assignments = vec![];
+    loop {
+        let column = match tokens.next() {
+            Some(Token::Ident(name)) => RawColumn::new(name),
+            token => return Err(format!("unexpected token {:?}", token))
+        };
+        if tokens.next() != Some(Token::EqualSign) {
+            return Err(format!("expected '=' after column {}", column));
+        }
+        assignments.push((column, parse_expression(tokens.by_ref())?));
+        if tokens.peek() != Some(&Token::Comma) {
+            break;
+        }
+        tokens.next();
+    }
+    Ok(assignments)
+}
+
+fn parse_on_conflict<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Option<OnConflict<RawColumn>>, String> {
+    if tokens.peek() != Some(&Token::On) {
+        return Ok(None);
+    }
+    tokens.next();
+    if tokens.next() != Some(Token::Conflict) {
+        return Err("expected 'CONFLICT' after 'ON'".into());
+    }
+    let target = if tokens.peek() == Some(&Token::LParent) {
+        parse_key_columns(tokens.by_ref())?
+    } else {
+        vec![]
+    };
+    if tokens.next() != Some(Token::Do) {
+        return Err("expected 'DO' in ON CONFLICT clause".into());
+    }
+    let action = match tokens.next() {
+        Some(Token::Nothing) => ConflictAction::Nothing,
+        Some(Token::Update) => {
+            if tokens.next() != Some(Token::Set) {
+                return Err("expected 'SET' after 'DO UPDATE'".into());
+            }
+            let assignments = parse_assignments(tokens.by_ref())?;
+            ConflictAction::Update(assignments, parse_where(tokens.by_ref())?)
+        },
+        token => return Err(format!("unexpected token {:?}", token))
+    };
+    Ok(Some(OnConflict::new(target, action)))
+}
+
+/// `RETURNING expr [AS alias], ...` of a data modifying statement.
+fn parse_returning<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Vec<Projection<RawColumn>>, String> {
+    let mut returning = vec![];
+    if tokens.peek() != Some(&Token::Returning) {
+        return Ok(returning);
+    }
+    tokens.next();
+    loop {
+        returning.push(parse_projection(tokens.by_ref())?);
+        if tokens.peek() != Some(&Token::Comma) {
+            break;
+        }
+        tokens.next();
+    }
+    Ok(returning)
+}
+
+fn parse_select_query<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<SelectQuery<RawColumn>, String> {
+    let mut query = parse_intersections(tokens.by_ref())?;
+    loop {
+        let operator = match tokens.peek() {
+            Some(&Token::Union) => {
+                tokens.next();
+                if tokens.peek() == Some(&Token::All) {
+                    tokens.next();
+                    SetOperator::UnionAll
+                } else {
+                    SetOperator::Union
+                }
+            },
+            Some(&Token::Except) => {
+                tokens.next();
+                SetOperator::Except
+            },
+            _ => break
+        };
+        parse_select_keyword(tokens.by_ref())?;
+        let branch = parse_intersections(tokens.by_ref())?;
+        query.set_operations.push(SetOperation::new(operator, branch));
+    }
+    if tokens.peek() == Some(&Token::Order) {
+        tokens.next();
+        query.order_by = parse_order_by(tokens.by_ref())?;
+    }
+    if tokens.peek() == Some(&Token::Limit) {
+        tokens.next();
+        query.limit = Some(parse_limit(tokens.by_ref())?);
+    }
+    if tokens.peek() == Some(&Token::For) {
+        tokens.next();
+        query.locking = Some(parse_locking(tokens.by_ref())?);
+    }
+    Ok(query)
+}
+
+fn parse_locking<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Locking, String> {
+    let strength = match tokens.next() {
+        Some(Token::Update) => LockStrength::Update,
+        Some(Token::Ident(ref word)) if word == "share" => LockStrength::Share,
+        token => return Err(format!("expected 'UPDATE' or 'SHARE' but found {:?}", token))
+    };
+    let wait = match tokens.peek() {
+        Some(Token::Ident(word)) if word == "nowait" => LockWait::NoWait,
+        Some(Token::Ident(word)) if word == "skip" => {
+            tokens.next();
+            match tokens.peek() {
+                Some(Token::Ident(word)) if word == "locked" => LockWait::SkipLocked,
+                token => return Err(format!("expected 'LOCKED' but found {:?}", token))
+            }
+        },
+        _ => return Ok(Locking::new(strength, LockWait::Wait))
+    };
+    tokens.next();
+    Ok(Locking::new(strength, wait))
+}
+
+/// `INTERSECT` binds tighter than `UNION` and `EXCEPT`, so its operands are
+/// attached to the nearest select before the other set operations.
+fn parse_intersections<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<SelectQuery<RawColumn>, String> {
+    let mut query = parse_select_core(tokens.by_ref())?;
+    while tokens.peek() == Some(&Token::Intersect) {
+        tokens.next();
+        parse_select_keyword(tokens.by_ref())?;
+        let branch = parse_select_core(tokens.by_ref())?;
+        query.set_operations.push(SetOperation::new(SetOperator::Intersect, branch));
+    }
+    Ok(query)
 }
 
-fn parse_select_query<I: Iterator<Item = Token>>(tokens: &mut I) -> SelectQuery<RawColumn> {
-    let columns = parse_columns_list(tokens.by_ref());
+fn parse_select_core<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<SelectQuery<RawColumn>, String> {
+    let distinct = tokens.peek() == Some(&Token::Distinct);
+    if distinct {
+        tokens.next();
+    }
+    let columns = parse_projections(tokens.by_ref())?;
 
     let table_name = match tokens.next() {
         Some(Token::Ident(table_name)) => table_name,
         _ => unimplemented!()
     };
 
-    SelectQuery::new(table_name, columns, parse_where(tokens.by_ref()))
+    let mut query = SelectQuery::new(table_name, columns, parse_where(tokens.by_ref())?);
+    query.distinct = distinct;
+    Ok(query)
+}
+
+fn parse_with_clause<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<WithClause<RawColumn>, String> {
+    let recursive = tokens.peek() == Some(&Token::Recursive);
+    if recursive {
+        tokens.next();
+    }
+    let mut ctes = vec![];
+    loop {
+        let name = match tokens.next() {
+            Some(Token::Ident(name)) => name,
+            token => return Err(format!("expected name of common table expression but found {:?}", token))
+        };
+        let columns = if tokens.peek() == Some(&Token::LParent) {
+            tokens.next();
+            parse_column_names(tokens.by_ref())?
+        } else {
+            vec![]
+        };
+        if tokens.next() != Some(Token::As) {
+            return Err(format!("missed 'AS' after common table expression '{}'", name));
+        }
+        let query = parse_sub_query(tokens.by_ref())?;
+        ctes.push(CommonTableExpression::new(name, columns, query));
+        if tokens.peek() != Some(&Token::Comma) {
+            break;
+        }
+        tokens.next();
+    }
+    Ok(WithClause::new(recursive, ctes))
 }
 
-fn parse_columns_list<I: Iterator<Item = Token>>(tokens: &mut I) -> Vec<RawColumn> {
+/// Comma separated names up to the closing parenthesis.
+fn parse_column_names<I: Iterator<Item = Token>>(tokens: &mut I) -> Result<Vec<String>, String> {
     let mut columns = vec![];
     loop {
         match tokens.next() {
-            Some(Token::From) => break, // skip 'FROM' keyword
-            Some(Token::Ident(column_name)) => columns.push(RawColumn::new(column_name)),
+            Some(Token::Ident(column)) => columns.push(column),
+            token => return Err(format!("unexpected token {:?}", token))
+        }
+        match tokens.next() {
             Some(Token::Comma) => {},
-            t => panic!("unexpected token {:?} - ", t),
+            Some(Token::RParent) => return Ok(columns),
+            token => return Err(format!("expected ')' but found {:?}", token))
         }
     }
-    columns
 }
 
-fn parse_where<I: Iterator<Item = Token>>(tokens: &mut I) -> Option<Condition> {
+fn parse_select_keyword<I: Iterator<Item = Token>>(tokens: &mut I) -> Result<(), String> {
+    match tokens.next() {
+        Some(Token::Select) => Ok(()),
+        token => Err(format!("expected 'SELECT' but found {:?}", token))
+    }
+}
+
+fn parse_end_of_statement<I: Iterator<Item = Token>>(tokens: &mut I) -> Result<(), String> {
     match tokens.next() {
-        Some(Token::Where) => {
-            let left = parse_predicate_arguments(tokens.by_ref());
+        Some(Token::Semicolon) | None => Ok(()),
+        token => Err(format!("unexpected token {:?}", token))
+    }
+}
+
+fn parse_projections<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Vec<Projection<RawColumn>>, String> {
+    let mut columns = vec![];
+    loop {
+        columns.push(parse_projection(tokens.by_ref())?);
+        match tokens.next() {
+            Some(Token::From) => break, // skip 'FROM' keyword
+            Some(Token::Comma) => {},
+            token => return Err(format!("unexpected token {:?}", token))
+        }
+    }
+    Ok(columns)
+}
+
+fn parse_projection<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Projection<RawColumn>, String> {
+    let expr = parse_expression(tokens.by_ref())?;
+    let alias = if tokens.peek() == Some(&Token::As) {
+        tokens.next();
+        match tokens.next() {
+            Some(Token::Ident(alias)) => Some(alias),
+            token => return Err(format!("unexpected token {:?}", token))
+        }
+    } else {
+        None
+    };
+    Ok(Projection::new(expr, alias))
+}
+
+fn parse_expression<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Expression<RawColumn>, String> {
+    let mut left = parse_conjunction(tokens.by_ref())?;
+    while tokens.peek() == Some(&Token::Or) {
+        tokens.next();
+        let right = parse_conjunction(tokens.by_ref())?;
+        left = Expression::binary(left, Operator::Or, right);
+    }
+    Ok(left)
+}
+
+fn parse_conjunction<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Expression<RawColumn>, String> {
+    let mut left = parse_negation(tokens.by_ref())?;
+    while tokens.peek() == Some(&Token::And) {
+        tokens.next();
+        let right = parse_negation(tokens.by_ref())?;
+        left = Expression::binary(left, Operator::And, right);
+    }
+    Ok(left)
+}
+
+fn parse_negation<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Expression<RawColumn>, String> {
+    if tokens.peek() == Some(&Token::Not) {
+        tokens.next();
+        Ok(Expression::Not(Box::new(parse_negation(tokens.by_ref())?)))
+    } else {
+        parse_comparison(tokens.by_ref())
+    }
+}
+
+fn parse_comparison<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Expression<RawColumn>, String> {
+    if tokens.peek() == Some(&Token::Exists) {
+        tokens.next();
+        return Ok(Expression::Exists(Box::new(parse_sub_query(tokens.by_ref())?)));
+    }
+    let left = parse_concatenation(tokens.by_ref())?;
+    let operator = match tokens.peek() {
+        Some(&Token::EqualSign) => Operator::Eq,
+        Some(&Token::NotEqualSign) => Operator::NotEq,
+        Some(&Token::Less) => Operator::Less,
+        Some(&Token::LessEqual) => Operator::LessEq,
+        Some(&Token::Greater) => Operator::Greater,
+        Some(&Token::GreaterEqual) => Operator::GreaterEq,
+        Some(&Token::In) | Some(&Token::Like) | Some(&Token::ILike) => return parse_membership(left, tokens.by_ref()),
+        Some(&Token::Not) => {
+            tokens.next();
+            match tokens.peek() {
+                Some(&Token::In) | Some(&Token::Like) | Some(&Token::ILike) =>
+                    return Ok(Expression::Not(Box::new(parse_membership(left, tokens.by_ref())?))),
+                _ => return Err("expected 'IN', 'LIKE' or 'ILIKE' after 'NOT'".into())
+            }
+        },
+        _ => return Ok(left)
+    };
+    tokens.next();
+    let right = parse_concatenation(tokens.by_ref())?;
+    Ok(Expression::binary(left, operator, right))
+}
+
+/// `left IN (subquery)` and `left LIKE | ILIKE pattern [ESCAPE escape]`
+fn parse_membership<I: Iterator<Item = Token>>(left: Expression<RawColumn>, tokens: &mut Peekable<I>) -> Result<Expression<RawColumn>, String> {
+    let case_insensitive = match tokens.next() {
+        Some(Token::In) => return Ok(Expression::In(Box::new(left), Box::new(parse_sub_query(tokens.by_ref())?))),
+        Some(Token::Like) => false,
+        Some(Token::ILike) => true,
+        token => return Err(format!("unexpected token {:?}", token))
+    };
+    let pattern = parse_concatenation(tokens.by_ref())?;
+    let escape = if tokens.peek() == Some(&Token::Escape) {
+        tokens.next();
+        Some(parse_concatenation(tokens.by_ref())?)
+    } else {
+        None
+    };
+    Ok(Expression::Like(Box::new(PatternMatch::new(left, pattern, escape, case_insensitive))))
+}
 
-            let cond_type = match tokens.next() {
-                Some(Token::EqualSign) => CondType::Eq,
-                Some(Token::NotEqualSign) => CondType::NotEq,
-                _ => unimplemented!(),
+fn parse_concatenation<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Expression<RawColumn>, String> {
+    let mut left = parse_operand(tokens.by_ref())?;
+    while tokens.peek() == Some(&Token::Concat) {
+        tokens.next();
+        let right = parse_operand(tokens.by_ref())?;
+        left = Expression::binary(left, Operator::Concat, right);
+    }
+    Ok(left)
+}
+
+/// `expr::type` casts bind tighter than any operator.
+fn parse_operand<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Expression<RawColumn>, String> {
+    let mut operand = parse_primary(tokens.by_ref())?;
+    while tokens.peek() == Some(&Token::DoubleColon) {
+        tokens.next();
+        operand = Expression::Cast(Box::new(operand), parse_type(tokens.by_ref())?);
+    }
+    Ok(operand)
+}
+
+fn parse_primary<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Expression<RawColumn>, String> {
+    match tokens.next() {
+        Some(Token::Ident(name)) => {
+            if tokens.peek() == Some(&Token::LParent) {
+                tokens.next();
+                parse_function_call(name, tokens.by_ref())
+            } else if name == "current_timestamp" {
+                Ok(Expression::Function(Function::CurrentTimestamp, vec![]))
+            } else {
+                Ok(Expression::Column(RawColumn::new(name)))
+            }
+        },
+        Some(Token::NumConst(num)) => Ok(Expression::Const(Value::new(num, Type::Integer))),
+        Some(Token::CharsConst(s)) => {
+            let size = s.len() as u8;
+            Ok(Expression::Const(Value::new(s, Type::Character(Option::from(size)))))
+        },
+        Some(Token::Null) => Ok(Expression::Const(Value::new("null", Type::Unknown))),
+        Some(Token::Limit) => Ok(Expression::Limit),
+        Some(Token::Case) => parse_case(tokens.by_ref()),
+        Some(Token::Cast) => {
+            if tokens.next() != Some(Token::LParent) {
+                return Err("expected '(' after 'CAST'".into());
+            }
+            let expr = parse_expression(tokens.by_ref())?;
+            if tokens.next() != Some(Token::As) {
+                return Err("missed 'AS' in CAST".into());
+            }
+            let target = parse_type(tokens.by_ref())?;
+            match tokens.next() {
+                Some(Token::RParent) => Ok(Expression::Cast(Box::new(expr), target)),
+                token => Err(format!("expected ')' but found {:?}", token))
+            }
+        },
+        Some(Token::LParent) => {
+            let expr = if tokens.peek() == Some(&Token::Select) {
+                tokens.next();
+                Expression::SubQuery(Box::new(parse_select_query(tokens.by_ref())?))
+            } else {
+                parse_expression(tokens.by_ref())?
             };
-            let right = parse_predicate_arguments(tokens.by_ref());
-            Some(Condition::new(left, right, cond_type))
+            match tokens.next() {
+                Some(Token::RParent) => Ok(expr),
+                token => Err(format!("expected ')' but found {:?}", token))
+            }
         },
-        Some(Token::Semicolon) => None,
-        _ => unimplemented!(),
+        token => Err(format!("unexpected token {:?}", token))
     }
 }
 
-fn parse_predicate_arguments<I: Iterator<Item = Token>>(tokens: &mut I) -> CondArg {
+fn parse_case<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Expression<RawColumn>, String> {
+    let operand = if tokens.peek() == Some(&Token::When) {
+        None
+    } else {
+        Some(parse_expression(tokens.by_ref())?)
+    };
+    let mut branches = vec![];
+    while tokens.peek() == Some(&Token::When) {
+        tokens.next();
+        let condition = parse_expression(tokens.by_ref())?;
+        if tokens.next() != Some(Token::Then) {
+            return Err("missed 'THEN' in CASE".into());
+        }
+        branches.push((condition, parse_expression(tokens.by_ref())?));
+    }
+    if branches.is_empty() {
+        return Err("CASE requires at least one WHEN clause".into());
+    }
+    let else_result = if tokens.peek() == Some(&Token::Else) {
+        tokens.next();
+        Some(parse_expression(tokens.by_ref())?)
+    } else {
+        None
+    };
     match tokens.next() {
-        Some(Token::CharsConst(s)) => CondArg::StringConstant(s),
-        Some(Token::NumConst(s)) => CondArg::NumConst(s),
-        Some(Token::Limit) => CondArg::Limit,
-        Some(Token::Ident(s)) => CondArg::ColumnName(s),
-        c => panic!("unexpected token - {:?}", c),
+        Some(Token::End) => Ok(Expression::Case(Box::new(CaseExpression::new(operand, branches, else_result)))),
+        token => Err(format!("expected 'END' but found {:?}", token))
+    }
+}
+
+fn parse_type<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Type, String> {
+    match tokens.next() {
+        Some(Token::Int) => Ok(Type::Integer),
+        Some(Token::Boolean) => Ok(Type::Boolean),
+        Some(Token::Character) => {
+            if tokens.peek() != Some(&Token::LParent) {
+                return Ok(Type::Character(None));
+            }
+            tokens.next();
+            let size = parse_size(tokens.by_ref())?;
+            match tokens.next() {
+                Some(Token::RParent) => Ok(Type::Character(Some(size))),
+                token => Err(format!("expected ')' but found {:?}", token))
+            }
+        },
+        token => Err(format!("expected type but found {:?}", token))
+    }
+}
+
+fn parse_function_call<I: Iterator<Item = Token>>(name: String, tokens: &mut Peekable<I>) -> Result<Expression<RawColumn>, String> {
+    match name.as_str() {
+        "substring" => return parse_substring(tokens.by_ref()),
+        "position" => return parse_position(tokens.by_ref()),
+        "trim" => return parse_trim(tokens.by_ref()),
+        _ => {}
+    }
+    let mut args = vec![];
+    if tokens.peek() == Some(&Token::RParent) {
+        tokens.next();
+    } else {
+        loop {
+            args.push(parse_expression(tokens.by_ref())?);
+            match tokens.next() {
+                Some(Token::Comma) => {},
+                Some(Token::RParent) => break,
+                token => return Err(format!("expected ')' but found {:?}", token))
+            }
+        }
+    }
+    match name.as_str() {
+        "coalesce" if !args.is_empty() => return Ok(Expression::Coalesce(args)),
+        "nullif" if args.len() == 2 => {
+            let right = args.pop().unwrap();
+            let left = args.pop().unwrap();
+            return Ok(Expression::NullIf(Box::new(left), Box::new(right)));
+        },
+        "coalesce" | "nullif" => return Err(format!("wrong number of arguments for function {}()", name)),
+        _ => {}
+    }
+    if let Some(function) = Function::from_name(name.as_str()) {
+        return Ok(Expression::Function(function, args));
+    }
+    let function = match WindowFunction::from_name(name.as_str()) {
+        Some(function) => function,
+        None => return Ok(Expression::UserCall(Box::new(UserCall::new(name, args))))
+    };
+    if tokens.next() != Some(Token::Over) {
+        return Err(format!("window function {}() requires an OVER clause", name));
+    }
+    let mut call = WindowCall::new(function, args);
+    parse_window_definition(&mut call, tokens.by_ref())?;
+    Ok(Expression::Window(Box::new(call)))
+}
+
+/// `SUBSTRING(string FROM start [FOR count])`, `SUBSTRING(string FOR count)`
+/// or `SUBSTRING(string, start [, count])`
+fn parse_substring<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Expression<RawColumn>, String> {
+    let mut args = vec![parse_expression(tokens.by_ref())?];
+    match tokens.next() {
+        Some(Token::Comma) => loop {
+            args.push(parse_expression(tokens.by_ref())?);
+            match tokens.next() {
+                Some(Token::Comma) => {},
+                Some(Token::RParent) => return Ok(Expression::Function(Function::Substring, args)),
+                token => return Err(format!("expected ')' but found {:?}", token))
+            }
+        },
+        Some(Token::From) => {
+            args.push(parse_expression(tokens.by_ref())?);
+            if tokens.peek() == Some(&Token::For) {
+                tokens.next();
+                args.push(parse_expression(tokens.by_ref())?);
+            }
+        },
+        Some(Token::For) => {
+            args.push(Expression::Const(Value::new("1", Type::Integer)));
+            args.push(parse_expression(tokens.by_ref())?);
+        },
+        Some(Token::RParent) => return Ok(Expression::Function(Function::Substring, args)),
+        token => return Err(format!("expected 'FROM' but found {:?}", token))
+    }
+    match tokens.next() {
+        Some(Token::RParent) => Ok(Expression::Function(Function::Substring, args)),
+        token => Err(format!("expected ')' but found {:?}", token))
+    }
+}
+
+/// `POSITION(substring IN string)`, operands can't be comparisons so that
+/// `IN` is not taken for a subquery membership test.
+fn parse_position<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Expression<RawColumn>, String> {
+    let sub = parse_concatenation(tokens.by_ref())?;
+    if tokens.next() != Some(Token::In) {
+        return Err("missed 'IN' in POSITION".into());
+    }
+    let string = parse_concatenation(tokens.by_ref())?;
+    match tokens.next() {
+        Some(Token::RParent) => Ok(Expression::Function(Function::Position, vec![sub, string])),
+        token => Err(format!("expected ')' but found {:?}", token))
+    }
+}
+
+/// `TRIM([LEADING | TRAILING | BOTH] [characters] FROM string)` or
+/// `TRIM([LEADING | TRAILING | BOTH] [FROM] string [, characters])`
+fn parse_trim<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Expression<RawColumn>, String> {
+    let side = match tokens.peek() {
+        Some(&Token::Leading) => Some(TrimSide::Leading),
+        Some(&Token::Trailing) => Some(TrimSide::Trailing),
+        Some(&Token::Both) => Some(TrimSide::Both),
+        _ => None
+    };
+    if side.is_some() {
+        tokens.next();
+    }
+    let args = if tokens.peek() == Some(&Token::From) {
+        tokens.next();
+        let mut args = vec![parse_expression(tokens.by_ref())?];
+        args.extend(parse_trim_characters(tokens.by_ref())?);
+        args
+    } else {
+        let first = parse_expression(tokens.by_ref())?;
+        if tokens.peek() == Some(&Token::From) {
+            tokens.next();
+            vec![parse_expression(tokens.by_ref())?, first]
+        } else {
+            let mut args = vec![first];
+            args.extend(parse_trim_characters(tokens.by_ref())?);
+            args
+        }
+    };
+    match tokens.next() {
+        Some(Token::RParent) => Ok(Expression::Function(Function::Trim(side.unwrap_or(TrimSide::Both)), args)),
+        token => Err(format!("expected ')' but found {:?}", token))
+    }
+}
+
+fn parse_trim_characters<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Option<Expression<RawColumn>>, String> {
+    if tokens.peek() == Some(&Token::Comma) {
+        tokens.next();
+        Ok(Some(parse_expression(tokens.by_ref())?))
+    } else {
+        Ok(None)
+    }
+}
+
+fn parse_window_definition<I: Iterator<Item = Token>>(call: &mut WindowCall<RawColumn>, tokens: &mut Peekable<I>) -> Result<(), String> {
+    if tokens.next() != Some(Token::LParent) {
+        return Err("expected '(' after 'OVER'".into());
+    }
+    if tokens.peek() == Some(&Token::Partition) {
+        tokens.next();
+        if tokens.next() != Some(Token::By) {
+            return Err("missed 'BY' after 'PARTITION'".into());
+        }
+        loop {
+            call.partition_by.push(parse_expression(tokens.by_ref())?);
+            if tokens.peek() != Some(&Token::Comma) {
+                break;
+            }
+            tokens.next();
+        }
+    }
+    if tokens.peek() == Some(&Token::Order) {
+        tokens.next();
+        call.order_by = parse_order_by(tokens.by_ref())?;
+    }
+    let units = match tokens.peek() {
+        Some(&Token::Rows) => Some(FrameUnits::Rows),
+        Some(&Token::Range) => Some(FrameUnits::Range),
+        _ => None
+    };
+    if let Some(units) = units {
+        tokens.next();
+        call.frame = Some(
+            if tokens.peek() == Some(&Token::Between) {
+                tokens.next();
+                let start = parse_frame_bound(tokens.by_ref())?;
+                if tokens.next() != Some(Token::And) {
+                    return Err("missed 'AND' in frame clause".into());
+                }
+                WindowFrame::new(units, start, parse_frame_bound(tokens.by_ref())?)
+            } else {
+                WindowFrame::new(units, parse_frame_bound(tokens.by_ref())?, FrameBound::CurrentRow)
+            }
+        );
+    }
+    match tokens.next() {
+        Some(Token::RParent) => Ok(()),
+        token => Err(format!("expected ')' but found {:?}", token))
+    }
+}
+
+fn parse_frame_bound<I: Iterator<Item = Token>>(tokens: &mut I) -> Result<FrameBound, String> {
+    match (tokens.next(), tokens.next()) {
+        (Some(Token::Unbounded), Some(Token::Preceding)) => Ok(FrameBound::UnboundedPreceding),
+        (Some(Token::Unbounded), Some(Token::Following)) => Ok(FrameBound::UnboundedFollowing),
+        (Some(Token::Current), Some(Token::Row)) => Ok(FrameBound::CurrentRow),
+        (Some(Token::NumConst(n)), Some(Token::Preceding)) => n.parse::<u64>().map(FrameBound::Preceding).map_err(|e| e.to_string()),
+        (Some(Token::NumConst(n)), Some(Token::Following)) => n.parse::<u64>().map(FrameBound::Following).map_err(|e| e.to_string()),
+        (token, _) => Err(format!("unexpected token {:?} in frame clause", token))
+    }
+}
+
+fn parse_sub_query<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<SelectQuery<RawColumn>, String> {
+    if tokens.next() != Some(Token::LParent) {
+        return Err("expected '(' before sub query".into());
+    }
+    parse_select_keyword(tokens.by_ref())?;
+    let query = parse_select_query(tokens.by_ref())?;
+    match tokens.next() {
+        Some(Token::RParent) => Ok(query),
+        token => Err(format!("expected ')' but found {:?}", token))
+    }
+}
+
+fn parse_order_by<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Vec<OrderingTerm<RawColumn>>, String> {
+    if tokens.next() != Some(Token::By) {
+        return Err("missed 'BY' after 'ORDER'".into());
+    }
+    let mut terms = vec![];
+    loop {
+        let key = parse_expression(tokens.by_ref())?;
+        let order = match tokens.peek() {
+            Some(&Token::Asc) => { tokens.next(); SortOrder::Asc },
+            Some(&Token::Desc) => { tokens.next(); SortOrder::Desc },
+            _ => SortOrder::Asc
+        };
+        let nulls = if tokens.peek() == Some(&Token::Nulls) {
+            tokens.next();
+            match tokens.next() {
+                Some(Token::First) => NullsOrder::First,
+                Some(Token::Last) => NullsOrder::Last,
+                token => return Err(format!("unexpected token {:?}", token))
+            }
+        } else if order == SortOrder::Desc {
+            NullsOrder::First
+        } else {
+            NullsOrder::Last
+        };
+        terms.push(OrderingTerm::new(key, order, nulls));
+        if tokens.peek() == Some(&Token::Comma) {
+            tokens.next();
+        } else {
+            break;
+        }
+    }
+    Ok(terms)
+}
+
+fn parse_limit<I: Iterator<Item = Token>>(tokens: &mut I) -> Result<usize, String> {
+    match tokens.next() {
+        Some(Token::NumConst(num)) => match num.parse::<usize>() {
+            Ok(limit) => Ok(limit),
+            Err(e) => Err(e.to_string())
+        },
+        token => Err(format!("unexpected token {:?}", token))
+    }
+}
+
+fn parse_where<I: Iterator<Item = Token>>(tokens: &mut Peekable<I>) -> Result<Option<Expression<RawColumn>>, String> {
+    match tokens.peek() {
+        Some(&Token::Where) => {
+            tokens.next();
+            parse_expression(tokens.by_ref()).map(Some)
+        },
+        _ => Ok(None)
     }
 }
@@ -1,84 +1,1563 @@
-use super::ast::{ValidatedStatement, TypedColumn, Condition, CondType, CondArg};
-use super::ast::create_table::CreateTableQuery;
-use super::ast::insert_query::{InsertQuery, ValueSource};
-use super::ast::select_query::SelectQuery;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::convert::TryFrom;
+use std::iter;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::ast::{ValidatedStatement, Type, TypedColumn, Expression, Operator, split_column_name};
+use super::ast::create_table::{CreateTableQuery, TableKey, IdentityGeneration};
+use super::ast::create_sequence::CreateSequenceQuery;
+use super::ast::create_view::{CreateViewQuery, DropViewQuery, RefreshViewQuery};
+use super::ast::insert_query::{InsertQuery, ValueSource, ConflictAction};
+use super::ast::delete_query::DeleteQuery;
+use super::ast::update_query::UpdateQuery;
+use super::ast::function::{Function, UserCall};
+use super::ast::window::{WindowCall, WindowFunction, WindowFrame, FrameUnits, FrameBound};
+use super::ast::select_query::{SelectQuery, Projection, SortOrder, NullsOrder, SetOperator, WithClause, CommonTableExpression, Locking};
+use super::ast::transaction::{TransactionStatement, IsolationLevel};
+use super::catalog::{ColumnMetadata, UniqueKey, Identity, Sequence, View};
 use super::catalog_manager::CatalogManager;
-use super::data_manager::DataManager;
+use super::data::Datum;
+use super::data_manager::{self, DataManager, RowId};
+use super::external_sort::{ExternalSorter, SortedRows};
+use super::functions::{self, Accumulator, UserFunctions};
+use super::transaction::CONCURRENT_UPDATE;
 
 #[derive(Debug, PartialEq)]
 pub enum ExecutionResult {
     Message(String),
-    Data(Vec<Vec<String>>)
+    Data(Vec<Vec<Datum>>)
 }
 
+/// Name of the savepoint that makes a statement inside of a transaction
+/// atomic, it can't be written in SQL.
+const STATEMENT_SAVEPOINT: &str = "";
+
+/// A statement outside of a transaction block runs in a transaction of its
+/// own, a failed statement inside of a block leaves no changes behind.
 pub fn execute(catalog_manager: &CatalogManager, data_manager: &DataManager, query: ValidatedStatement) -> Result<ExecutionResult, String> {
+    execute_source(catalog_manager, data_manager, query, None)
+}
+
+/// Same as `execute` but a statement that changes the catalog of a database
+/// file is kept in it as `source`, so it is run again when the file is
+/// opened.
+pub fn execute_with_source(catalog_manager: &CatalogManager, data_manager: &DataManager, query: ValidatedStatement, source: &str) -> Result<ExecutionResult, String> {
+    execute_source(catalog_manager, data_manager, query, Some(source))
+}
+
+fn execute_source(catalog_manager: &CatalogManager, data_manager: &DataManager, query: ValidatedStatement, source: Option<&str>) -> Result<ExecutionResult, String> {
+    if let ValidatedStatement::Transaction(statement) = query {
+        return control_transaction(catalog_manager, data_manager, statement);
+    }
+    let definition = source.filter(|_| defines_catalog(&query));
+    if !catalog_manager.in_transaction() {
+        catalog_manager.begin();
+        data_manager.begin(IsolationLevel::default());
+        data_manager.begin_statement();
+        let result = execute_definition(catalog_manager, data_manager, query, definition)
+            .and_then(|result| commit(catalog_manager, data_manager).map(|()| result));
+        if result.is_ok() {
+            catalog_manager.commit();
+            return result;
+        }
+        let rolled_back = data_manager.rollback();
+        catalog_manager.rollback();
+        return rolled_back.and(result);
+    }
+    if data_manager.is_failed() {
+        return Err("current transaction is aborted, commands ignored until end of transaction block".into());
+    }
+    catalog_manager.savepoint(STATEMENT_SAVEPOINT)?;
+    data_manager.savepoint(STATEMENT_SAVEPOINT)?;
+    data_manager.begin_statement();
+    let result = execute_definition(catalog_manager, data_manager, query, definition)
+        .map_err(|error| if error == CONCURRENT_UPDATE { data_manager.conflict() } else { error });
+    if result.is_err() && !data_manager.is_failed() {
+        data_manager.rollback_to(STATEMENT_SAVEPOINT)?;
+        catalog_manager.rollback_to(STATEMENT_SAVEPOINT)?;
+    }
+    data_manager.release(STATEMENT_SAVEPOINT)?;
+    catalog_manager.release(STATEMENT_SAVEPOINT)?;
+    result
+}
+
+fn control_transaction(catalog_manager: &CatalogManager, data_manager: &DataManager, statement: TransactionStatement) -> Result<ExecutionResult, String> {
+    match statement {
+        TransactionStatement::Begin(isolation) => {
+            if catalog_manager.in_transaction() {
+                return Err("there is already a transaction in progress".into());
+            }
+            catalog_manager.begin();
+            data_manager.begin(isolation.unwrap_or_default());
+            Ok(ExecutionResult::Message("transaction was started".into()))
+        },
+        TransactionStatement::Commit => {
+            if !catalog_manager.in_transaction() {
+                return Err("there is no transaction in progress".into());
+            }
+            if data_manager.is_failed() {
+                let rolled_back = data_manager.rollback();
+                catalog_manager.rollback();
+                return rolled_back.map(|()| ExecutionResult::Message("transaction was rolled back".into()));
+            }
+            if let Err(error) = commit(catalog_manager, data_manager) {
+                catalog_manager.rollback();
+                return Err(error);
+            }
+            catalog_manager.commit();
+            Ok(ExecutionResult::Message("transaction was committed".into()))
+        },
+        TransactionStatement::Rollback => {
+            if !catalog_manager.in_transaction() {
+                return Err("there is no transaction in progress".into());
+            }
+            let rolled_back = data_manager.rollback();
+            catalog_manager.rollback();
+            rolled_back.map(|()| ExecutionResult::Message("transaction was rolled back".into()))
+        },
+        TransactionStatement::Savepoint(name) => {
+            catalog_manager.savepoint(name.as_str())?;
+            data_manager.savepoint(name.as_str())?;
+            Ok(ExecutionResult::Message(format!("savepoint '{}' was created", name)))
+        },
+        TransactionStatement::RollbackTo(name) => {
+            catalog_manager.rollback_to(name.as_str())?;
+            data_manager.rollback_to(name.as_str())?;
+            Ok(ExecutionResult::Message(format!("transaction was rolled back to savepoint '{}'", name)))
+        },
+        TransactionStatement::Release(name) => {
+            catalog_manager.release(name.as_str())?;
+            data_manager.release(name.as_str())?;
+            Ok(ExecutionResult::Message(format!("savepoint '{}' was released", name)))
+        }
+    }
+}
+
+/// Values handed out by sequences are saved together with the rows that
+/// took them.
+fn commit(catalog_manager: &CatalogManager, data_manager: &DataManager) -> Result<(), String> {
+    if let Err(error) = data_manager.save_sequences(&catalog_manager.sequence_values()) {
+        return data_manager.rollback().and(Err(error));
+    }
+    data_manager.commit()
+}
+
+fn defines_catalog(query: &ValidatedStatement) -> bool {
+    match *query {
+        ValidatedStatement::Create(ref query) => !query.temporary,
+        ValidatedStatement::CreateSequence(_) | ValidatedStatement::CreateView(_) | ValidatedStatement::DropView(_) => true,
+        _ => false
+    }
+}
+
+fn execute_definition(catalog_manager: &CatalogManager, data_manager: &DataManager, query: ValidatedStatement, definition: Option<&str>) -> Result<ExecutionResult, String> {
+    let result = execute_statement(catalog_manager, data_manager, query)?;
+    if let Some(definition) = definition {
+        data_manager.remember_definition(definition)?;
+    }
+    Ok(result)
+}
+
+fn execute_statement(catalog_manager: &CatalogManager, data_manager: &DataManager, query: ValidatedStatement) -> Result<ExecutionResult, String> {
     match query {
-        ValidatedStatement::Create(query) => create_table(catalog_manager, query),
+        ValidatedStatement::Transaction(statement) => control_transaction(catalog_manager, data_manager, statement),
+        ValidatedStatement::Create(query) => create_table(catalog_manager, data_manager, query),
+        ValidatedStatement::CreateSequence(query) => create_sequence(catalog_manager, query),
+        ValidatedStatement::CreateView(query) => create_view(catalog_manager, data_manager, query),
+        ValidatedStatement::DropView(query) => drop_view(catalog_manager, data_manager, query),
+        ValidatedStatement::RefreshView(query) => refresh_view(catalog_manager, data_manager, query),
         ValidatedStatement::Insert(query) => insert_into(catalog_manager, data_manager, query),
-        ValidatedStatement::Select(query) => select_data(catalog_manager, data_manager, query),
-        _ => unimplemented!(),
+        ValidatedStatement::Select(query) => select_data(catalog_manager, data_manager, &query),
+        ValidatedStatement::Delete(query) => delete_from(catalog_manager, data_manager, query),
+        ValidatedStatement::Update(query) => update_rows(catalog_manager, data_manager, query),
     }
 }
 
-fn create_table(catalog_manager: &CatalogManager, create_query: CreateTableQuery) -> Result<ExecutionResult, String> {
-    let CreateTableQuery { table_name, table_columns } = create_query;
-    catalog_manager.add_table(table_name.as_str());
+fn create_table(catalog_manager: &CatalogManager, data_manager: &DataManager, create_query: CreateTableQuery<TypedColumn>) -> Result<ExecutionResult, String> {
+    let CreateTableQuery { table_name, table_columns, keys, temporary } = create_query;
+    if temporary {
+        catalog_manager.add_temporary_table(table_name.as_str());
+        data_manager.create_temporary(table_name.as_str());
+    } else {
+        catalog_manager.add_table(table_name.as_str())?;
+    }
+    let primary_key = table_columns.iter().filter(|c| c.is_primary_key).map(|c| c.column_name.clone()).collect::<Vec<String>>();
+    let mut declared = vec![];
+    if !primary_key.is_empty() {
+        declared.push(TableKey::new(primary_key, true));
+    }
+    declared.extend(table_columns.iter().filter(|c| c.is_unique).map(|c| TableKey::new(vec![c.column_name.clone()], false)));
+    declared.extend(keys);
+    declared.sort_by_key(|key| !key.primary);
+    for key in declared {
+        let name = if key.primary {
+            format!("{}_pkey", table_name)
+        } else {
+            format!("{}_{}_key", table_name, key.columns.join("_"))
+        };
+        catalog_manager.add_unique_key(table_name.as_str(), UniqueKey::new(name, key.columns));
+    }
     for column in table_columns.into_iter() {
-        catalog_manager.add_column_to(table_name.as_str(), (column.column_name, column.column_type, column.default_value))
+        let name = column.column_name.clone();
+        catalog_manager.add_column_to(table_name.as_str(), (column.column_name, column.column_type, column.default_value));
+        if let Some(generation) = column.generated {
+            catalog_manager.set_generation_of(table_name.as_str(), name.as_str(), generation);
+        }
+        if let Some(generation) = column.identity {
+            let sequence = format!("{}_{}_seq", table_name, name);
+            if temporary {
+                catalog_manager.create_temporary_sequence(sequence.as_str(), Sequence::new(1, 1))?;
+            } else {
+                catalog_manager.create_sequence(sequence.as_str(), Sequence::new(1, 1))?;
+            }
+            let always = generation == IdentityGeneration::Always;
+            catalog_manager.set_identity_of(table_name.as_str(), name.as_str(), Identity { sequence, always });
+        }
     }
     Ok(ExecutionResult::Message(format!("'{}' was created", table_name.as_str())))
 }
 
+fn create_sequence(catalog_manager: &CatalogManager, query: CreateSequenceQuery) -> Result<ExecutionResult, String> {
+    catalog_manager.create_sequence(query.name.as_str(), Sequence::new(query.start, query.increment))?;
+    Ok(ExecutionResult::Message(format!("'{}' was created", query.name)))
+}
+
+/// Rows of a materialized view are computed when it is created and stored
+/// as rows of a table until the view is refreshed.
+fn create_view(catalog_manager: &CatalogManager, data_manager: &DataManager, query: CreateViewQuery<TypedColumn>) -> Result<ExecutionResult, String> {
+    let CreateViewQuery { name, columns, query, definition, materialized } = query;
+    let rows = if materialized {
+        Some(query_rows(&Context::new(catalog_manager, data_manager), &query, None)?)
+    } else {
+        None
+    };
+    catalog_manager.create_view(name.as_str(), View::new(columns.clone(), definition, materialized))?;
+    if let Some(rows) = rows {
+        catalog_manager.add_table(name.as_str())?;
+        for (column, projection) in columns.into_iter().zip(query.columns.iter()) {
+            catalog_manager.add_column_to(name.as_str(), (column, projection.expr.expr_type(), None));
+        }
+        for row in rows {
+            data_manager.save_to(name.as_str(), row)?;
+        }
+    }
+    Ok(ExecutionResult::Message(format!("'{}' was created", name)))
+}
+
+fn drop_view(catalog_manager: &CatalogManager, data_manager: &DataManager, query: DropViewQuery) -> Result<ExecutionResult, String> {
+    catalog_manager.drop_view(query.name.as_str(), query.materialized)?;
+    if query.materialized {
+        data_manager.drop_table(query.name.as_str())?;
+    }
+    Ok(ExecutionResult::Message(format!("'{}' was dropped", query.name)))
+}
+
+/// Replaces rows of a materialized view with the current result of its
+/// query.
+fn refresh_view(catalog_manager: &CatalogManager, data_manager: &DataManager, query: RefreshViewQuery<TypedColumn>) -> Result<ExecutionResult, String> {
+    let rows = match query.query {
+        Some(ref select) => query_rows(&Context::new(catalog_manager, data_manager), select, None)?,
+        None => return Err(format!("\"{}\" is not a materialized view", query.name))
+    };
+    data_manager.drop_table(query.name.as_str())?;
+    for row in rows {
+        data_manager.save_to(query.name.as_str(), row)?;
+    }
+    Ok(ExecutionResult::Message(format!("'{}' was refreshed", query.name)))
+}
+
 fn insert_into(catalog_manager: &CatalogManager, data_manager: &DataManager, insert: InsertQuery<TypedColumn>) -> Result<ExecutionResult, String> {
-    match insert.values {
-        ValueSource::Row(row) => {
-            let data = row.into_iter().map(|v| v.val).collect::<Vec<String>>();
-            data_manager.save_to(insert.table_name.as_str(), data);
-            println!("data manager - {:?}", data_manager.get_row_from(insert.table_name.as_str(), 0));
-            Ok(ExecutionResult::Message("row was inserted".to_owned()))
-        },
-        ValueSource::SubQuery(query) => {
-            if let Ok(ExecutionResult::Data(query_result)) = select_data(catalog_manager, data_manager, query) {
-                let row_num = query_result.len();
-                for row in query_result {
-                    data_manager.save_to(insert.table_name.as_str(), row);
-                }
-                Ok(ExecutionResult::Message(format!("{} rows were inserted", row_num)))
+    let table_columns = catalog_manager.get_table_columns(insert.table_name.as_str());
+    let context = Context::new(catalog_manager, data_manager);
+    let mut data = vec![];
+    let message: fn(usize) -> String = match insert.values {
+        ValueSource::Rows(ref rows) => {
+            for row in rows.iter() {
+                let values = row.iter().map(Datum::from_value).collect::<Result<Vec<Datum>, String>>()?;
+                data.push(table_row(&context, insert.table_name.as_str(), &table_columns, &insert.columns[..row.len()], values)?);
+            }
+            |count| if count == 1 {
+                "row was inserted".to_owned()
             } else {
-                panic!("unexpected sub query result")
+                format!("{} rows were inserted", count)
+            }
+        },
+        ValueSource::SubQuery(ref query) => {
+            let rows = query_rows(&context, query, None)?;
+            let targets = &insert.columns[..query.columns.len()];
+            for row in rows {
+                data.push(table_row(&context, insert.table_name.as_str(), &table_columns, targets, row)?);
+            }
+            |count| format!("{} rows were inserted", count)
+        },
+    };
+    let table_name = insert.table_name.as_str();
+    let WrittenRows { rows, row_ids, written } = write_rows(&context, &insert, &table_columns, data)?;
+    let result = if insert.returning.is_empty() {
+        ExecutionResult::Message(message(written.len()))
+    } else {
+        let rows = written.iter().map(|&position| rows[position].clone()).collect::<Vec<Vec<Datum>>>();
+        ExecutionResult::Data(returning_rows(&context, table_name, &table_columns, &insert.returning, &rows)?)
+    };
+    let updated = written.iter().filter_map(|&position| row_ids[position].map(|row_id| (row_id, rows[position].clone()))).collect();
+    data_manager.update_rows_by_id(table_name, updated)?;
+    for (row, row_id) in rows.into_iter().zip(row_ids) {
+        if row_id.is_none() {
+            data_manager.save_to(table_name, row)?;
+        }
+    }
+    Ok(result)
+}
+
+/// Rows of a table written by an insert, stored rows that its rows conflict
+/// with come with their ids. `written` are positions of inserted or updated
+/// rows.
+struct WrittenRows {
+    rows: Vec<Vec<Datum>>,
+    row_ids: Vec<Option<RowId>>,
+    written: Vec<usize>
+}
+
+impl WrittenRows {
+
+    fn add(&mut self, indexes: &mut [UniqueIndex], row_id: Option<RowId>, row: Vec<Datum>) -> Result<usize, String> {
+        let position = self.rows.len();
+        for index in indexes.iter_mut() {
+            index.insert(&row, position)?;
+        }
+        self.rows.push(row);
+        self.row_ids.push(row_id);
+        Ok(position)
+    }
+}
+
+/// Adds `data` to the rows of the table resolving conflicts on its unique
+/// keys by `ON CONFLICT`. Stored rows are looked up by values of the keys
+/// and loaded only when they conflict, so the table is never scanned.
+fn write_rows(context: &Context, insert: &InsertQuery<TypedColumn>, table_columns: &[ColumnMetadata], data: Vec<Vec<Datum>>) -> Result<WrittenRows, String> {
+    let table_name = insert.table_name.as_str();
+    let mut indexes = context.catalog_manager.get_unique_keys(table_name).into_iter().map(|key| UniqueIndex::new(key, table_columns)).collect::<Vec<UniqueIndex>>();
+    let arbiter = |index: &UniqueIndex| match insert.on_conflict {
+        Some(ref on_conflict) => on_conflict.target.is_empty() || index.key.matches(&on_conflict.target),
+        None => false
+    };
+    let mut written = WrittenRows { rows: vec![], row_ids: vec![], written: vec![] };
+    let mut loaded = HashSet::new();
+    let mut affected = HashSet::new();
+    for row in data {
+        let mut conflict = indexes.iter().filter(|index| arbiter(index)).find_map(|index| index.find(&row));
+        if conflict.is_none() {
+            if let Some((_, row_id, stored)) = stored_row(context, table_name, indexes.iter().filter(|index| arbiter(index)), &row, &loaded)? {
+                loaded.insert(row_id);
+                conflict = Some(written.add(&mut indexes, Some(row_id), stored)?);
+            }
+        }
+        let position = match (conflict, insert.on_conflict.as_ref().map(|on_conflict| &on_conflict.action)) {
+            (Some(_), Some(ConflictAction::Nothing)) => continue,
+            (Some(position), Some(ConflictAction::Update(assignments, predicate))) => {
+                if affected.contains(&position) {
+                    return Err("ON CONFLICT DO UPDATE command cannot affect row a second time".into());
+                }
+                let proposed = read_row(context, table_name, table_columns, row)?;
+                let existing_row = read_row(context, table_name, table_columns, written.rows[position].clone())?;
+                let excluded = Scope { table_name: "excluded", columns: table_columns, row: &proposed, outer: None, windows: &[], aggregates: &[] };
+                let scope = Scope { table_name, columns: table_columns, row: &existing_row, outer: Some(&excluded), windows: &[], aggregates: &[] };
+                let passed = match *predicate {
+                    Some(ref predicate) => evaluate(context, predicate, &scope)? == Datum::Boolean(true),
+                    None => true
+                };
+                if !passed {
+                    continue;
+                }
+                let new_row = assigned_row(context, assignments, table_columns, &scope)?;
+                let new_row = written_row(context, table_name, table_columns, new_row)?;
+                for index in indexes.iter_mut() {
+                    index.remove(&written.rows[position]);
+                }
+                if let Some((index, _, _)) = stored_row(context, table_name, indexes.iter(), &new_row, &loaded)? {
+                    return Err(index.duplicate());
+                }
+                for index in indexes.iter_mut() {
+                    index.insert(&new_row, position)?;
+                }
+                written.rows[position] = new_row;
+                position
+            },
+            _ => {
+                if let Some((index, _, _)) = stored_row(context, table_name, indexes.iter(), &row, &loaded)? {
+                    return Err(index.duplicate());
+                }
+                written.add(&mut indexes, None, row)?
+            }
+        };
+        affected.insert(position);
+        written.written.push(position);
+    }
+    lock_unique_values(context, table_name, &indexes, written.written.iter().map(|&position| &written.rows[position]))?;
+    Ok(written)
+}
+
+/// Stored row with values of a unique key, the index of the key comes first.
+type StoredRow<'i> = (&'i UniqueIndex, RowId, Vec<Datum>);
+
+/// Stored row that the statement sees with values of one of `indexes` in
+/// `row`. Loaded rows are skipped, their current values are in the indexes.
+fn stored_row<'i, I>(context: &Context, table_name: &str, indexes: I, row: &[Datum], loaded: &HashSet<RowId>) -> Result<Option<StoredRow<'i>>, String>
+    where I: Iterator<Item = &'i UniqueIndex> {
+    for index in indexes {
+        if let Some(values) = index.key_values(row) {
+            match context.data_manager.find_key(table_name, index.key.name.as_str(), &index.columns, &values)? {
+                Some((row_id, stored)) if !loaded.contains(&row_id) => return Ok(Some((index, row_id, stored))),
+                _ => {}
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn delete_from(catalog_manager: &CatalogManager, data_manager: &DataManager, delete: DeleteQuery<TypedColumn>) -> Result<ExecutionResult, String> {
+    let table_name = delete.from.as_str();
+    let table_columns = catalog_manager.get_table_columns(table_name);
+    let context = Context::new(catalog_manager, data_manager);
+    let mut row_ids = vec![];
+    let mut deleted = vec![];
+    for (row_id, row) in data_manager.scan(table_name).enumerate() {
+        let row = read_row(&context, table_name, &table_columns, row?)?;
+        if row_matches(&context, delete.predicates.as_ref(), table_name, &table_columns, &row)? {
+            row_ids.push(row_id);
+            deleted.push(row);
+        }
+    }
+    let result = if delete.returning.is_empty() {
+        ExecutionResult::Message(format!("{} rows were deleted", deleted.len()))
+    } else {
+        ExecutionResult::Data(returning_rows(&context, table_name, &table_columns, &delete.returning, &deleted)?)
+    };
+    data_manager.delete_rows(table_name, &row_ids)?;
+    Ok(result)
+}
+
+/// New values are computed from the row as it was before the update.
+fn update_rows(catalog_manager: &CatalogManager, data_manager: &DataManager, update: UpdateQuery<TypedColumn>) -> Result<ExecutionResult, String> {
+    let table_name = update.table_name.as_str();
+    let table_columns = catalog_manager.get_table_columns(table_name);
+    let context = Context::new(catalog_manager, data_manager);
+    let mut updated = vec![];
+    for (row_id, row) in data_manager.scan(table_name).enumerate() {
+        let row = read_row(&context, table_name, &table_columns, row?)?;
+        if !row_matches(&context, update.predicates.as_ref(), table_name, &table_columns, &row)? {
+            continue;
+        }
+        let scope = Scope { table_name, columns: &table_columns, row: &row, outer: None, windows: &[], aggregates: &[] };
+        let new_row = assigned_row(&context, &update.assignments, &table_columns, &scope)?;
+        updated.push((row_id, written_row(&context, table_name, &table_columns, new_row)?));
+    }
+    let keys = catalog_manager.get_unique_keys(table_name);
+    if !keys.is_empty() {
+        let mut rows = data_manager.scan(table_name).collect::<Result<Vec<Vec<Datum>>, String>>()?;
+        for (row_id, row) in updated.iter() {
+            rows[*row_id] = row.clone();
+        }
+        let indexes = unique_indexes(keys, &table_columns, &rows)?;
+        lock_unique_values(&context, table_name, &indexes, updated.iter().map(|(_, row)| row))?;
+    }
+    let result = if update.returning.is_empty() {
+        ExecutionResult::Message(format!("{} rows were updated", updated.len()))
+    } else {
+        let rows = updated.iter().map(|(_, row)| row.clone()).collect::<Vec<Vec<Datum>>>();
+        ExecutionResult::Data(returning_rows(&context, table_name, &table_columns, &update.returning, &rows)?)
+    };
+    data_manager.update_rows(table_name, updated)?;
+    Ok(result)
+}
+
+/// Row of `scope` with `assignments` applied, values are computed from the
+/// row as it was before.
+fn assigned_row(context: &Context, assignments: &[(TypedColumn, Expression<TypedColumn>)], table_columns: &[ColumnMetadata], scope: &Scope) -> Result<Vec<Datum>, String> {
+    let mut row = scope.row.to_vec();
+    for (target, value) in assignments {
+        let index = match table_columns.iter().position(|c| c.name == target.name) {
+            Some(index) => index,
+            None => return Err(format!("Column <{}> does not exist", target.name))
+        };
+        let value = evaluate(context, value, scope)?;
+        check_size(&value, &table_columns[index])?;
+        row[index] = value;
+    }
+    Ok(row)
+}
+
+/// Positions of rows by the values of their unique key columns.
+struct UniqueIndex {
+    key: UniqueKey,
+    columns: Vec<usize>,
+    positions: HashMap<Vec<Datum>, usize>
+}
+
+impl UniqueIndex {
+
+    fn new(key: UniqueKey, table_columns: &[ColumnMetadata]) -> UniqueIndex {
+        let columns = key.columns.iter().filter_map(|name| table_columns.iter().position(|c| c.name == *name)).collect();
+        UniqueIndex { key, columns, positions: HashMap::new() }
+    }
+
+    fn key_values(&self, row: &[Datum]) -> Option<Vec<Datum>> {
+        data_manager::key_values(row, &self.columns)
+    }
+
+    fn duplicate(&self) -> String {
+        format!("duplicate key value violates unique constraint \"{}\"", self.key.name)
+    }
+
+    fn find(&self, row: &[Datum]) -> Option<usize> {
+        self.key_values(row).and_then(|values| self.positions.get(&values).cloned())
+    }
+
+    fn insert(&mut self, row: &[Datum], position: usize) -> Result<(), String> {
+        if let Some(values) = self.key_values(row) {
+            if self.positions.contains_key(&values) {
+                return Err(self.duplicate());
+            }
+            self.positions.insert(values, position);
+        }
+        Ok(())
+    }
+
+    fn remove(&mut self, row: &[Datum]) {
+        if let Some(values) = self.key_values(row) {
+            self.positions.remove(&values);
+        }
+    }
+}
+
+fn unique_indexes(keys: Vec<UniqueKey>, table_columns: &[ColumnMetadata], rows: &[Vec<Datum>]) -> Result<Vec<UniqueIndex>, String> {
+    let mut indexes = vec![];
+    for key in keys {
+        let mut index = UniqueIndex::new(key, table_columns);
+        for (position, row) in rows.iter().enumerate() {
+            index.insert(row, position)?;
+        }
+        indexes.push(index);
+    }
+    Ok(indexes)
+}
+
+/// Values of unique keys of written rows stay locked until the end of the
+/// transaction. A row with the same values written by a transaction that
+/// the statement doesn't see fails it, as the indexes were built without it.
+fn lock_unique_values<'r, I: Iterator<Item = &'r Vec<Datum>>>(context: &Context, table_name: &str, indexes: &[UniqueIndex], rows: I) -> Result<(), String> {
+    let mut locked = vec![HashSet::new(); indexes.len()];
+    for row in rows {
+        for (index, values) in indexes.iter().zip(locked.iter_mut()) {
+            if let Some(key_values) = index.key_values(row) {
+                context.data_manager.lock_key(table_name, index.key.name.as_str(), &key_values)?;
+                values.insert(key_values);
+            }
+        }
+    }
+    for (index, values) in indexes.iter().zip(locked) {
+        for key_values in values {
+            context.data_manager.check_unseen_key(table_name, index.key.name.as_str(), &index.columns, &key_values)?;
+        }
+    }
+    Ok(())
+}
+
+fn row_matches(context: &Context, predicate: Option<&Expression<TypedColumn>>, table_name: &str, table_columns: &[ColumnMetadata], row: &[Datum]) -> Result<bool, String> {
+    match predicate {
+        Some(predicate) => {
+            let scope = Scope { table_name, columns: table_columns, row, outer: None, windows: &[], aggregates: &[] };
+            evaluate(context, predicate, &scope).map(|v| v == Datum::Boolean(true))
+        },
+        None => Ok(true)
+    }
+}
+
+/// Computes `RETURNING` over the rows written by a statement.
+fn returning_rows(context: &Context, table_name: &str, table_columns: &[ColumnMetadata], returning: &[Projection<TypedColumn>], rows: &[Vec<Datum>]) -> Result<Vec<Vec<Datum>>, String> {
+    rows.iter().map(
+        |row| {
+            let row = read_row(context, table_name, table_columns, row.clone())?;
+            let scope = Scope { table_name, columns: table_columns, row: &row, outer: None, windows: &[], aggregates: &[] };
+            returning.iter().map(|projection| evaluate(context, &projection.expr, &scope)).collect::<Result<Vec<Datum>, String>>()
+        }
+    ).collect()
+}
+
+/// Builds a row in the column order of the table from `values` written to
+/// `targets`, columns that are not targeted get their defaults.
+fn table_row(context: &Context, table_name: &str, table_columns: &[ColumnMetadata], targets: &[TypedColumn], values: Vec<Datum>) -> Result<Vec<Datum>, String> {
+    let mut values = values.into_iter().map(Some).collect::<Vec<Option<Datum>>>();
+    let mut row = vec![];
+    for column in table_columns {
+        let value = match targets.iter().position(|target| target.name == column.name) {
+            Some(index) => values[index].take().unwrap_or(Datum::Null),
+            None => match column.identity {
+                Some(ref identity) => Datum::Integer(context.catalog_manager.next_value(identity.sequence.as_str())?),
+                None => match column.default_val {
+                    Some(ref default) => default_value(context, default)?,
+                    None => Datum::Null
+                }
+            }
+        };
+        check_size(&value, column)?;
+        row.push(value);
+    }
+    written_row(context, table_name, table_columns, row)
+}
+
+fn default_value(context: &Context, default: &Expression<TypedColumn>) -> Result<Datum, String> {
+    let scope = Scope { table_name: "", columns: &[], row: &[], outer: None, windows: &[], aggregates: &[] };
+    evaluate(context, default, &scope)
+}
+
+fn padded(value: Datum, column: &ColumnMetadata) -> Datum {
+    match (value, column.col_type) {
+        (Datum::Character(s), Type::Character(Some(size))) => {
+            let padding = (size as usize).saturating_sub(s.chars().count());
+            Datum::Character(s + &" ".repeat(padding))
+        },
+        (value, _) => value
+    }
+}
+
+/// Row as it is kept by `DataManager`: `STORED` generated columns are
+/// computed and `VIRTUAL` ones are left `NULL`. Values of fixed-width
+/// columns are padded whether they were written, defaulted or generated.
+fn written_row(context: &Context, table_name: &str, table_columns: &[ColumnMetadata], row: Vec<Datum>) -> Result<Vec<Datum>, String> {
+    let row = generated_values(context, table_name, table_columns, row, true)?;
+    Ok(row.into_iter().zip(table_columns.iter())
+        .map(|(value, column)| match column.generated {
+            Some(ref generation) if !generation.stored => Datum::Null,
+            _ => padded(value, column)
+        })
+        .collect())
+}
+
+/// Stored row with its `VIRTUAL` generated columns computed.
+fn read_row(context: &Context, table_name: &str, table_columns: &[ColumnMetadata], row: Vec<Datum>) -> Result<Vec<Datum>, String> {
+    generated_values(context, table_name, table_columns, row, false)
+}
+
+fn generated_values(context: &Context, table_name: &str, table_columns: &[ColumnMetadata], mut row: Vec<Datum>, stored: bool) -> Result<Vec<Datum>, String> {
+    let mut values = vec![];
+    let scope = Scope { table_name, columns: table_columns, row: &row, outer: None, windows: &[], aggregates: &[] };
+    for (index, column) in table_columns.iter().enumerate() {
+        match column.generated {
+            Some(ref generation) if generation.stored == stored => {
+                let value = padded(evaluate(context, &generation.expr, &scope)?, column);
+                check_size(&value, column)?;
+                values.push((index, value));
+            },
+            _ => {}
+        }
+    }
+    for (index, value) in values {
+        row[index] = value;
+    }
+    Ok(row)
+}
+
+fn check_size(value: &Datum, column: &ColumnMetadata) -> Result<(), String> {
+    if let (Datum::Character(ref s), Type::Character(Some(size))) = (value, column.col_type) {
+        if s.trim_end_matches(' ').chars().count() > size as usize {
+            return Err(format!("value too long for type {:?}", column.col_type));
+        }
+    }
+    Ok(())
+}
+
+/// Number of iterations after which a recursive query is considered to
+/// never reach its fixpoint.
+pub const MAX_RECURSION_DEPTH: usize = 1000;
+
+fn select_data(catalog_manager: &CatalogManager, data_manager: &DataManager, query: &SelectQuery<TypedColumn>) -> Result<ExecutionResult, String> {
+    let context = Context::new(catalog_manager, data_manager);
+    query_rows(&context, query, None).map(ExecutionResult::Data)
+}
+
+type Rows<'s> = Box<dyn Iterator<Item = Result<Vec<Datum>, String>> + 's>;
+type Consumer<'c> = dyn FnMut(Rows) -> Result<Vec<Vec<Datum>>, String> + 'c;
+
+/// Relations a query reads from: stored tables and the materialized
+/// results of common table expressions in scope.
+struct Context<'c> {
+    catalog_manager: &'c CatalogManager,
+    data_manager: &'c DataManager,
+    functions: Rc<UserFunctions>,
+    ctes: HashMap<String, Rc<Relation>>,
+    /// `CURRENT_TIMESTAMP` is the same for the whole statement
+    started: SystemTime
+}
+
+impl <'c> Context<'c> {
+
+    fn new(catalog_manager: &'c CatalogManager, data_manager: &'c DataManager) -> Context<'c> {
+        let functions = Rc::new(catalog_manager.functions());
+        Context { catalog_manager, data_manager, functions, ctes: HashMap::new(), started: SystemTime::now() }
+    }
+
+    fn with_cte(&self, name: &str, relation: Relation) -> Context<'c> {
+        let mut ctes = self.ctes.clone();
+        ctes.insert(name.to_owned(), Rc::new(relation));
+        Context { catalog_manager: self.catalog_manager, data_manager: self.data_manager, functions: self.functions.clone(), ctes, started: self.started }
+    }
+
+    fn columns(&self, table_name: &str) -> Vec<ColumnMetadata> {
+        match self.ctes.get(table_name) {
+            Some(relation) => relation.columns.clone(),
+            None => self.catalog_manager.get_table_columns(table_name)
+        }
+    }
+
+    /// Rows of base tables come with their virtual columns computed.
+    fn scan<'s>(&'s self, table_name: &'s str) -> Rows<'s> {
+        match self.ctes.get(table_name) {
+            Some(relation) => {
+                let relation = relation.clone();
+                Box::new((0..relation.rows.len()).map(move |index| Ok(relation.rows[index].clone())))
+            },
+            None => {
+                let columns = self.catalog_manager.get_table_columns(table_name);
+                if columns.iter().any(|c| c.generated.as_ref().is_some_and(|generation| !generation.stored)) {
+                    Box::new(self.data_manager.scan(table_name).map(move |row| read_row(self, table_name, &columns, row?)))
+                } else {
+                    Box::new(self.data_manager.scan(table_name))
+                }
             }
+        }
+    }
+
+    /// Rows of a base table with their ids appended after the columns, so
+    /// the selected rows can be locked.
+    fn scan_with_row_ids<'s>(&'s self, table_name: &'s str, locking: &Locking) -> Result<Rows<'s>, String> {
+        if self.ctes.contains_key(table_name) {
+            return Err(format!("{} cannot be applied to a WITH query", locking.strength.clause()));
+        }
+        let columns = self.catalog_manager.get_table_columns(table_name);
+        Ok(Box::new(
+            self.data_manager.scan_with_row_ids(table_name).map(
+                move |row| {
+                    let (row_id, row) = row?;
+                    let mut row = read_row(self, table_name, &columns, row)?;
+                    row.push(Datum::Integer(row_id as i64));
+                    Ok(row)
+                }
+            )
+        ))
+    }
+}
+
+struct Relation {
+    columns: Vec<ColumnMetadata>,
+    rows: Vec<Vec<Datum>>
+}
+
+/// Row that column references are resolved against. Correlated sub queries
+/// look up columns they don't have in the rows of the enclosing queries.
+struct Scope<'s> {
+    table_name: &'s str,
+    columns: &'s [ColumnMetadata],
+    row: &'s [Datum],
+    outer: Option<&'s Scope<'s>>,
+    /// results of window functions are appended to the row after its columns
+    windows: &'s [WindowCall<TypedColumn>],
+    /// results of aggregate functions follow the results of window functions
+    aggregates: &'s [UserCall<TypedColumn>]
+}
+
+impl <'s> Scope<'s> {
+
+    fn value(&self, column_name: &str) -> Result<Datum, String> {
+        let (table, name) = split_column_name(column_name);
+        if table.is_none_or(|t| t == self.table_name) {
+            if let Some(index) = self.columns.iter().position(|c| c.name == name) {
+                return Ok(self.row.get(index).cloned().unwrap_or(Datum::Null));
+            }
+        }
+        match self.outer {
+            Some(outer) => outer.value(column_name),
+            None => Err(format!("Column <{}> does not exist", column_name))
+        }
+    }
+}
+
+fn query_rows(context: &Context, query: &SelectQuery<TypedColumn>, outer: Option<&Scope>) -> Result<Vec<Vec<Datum>>, String> {
+    stream_rows(context, query, outer, &mut |rows| rows.collect())
+}
+
+/// Rows of the query are handed to `consume` as they are produced when
+/// they don't have to be sorted, otherwise after sorting.
+///
+/// `ORDER BY` keys that are not part of the select list are computed as
+/// hidden columns appended to the output rows and cut off after sorting.
+fn stream_rows(context: &Context, query: &SelectQuery<TypedColumn>, outer: Option<&Scope>, consume: &mut Consumer) -> Result<Vec<Vec<Datum>>, String> {
+    let scoped;
+    let context = match query.with {
+        Some(ref with) => {
+            scoped = materialize(context, with, outer)?;
+            &scoped
+        },
+        None => context
+    };
+    let table_name = query.table_name.as_str();
+    let table_columns = context.columns(table_name);
+
+    let mut limit = query.limit;
+    let filter = match query.predicates {
+        Some(ref predicate) => match where_limit(predicate) {
+            Some(where_limit) => {
+                let where_limit = where_limit?;
+                limit = Some(limit.map_or(where_limit, |l| l.min(where_limit)));
+                None
+            },
+            None => Some(predicate)
         },
+        None => None
+    };
+
+    let width = query.columns.len();
+    let mut outputs = query.columns.iter().map(|p| p.expr.clone()).collect::<Vec<Expression<TypedColumn>>>();
+    let mut sort_keys = vec![];
+    for term in query.order_by.iter() {
+        let index = match outputs.iter().position(|expr| *expr == term.key) {
+            Some(index) => index,
+            None => {
+                outputs.push(term.key.clone());
+                outputs.len() - 1
+            }
+        };
+        sort_keys.push(SortKeyColumn { index, order: term.order, nulls: term.nulls });
+    }
+
+    let windows = window_calls(&outputs);
+    let aggregates = aggregate_calls(&outputs);
+    let scanned = match query.locking {
+        Some(ref locking) => context.scan_with_row_ids(table_name, locking)?,
+        None => context.scan(table_name)
+    };
+    let filtered = scanned
+        .filter_map(
+            |row| {
+                let row = match row {
+                    Ok(row) => row,
+                    Err(e) => return Some(Err(e))
+                };
+                let scope = Scope { table_name, columns: &table_columns, row: &row, outer, windows: &[], aggregates: &[] };
+                let passed = match filter {
+                    Some(predicate) => evaluate(context, predicate, &scope).map(|v| v == Datum::Boolean(true)),
+                    None => Ok(true)
+                };
+                match passed {
+                    Ok(true) => Some(Ok(row)),
+                    Ok(false) => None,
+                    Err(e) => Some(Err(e))
+                }
+            }
+        );
+    let project = |row: Vec<Datum>| {
+        let scope = Scope { table_name, columns: &table_columns, row: &row, outer, windows: &windows, aggregates: &aggregates };
+        let mut values = outputs.iter().map(|expr| evaluate(context, expr, &scope)).collect::<Result<Vec<Datum>, String>>()?;
+        if query.locking.is_some() {
+            values.push(row[table_columns.len()].clone());
+        }
+        Ok(values)
+    };
+    let projected: Box<dyn Iterator<Item = Result<Vec<Datum>, String>>> = if !aggregates.is_empty() {
+        let mut accumulators = aggregates.iter().map(|call| context.functions.accumulator(call.name.as_str())).collect::<Result<Vec<Accumulator>, String>>()?;
+        for row in filtered {
+            let row = row?;
+            let scope = Scope { table_name, columns: &table_columns, row: &row, outer, windows: &[], aggregates: &[] };
+            for (call, accumulator) in aggregates.iter().zip(accumulators.iter_mut()) {
+                accumulator.push(&call.args.iter().map(|arg| evaluate(context, arg, &scope)).collect::<Result<Vec<Datum>, String>>()?)?;
+            }
+        }
+        let mut row = vec![Datum::Null; table_columns.len()];
+        for accumulator in accumulators {
+            row.push(accumulator.finish()?);
+        }
+        Box::new(iter::once(row).map(project))
+    } else if windows.is_empty() {
+        Box::new(filtered.map(|row| row.and_then(project)))
+    } else {
+        // window functions see every row of their partitions, so rows are
+        // kept in memory whatever the memory limit is
+        let mut rows = filtered.collect::<Result<Vec<Vec<Datum>>, String>>()?;
+        let mut values = vec![];
+        for call in windows.iter() {
+            values.push(window_values(context, call, &rows, table_name, &table_columns, outer)?);
+        }
+        for (index, row) in rows.iter_mut().enumerate() {
+            row.resize(table_columns.len(), Datum::Null);
+            row.extend(values.iter().map(|column| column[index].clone()));
+        }
+        Box::new(rows.into_iter().map(project))
+    };
+
+    // rows skipped by locking don't count towards the limit
+    let (limit, locked_limit) = match query.locking {
+        Some(_) => (None, limit),
+        None => (limit, None)
+    };
+    let data_manager = context.data_manager;
+    let mut rows = if query.distinct {
+        Box::new(distinct_rows(projected, data_manager)?.into_iter().map(Ok))
+    } else {
+        projected
+    };
+
+    for operation in query.set_operations.iter() {
+        let combined = stream_rows(context, &operation.query, outer, &mut |other| combine(operation.operator, &mut rows, other, data_manager))?;
+        rows = Box::new(combined.into_iter().map(Ok));
+    }
+
+    if sort_keys.is_empty() && query.locking.is_none() {
+        return consume(Box::new(rows.take(limit.unwrap_or(usize::MAX)).map(|row| row.map(|mut row| {
+            row.truncate(width);
+            row
+        }))));
+    }
+    let mut rows = if sort_keys.is_empty() {
+        rows.take(limit.unwrap_or(usize::MAX)).collect::<Result<Vec<Vec<Datum>>, String>>()?
+    } else {
+        sort_rows(rows, &sort_keys, limit, data_manager)?
+    };
+    if let Some(ref locking) = query.locking {
+        rows = locked_rows(context, table_name, locking, rows, locked_limit)?;
+    }
+    for row in rows.iter_mut() {
+        row.truncate(width);
+    }
+    consume(Box::new(rows.into_iter().map(Ok)))
+}
+
+/// Locks selected rows in their order, the id of a row is its last value.
+fn locked_rows(context: &Context, table_name: &str, locking: &Locking, rows: Vec<Vec<Datum>>, limit: Option<usize>) -> Result<Vec<Vec<Datum>>, String> {
+    let mut locked = vec![];
+    for mut row in rows {
+        if limit == Some(locked.len()) {
+            break;
+        }
+        let row_id = match row.pop() {
+            Some(Datum::Integer(row_id)) => row_id as usize,
+            _ => return Err(format!("row of \"{}\" has no id", table_name))
+        };
+        if context.data_manager.lock_row(table_name, row_id, locking.strength, locking.wait)? {
+            locked.push(row);
+        }
+    }
+    Ok(locked)
+}
+
+/// Window functions of the select list, each computed once per query.
+fn window_calls(outputs: &[Expression<TypedColumn>]) -> Vec<WindowCall<TypedColumn>> {
+    fn collect(expr: &Expression<TypedColumn>, calls: &mut Vec<WindowCall<TypedColumn>>) {
+        match *expr {
+            Expression::Window(ref call) => if !calls.contains(call) {
+                calls.push((**call).clone());
+            },
+            _ => for operand in expr.operands() {
+                collect(operand, calls);
+            }
+        }
     }
+    let mut calls = vec![];
+    for expr in outputs {
+        collect(expr, &mut calls);
+    }
+    calls
 }
 
-fn select_data(catalog_manager: &CatalogManager, data_manager: &DataManager, query: SelectQuery<TypedColumn>) -> Result<ExecutionResult, String> {
-    let SelectQuery { table_name, columns, predicates } = query;
-    match predicates {
-        Some(Condition { left, right, cond_type }) => {
-            match (left, right, cond_type) {
-                (CondArg::Limit, CondArg::NumConst(n), CondType::Eq) => {
-                    let limit = match n.parse::<usize>() {
-                        Ok(v) => v,
-                        Err(e) => panic!(e),
+/// Aggregate functions of the select list, a query with any of them
+/// returns a single row computed over all rows that passed `WHERE`.
+fn aggregate_calls(outputs: &[Expression<TypedColumn>]) -> Vec<UserCall<TypedColumn>> {
+    fn collect(expr: &Expression<TypedColumn>, calls: &mut Vec<UserCall<TypedColumn>>) {
+        match *expr {
+            Expression::UserCall(ref call) if call.aggregate => if !calls.contains(call) {
+                calls.push((**call).clone());
+            },
+            _ => for operand in expr.operands() {
+                collect(operand, calls);
+            }
+        }
+    }
+    let mut calls = vec![];
+    for expr in outputs {
+        collect(expr, &mut calls);
+    }
+    calls
+}
+
+/// Computes `call` for every row: rows are split into partitions, each
+/// partition is sorted by the window `ORDER BY` and the function is applied
+/// to the frame of every row.
+fn window_values(context: &Context, call: &WindowCall<TypedColumn>, rows: &[Vec<Datum>], table_name: &str, table_columns: &[ColumnMetadata], outer: Option<&Scope>) -> Result<Vec<Datum>, String> {
+    let eval = |expr: &Expression<TypedColumn>, row: &[Datum]| {
+        let scope = Scope { table_name, columns: table_columns, row, outer, windows: &[], aggregates: &[] };
+        evaluate(context, expr, &scope)
+    };
+
+    let key_columns = call.order_by.iter()
+        .enumerate()
+        .map(|(index, term)| SortKeyColumn { index, order: term.order, nulls: term.nulls })
+        .collect::<Vec<SortKeyColumn>>();
+    let mut partitions: Vec<Vec<usize>> = vec![];
+    let mut partition_of = HashMap::new();
+    let mut order_values = vec![];
+    let mut sort_keys = vec![];
+    for (index, row) in rows.iter().enumerate() {
+        let partition = call.partition_by.iter().map(|key| eval(key, row)).collect::<Result<Vec<Datum>, String>>()?;
+        let number = *partition_of.entry(partition).or_insert_with(
+            || {
+                partitions.push(vec![]);
+                partitions.len() - 1
+            }
+        );
+        partitions[number].push(index);
+        let values = call.order_by.iter().map(|term| eval(&term.key, row)).collect::<Result<Vec<Datum>, String>>()?;
+        sort_keys.push(sort_key(&key_columns, &values)?);
+        order_values.push(values);
+    }
+
+    let frame = call.effective_frame();
+    let mut result = vec![Datum::Null; rows.len()];
+    for mut positions in partitions {
+        positions.sort_by(|a, b| sort_keys[*a].cmp(&sort_keys[*b]));
+        let mut peers = vec![(0, 0); positions.len()];
+        let mut group_start = 0;
+        for position in 1..=positions.len() {
+            if position == positions.len() || sort_keys[positions[position]] != sort_keys[positions[group_start]] {
+                for peer in peers.iter_mut().take(position).skip(group_start) {
+                    *peer = (group_start, position);
+                }
+                group_start = position;
+            }
+        }
+        // offsets of RANGE frames are applied to the single integer sort key,
+        // negated for descending order so that values never decrease
+        let offsets = positions.iter()
+            .map(
+                |&index| match (order_values[index].first(), call.order_by.first().map(|t| t.order)) {
+                    (Some(&Datum::Integer(v)), Some(SortOrder::Desc)) => Some(v.saturating_neg()),
+                    (Some(&Datum::Integer(v)), _) => Some(v),
+                    _ => None
+                }
+            )
+            .collect::<Vec<Option<i64>>>();
+
+        let mut sums = vec![(0i64, 0i64)];
+        if call.function == WindowFunction::Sum || call.function == WindowFunction::Avg {
+            for &index in positions.iter() {
+                let &(sum, count) = sums.last().unwrap();
+                sums.push(
+                    match eval(&call.args[0], &rows[index])? {
+                        Datum::Integer(v) => (sum.checked_add(v).ok_or("integer out of range")?, count + 1),
+                        _ => (sum, count)
+                    }
+                );
+            }
+        }
+
+        let mut dense_rank = 0;
+        for (position, &index) in positions.iter().enumerate() {
+            if peers[position].0 == position {
+                dense_rank += 1;
+            }
+            let (start, end) = frame_bounds(&frame, position, &peers, &offsets);
+            result[index] = match call.function {
+                WindowFunction::RowNumber => Datum::Integer(position as i64 + 1),
+                WindowFunction::Rank => Datum::Integer(peers[position].0 as i64 + 1),
+                WindowFunction::DenseRank => Datum::Integer(dense_rank),
+                WindowFunction::Lag | WindowFunction::Lead => {
+                    let offset = match call.args.get(1).map(|offset| eval(offset, &rows[index])).unwrap_or(Ok(Datum::Integer(1)))? {
+                        Datum::Integer(offset) => offset,
+                        _ => return Err(format!("offset of {}() must not be null", call.function))
+                    };
+                    let target = if call.function == WindowFunction::Lag {
+                        (position as i64).checked_sub(offset)
+                    } else {
+                        (position as i64).checked_add(offset)
                     };
-                    Ok(ExecutionResult::Data(data_manager.get_range(table_name.as_str(), 0, limit)))
+                    match target {
+                        Some(target) if target >= 0 && (target as usize) < positions.len() => eval(&call.args[0], &rows[positions[target as usize]])?,
+                        _ => match call.args.get(2) {
+                            Some(default) => eval(default, &rows[index])?,
+                            None => Datum::Null
+                        }
+                    }
                 },
-                (CondArg::ColumnName(name), CondArg::StringConstant(value), CondType::NotEq) => {
-                    if let Some(index) = catalog_manager.get_column_index(table_name.as_str(), &name) {
-                        Ok(ExecutionResult::Data(data_manager.get_not_equal(table_name.as_str(), index, &value)))
+                WindowFunction::FirstValue if start < end => eval(&call.args[0], &rows[positions[start]])?,
+                WindowFunction::FirstValue => Datum::Null,
+                WindowFunction::Sum | WindowFunction::Avg => {
+                    let (sum, count) = if start < end { (sums[end].0 - sums[start].0, sums[end].1 - sums[start].1) } else { (0, 0) };
+                    match (call.function, count) {
+                        (_, 0) => Datum::Null,
+                        (WindowFunction::Sum, _) => Datum::Integer(sum),
+                        _ => average(sum, count)
+                    }
+                }
+            };
+        }
+    }
+    Ok(result)
+}
+
+/// Digits after the decimal point of an average, as many as PostgreSQL
+/// gives to the average of integers.
+const AVERAGE_SCALE: u32 = 16;
+
+/// Exact average rounded half away from zero in its last digit.
+fn average(sum: i64, count: i64) -> Datum {
+    let unit = 10i128.pow(AVERAGE_SCALE);
+    let scaled = i128::from(sum) * unit;
+    let count = i128::from(count);
+    let mut quotient = scaled / count;
+    if 2 * (scaled % count).abs() >= count {
+        quotient += scaled.signum();
+    }
+    let sign = if quotient < 0 { "-" } else { "" };
+    let quotient = quotient.abs();
+    Datum::Character(format!("{}{}.{:0width$}", sign, quotient / unit, quotient % unit, width = AVERAGE_SCALE as usize))
+}
+
+/// Half-open range of partition positions making up the frame of the row at
+/// `position`; `peers` holds the range of rows equal to it by sort key.
+fn frame_bounds(frame: &WindowFrame, position: usize, peers: &[(usize, usize)], offsets: &[Option<i64>]) -> (usize, usize) {
+    let len = peers.len();
+    let (peers_start, peers_end) = peers[position];
+    let offset_bound = |n: u64, preceding: bool, is_start: bool| -> usize {
+        let n = i64::try_from(n).unwrap_or(i64::MAX);
+        match frame.units {
+            FrameUnits::Rows => {
+                let target = if preceding { (position as i64).saturating_sub(n) } else { (position as i64).saturating_add(n) };
+                let target = if is_start { target } else { target.saturating_add(1) };
+                target.clamp(0, len as i64) as usize
+            },
+            FrameUnits::Range => match offsets[position] {
+                Some(value) => {
+                    let target = if preceding { value.saturating_sub(n) } else { value.saturating_add(n) };
+                    let first = offsets.iter().position(|o| o.is_some()).unwrap_or(0);
+                    let last = offsets.iter().rposition(|o| o.is_some()).map_or(0, |p| p + 1);
+                    let segment = &offsets[first..last];
+                    first + if is_start {
+                        segment.partition_point(|o| o.is_some_and(|v| v < target))
                     } else {
-                        unimplemented!()
+                        segment.partition_point(|o| o.is_some_and(|v| v <= target))
                     }
+                },
+                None => if is_start { peers_start } else { peers_end }
+            }
+        }
+    };
+    let start = match frame.start {
+        FrameBound::UnboundedPreceding => 0,
+        FrameBound::Preceding(n) => offset_bound(n, true, true),
+        FrameBound::CurrentRow if frame.units == FrameUnits::Range => peers_start,
+        FrameBound::CurrentRow => position,
+        FrameBound::Following(n) => offset_bound(n, false, true),
+        FrameBound::UnboundedFollowing => len
+    };
+    let end = match frame.end {
+        FrameBound::UnboundedPreceding => 0,
+        FrameBound::Preceding(n) => offset_bound(n, true, false),
+        FrameBound::CurrentRow if frame.units == FrameUnits::Range => peers_end,
+        FrameBound::CurrentRow => position + 1,
+        FrameBound::Following(n) => offset_bound(n, false, false),
+        FrameBound::UnboundedFollowing => len
+    };
+    (start, end.max(start))
+}
+
+fn materialize<'c>(context: &Context<'c>, with: &WithClause<TypedColumn>, outer: Option<&Scope>) -> Result<Context<'c>, String> {
+    let mut scoped = Context {
+        catalog_manager: context.catalog_manager,
+        data_manager: context.data_manager,
+        functions: context.functions.clone(),
+        ctes: context.ctes.clone(),
+        started: context.started
+    };
+    for cte in with.ctes.iter() {
+        let rows = if with.recursive && cte.query.references(cte.name.as_str()) {
+            recursive_rows(&scoped, cte, outer)?
+        } else {
+            query_rows(&scoped, &cte.query, outer)?
+        };
+        scoped = scoped.with_cte(cte.name.as_str(), Relation { columns: cte_columns(cte), rows });
+    }
+    Ok(scoped)
+}
+
+/// Evaluates the recursive term over the rows produced by its previous
+/// iteration until no new rows appear.
+fn recursive_rows(context: &Context, cte: &CommonTableExpression<TypedColumn>, outer: Option<&Scope>) -> Result<Vec<Vec<Datum>>, String> {
+    let term = &cte.query.set_operations[0];
+    let mut anchor = cte.query.clone();
+    anchor.set_operations.clear();
+    let distinct = term.operator == SetOperator::Union;
+    let mut seen = HashSet::new();
+    let mut rows = query_rows(context, &anchor, outer)?;
+    if distinct {
+        rows.retain(|row| seen.insert(row.clone()));
+    }
+    let mut working = rows.clone();
+    let mut depth = 0;
+    while !working.is_empty() {
+        if depth == MAX_RECURSION_DEPTH {
+            return Err(format!("recursive query \"{}\" exceeded maximum recursion depth of {}", cte.name, MAX_RECURSION_DEPTH));
+        }
+        depth += 1;
+        let iteration = context.with_cte(cte.name.as_str(), Relation { columns: cte_columns(cte), rows: working });
+        let mut next = query_rows(&iteration, &term.query, outer)?;
+        if distinct {
+            next.retain(|row| seen.insert(row.clone()));
+        }
+        rows.extend(next.iter().cloned());
+        working = next;
+    }
+    Ok(rows)
+}
+
+fn cte_columns(cte: &CommonTableExpression<TypedColumn>) -> Vec<ColumnMetadata> {
+    cte.columns.iter()
+        .zip(cte.query.columns.iter())
+        .map(|(name, projection)| ColumnMetadata::new(name.as_str(), projection.expr.expr_type(), None))
+        .collect()
+}
+
+/// Extracts `n` out of the `WHERE LIMIT = n` predicate.
+fn where_limit(predicate: &Expression<TypedColumn>) -> Option<Result<usize, String>> {
+    match *predicate {
+        Expression::Binary(ref left, Operator::Eq, ref right) if **left == Expression::Limit => match **right {
+            Expression::Const(ref value) => Some(value.val.parse::<usize>().map_err(|e| e.to_string())),
+            _ => Some(Err("LIMIT should be compared with an integer constant".into()))
+        },
+        _ => None
+    }
+}
+
+/// Predicates follow three-valued logic: comparisons with `NULL` evaluate
+/// to `NULL`, which `WHERE` treats as false.
+fn evaluate(context: &Context, expr: &Expression<TypedColumn>, scope: &Scope) -> Result<Datum, String> {
+    match *expr {
+        Expression::Column(ref column) => scope.value(column.name.as_str()),
+        Expression::Const(ref value) => Datum::from_value(value),
+        Expression::Limit => Err("LIMIT can't be used as a predicate argument".into()),
+        Expression::Binary(ref left, Operator::And, ref right) => {
+            let left = evaluate(context, left, scope)?;
+            if left == Datum::Boolean(false) {
+                return Ok(left);
+            }
+            match evaluate(context, right, scope)? {
+                Datum::Boolean(true) => Ok(left),
+                right => Ok(right)
+            }
+        },
+        Expression::Binary(ref left, Operator::Or, ref right) => {
+            let left = evaluate(context, left, scope)?;
+            if left == Datum::Boolean(true) {
+                return Ok(left);
+            }
+            match evaluate(context, right, scope)? {
+                Datum::Boolean(false) => Ok(left),
+                right => Ok(right)
+            }
+        },
+        Expression::Binary(ref left, Operator::Concat, ref right) => {
+            let left = unpadded(context, left, scope)?;
+            let right = unpadded(context, right, scope)?;
+            if left.is_null() || right.is_null() {
+                return Ok(Datum::Null);
+            }
+            Ok(Datum::Character(format!("{}{}", left, right)))
+        },
+        Expression::Binary(ref left, operator, ref right) => {
+            let left = evaluate(context, left, scope)?;
+            let right = evaluate(context, right, scope)?;
+            Ok(compare(&left, operator, &right))
+        },
+        Expression::Not(ref expr) => match evaluate(context, expr, scope)? {
+            Datum::Boolean(v) => Ok(Datum::Boolean(!v)),
+            value => Ok(value)
+        },
+        Expression::SubQuery(ref query) => {
+            let mut rows = query_rows(context, query, Some(scope))?;
+            if rows.len() > 1 {
+                return Err("more than one row returned by a subquery used as an expression".into());
+            }
+            Ok(rows.pop().and_then(|row| row.into_iter().next()).unwrap_or(Datum::Null))
+        },
+        Expression::Exists(ref query) => {
+            let rows = query_rows(context, query, Some(scope))?;
+            Ok(Datum::Boolean(!rows.is_empty()))
+        },
+        Expression::Window(ref call) => match scope.windows.iter().position(|w| **call == *w) {
+            Some(index) => Ok(scope.row.get(scope.columns.len() + index).cloned().unwrap_or(Datum::Null)),
+            None => Err(format!("window function {}() is not allowed here", call.function))
+        },
+        Expression::Case(ref case) => {
+            let operand = match case.operand {
+                Some(ref operand) => Some(evaluate(context, operand, scope)?),
+                None => None
+            };
+            for (condition, result) in case.branches.iter() {
+                let condition = evaluate(context, condition, scope)?;
+                let matched = match operand {
+                    Some(ref operand) => compare(operand, Operator::Eq, &condition),
+                    None => condition
+                };
+                if matched == Datum::Boolean(true) {
+                    return evaluate(context, result, scope);
+                }
+            }
+            match case.else_result {
+                Some(ref else_result) => evaluate(context, else_result, scope),
+                None => Ok(Datum::Null)
+            }
+        },
+        Expression::Coalesce(ref args) => {
+            for arg in args.iter() {
+                let value = evaluate(context, arg, scope)?;
+                if !value.is_null() {
+                    return Ok(value);
+                }
+            }
+            Ok(Datum::Null)
+        },
+        Expression::NullIf(ref left, ref right) => {
+            let left = evaluate(context, left, scope)?;
+            let right = evaluate(context, right, scope)?;
+            if compare(&left, Operator::Eq, &right) == Datum::Boolean(true) {
+                Ok(Datum::Null)
+            } else {
+                Ok(left)
+            }
+        },
+        Expression::Cast(ref expr, target) => evaluate(context, expr, scope)?.cast(target),
+        Expression::In(ref expr, ref query) => {
+            let value = evaluate(context, expr, scope)?;
+            let rows = query_rows(context, query, Some(scope))?;
+            let mut result = Datum::Boolean(false);
+            for row in rows {
+                match compare(&value, Operator::Eq, &row[0]) {
+                    Datum::Boolean(true) => return Ok(Datum::Boolean(true)),
+                    Datum::Null => result = Datum::Null,
+                    _ => {}
                 }
-                _ => unimplemented!(),
             }
+            Ok(result)
+        },
+        Expression::Function(Function::CurrentTimestamp, _) => {
+            let seconds = context.started.duration_since(UNIX_EPOCH).map_err(|e| e.to_string())?.as_secs();
+            Ok(Datum::Character(functions::timestamp(seconds)))
+        },
+        Expression::Function(function @ Function::NextVal, ref args) | Expression::Function(function @ Function::CurrVal, ref args) => {
+            let name = match evaluate(context, &args[0], scope)? {
+                Datum::Character(name) => name.trim_end_matches(' ').to_owned(),
+                _ => return Ok(Datum::Null)
+            };
+            let value = if function == Function::NextVal {
+                context.catalog_manager.next_value(name.as_str())?
+            } else {
+                context.catalog_manager.current_value(name.as_str())?
+            };
+            Ok(Datum::Integer(value))
+        },
+        Expression::Function(function, ref args) => {
+            let mut values = vec![];
+            for arg in args.iter() {
+                values.push(unpadded(context, arg, scope)?);
+            }
+            functions::call(function, &values)
+        },
+        Expression::UserCall(ref call) if call.aggregate => match scope.aggregates.iter().position(|a| **call == *a) {
+            Some(index) => Ok(scope.row.get(scope.columns.len() + scope.windows.len() + index).cloned().unwrap_or(Datum::Null)),
+            None => Err(format!("aggregate function {}() is not allowed here", call.name))
+        },
+        Expression::UserCall(ref call) => {
+            let mut args = vec![];
+            for arg in call.args.iter() {
+                args.push(evaluate(context, arg, scope)?);
+            }
+            context.functions.call(call.name.as_str(), &args)
+        },
+        Expression::Like(ref like) => {
+            let value = unpadded(context, &like.expr, scope)?;
+            let pattern = unpadded(context, &like.pattern, scope)?;
+            let escape = match like.escape {
+                Some(ref escape) => Some(unpadded(context, escape, scope)?),
+                None => None
+            };
+            match (value, pattern, escape) {
+                (Datum::Character(value), Datum::Character(pattern), None) =>
+                    Ok(Datum::Boolean(functions::like(&value, &pattern, None, like.case_insensitive)?)),
+                (Datum::Character(value), Datum::Character(pattern), Some(Datum::Character(escape))) =>
+                    Ok(Datum::Boolean(functions::like(&value, &pattern, Some(&escape), like.case_insensitive)?)),
+                _ => Ok(Datum::Null)
+            }
+        }
+    }
+}
+
+/// Trailing spaces of fixed-width `character(n)` values are padding and
+/// are not part of the string that functions and operators see.
+fn unpadded(context: &Context, expr: &Expression<TypedColumn>, scope: &Scope) -> Result<Datum, String> {
+    let value = evaluate(context, expr, scope)?;
+    let padded = match *expr {
+        Expression::Const(_) => false,
+        _ => matches!(expr.expr_type(), Type::Character(Some(_)))
+    };
+    match value {
+        Datum::Character(ref s) if padded => Ok(Datum::Character(s.trim_end_matches(' ').to_owned())),
+        value => Ok(value)
+    }
+}
+
+/// Trailing spaces of strings are ignored, so padded values of fixed-width
+/// columns are equal to the same value without padding.
+fn compare(left: &Datum, operator: Operator, right: &Datum) -> Datum {
+    if left.is_null() || right.is_null() {
+        return Datum::Null;
+    }
+    let ordering = match (left, right) {
+        (Datum::Character(left), Datum::Character(right)) => left.trim_end_matches(' ').cmp(right.trim_end_matches(' ')),
+        _ => left.cmp(right)
+    };
+    Datum::Boolean(
+        match operator {
+            Operator::Eq => ordering == Ordering::Equal,
+            Operator::NotEq => ordering != Ordering::Equal,
+            Operator::Less => ordering == Ordering::Less,
+            Operator::LessEq => ordering != Ordering::Greater,
+            Operator::Greater => ordering == Ordering::Greater,
+            Operator::GreaterEq => ordering != Ordering::Less,
+            Operator::And | Operator::Or | Operator::Concat => unreachable!()
         }
+    )
+}
+
+struct SortKeyColumn {
+    index: usize,
+    order: SortOrder,
+    nulls: NullsOrder
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum SortValue {
+    Asc(Datum),
+    Desc(Reverse<Datum>)
+}
+
+/// Every key is paired with a flag that places `NULL`s before or after
+/// the other values regardless of the sort direction.
+type SortKey = Vec<(bool, SortValue)>;
+
+fn sort_key(keys: &[SortKeyColumn], row: &[Datum]) -> Result<SortKey, String> {
+    let mut key = vec![];
+    for column in keys {
+        let value = row.get(column.index).cloned().unwrap_or(Datum::Null);
+        let nulls_rank = match column.nulls {
+            NullsOrder::First => !value.is_null(),
+            NullsOrder::Last => value.is_null()
+        };
+        let value = match column.order {
+            SortOrder::Asc => SortValue::Asc(value),
+            SortOrder::Desc => SortValue::Desc(Reverse(value))
+        };
+        key.push((nulls_rank, value));
+    }
+    Ok(key)
+}
+
+/// With a limit only the top `limit` rows are kept in a bounded heap,
+/// otherwise rows go through the external sorter that spills to disk
+/// once the query memory limit of the session is exceeded.
+fn sort_rows<I>(rows: I, keys: &[SortKeyColumn], limit: Option<usize>, data_manager: &DataManager) -> Result<Vec<Vec<Datum>>, String>
+        where I: Iterator<Item = Result<Vec<Datum>, String>> {
+    match limit {
+        Some(limit) => {
+            let mut heap = BinaryHeap::new();
+            for (seq, row) in rows.enumerate() {
+                let row = row?;
+                heap.push((sort_key(keys, &row)?, seq, row));
+                if heap.len() > limit {
+                    heap.pop();
+                }
+            }
+            Ok(heap.into_sorted_vec().into_iter().map(|(_, _, row)| row).collect())
+        },
         None => {
-            if let Some(index) = catalog_manager.get_column_index(table_name.as_str(), &columns[0].name) {
-                Ok(ExecutionResult::Data(data_manager.get_range_till_end_for_column(table_name.as_str(), index, columns.len())))
-            } else {
-                Ok(ExecutionResult::Data(data_manager.get_range_till_end(table_name.as_str(), 0)))
+            let mut sorter = ExternalSorter::new(|row: &[Datum]| sort_key(keys, row), data_manager.query_memory_limit());
+            for row in rows {
+                sorter.push(row?)?;
             }
+            finish(sorter, data_manager)?.collect()
+        }
+    }
+}
+
+/// Duplicates are removed by sorting rows, so `DISTINCT` spills to disk
+/// the same way `ORDER BY` does.
+fn distinct_rows<I>(rows: I, data_manager: &DataManager) -> Result<Vec<Vec<Datum>>, String>
+        where I: Iterator<Item = Result<Vec<Datum>, String>> {
+    let mut sorter = ExternalSorter::new(|row: &[Datum]| Ok(row.to_vec()), data_manager.query_memory_limit());
+    for row in rows {
+        sorter.push(row?)?;
+    }
+    let mut result: Vec<Vec<Datum>> = vec![];
+    for row in finish(sorter, data_manager)? {
+        let row = row?;
+        if result.last() != Some(&row) {
+            result.push(row);
+        }
+    }
+    Ok(result)
+}
+
+/// Finishes sorting and counts runs the sorter spilled for the session.
+fn finish<K: Ord, F: Fn(&[Datum]) -> Result<K, String>>(sorter: ExternalSorter<K, F>, data_manager: &DataManager) -> Result<SortedRows<K, F>, String> {
+    data_manager.add_spilled_runs(sorter.spilled_runs());
+    sorter.finish()
+}
+
+fn combine<L, R>(operator: SetOperator, left: L, right: R, data_manager: &DataManager) -> Result<Vec<Vec<Datum>>, String>
+        where L: Iterator<Item = Result<Vec<Datum>, String>>, R: Iterator<Item = Result<Vec<Datum>, String>> {
+    match operator {
+        SetOperator::UnionAll => left.chain(right).collect(),
+        SetOperator::Union => distinct_rows(left.chain(right), data_manager),
+        SetOperator::Intersect | SetOperator::Except => merge_sorted(operator, left, right, data_manager)
+    }
+}
+
+/// Both sides are sorted the way `DISTINCT` sorts rows and walked together,
+/// so neither of them is kept in memory beyond the limit.
+fn merge_sorted<L, R>(operator: SetOperator, left: L, right: R, data_manager: &DataManager) -> Result<Vec<Vec<Datum>>, String>
+        where L: Iterator<Item = Result<Vec<Datum>, String>>, R: Iterator<Item = Result<Vec<Datum>, String>> {
+    let mut left_sorter = ExternalSorter::new(|row: &[Datum]| Ok(row.to_vec()), data_manager.query_memory_limit());
+    for row in left {
+        left_sorter.push(row?)?;
+    }
+    let mut right_sorter = ExternalSorter::new(|row: &[Datum]| Ok(row.to_vec()), data_manager.query_memory_limit());
+    for row in right {
+        right_sorter.push(row?)?;
+    }
+    let mut right = finish(right_sorter, data_manager)?;
+    let mut candidate = right.next().transpose()?;
+    let mut result: Vec<Vec<Datum>> = vec![];
+    let mut previous = None;
+    for row in finish(left_sorter, data_manager)? {
+        let row = row?;
+        if previous.as_ref() == Some(&row) {
+            continue;
+        }
+        while candidate.as_ref().is_some_and(|candidate| *candidate < row) {
+            candidate = right.next().transpose()?;
+        }
+        if (candidate.as_ref() == Some(&row)) == (operator == SetOperator::Intersect) {
+            result.push(row.clone());
         }
+        previous = Some(row);
     }
+    Ok(result)
 }
@@ -1,176 +1,878 @@
 use std::collections::HashMap;
+use std::mem;
 
 use super::catalog_manager::CatalogManager;
-use super::catalog::ColumnMetadata;
-use super::ast::{RawStatement, RawColumn, Type, TypedStatement, TypedColumn};
-use super::ast::insert_query::{Value, ValueSource, InsertQuery};
-use super::ast::create_table::{CreateTableQuery, ColumnTable};
-use super::ast::select_query::SelectQuery;
+use super::catalog::{ColumnMetadata, View};
+use super::functions::UserFunctions;
+use super::ast::{RawStatement, RawColumn, Type, TypedStatement, TypedColumn, Expression, Operator, CaseExpression, split_column_name};
+use super::ast::insert_query::{Value, ValueSource, InsertQuery, OnConflict, ConflictAction};
+use super::ast::delete_query::DeleteQuery;
+use super::ast::update_query::UpdateQuery;
+use super::ast::create_table::{CreateTableQuery, ColumnTable, Generation};
+use super::ast::create_view::{CreateViewQuery, RefreshViewQuery};
+use super::ast::function::{Function, PatternMatch, UserCall};
+use super::ast::window::{WindowCall, WindowFunction, FrameUnits, FrameBound};
+use super::ast::select_query::{SelectQuery, Projection, OrderingTerm, SetOperation, SetOperator, WithClause, CommonTableExpression};
 
 pub fn type_inferring(tables_set: &HashMap<String, Vec<ColumnMetadata>>, statement: RawStatement) -> Result<TypedStatement, String> {
     match statement {
-        RawStatement::Create(mut create_table_query) => {
-            infer_table_columns_type(&mut create_table_query.table_columns);
-            Ok(TypedStatement::Create(create_table_query))
-        }
+        RawStatement::Create(query) => Ok(TypedStatement::Create(typed_create_table(query, &UserFunctions::default())?)),
+        RawStatement::CreateSequence(query) => Ok(TypedStatement::CreateSequence(query)),
+        RawStatement::CreateView(query) => {
+            Ok(TypedStatement::CreateView(typed_create_view(query, &|table_name| table_columns(tables_set, table_name), &UserFunctions::default())?))
+        },
+        RawStatement::DropView(query) => Ok(TypedStatement::DropView(query)),
+        RawStatement::Transaction(statement) => Ok(TypedStatement::Transaction(statement)),
+        RawStatement::RefreshView(query) => Err(format!("relation \"{}\" does not exist", query.name)),
         RawStatement::Insert(query) => {
-            let columns = resolve_columns(&query, tables_set);
-            let mut value_types = resolve_missed_column_value_types(&query, tables_set);
-            let new_values = match query.values {
-                ValueSource::Row(mut query_values) => {
-                    query_values.append(&mut value_types);
-                    ValueSource::Row(query_values)
-                }
-                ValueSource::SubQuery(query) => {
-                    ValueSource::SubQuery(typed_from_raw(query, tables_set))
-                }
-            };
-            Ok(TypedStatement::Insert(InsertQuery::new(query.table_name, columns, new_values)))
+            let table = tables_set.get(query.table_name.as_str()).cloned();
+            typed_insert(query, table, &|table_name| table_columns(tables_set, table_name), &UserFunctions::default())
         }
         RawStatement::Select(query) => {
-            Ok(TypedStatement::Select(typed_from_raw(query, tables_set)))
+            Ok(TypedStatement::Select(typed_select(query, &|table_name| table_columns(tables_set, table_name), &UserFunctions::default(), None)?))
         }
-        s => Err(format!("unimplemented type inferring for {:?}", s))
+        RawStatement::Delete(query) => typed_delete(query, &|table_name| table_columns(tables_set, table_name), &UserFunctions::default()),
+        RawStatement::Update(query) => typed_update(query, &|table_name| table_columns(tables_set, table_name), &UserFunctions::default())
     }
 }
 
-fn infer_table_columns_type(table_columns: &mut Vec<ColumnTable>) {
-    for col in table_columns.iter_mut() {
-        if col.column_type == Type::Character(None) {
-            col.column_type = Type::Character(Option::from(255));
+/// Column names of a view default to the names of its select list, names
+/// given for the view replace them from the start.
+fn typed_create_view(query: CreateViewQuery<RawColumn>, columns_of: &ColumnsOf, functions: &UserFunctions) -> Result<CreateViewQuery<TypedColumn>, String> {
+    let CreateViewQuery { name, columns, query, definition, materialized } = query;
+    let query = typed_select(query, columns_of, functions, None)?;
+    if columns.len() > query.columns.len() {
+        return Err("CREATE VIEW specifies more column names than columns".into());
+    }
+    let mut names = cte_output_columns(name.as_str(), &[], &query.columns)?.into_iter().map(|c| c.name).collect::<Vec<String>>();
+    names.splice(..columns.len(), columns);
+    for (index, column) in names.iter().enumerate() {
+        if names[..index].contains(column) {
+            return Err(format!("column \"{}\" specified more than once", column));
         }
     }
+    Ok(CreateViewQuery::new(name, names, query, definition, materialized))
 }
 
-fn resolve_columns(query: &InsertQuery<RawColumn>, table_set: &HashMap<String, Vec<ColumnMetadata>>) -> Vec<TypedColumn> {
-    let mut query_columns = match table_set.get(query.table_name.as_str()) {
-        Some(v) => v.iter()
-            .filter(|c| query.columns.contains(&RawColumn::new(c.name.as_str())))
-            .map(|c| TypedColumn::new(c.name.as_str(), c.col_type))
-            .collect::<Vec<TypedColumn>>(),
-        _ => vec![]
-    };
-    let mut missed_columns = match table_set.get(query.table_name.as_str()) {
-        Some(v) => v.iter()
-            .filter(|c| !query.columns.contains(&RawColumn::new(c.name.as_str())))
-            .map(|c| TypedColumn::new(c.name.as_str(), c.col_type))
-            .collect::<Vec<TypedColumn>>(),
-        _ => vec![]
+/// Looks up definitions of views that are expanded when a query reads them.
+type ViewsOf<'v> = dyn Fn(&str) -> Option<View> + 'v;
+
+/// Views read by the statement become common table expressions of the
+/// queries reading them, so typing and execution see plain `WITH` queries.
+fn expand_views(statement: &mut RawStatement, views: &ViewsOf) {
+    let (expressions, returning): (Vec<&mut Expression<RawColumn>>, &mut Vec<Projection<RawColumn>>) = match *statement {
+        RawStatement::Select(ref mut query) => return expand_views_in_query(query, &[], views),
+        RawStatement::CreateView(ref mut query) => return expand_views_in_query(&mut query.query, &[], views),
+        RawStatement::Insert(ref mut query) => {
+            if let ValueSource::SubQuery(ref mut sub_query) = query.values {
+                expand_views_in_query(sub_query, &[], views);
+            }
+            let expressions = match query.on_conflict {
+                Some(OnConflict { action: ConflictAction::Update(ref mut assignments, ref mut predicate), .. }) =>
+                    assignments.iter_mut().map(|(_, value)| value).chain(predicate.iter_mut()).collect(),
+                _ => vec![]
+            };
+            (expressions, &mut query.returning)
+        },
+        RawStatement::Update(ref mut query) =>
+            (query.assignments.iter_mut().map(|(_, value)| value).chain(query.predicates.iter_mut()).collect(), &mut query.returning),
+        RawStatement::Delete(ref mut query) => (query.predicates.iter_mut().collect(), &mut query.returning),
+        RawStatement::Create(_) | RawStatement::CreateSequence(_) | RawStatement::DropView(_) | RawStatement::RefreshView(_)
+            | RawStatement::Transaction(_) => return
     };
-    query_columns.append(&mut missed_columns);
-    query_columns
-}
-
-fn resolve_missed_column_value_types(query: &InsertQuery<RawColumn>, table_set: &HashMap<String, Vec<ColumnMetadata>>) -> Vec<Value> {
-    match table_set.get(query.table_name.as_str()) {
-        Some(v) => v.into_iter()
-            .filter(|c| !query.columns.contains(&RawColumn::new(c.name.as_str())) && c.default_val.is_some())
-            .map(
-                |ref c| match c.col_type {
-                    Type::Integer => Value::new(c.default_val.as_ref().unwrap().as_str(), Type::Integer),
-                    Type::Character(_) => {
-                        let val = c.default_val.as_ref().unwrap().as_str();
-                        let size = val.len() as u8;
-                        Value::new(val, Type::Character(Option::from(size)))
-                    }
-                }
-            ).collect::<Vec<Value>>(),
-        _ => vec![]
+    for expr in expressions.into_iter().chain(returning.iter_mut().map(|p| &mut p.expr)) {
+        expand_views_in_expression(expr, &[], views);
+    }
+}
+
+/// `visible` are names of common table expressions of the enclosing
+/// queries, they hide views with the same names.
+fn expand_views_in_query(query: &mut SelectQuery<RawColumn>, visible: &[String], views: &ViewsOf) {
+    let mut visible = visible.to_vec();
+    if let Some(ref mut with) = query.with {
+        for cte in with.ctes.iter_mut() {
+            if with.recursive {
+                visible.push(cte.name.clone());
+            }
+            expand_views_in_query(&mut cte.query, &visible, views);
+            if !with.recursive {
+                visible.push(cte.name.clone());
+            }
+        }
+    }
+    if !visible.contains(&query.table_name) {
+        if let Some(view) = views(query.table_name.as_str()) {
+            let mut definition = view.definition;
+            expand_views_in_query(&mut definition, &[], views);
+            let cte = CommonTableExpression::new(query.table_name.as_str(), view.columns, definition);
+            match query.with {
+                Some(ref mut with) => with.ctes.insert(0, cte),
+                None => query.with = Some(WithClause::new(false, vec![cte]))
+            }
+            visible.push(query.table_name.clone());
+        }
+    }
+    let expressions = query.columns.iter_mut().map(|p| &mut p.expr)
+        .chain(query.predicates.iter_mut())
+        .chain(query.order_by.iter_mut().map(|t| &mut t.key));
+    for expr in expressions {
+        expand_views_in_expression(expr, &visible, views);
+    }
+    for operation in query.set_operations.iter_mut() {
+        expand_views_in_query(&mut operation.query, &visible, views);
     }
 }
 
-fn typed_from_raw(query: SelectQuery<RawColumn>, table_set: &HashMap<String, Vec<ColumnMetadata>>) -> SelectQuery<TypedColumn> {
-    let table_name = query.table_name.as_str();
-    let typed = query.columns.into_iter().map(|c| {
-        let t = match table_set.get(table_name).and_then(|v| v.iter().find(|&rc| rc.name == c.name)) {
-            Some(ref col) => col.col_type,
-            _ => panic!("unimplemented if column with <{}> name does not exist in <{}> table", c.name, table_name)
+fn expand_views_in_expression(expr: &mut Expression<RawColumn>, visible: &[String], views: &ViewsOf) {
+    for query in expr.sub_queries_mut() {
+        expand_views_in_query(query, visible, views);
+    }
+}
+
+/// Character columns without size hold up to 255 characters.
+fn typed_create_table(query: CreateTableQuery<RawColumn>, functions: &UserFunctions) -> Result<CreateTableQuery<TypedColumn>, String> {
+    let CreateTableQuery { table_name, mut table_columns, keys, temporary } = query;
+    for column in table_columns.iter_mut() {
+        if column.column_type == Type::Character(None) {
+            column.column_type = Type::Character(Option::from(255));
+        }
+    }
+    let generated = table_columns.iter().filter(|c| c.generated.is_some()).map(|c| c.column_name.clone()).collect::<Vec<String>>();
+    let source_columns = table_columns.iter()
+        .filter(|c| c.generated.is_none())
+        .map(|c| ColumnMetadata::new(c.column_name.as_str(), c.column_type, None))
+        .collect::<Vec<ColumnMetadata>>();
+    let scope = Scope { table_name: table_name.as_str(), columns: &source_columns, functions, outer: None };
+    let mut columns = vec![];
+    for column in table_columns {
+        let ColumnTable { column_name, column_type, is_primary_key, foreign_key, nullable, default_value, is_unique, identity, generated: generation } = column;
+        let default_value = match default_value {
+            Some(default) => Some(typed_default(column_name.as_str(), column_type, default, functions)?),
+            None => None
         };
-        TypedColumn::new(c.name, t)
-    }).collect::<Vec<TypedColumn>>();
-    SelectQuery::new(table_name, typed, query.predicates)
+        let mut typed = ColumnTable::new(column_name, column_type, is_primary_key, foreign_key, nullable, default_value);
+        typed.is_unique = is_unique;
+        typed.identity = identity;
+        if let Some(generation) = generation {
+            check_generation_operands(&generation.expr, &generated)?;
+            let expr = typed_expression(generation.expr, &scope, &|_| vec![])?;
+            if expr.expr_type().unify(column_type).is_none() {
+                return Err(format!("column \"{}\" is of type {:?} but generation expression is of type {:?}", typed.column_name, column_type, expr.expr_type()));
+            }
+            typed.generated = Some(Generation::new(expr, generation.stored));
+        }
+        columns.push(typed);
+    }
+    let mut typed = CreateTableQuery::new(table_name, columns);
+    typed.keys = keys;
+    typed.temporary = temporary;
+    Ok(typed)
 }
 
+/// Default values are computed for a row that doesn't exist yet, so they
+/// can't read columns or other tables.
+fn typed_default(column_name: &str, column_type: Type, default: Expression<RawColumn>, functions: &UserFunctions) -> Result<Expression<TypedColumn>, String> {
+    check_default_operands(&default)?;
+    let scope = Scope { table_name: "", columns: &[], functions, outer: None };
+    let default = typed_expression(default, &scope, &|_| vec![])?;
+    if default.expr_type().unify(column_type).is_none() {
+        return Err(format!("column \"{}\" is of type {:?} but default expression is of type {:?}", column_name, column_type, default.expr_type()));
+    }
+    Ok(default)
+}
 
+/// Generated values depend only on the other columns of their row.
+fn check_generation_operands(expr: &Expression<RawColumn>, generated: &[String]) -> Result<(), String> {
+    match *expr {
+        Expression::Column(ref column) if generated.contains(&column.name) =>
+            Err(format!("cannot use generated column \"{}\" in column generation expression", column.name)),
+        Expression::SubQuery(_) | Expression::Exists(_) | Expression::In(..) => Err("cannot use subquery in column generation expression".into()),
+        Expression::Function(Function::NextVal, _) | Expression::Function(Function::CurrVal, _) | Expression::Function(Function::CurrentTimestamp, _) =>
+            Err("generation expression is not immutable".into()),
+        _ => expr.operands().into_iter().try_for_each(|operand| check_generation_operands(operand, generated))
+    }
+}
 
+fn check_default_operands(expr: &Expression<RawColumn>) -> Result<(), String> {
+    match *expr {
+        Expression::Column(_) => Err("cannot use column reference in DEFAULT expression".into()),
+        Expression::SubQuery(_) | Expression::Exists(_) | Expression::In(..) => Err("cannot use subquery in DEFAULT expression".into()),
+        _ => expr.operands().into_iter().try_for_each(check_default_operands)
+    }
+}
 
-pub fn type_inferring_old(catalog_manager: &CatalogManager, statement: RawStatement) -> Result<TypedStatement, String> {
-    match statement {
-        RawStatement::Create(create_table_query) => {
-            let CreateTableQuery { table_name, table_columns } = create_table_query;
-            let columns = infer_table_columns_type_old(table_columns);
-            Ok(TypedStatement::Create(CreateTableQuery::new(table_name.as_str(), columns)))
-        }
-        RawStatement::Insert(query) => {
-            let columns = resolve_columns_old(&query, catalog_manager);
-            let mut value_types = resolve_missed_column_value_types_old(&query, catalog_manager);
-            println!("value types - {:?}", value_types);
-            let new_values = match query.values {
-                ValueSource::Row(mut query_values) => {
-                    query_values.append(&mut value_types);
-                    ValueSource::Row(query_values)
-                }
-                ValueSource::SubQuery(query) => {
-                    ValueSource::SubQuery(typed_from_raw_old(query, catalog_manager))
-                }
+/// Pairs the values of an INSERT with the columns they are written to:
+/// the listed columns or all columns of the table. Columns left out get
+/// their default values when rows are written.
+fn typed_insert(query: InsertQuery<RawColumn>, table: Option<Vec<ColumnMetadata>>, columns_of: &ColumnsOf, functions: &UserFunctions) -> Result<TypedStatement, String> {
+    let InsertQuery { table_name, columns: listed, values, on_conflict, returning } = query;
+    let typed_sub_query = |query| typed_select(query, columns_of, functions, None);
+    let table = match table {
+        Some(table) => table,
+        None => {
+            let values = match values {
+                ValueSource::Rows(rows) => ValueSource::Rows(rows),
+                ValueSource::SubQuery(query) => ValueSource::SubQuery(typed_sub_query(query)?)
             };
-            Ok(TypedStatement::Insert(InsertQuery::new(query.table_name, columns, new_values)))
+            let mut typed = InsertQuery::new(table_name.as_str(), vec![], values);
+            typed.on_conflict = typed_on_conflict(on_conflict, table_name.as_str(), &[], columns_of, functions)?;
+            typed.returning = typed_returning(returning, &Scope { table_name: table_name.as_str(), columns: &[], functions, outer: None }, columns_of)?;
+            return Ok(TypedStatement::Insert(typed));
         }
-        RawStatement::Select(query) => {
-            Ok(TypedStatement::Select(typed_from_raw_old(query, catalog_manager)))
+    };
+    let mut columns: Vec<TypedColumn> = vec![];
+    for column in listed.iter() {
+        let metadata = match table.iter().find(|c| c.name == column.name) {
+            Some(metadata) => metadata,
+            None => return Err(format!("column \"{}\" of relation \"{}\" does not exist", column.name, table_name))
+        };
+        if columns.iter().any(|c| c.name == column.name) {
+            return Err(format!("column \"{}\" specified more than once", column.name));
         }
-        s => panic!("unimplemented type inferring for {:?}", s)
+        columns.push(TypedColumn::new(metadata.name.as_str(), metadata.col_type));
+    }
+    if listed.is_empty() {
+        columns = table.iter().map(|c| TypedColumn::new(c.name.as_str(), c.col_type)).collect();
     }
+    let targets = columns.len();
+    let values = match values {
+        ValueSource::Rows(rows) => {
+            for row in rows.iter() {
+                check_insert_width(row.len(), targets, !listed.is_empty())?;
+                check_generated_always(&table, &columns[..row.len()])?;
+            }
+            ValueSource::Rows(rows)
+        },
+        ValueSource::SubQuery(query) => {
+            let query = typed_sub_query(query)?;
+            check_insert_width(query.columns.len(), targets, !listed.is_empty())?;
+            check_generated_always(&table, &columns[..query.columns.len()])?;
+            check_insert_sources(&columns, &query)?;
+            ValueSource::SubQuery(query)
+        }
+    };
+    let mut typed = InsertQuery::new(table_name.as_str(), columns, values);
+    typed.on_conflict = typed_on_conflict(on_conflict, table_name.as_str(), &table, columns_of, functions)?;
+    typed.returning = typed_returning(returning, &Scope { table_name: table_name.as_str(), columns: &table, functions, outer: None }, columns_of)?;
+    Ok(TypedStatement::Insert(typed))
 }
 
-fn infer_table_columns_type_old(table_columns: Vec<ColumnTable>) -> Vec<ColumnTable> {
-    table_columns.into_iter().map(
-        |mut column| {
-            column.column_type = match column.column_type {
-                Type::Character(None) => Type::Character(Option::from(255)),
-                col_type => col_type
+/// Values of `GENERATED ALWAYS` identity columns come only from their
+/// sequences.
+fn check_generated_always(table: &[ColumnMetadata], targets: &[TypedColumn]) -> Result<(), String> {
+    for target in targets {
+        if table.iter().any(|c| c.name == target.name && c.identity.as_ref().is_some_and(|identity| identity.always)) {
+            return Err(format!("cannot insert a non-DEFAULT value into column \"{}\"", target.name));
+        }
+    }
+    Ok(())
+}
+
+/// Without a column list trailing columns may be left out to get their
+/// default values.
+fn check_insert_width(values: usize, targets: usize, listed: bool) -> Result<(), String> {
+    if values > targets {
+        return Err("INSERT has more expressions than target columns".into());
+    }
+    if values < targets && listed {
+        return Err("INSERT has more target columns than expressions".into());
+    }
+    Ok(())
+}
+
+/// Every column of INSERT ... SELECT feeds one target column and has to
+/// be of its type, character columns accept text of any size.
+fn check_insert_sources(targets: &[TypedColumn], query: &SelectQuery<TypedColumn>) -> Result<(), String> {
+    for (target, source) in targets.iter().zip(query.columns.iter()) {
+        check_assignment(target, &source.expr)?;
+    }
+    Ok(())
+}
+
+fn check_assignment(target: &TypedColumn, value: &Expression<TypedColumn>) -> Result<(), String> {
+    if !target.col_type.accepts(value.expr_type()) {
+        return Err(format!("column \"{}\" is of type {:?} but expression is of type {:?}", target.name, target.col_type, value.expr_type()));
+    }
+    Ok(())
+}
+
+fn typed_delete(query: DeleteQuery<RawColumn>, columns_of: &ColumnsOf, functions: &UserFunctions) -> Result<TypedStatement, String> {
+    let DeleteQuery { from, predicates, returning } = query;
+    let table_columns = columns_of(from.as_str());
+    let scope = Scope { table_name: from.as_str(), columns: &table_columns, functions, outer: None };
+    let predicates = match predicates {
+        Some(predicate) => Some(typed_condition(predicate, &scope, columns_of)?),
+        None => None
+    };
+    let mut typed = DeleteQuery::new(from.as_str(), predicates);
+    typed.returning = typed_returning(returning, &scope, columns_of)?;
+    Ok(TypedStatement::Delete(typed))
+}
+
+/// Every assigned column is set at most once to a value of its type, the
+/// values are computed from the row before it is updated.
+fn typed_update(query: UpdateQuery<RawColumn>, columns_of: &ColumnsOf, functions: &UserFunctions) -> Result<TypedStatement, String> {
+    let UpdateQuery { table_name, assignments, predicates, returning } = query;
+    let table_columns = columns_of(table_name.as_str());
+    let scope = Scope { table_name: table_name.as_str(), columns: &table_columns, functions, outer: None };
+    let typed_assignments = typed_assignments(assignments, &scope, columns_of)?;
+    let predicates = match predicates {
+        Some(predicate) => Some(typed_condition(predicate, &scope, columns_of)?),
+        None => None
+    };
+    let mut typed = UpdateQuery::new(table_name.as_str(), typed_assignments, predicates);
+    typed.returning = typed_returning(returning, &scope, columns_of)?;
+    Ok(TypedStatement::Update(typed))
+}
+
+fn typed_assignments(assignments: Vec<(RawColumn, Expression<RawColumn>)>, scope: &Scope, columns_of: &ColumnsOf) -> Result<Vec<(TypedColumn, Expression<TypedColumn>)>, String> {
+    let mut typed: Vec<(TypedColumn, Expression<TypedColumn>)> = vec![];
+    for (column, value) in assignments {
+        let target = match scope.columns.iter().find(|c| c.name == column.name) {
+            Some(metadata) if metadata.identity.as_ref().is_some_and(|identity| identity.always) =>
+                return Err(format!("column \"{}\" can only be updated to DEFAULT", metadata.name)),
+            Some(metadata) => TypedColumn::new(metadata.name.as_str(), metadata.col_type),
+            None => return Err(format!("column \"{}\" of relation \"{}\" does not exist", column.name, scope.table_name))
+        };
+        if typed.iter().any(|(assigned, _)| assigned.name == target.name) {
+            return Err(format!("multiple assignments to same column \"{}\"", target.name));
+        }
+        let value = typed_expression(value, scope, columns_of)?;
+        check_assignment(&target, &value)?;
+        typed.push((target, value));
+    }
+    Ok(typed)
+}
+
+/// `DO UPDATE` sees the conflicting row of the table and, as `excluded`,
+/// the row that was proposed for insertion.
+fn typed_on_conflict(on_conflict: Option<OnConflict<RawColumn>>, table_name: &str, table: &[ColumnMetadata], columns_of: &ColumnsOf, functions: &UserFunctions) -> Result<Option<OnConflict<TypedColumn>>, String> {
+    let OnConflict { target, action } = match on_conflict {
+        Some(on_conflict) => on_conflict,
+        None => return Ok(None)
+    };
+    for column in target.iter() {
+        if !table.iter().any(|c| c.name == *column) {
+            return Err(format!("column \"{}\" of relation \"{}\" does not exist", column, table_name));
+        }
+    }
+    let action = match action {
+        ConflictAction::Nothing => ConflictAction::Nothing,
+        ConflictAction::Update(assignments, predicates) => {
+            let excluded = Scope { table_name: "excluded", columns: table, functions, outer: None };
+            let scope = Scope { table_name, columns: table, functions, outer: Some(&excluded) };
+            let assignments = typed_assignments(assignments, &scope, columns_of)?;
+            let predicates = match predicates {
+                Some(predicate) => Some(typed_condition(predicate, &scope, columns_of)?),
+                None => None
             };
-            column
-        }
-    ).collect::<Vec<ColumnTable>>()
-}
-
-fn resolve_columns_old(query: &InsertQuery<RawColumn>, catalog_manager: &CatalogManager) -> Vec<TypedColumn> {
-    let mut query_columns = catalog_manager.get_table_columns(query.table_name.as_str())
-        .into_iter()
-        .filter(|c| query.columns.contains(&RawColumn::new(c.name.as_str())))
-        .map(|c| TypedColumn::new(c.name.as_str(), c.col_type))
-        .collect::<Vec<TypedColumn>>();
-    let mut missed_columns = catalog_manager.get_table_columns(query.table_name.as_str())
-        .into_iter()
-        .filter(|c| !query.columns.contains(&RawColumn::new(c.name.as_str())))
-        .map(|c| TypedColumn::new(c.name.as_str(), c.col_type))
-        .collect::<Vec<TypedColumn>>();
-    query_columns.append(&mut missed_columns);
-    query_columns
-}
-
-fn resolve_missed_column_value_types_old(query: &InsertQuery<RawColumn>, catalog_manager: &CatalogManager) -> Vec<Value> {
-    catalog_manager.get_table_columns(query.table_name.as_str())
-        .into_iter()
-        .filter(|c| !query.columns.contains(&RawColumn::new(c.name.as_str())) && c.default_val.is_some())
-        .map(
-            |ref c| match c.col_type {
-                    Type::Integer => Value::new(c.default_val.as_ref().unwrap().as_str(), Type::Integer),
-                    Type::Character(_) => {
-                        let val = c.default_val.as_ref().unwrap().as_str();
-                        let size = val.len() as u8;
-                        Value::new(val, Type::Character(Option::from(size)))
+            ConflictAction::Update(assignments, predicates)
+        }
+    };
+    Ok(Some(OnConflict::new(target, action)))
+}
+
+/// `RETURNING` is computed over the written rows of the modified table.
+fn typed_returning(returning: Vec<Projection<RawColumn>>, scope: &Scope, columns_of: &ColumnsOf) -> Result<Vec<Projection<TypedColumn>>, String> {
+    let mut typed = vec![];
+    for projection in returning {
+        let expr = typed_expression(projection.expr, scope, columns_of)?;
+        typed.push(Projection::new(expr, projection.alias));
+    }
+    Ok(typed)
+}
+
+fn typed_condition(predicate: Expression<RawColumn>, scope: &Scope, columns_of: &ColumnsOf) -> Result<Expression<TypedColumn>, String> {
+    let predicate = typed_expression(predicate, scope, columns_of)?;
+    if predicate.expr_type() != Type::Boolean {
+        return Err(format!("argument of WHERE must be type boolean, not type {:?}", predicate.expr_type()));
+    }
+    Ok(predicate)
+}
+
+
+fn table_columns(table_set: &HashMap<String, Vec<ColumnMetadata>>, table_name: &str) -> Vec<ColumnMetadata> {
+    table_set.get(table_name).cloned().unwrap_or_default()
+}
+
+/// Looks up columns of a table or of a common table expression in scope.
+type ColumnsOf<'c> = dyn Fn(&str) -> Vec<ColumnMetadata> + 'c;
+
+/// Columns of the common table expressions visible to a query.
+type CteColumns = Vec<(String, Vec<ColumnMetadata>)>;
+
+/// Tables visible to an expression: the table of the query itself and,
+/// for correlated sub queries, tables of the enclosing queries.
+struct Scope<'s> {
+    table_name: &'s str,
+    columns: &'s [ColumnMetadata],
+    functions: &'s UserFunctions,
+    outer: Option<&'s Scope<'s>>
+}
+
+impl <'s> Scope<'s> {
+
+    fn resolve(&self, column_name: &str) -> Option<Type> {
+        let (table, name) = split_column_name(column_name);
+        let found = if table.is_none_or(|t| t == self.table_name) {
+            self.columns.iter().find(|c| c.name == name).map(|c| c.col_type)
+        } else {
+            None
+        };
+        found.or_else(|| self.outer.and_then(|outer| outer.resolve(column_name)))
+    }
+}
+
+fn typed_select(query: SelectQuery<RawColumn>, columns_of: &ColumnsOf, functions: &UserFunctions, outer: Option<&Scope>) -> Result<SelectQuery<TypedColumn>, String> {
+    let SelectQuery { table_name, columns, predicates, distinct, set_operations, order_by, limit, with, locking } = query;
+    let (with, ctes) = match with {
+        Some(with) => {
+            let (with, ctes) = typed_with_clause(with, columns_of, functions, outer)?;
+            (Some(with), ctes)
+        },
+        None => (None, vec![])
+    };
+    let lookup = |name: &str| cte_columns(&ctes, name).unwrap_or_else(|| columns_of(name));
+    let columns_of: &ColumnsOf = &lookup;
+    let table_columns = columns_of(table_name.as_str());
+    let scope = Scope { table_name: table_name.as_str(), columns: &table_columns, functions, outer };
+    let mut projections = vec![];
+    for projection in columns {
+        let expr = typed_expression(projection.expr, &scope, columns_of)?;
+        projections.push(Projection::new(expr, projection.alias));
+    }
+    let predicates = match predicates {
+        Some(Expression::Binary(ref left, Operator::Eq, ref right)) if **left == Expression::Limit => match **right {
+            Expression::Const(ref value) if value.val_type == Type::Integer =>
+                Some(Expression::binary(Expression::Limit, Operator::Eq, Expression::Const(value.clone()))),
+            _ => return Err("LIMIT should be compared with an integer constant".into())
+        },
+        Some(predicate) => Some(typed_condition(predicate, &scope, columns_of)?),
+        None => None
+    };
+    let mut operations = vec![];
+    for operation in set_operations {
+        let branch = typed_select(operation.query, columns_of, functions, outer)?;
+        unify_set_operation_columns(operation.operator, &mut projections, &branch.columns)?;
+        operations.push(SetOperation::new(operation.operator, branch));
+    }
+    let mut ordering = vec![];
+    for term in order_by {
+        let key = resolve_ordering_key(term.key, &projections, &scope, columns_of)?;
+        if !projections.iter().any(|p| p.expr == key) {
+            if !operations.is_empty() {
+                return Err("invalid UNION/INTERSECT/EXCEPT ORDER BY clause".into());
+            }
+            if distinct {
+                return Err("for SELECT DISTINCT, ORDER BY expressions must appear in select list".into());
+            }
+        }
+        ordering.push(OrderingTerm::new(key, term.order, term.nulls));
+    }
+    let mut typed = SelectQuery::new(table_name.as_str(), projections, predicates);
+    typed.distinct = distinct;
+    typed.set_operations = operations;
+    typed.order_by = ordering;
+    typed.limit = limit;
+    typed.locking = locking;
+    typed.with = with;
+    Ok(typed)
+}
+
+/// Every common table expression sees the ones defined before it. Its
+/// column list is filled in from the select list when it is omitted.
+fn typed_with_clause(with: WithClause<RawColumn>, columns_of: &ColumnsOf, functions: &UserFunctions, outer: Option<&Scope>) -> Result<(WithClause<TypedColumn>, CteColumns), String> {
+    let WithClause { recursive, ctes } = with;
+    let mut typed = vec![];
+    let mut visible: CteColumns = vec![];
+    for cte in ctes {
+        let CommonTableExpression { name, columns, query } = cte;
+        if visible.iter().any(|(defined, _)| *defined == name) {
+            return Err(format!("WITH query name \"{}\" specified more than once", name));
+        }
+        let query = {
+            let lookup = |table_name: &str| cte_columns(&visible, table_name).unwrap_or_else(|| columns_of(table_name));
+            if recursive && query.references(name.as_str()) {
+                typed_recursive_query(name.as_str(), &columns, query, &lookup, functions, outer)?
+            } else {
+                typed_select(query, &lookup, functions, outer)?
+            }
+        };
+        let metadata = cte_output_columns(name.as_str(), &columns, &query.columns)?;
+        let columns = metadata.iter().map(|c| c.name.clone()).collect();
+        visible.push((name.clone(), metadata));
+        typed.push(CommonTableExpression::new(name, columns, query));
+    }
+    Ok((WithClause::new(recursive, typed), visible))
+}
+
+/// A recursive query is a non-recursive term, which defines the column
+/// types, combined by `UNION [ALL]` with a term that reads the query itself.
+fn typed_recursive_query(name: &str, columns: &[String], query: SelectQuery<RawColumn>, columns_of: &ColumnsOf, functions: &UserFunctions, outer: Option<&Scope>) -> Result<SelectQuery<TypedColumn>, String> {
+    let mut anchor = query;
+    let form_error = format!("recursive query \"{}\" must have the form non-recursive-term UNION [ALL] recursive-term", name);
+    if anchor.set_operations.len() != 1 {
+        return Err(form_error);
+    }
+    let term = anchor.set_operations.remove(0);
+    if (term.operator != SetOperator::Union && term.operator != SetOperator::UnionAll) || anchor.references(name) {
+        return Err(form_error);
+    }
+    if !anchor.order_by.is_empty() || anchor.limit.is_some() {
+        return Err(format!("ORDER BY and LIMIT are not allowed in recursive query \"{}\"", name));
+    }
+    let mut anchor = typed_select(anchor, columns_of, functions, outer)?;
+    let metadata = cte_output_columns(name, columns, &anchor.columns)?;
+    let lookup = |table_name: &str| if table_name == name { metadata.clone() } else { columns_of(table_name) };
+    let recursive_term = typed_select(term.query, &lookup, functions, outer)?;
+    unify_set_operation_columns(term.operator, &mut anchor.columns, &recursive_term.columns)?;
+    anchor.set_operations.push(SetOperation::new(term.operator, recursive_term));
+    Ok(anchor)
+}
+
+fn cte_columns(ctes: &[(String, Vec<ColumnMetadata>)], name: &str) -> Option<Vec<ColumnMetadata>> {
+    ctes.iter().find(|(cte, _)| cte == name).map(|(_, columns)| columns.clone())
+}
+
+fn cte_output_columns(name: &str, columns: &[String], projections: &[Projection<TypedColumn>]) -> Result<Vec<ColumnMetadata>, String> {
+    if !columns.is_empty() && columns.len() != projections.len() {
+        return Err(format!("WITH query \"{}\" has {} columns available but {} columns specified", name, projections.len(), columns.len()));
+    }
+    let mut metadata = vec![];
+    for (index, projection) in projections.iter().enumerate() {
+        let column_name = match (columns.get(index), projection.alias.as_ref(), &projection.expr) {
+            (Some(column), _, _) | (None, Some(column), _) => column.clone(),
+            (None, None, Expression::Column(column)) => split_column_name(column.name.as_str()).1.to_owned(),
+            (None, None, _) => "?column?".to_owned()
+        };
+        metadata.push(ColumnMetadata::new(column_name, projection.expr.expr_type(), None));
+    }
+    Ok(metadata)
+}
+
+/// Columns of the combined query get the common type of both branches,
+/// names of the left branch are kept.
+fn unify_set_operation_columns(operator: SetOperator, left: &mut [Projection<TypedColumn>], right: &[Projection<TypedColumn>]) -> Result<(), String> {
+    let name = match operator {
+        SetOperator::Union | SetOperator::UnionAll => "UNION",
+        SetOperator::Intersect => "INTERSECT",
+        SetOperator::Except => "EXCEPT"
+    };
+    if left.len() != right.len() {
+        return Err(format!("each {} query must have the same number of columns", name));
+    }
+    for (l, r) in left.iter_mut().zip(right.iter()) {
+        let unified = l.expr.expr_type().unify(r.expr.expr_type())
+            .ok_or_else(|| format!("{} types {:?} and {:?} cannot be matched", name, l.expr.expr_type(), r.expr.expr_type()))?;
+        if unified == l.expr.expr_type() {
+            continue;
+        }
+        match l.expr {
+            Expression::Column(ref mut column) => column.col_type = unified,
+            Expression::Const(ref mut value) if value.val_type != Type::Unknown => value.val_type = unified,
+            ref mut expr => {
+                let operand = mem::replace(expr, Expression::Limit);
+                *expr = Expression::Cast(Box::new(operand), unified);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn typed_expression(expr: Expression<RawColumn>, scope: &Scope, columns_of: &ColumnsOf) -> Result<Expression<TypedColumn>, String> {
+    match expr {
+        Expression::Column(column) => match scope.resolve(column.name.as_str()) {
+            Some(col_type) => Ok(Expression::Column(TypedColumn::new(column.name, col_type))),
+            None => Err(format!("Column <{}> does not exist in <{}>", column.name, scope.table_name))
+        },
+        Expression::Const(value) => Ok(Expression::Const(value)),
+        Expression::Limit => Err("LIMIT can be used only in 'WHERE LIMIT = n' predicate".into()),
+        Expression::Binary(left, operator, right) => {
+            let left = typed_expression(*left, scope, columns_of)?;
+            let right = typed_expression(*right, scope, columns_of)?;
+            match operator {
+                Operator::And | Operator::Or => {
+                    if left.expr_type() != Type::Boolean || right.expr_type() != Type::Boolean {
+                        return Err(format!("arguments of {} must be type boolean", operator.to_string().to_uppercase()));
                     }
+                },
+                Operator::Concat => {},
+                _ => {
+                    if left.expr_type().unify(right.expr_type()).is_none() {
+                        return Err(format!("can't compare {:?} with {:?}", left.expr_type(), right.expr_type()));
+                    }
+                }
+            }
+            Ok(Expression::binary(left, operator, right))
+        },
+        Expression::Not(expr) => {
+            let expr = typed_expression(*expr, scope, columns_of)?;
+            if expr.expr_type() != Type::Boolean {
+                return Err(format!("argument of NOT must be type boolean, not type {:?}", expr.expr_type()));
+            }
+            Ok(Expression::Not(Box::new(expr)))
+        },
+        Expression::SubQuery(query) => {
+            let query = typed_sub_query(*query, scope, columns_of)?;
+            Ok(Expression::SubQuery(Box::new(query)))
+        },
+        Expression::Exists(query) => {
+            let query = typed_select(*query, columns_of, scope.functions, Some(scope))?;
+            Ok(Expression::Exists(Box::new(query)))
+        },
+        Expression::In(expr, query) => {
+            let expr = typed_expression(*expr, scope, columns_of)?;
+            let query = typed_sub_query(*query, scope, columns_of)?;
+            if expr.expr_type().unify(query.columns[0].expr.expr_type()).is_none() {
+                return Err(format!("can't compare {:?} with {:?}", expr.expr_type(), query.columns[0].expr.expr_type()));
+            }
+            Ok(Expression::In(Box::new(expr), Box::new(query)))
+        },
+        Expression::Window(call) => Ok(Expression::Window(Box::new(typed_window_call(*call, scope, columns_of)?))),
+        Expression::Case(case) => Ok(Expression::Case(Box::new(typed_case(*case, scope, columns_of)?))),
+        Expression::Coalesce(args) => {
+            let mut typed = vec![];
+            for arg in args {
+                typed.push(typed_expression(arg, scope, columns_of)?);
+            }
+            common_type("COALESCE", &typed)?;
+            Ok(Expression::Coalesce(typed))
+        },
+        Expression::NullIf(left, right) => {
+            let left = typed_expression(*left, scope, columns_of)?;
+            let right = typed_expression(*right, scope, columns_of)?;
+            if left.expr_type().unify(right.expr_type()).is_none() {
+                return Err(format!("can't compare {:?} with {:?}", left.expr_type(), right.expr_type()));
+            }
+            Ok(Expression::NullIf(Box::new(left), Box::new(right)))
+        },
+        Expression::Cast(expr, target) => {
+            let expr = typed_expression(*expr, scope, columns_of)?;
+            Ok(Expression::Cast(Box::new(expr), target))
+        },
+        Expression::Function(function, args) => {
+            let mut typed = vec![];
+            for arg in args {
+                typed.push(typed_expression(arg, scope, columns_of)?);
+            }
+            let arg_types = typed.iter().map(|arg| arg.expr_type()).collect::<Vec<Type>>();
+            if function.result_type(&arg_types).is_none() {
+                let arg_types = arg_types.iter().map(|t| format!("{:?}", t)).collect::<Vec<String>>();
+                return Err(format!("function {}({}) does not exist", function, arg_types.join(", ")));
+            }
+            Ok(Expression::Function(function, typed))
+        },
+        Expression::Like(like) => Ok(Expression::Like(Box::new(typed_pattern_match(*like, scope, columns_of)?))),
+        Expression::UserCall(call) => Ok(Expression::UserCall(Box::new(typed_user_call(*call, scope, columns_of)?)))
+    }
+}
+
+fn typed_user_call(call: UserCall<RawColumn>, scope: &Scope, columns_of: &ColumnsOf) -> Result<UserCall<TypedColumn>, String> {
+    let mut typed = UserCall::new(call.name, vec![]);
+    for arg in call.args {
+        typed.args.push(typed_expression(arg, scope, columns_of)?);
+    }
+    let arg_types = typed.args.iter().map(|arg| arg.expr_type()).collect::<Vec<Type>>();
+    match scope.functions.signature(typed.name.as_str()) {
+        Some(signature) if signature.accepts(&arg_types) => {
+            typed.return_type = Some(signature.return_type);
+            typed.aggregate = signature.aggregate;
+            Ok(typed)
+        },
+        _ => {
+            let arg_types = arg_types.iter().map(|t| format!("{:?}", t)).collect::<Vec<String>>();
+            Err(format!("function {}({}) does not exist", typed.name, arg_types.join(", ")))
+        }
+    }
+}
+
+fn typed_pattern_match(like: PatternMatch<RawColumn>, scope: &Scope, columns_of: &ColumnsOf) -> Result<PatternMatch<TypedColumn>, String> {
+    let PatternMatch { expr, pattern, escape, case_insensitive } = like;
+    let expr = typed_expression(expr, scope, columns_of)?;
+    let pattern = typed_expression(pattern, scope, columns_of)?;
+    let escape = match escape {
+        Some(escape) => Some(typed_expression(escape, scope, columns_of)?),
+        None => None
+    };
+    let operator = if case_insensitive { "ILIKE" } else { "LIKE" };
+    match (expr.expr_type(), pattern.expr_type()) {
+        (Type::Character(_), Type::Character(_)) => {},
+        (left, right) => return Err(format!("operator does not exist: {:?} {} {:?}", left, operator, right))
+    }
+    match escape.as_ref().map(|e| e.expr_type()) {
+        None | Some(Type::Character(_)) => {},
+        Some(other) => return Err(format!("argument of ESCAPE must be type character, not type {:?}", other))
+    }
+    Ok(PatternMatch::new(expr, pattern, escape, case_insensitive))
+}
+
+fn typed_case(case: CaseExpression<RawColumn>, scope: &Scope, columns_of: &ColumnsOf) -> Result<CaseExpression<TypedColumn>, String> {
+    let CaseExpression { operand, branches, else_result } = case;
+    let operand = match operand {
+        Some(operand) => Some(typed_expression(operand, scope, columns_of)?),
+        None => None
+    };
+    let mut typed_branches = vec![];
+    let mut results = vec![];
+    for (condition, result) in branches {
+        let condition = typed_expression(condition, scope, columns_of)?;
+        match operand {
+            Some(ref operand) => if operand.expr_type().unify(condition.expr_type()).is_none() {
+                return Err(format!("can't compare {:?} with {:?}", operand.expr_type(), condition.expr_type()));
+            },
+            None => if condition.expr_type() != Type::Boolean {
+                return Err(format!("argument of CASE/WHEN must be type boolean, not type {:?}", condition.expr_type()));
+            }
+        }
+        let result = typed_expression(result, scope, columns_of)?;
+        results.push(result.clone());
+        typed_branches.push((condition, result));
+    }
+    let else_result = match else_result {
+        Some(else_result) => {
+            let else_result = typed_expression(else_result, scope, columns_of)?;
+            results.push(else_result.clone());
+            Some(else_result)
+        },
+        None => None
+    };
+    common_type("CASE", &results)?;
+    Ok(CaseExpression::new(operand, typed_branches, else_result))
+}
+
+/// Branches of `CASE` and arguments of `COALESCE` have to share a type.
+fn common_type(construct: &str, exprs: &[Expression<TypedColumn>]) -> Result<Type, String> {
+    let mut types = exprs.iter().map(|e| e.expr_type());
+    let first = match types.next() {
+        Some(first) => first,
+        None => return Err(format!("{} requires at least one argument", construct))
+    };
+    types.try_fold(
+        first,
+        |acc, t| acc.unify(t).ok_or_else(|| format!("{} types {:?} and {:?} cannot be matched", construct, acc, t))
+    )
+}
+
+fn typed_window_call(call: WindowCall<RawColumn>, scope: &Scope, columns_of: &ColumnsOf) -> Result<WindowCall<TypedColumn>, String> {
+    let WindowCall { function, args, partition_by, order_by, frame } = call;
+    let mut typed = WindowCall::new(function, vec![]);
+    for arg in args {
+        typed.args.push(typed_expression(arg, scope, columns_of)?);
+    }
+    let arg_types = typed.args.iter().map(|arg| arg.expr_type()).collect::<Vec<Type>>();
+    let signature_matches = match (function, arg_types.as_slice()) {
+        (WindowFunction::RowNumber, []) | (WindowFunction::Rank, []) | (WindowFunction::DenseRank, []) => true,
+        (WindowFunction::Lag, [_]) | (WindowFunction::Lead, [_]) => true,
+        (WindowFunction::Lag, [_, Type::Integer]) | (WindowFunction::Lead, [_, Type::Integer]) => true,
+        (WindowFunction::Lag, [value, Type::Integer, default]) | (WindowFunction::Lead, [value, Type::Integer, default]) =>
+            value.unify(*default).is_some(),
+        (WindowFunction::FirstValue, [_]) => true,
+        (WindowFunction::Sum, [Type::Integer]) | (WindowFunction::Avg, [Type::Integer]) => true,
+        _ => false
+    };
+    if !signature_matches {
+        let arg_types = arg_types.iter().map(|t| format!("{:?}", t)).collect::<Vec<String>>();
+        return Err(format!("function {}({}) does not exist", function, arg_types.join(", ")));
+    }
+    for key in partition_by {
+        typed.partition_by.push(typed_expression(key, scope, columns_of)?);
+    }
+    for term in order_by {
+        let key = typed_expression(term.key, scope, columns_of)?;
+        typed.order_by.push(OrderingTerm::new(key, term.order, term.nulls));
+    }
+    if let Some(frame) = frame {
+        if frame.start == FrameBound::UnboundedFollowing {
+            return Err("frame start cannot be UNBOUNDED FOLLOWING".into());
+        }
+        if frame.end == FrameBound::UnboundedPreceding {
+            return Err("frame end cannot be UNBOUNDED PRECEDING".into());
+        }
+        let has_offset = [frame.start, frame.end].iter().any(
+            |bound| matches!(*bound, FrameBound::Preceding(_) | FrameBound::Following(_))
+        );
+        if frame.units == FrameUnits::Range && has_offset
+                && (typed.order_by.len() != 1 || typed.order_by[0].key.expr_type() != Type::Integer) {
+            return Err("RANGE with offset PRECEDING/FOLLOWING requires exactly one ORDER BY column of type integer".into());
+        }
+    }
+    typed.frame = frame;
+    Ok(typed)
+}
+
+/// Sub queries used as a value must return a single column.
+fn typed_sub_query(query: SelectQuery<RawColumn>, scope: &Scope, columns_of: &ColumnsOf) -> Result<SelectQuery<TypedColumn>, String> {
+    let query = typed_select(query, columns_of, scope.functions, Some(scope))?;
+    if query.columns.len() != 1 {
+        return Err("subquery must return only one column".into());
+    }
+    Ok(query)
+}
+
+/// `ORDER BY` keys may point at the output column by its position or alias,
+/// otherwise they are resolved against the columns of the queried table.
+fn resolve_ordering_key(key: Expression<RawColumn>, projections: &[Projection<TypedColumn>], scope: &Scope, columns_of: &ColumnsOf) -> Result<Expression<TypedColumn>, String> {
+    match key {
+        Expression::Const(Value { val, val_type: Type::Integer }) => {
+            match val.parse::<usize>() {
+                Ok(position) if position >= 1 && position <= projections.len() => Ok(projections[position - 1].expr.clone()),
+                _ => Err(format!("ORDER BY position {} is not in select list", val))
+            }
+        },
+        Expression::Const(value) => Err(format!("non-integer constant '{}' in ORDER BY", value.val)),
+        Expression::Column(column) => {
+            let output = projections.iter().find(
+                |p| match (p.alias.as_ref(), &p.expr) {
+                    (Some(alias), _) => *alias == column.name,
+                    (None, Expression::Column(c)) => c.name == column.name,
+                    _ => false
                 }
-        ).collect::<Vec<Value>>()
+            );
+            match output {
+                Some(projection) => Ok(projection.expr.clone()),
+                None => typed_expression(Expression::Column(column), scope, columns_of)
+            }
+        },
+        key => typed_expression(key, scope, columns_of)
+    }
 }
 
-fn typed_from_raw_old(query: SelectQuery<RawColumn>, catalog_manager: &CatalogManager) -> SelectQuery<TypedColumn> {
-    let table_name = query.table_name.as_str();
-    let typed = query.columns.into_iter().map(|c| {
-        let t = catalog_manager.get_column_type(table_name, &c.name);
-        TypedColumn::new(c.name, t)
-    }).collect::<Vec<TypedColumn>>();
-    SelectQuery::new(table_name, typed, query.predicates)
+pub fn type_inferring_old(catalog_manager: &CatalogManager, statement: RawStatement) -> Result<TypedStatement, String> {
+    let functions = catalog_manager.functions();
+    let views = |name: &str| catalog_manager.get_view(name).filter(|view| !view.materialized);
+    let mut statement = statement;
+    expand_views(&mut statement, &views);
+    match statement {
+        RawStatement::Create(query) => Ok(TypedStatement::Create(typed_create_table(query, &functions)?)),
+        RawStatement::CreateSequence(query) => Ok(TypedStatement::CreateSequence(query)),
+        RawStatement::CreateView(query) => {
+            Ok(TypedStatement::CreateView(typed_create_view(query, &|table_name| catalog_manager.get_table_columns(table_name), &functions)?))
+        },
+        RawStatement::DropView(query) => Ok(TypedStatement::DropView(query)),
+        RawStatement::Transaction(statement) => Ok(TypedStatement::Transaction(statement)),
+        RawStatement::RefreshView(query) => {
+            let mut definition = match catalog_manager.get_view(query.name.as_str()) {
+                Some(ref view) if view.materialized => view.definition.clone(),
+                Some(_) => return Err(format!("\"{}\" is not a materialized view", query.name)),
+                None => return Err(format!("relation \"{}\" does not exist", query.name))
+            };
+            expand_views_in_query(&mut definition, &[], &views);
+            let mut typed = RefreshViewQuery::new(query.name);
+            typed.query = Some(typed_select(definition, &|table_name| catalog_manager.get_table_columns(table_name), &functions, None)?);
+            Ok(TypedStatement::RefreshView(typed))
+        },
+        RawStatement::Insert(query) => {
+            let table = if catalog_manager.contains_table(query.table_name.as_str()) {
+                Some(catalog_manager.get_table_columns(query.table_name.as_str()))
+            } else {
+                None
+            };
+            typed_insert(query, table, &|table_name| catalog_manager.get_table_columns(table_name), &functions)
+        }
+        RawStatement::Select(query) => {
+            Ok(TypedStatement::Select(typed_select(query, &|table_name| catalog_manager.get_table_columns(table_name), &functions, None)?))
+        }
+        RawStatement::Delete(query) => typed_delete(query, &|table_name| catalog_manager.get_table_columns(table_name), &functions),
+        RawStatement::Update(query) => typed_update(query, &|table_name| catalog_manager.get_table_columns(table_name), &functions)
+    }
 }
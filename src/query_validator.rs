@@ -1,9 +1,15 @@
 use std::collections::HashMap;
 
-use super::ast::{TypedStatement, ValidatedStatement};
-use super::ast::insert_query::{ValueSource};
+use super::ast::{TypedStatement, ValidatedStatement, TypedColumn, Expression, Type};
+use super::ast::select_query::{SelectQuery, Projection, Locking};
+use super::ast::delete_query::DeleteQuery;
+use super::ast::update_query::UpdateQuery;
+use super::ast::insert_query::{ValueSource, OnConflict, ConflictAction};
+use super::ast::create_table::CreateTableQuery;
+use super::ast::create_sequence::CreateSequenceQuery;
+use super::ast::create_view::CreateViewQuery;
 use super::catalog_manager::CatalogManager;
-use super::catalog::ColumnMetadata;
+use super::catalog::{ColumnMetadata, View};
 
 pub fn validate(tables_set: &HashMap<String, Vec<ColumnMetadata>>, statement: TypedStatement) -> Result<ValidatedStatement, String> {
     match statement {
@@ -15,6 +21,22 @@ pub fn validate(tables_set: &HashMap<String, Vec<ColumnMetadata>>, statement: Ty
             }
             Ok(ValidatedStatement::Create(query))
         }
+        TypedStatement::CreateSequence(query) => {
+            validate_sequence(tables_set.contains_key(query.name.as_str()), &query)?;
+            Ok(ValidatedStatement::CreateSequence(query))
+        },
+        TypedStatement::CreateView(query) => {
+            validate_view(tables_set.contains_key(query.name.as_str()), &query)?;
+            Ok(ValidatedStatement::CreateView(query))
+        },
+        TypedStatement::DropView(query) => Ok(ValidatedStatement::DropView(query)),
+        TypedStatement::Transaction(statement) => Ok(ValidatedStatement::Transaction(statement)),
+        TypedStatement::RefreshView(query) => {
+            if let Some(ref select) = query.query {
+                validate_select(select)?;
+            }
+            Ok(ValidatedStatement::RefreshView(query))
+        },
         TypedStatement::Insert(query) => {
             if tables_set.is_empty() {
                 Err("[ERR 100] table 'table_name' does not exist".into())
@@ -22,7 +44,23 @@ pub fn validate(tables_set: &HashMap<String, Vec<ColumnMetadata>>, statement: Ty
                 Err("column type is INT find VARCHAR".into())
             }
         },
-        _ => panic!("unimplemented branch")
+        TypedStatement::Select(query) => {
+            validate_select(&query)?;
+            Ok(ValidatedStatement::Select(query))
+        },
+        TypedStatement::Delete(query) => {
+            check_table_exists(tables_set.contains_key(query.from.as_str()), query.from.as_str())?;
+            validate_delete(&query)?;
+            Ok(ValidatedStatement::Delete(query))
+        },
+        TypedStatement::Update(query) => {
+            check_table_exists(tables_set.contains_key(query.table_name.as_str()), query.table_name.as_str())?;
+            if let Some(table) = tables_set.get(query.table_name.as_str()) {
+                check_generated_targets(table, query.assignments.iter().map(|(column, _)| column), true)?;
+            }
+            validate_update(&query)?;
+            Ok(ValidatedStatement::Update(query))
+        }
     }
 }
 
@@ -30,7 +68,15 @@ pub fn validate_old(catalog_manager: &CatalogManager, statement: TypedStatement)
     match statement {
         TypedStatement::Create(mut query) => {
             let ret = query.clone();
-            if catalog_manager.contains_table(query.table_name.as_str()) {
+            if !query.temporary && catalog_manager.contains_view(query.table_name.as_str()) {
+                return Err(format!("relation \"{}\" already exists", query.table_name));
+            }
+            let exists = if query.temporary {
+                catalog_manager.contains_temporary_table(query.table_name.as_str())
+            } else {
+                catalog_manager.contains_table(query.table_name.as_str())
+            };
+            if exists {
                 return Err(format!("Table <{}> already exists", query.table_name.as_str()));
             }
             match query.table_columns.pop() {
@@ -41,27 +87,334 @@ pub fn validate_old(catalog_manager: &CatalogManager, statement: TypedStatement)
                 }
                 _ => unimplemented!()
             }
+            validate_keys(&ret)?;
+            validate_identity(&ret)?;
+            validate_generated(&ret)?;
+            for default in ret.table_columns.iter().filter_map(|c| c.default_value.as_ref()) {
+                validate_expression(default, Some("window functions are not allowed in DEFAULT expressions"), Some("aggregate functions are not allowed in DEFAULT expressions"))?;
+            }
             Ok(ValidatedStatement::Create(ret))
         },
+        TypedStatement::CreateSequence(query) => {
+            let exists = catalog_manager.contains_table(query.name.as_str()) || catalog_manager.contains_sequence(query.name.as_str())
+                || catalog_manager.contains_view(query.name.as_str());
+            validate_sequence(exists, &query)?;
+            Ok(ValidatedStatement::CreateSequence(query))
+        },
+        TypedStatement::CreateView(query) => {
+            let exists = catalog_manager.contains_table(query.name.as_str()) || catalog_manager.contains_sequence(query.name.as_str())
+                || catalog_manager.contains_view(query.name.as_str());
+            validate_view(exists, &query)?;
+            if !query.materialized {
+                if let Some(table) = catalog_manager.temporary_table_names().into_iter().find(|t| query.definition.references(t)) {
+                    return Err(format!("view \"{}\" must not read temporary table \"{}\"", query.name, table));
+                }
+            }
+            Ok(ValidatedStatement::CreateView(query))
+        },
+        TypedStatement::DropView(query) => Ok(ValidatedStatement::DropView(query)),
+        TypedStatement::Transaction(statement) => Ok(ValidatedStatement::Transaction(statement)),
+        TypedStatement::RefreshView(query) => {
+            if let Some(ref select) = query.query {
+                validate_select(select)?;
+            }
+            Ok(ValidatedStatement::RefreshView(query))
+        },
         TypedStatement::Insert(query) => {
+            check_not_view(catalog_manager.get_view(query.table_name.as_str()), "insert into", query.table_name.as_str())?;
             if catalog_manager.contains_table(query.table_name.as_str()) {
                 match query.values {
-                    ValueSource::Row(ref row) => {
-                        for (index, value) in row.iter().enumerate() {
-                            let col_type = catalog_manager.get_column_type_by_index(query.table_name.as_str(), index);
-                            if col_type != value.val_type {
-                                return Err(format!("column type is INT find VARCHAR"));
+                    ValueSource::Rows(ref rows) => for row in rows {
+                        for (column, value) in query.columns.iter().zip(row.iter()) {
+                            if !column.col_type.accepts(value.val_type) {
+                                return Err(format!("column \"{}\" is of type {:?} but expression is of type {:?}", column.name, column.col_type, value.val_type));
                             }
                         }
                     },
                     _ => {}
                 }
+                let table = catalog_manager.get_table_columns(query.table_name.as_str());
+                let written = match query.values {
+                    ValueSource::Rows(ref rows) => rows.iter().map(|row| row.len()).max().unwrap_or(0),
+                    ValueSource::SubQuery(ref sub_query) => sub_query.columns.len()
+                };
+                check_generated_targets(&table, query.columns.iter().take(written), false)?;
+                if let Some(ref on_conflict) = query.on_conflict {
+                    if let ConflictAction::Update(ref assignments, _) = on_conflict.action {
+                        check_generated_targets(&table, assignments.iter().map(|(column, _)| column), true)?;
+                    }
+                    let keys = catalog_manager.get_unique_keys(query.table_name.as_str());
+                    if !on_conflict.target.is_empty() && !keys.iter().any(|key| key.matches(&on_conflict.target)) {
+                        return Err("there is no unique or exclusion constraint matching the ON CONFLICT specification".into());
+                    }
+                    validate_on_conflict(on_conflict)?;
+                }
+                validate_returning(&query.returning)?;
                 Ok(ValidatedStatement::Insert(query))
             } else {
                 Err(String::from("[ERR 100] table 'table_name' does not exist"))
             }
         },
-        TypedStatement::Select(query) => Ok(ValidatedStatement::Select(query)),
-        s => panic!("validation procedure for the {:?} statement has not been implemented yet", s)
+        TypedStatement::Select(query) => {
+            validate_select(&query)?;
+            Ok(ValidatedStatement::Select(query))
+        },
+        TypedStatement::Delete(query) => {
+            check_not_view(catalog_manager.get_view(query.from.as_str()), "delete from", query.from.as_str())?;
+            check_table_exists(catalog_manager.contains_table(query.from.as_str()), query.from.as_str())?;
+            validate_delete(&query)?;
+            Ok(ValidatedStatement::Delete(query))
+        },
+        TypedStatement::Update(query) => {
+            check_not_view(catalog_manager.get_view(query.table_name.as_str()), "update", query.table_name.as_str())?;
+            check_table_exists(catalog_manager.contains_table(query.table_name.as_str()), query.table_name.as_str())?;
+            let table = catalog_manager.get_table_columns(query.table_name.as_str());
+            check_generated_targets(&table, query.assignments.iter().map(|(column, _)| column), true)?;
+            validate_update(&query)?;
+            Ok(ValidatedStatement::Update(query))
+        }
+    }
+}
+
+fn validate_keys(query: &CreateTableQuery<TypedColumn>) -> Result<(), String> {
+    let primary_keys = query.keys.iter().filter(|key| key.primary).count()
+        + if query.table_columns.iter().any(|c| c.is_primary_key) { 1 } else { 0 };
+    if primary_keys > 1 {
+        return Err(format!("multiple primary keys for table \"{}\" are not allowed", query.table_name));
+    }
+    for column in query.keys.iter().flat_map(|key| key.columns.iter()) {
+        if !query.table_columns.iter().any(|c| c.column_name == *column) {
+            return Err(format!("column \"{}\" named in key does not exist", column));
+        }
+    }
+    Ok(())
+}
+
+fn validate_generated(query: &CreateTableQuery<TypedColumn>) -> Result<(), String> {
+    for column in query.table_columns.iter() {
+        let generation = match column.generated {
+            Some(ref generation) => generation,
+            None => continue
+        };
+        if column.default_value.is_some() {
+            return Err(format!("both default and generation expression specified for column \"{}\"", column.column_name));
+        }
+        if column.identity.is_some() {
+            return Err(format!("both identity and generation expression specified for column \"{}\"", column.column_name));
+        }
+        let in_key = column.is_primary_key || column.is_unique || query.keys.iter().any(|key| key.columns.contains(&column.column_name));
+        if !generation.stored && in_key {
+            return Err("unique constraints on virtual generated columns are not supported".into());
+        }
+        validate_expression(&generation.expr, Some("window functions are not allowed in column generation expressions"), Some("aggregate functions are not allowed in column generation expressions"))?;
+    }
+    Ok(())
+}
+
+/// Values of generated columns can't be written directly.
+fn check_generated_targets<'t, I: Iterator<Item = &'t TypedColumn>>(table: &[ColumnMetadata], targets: I, updated: bool) -> Result<(), String> {
+    for target in targets {
+        if table.iter().any(|c| c.name == target.name && c.generated.is_some()) {
+            return Err(if updated {
+                format!("column \"{}\" can only be updated to DEFAULT", target.name)
+            } else {
+                format!("cannot insert a non-DEFAULT value into column \"{}\"", target.name)
+            });
+        }
+    }
+    Ok(())
+}
+
+fn validate_identity(query: &CreateTableQuery<TypedColumn>) -> Result<(), String> {
+    match query.table_columns.iter().find(|c| c.identity.is_some() && c.column_type != Type::Integer) {
+        Some(column) => Err(format!("identity column type must be integer, column \"{}\" is {:?}", column.column_name, column.column_type)),
+        None => Ok(())
+    }
+}
+
+fn validate_sequence(exists: bool, query: &CreateSequenceQuery) -> Result<(), String> {
+    if exists {
+        return Err(format!("relation \"{}\" already exists", query.name));
+    }
+    if query.increment == 0 {
+        return Err("INCREMENT must not be zero".into());
+    }
+    Ok(())
+}
+
+fn validate_view(exists: bool, query: &CreateViewQuery<TypedColumn>) -> Result<(), String> {
+    if exists {
+        return Err(format!("relation \"{}\" already exists", query.name));
+    }
+    validate_select(&query.query)
+}
+
+/// Rows of views are only changed by refreshing a materialized view.
+fn check_not_view(view: Option<View>, action: &str, name: &str) -> Result<(), String> {
+    match view {
+        Some(ref view) if view.materialized => Err(format!("cannot change materialized view \"{}\"", name)),
+        Some(_) => Err(format!("cannot {} view \"{}\"", action, name)),
+        None => Ok(())
+    }
+}
+
+fn validate_on_conflict(on_conflict: &OnConflict<TypedColumn>) -> Result<(), String> {
+    match on_conflict.action {
+        ConflictAction::Nothing => Ok(()),
+        ConflictAction::Update(_, _) if on_conflict.target.is_empty() =>
+            Err("ON CONFLICT DO UPDATE requires inference specification or constraint name".into()),
+        ConflictAction::Update(ref assignments, ref predicate) => validate_assignments(assignments, predicate)
+    }
+}
+
+fn check_table_exists(exists: bool, table_name: &str) -> Result<(), String> {
+    if exists {
+        Ok(())
+    } else {
+        Err(format!("[ERR 100] table '{}' does not exist", table_name))
+    }
+}
+
+fn validate_delete(query: &DeleteQuery<TypedColumn>) -> Result<(), String> {
+    if let Some(ref predicate) = query.predicates {
+        validate_expression(predicate, Some("window functions are not allowed in WHERE"), Some("aggregate functions are not allowed in WHERE"))?;
+    }
+    validate_returning(&query.returning)
+}
+
+fn validate_update(query: &UpdateQuery<TypedColumn>) -> Result<(), String> {
+    validate_assignments(&query.assignments, &query.predicates)?;
+    validate_returning(&query.returning)
+}
+
+fn validate_assignments(assignments: &[(TypedColumn, Expression<TypedColumn>)], predicates: &Option<Expression<TypedColumn>>) -> Result<(), String> {
+    for (_, value) in assignments.iter() {
+        validate_expression(value, Some("window functions are not allowed in UPDATE"), Some("aggregate functions are not allowed in UPDATE"))?;
+    }
+    if let Some(ref predicate) = *predicates {
+        validate_expression(predicate, Some("window functions are not allowed in WHERE"), Some("aggregate functions are not allowed in WHERE"))?;
+    }
+    Ok(())
+}
+
+/// `RETURNING` is computed for every written row on its own.
+fn validate_returning(returning: &[Projection<TypedColumn>]) -> Result<(), String> {
+    for projection in returning {
+        validate_expression(&projection.expr, Some("window functions are not allowed in RETURNING"), Some("aggregate functions are not allowed in RETURNING"))?;
+    }
+    Ok(())
+}
+
+/// Window and aggregate functions are computed over the rows that passed
+/// `WHERE`, so they may appear only in the select list and in `ORDER BY`.
+fn validate_select(query: &SelectQuery<TypedColumn>) -> Result<(), String> {
+    let outputs = query.columns.iter().map(|p| &p.expr).chain(query.order_by.iter().map(|term| &term.key)).collect::<Vec<&Expression<TypedColumn>>>();
+    for expr in outputs.iter() {
+        validate_expression(expr, None, None)?;
+    }
+    if let Some(ref predicate) = query.predicates {
+        validate_expression(predicate, Some("window functions are not allowed in WHERE"), Some("aggregate functions are not allowed in WHERE"))?;
+    }
+    if outputs.iter().any(|expr| contains_aggregate(expr)) {
+        for expr in outputs.iter() {
+            validate_aggregated_output(expr)?;
+        }
+    }
+    if let Some(ref locking) = query.locking {
+        validate_locking(query, outputs.as_slice(), locking)?;
+    }
+    for operation in query.set_operations.iter() {
+        validate_select(&operation.query)?;
+    }
+    if let Some(ref with) = query.with {
+        for cte in with.ctes.iter() {
+            validate_select(&cte.query)?;
+        }
+    }
+    Ok(())
+}
+
+/// Locked rows have to be rows of the table rather than rows computed
+/// from several of them.
+fn validate_locking(query: &SelectQuery<TypedColumn>, outputs: &[&Expression<TypedColumn>], locking: &Locking) -> Result<(), String> {
+    let clause = if query.distinct {
+        Some("DISTINCT clause")
+    } else if outputs.iter().any(|expr| contains_aggregate(expr)) {
+        Some("aggregate functions")
+    } else if outputs.iter().any(|expr| contains_window(expr)) {
+        Some("window functions")
+    } else if !query.set_operations.is_empty() {
+        Some("UNION/INTERSECT/EXCEPT")
+    } else {
+        None
+    };
+    match clause {
+        Some(clause) => Err(format!("{} is not allowed with {}", locking.strength.clause(), clause)),
+        None => Ok(())
+    }
+}
+
+fn validate_expression(expr: &Expression<TypedColumn>, window_error: Option<&str>, aggregate_error: Option<&str>) -> Result<(), String> {
+    match *expr {
+        Expression::SubQuery(ref query) | Expression::Exists(ref query) => validate_select(query),
+        Expression::In(ref expr, ref query) => {
+            validate_expression(expr, window_error, aggregate_error)?;
+            validate_select(query)
+        },
+        Expression::Window(_) => {
+            if let Some(error) = window_error {
+                return Err(error.into());
+            }
+            for operand in expr.operands() {
+                validate_expression(operand, Some("window function calls cannot be nested"), Some("aggregate functions are not allowed in window function arguments"))?;
+            }
+            Ok(())
+        },
+        Expression::UserCall(ref call) if call.aggregate => {
+            if let Some(error) = aggregate_error {
+                return Err(error.into());
+            }
+            for operand in expr.operands() {
+                validate_expression(operand, Some("aggregate function calls cannot contain window function calls"), Some("aggregate function calls cannot be nested"))?;
+            }
+            Ok(())
+        },
+        _ => {
+            for operand in expr.operands() {
+                validate_expression(operand, window_error, aggregate_error)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn contains_aggregate(expr: &Expression<TypedColumn>) -> bool {
+    match *expr {
+        Expression::UserCall(ref call) if call.aggregate => true,
+        _ => expr.operands().into_iter().any(contains_aggregate)
+    }
+}
+
+fn contains_window(expr: &Expression<TypedColumn>) -> bool {
+    match *expr {
+        Expression::Window(_) => true,
+        _ => expr.operands().into_iter().any(contains_window)
+    }
+}
+
+/// Without `GROUP BY` an aggregated query returns a single row, so columns
+/// and window functions can be used only as arguments of aggregates.
+fn validate_aggregated_output(expr: &Expression<TypedColumn>) -> Result<(), String> {
+    match *expr {
+        Expression::UserCall(ref call) if call.aggregate => Ok(()),
+        Expression::Column(ref column) =>
+            Err(format!("column \"{}\" must appear in the GROUP BY clause or be used in an aggregate function", column.name)),
+        Expression::Window(_) => Err("window functions are not allowed in aggregated queries".into()),
+        _ => {
+            for operand in expr.operands() {
+                validate_aggregated_output(operand)?;
+            }
+            Ok(())
+        }
     }
 }
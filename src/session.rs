@@ -0,0 +1,72 @@
+use super::catalog_manager::CatalogManager;
+use super::data_manager::DataManager;
+use super::lexer::{tokenize, render, Tokens};
+use super::parser::{parse, split_statements};
+use super::query_executer::{execute, execute_with_source, ExecutionResult};
+use super::query_typer::type_inferring_old;
+use super::query_validator::validate_old;
+
+/// Connection of a single client to the shared catalog and data. Temporary
+/// tables created through a session are visible to it only and are dropped
+/// together with the session.
+pub struct Session {
+    catalog_manager: CatalogManager,
+    data_manager: DataManager
+}
+
+/// Failure of a script, statements before the failed one stay applied.
+#[derive(Debug, PartialEq)]
+pub struct ScriptError {
+    /// position of the failed statement counting from 1, or 0 when the
+    /// script couldn't be split into statements and nothing was run
+    pub statement: usize,
+    pub message: String
+}
+
+impl Session {
+
+    pub fn new(catalog_manager: &CatalogManager, data_manager: &DataManager) -> Session {
+        Session {
+            catalog_manager: catalog_manager.session(),
+            data_manager: data_manager.session()
+        }
+    }
+
+    pub fn catalog_manager(&self) -> &CatalogManager {
+        &self.catalog_manager
+    }
+
+    pub fn data_manager(&self) -> &DataManager {
+        &self.data_manager
+    }
+
+    pub fn execute(&self, query: &str) -> Result<ExecutionResult, String> {
+        tokenize(query).and_then(|tokens| self.run(tokens))
+    }
+
+    /// Runs semicolon separated statements in order until one of them fails.
+    pub fn execute_script(&self, script: &str) -> Result<Vec<ExecutionResult>, ScriptError> {
+        let tokens = tokenize(script).map_err(|message| ScriptError { statement: 0, message })?;
+        let mut results = vec![];
+        for (index, tokens) in split_statements(tokens).into_iter().enumerate() {
+            match self.run(tokens) {
+                Ok(result) => results.push(result),
+                Err(message) => return Err(ScriptError { statement: index + 1, message })
+            }
+        }
+        Ok(results)
+    }
+
+    /// Statements that change the catalog of a database file are kept in it
+    /// as text made of their tokens.
+    fn run(&self, tokens: Tokens) -> Result<ExecutionResult, String> {
+        let source = if self.data_manager.is_durable() { Some(render(&tokens)) } else { None };
+        parse(tokens)
+            .and_then(|statement| type_inferring_old(&self.catalog_manager, statement))
+            .and_then(|statement| validate_old(&self.catalog_manager, statement))
+            .and_then(|statement| match source {
+                Some(ref source) => execute_with_source(&self.catalog_manager, &self.data_manager, statement, source),
+                None => execute(&self.catalog_manager, &self.data_manager, statement)
+            })
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::buffer_pool::{BufferPool, PageId};
+use super::page::MAX_TUPLE_SIZE;
+use super::transaction::{TransactionId, FROZEN};
+use super::wal::{Wal, Recovery, wal_path};
+
+pub const DEFAULT_BUFFER_POOL_PAGES: usize = 256;
+
+/// owner of pages with the table directory and values of sequences
+const SYSTEM: u32 = 0;
+const MAGIC: &[u8] = b"rust-sql-engine database 1";
+
+const MAGIC_ENTRY: u8 = 0;
+const TABLE_ENTRY: u8 = 1;
+const SEQUENCE_ENTRY: u8 = 2;
+
+/// Location of a tuple, it doesn't change while the tuple lives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TupleId {
+    pub page: PageId,
+    pub slot: u16
+}
+
+/// Tuples of tables kept in pages of a single database file. A page holds
+/// tuples of a single table, the first page starts the directory that
+/// gives every table the number its pages are marked with. Changes of pages
+/// are logged ahead in a file next to the database file.
+#[derive(Debug)]
+pub struct Storage {
+    heap: Mutex<Heap>,
+    recovery: Recovery
+}
+
+#[derive(Debug)]
+struct Heap {
+    pool: BufferPool,
+    free_space: FreeSpaceMap,
+    owners: HashMap<String, u32>,
+    sequences: HashMap<String, (TupleId, i64)>
+}
+
+/// Free space of pages of every table, inserts look a page up in it instead
+/// of reading pages until one of them has enough space.
+#[derive(Debug, Default)]
+struct FreeSpaceMap {
+    pages_of: HashMap<u32, Vec<PageId>>,
+    free: Vec<usize>
+}
+
+impl FreeSpaceMap {
+
+    fn add(&mut self, owner: u32, page_id: PageId, free: usize) {
+        self.pages_of.entry(owner).or_default().push(page_id);
+        self.update(page_id, free);
+    }
+
+    fn update(&mut self, page_id: PageId, free: usize) {
+        let index = page_id as usize;
+        if index >= self.free.len() {
+            self.free.resize(index + 1, 0);
+        }
+        self.free[index] = free;
+    }
+
+    /// The most recently added page of the owner that has `needed` bytes.
+    fn find(&self, owner: u32, needed: usize) -> Option<PageId> {
+        self.pages_of.get(&owner)?.iter().rev().find(|&&page_id| self.free[page_id as usize] >= needed).cloned()
+    }
+
+    fn pages_of(&self, owner: u32) -> Vec<PageId> {
+        self.pages_of.get(&owner).cloned().unwrap_or_default()
+    }
+}
+
+impl Storage {
+
+    /// Opens the database file creating it when it doesn't exist, at most
+    /// `buffer_pool_pages` pages of it are kept in memory. Pages found in the
+    /// log are written to the file first.
+    pub fn open<P: AsRef<Path>>(path: P, buffer_pool_pages: usize) -> Result<Storage, String> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path.as_ref())
+            .map_err(|e| format!("could not open database file {}: {}", path.as_ref().display(), e))?;
+        let (wal, mut recovery) = Wal::open(wal_path(path.as_ref()))?;
+        let mut pool = BufferPool::new(file, wal, buffer_pool_pages)?;
+        pool.restore(recovery.take_pages())?;
+        let mut heap = Heap {
+            pool,
+            free_space: FreeSpaceMap::default(),
+            owners: HashMap::default(),
+            sequences: HashMap::default()
+        };
+        if heap.pool.page_count() == 0 {
+            let mut magic = vec![MAGIC_ENTRY];
+            magic.extend_from_slice(MAGIC);
+            heap.insert(SYSTEM, &magic)?;
+            heap.pool.checkpoint(FROZEN + 1, &[])?;
+        } else {
+            heap.load()?;
+        }
+        Ok(Storage { heap: Mutex::new(heap), recovery })
+    }
+
+    /// Whether the transaction that stamped a tuple found in the file when
+    /// it was opened had committed.
+    pub fn is_committed(&self, id: TransactionId) -> bool {
+        self.recovery.is_committed(id)
+    }
+
+    /// Tables that have pages in the file.
+    pub fn tables(&self) -> Vec<String> {
+        let heap = self.heap.lock().unwrap();
+        let mut tables = heap.owners.keys().cloned().collect::<Vec<String>>();
+        drop(heap);
+        tables.sort();
+        tables
+    }
+
+    /// All tuples of the table in the order of their pages and slots.
+    pub fn tuples(&self, table_name: &str) -> Result<Vec<(TupleId, Vec<u8>)>, String> {
+        let mut heap = self.heap.lock().unwrap();
+        let owner = match heap.owners.get(table_name) {
+            Some(&owner) => owner,
+            None => return Ok(vec![])
+        };
+        let mut tuples = vec![];
+        for page_id in heap.free_space.pages_of(owner) {
+            let page = heap.pool.page(page_id)?;
+            tuples.extend(page.tuples().into_iter().map(|(slot, tuple)| (TupleId { page: page_id, slot }, tuple.to_vec())));
+        }
+        drop(heap);
+        Ok(tuples)
+    }
+
+    pub fn insert(&self, table_name: &str, tuple: &[u8]) -> Result<TupleId, String> {
+        if tuple.len() > MAX_TUPLE_SIZE {
+            return Err(format!("row of \"{}\" takes {} bytes, it can't be bigger than {} bytes", table_name, tuple.len(), MAX_TUPLE_SIZE));
+        }
+        let mut heap = self.heap.lock().unwrap();
+        let owner = heap.owner_of(table_name)?;
+        let inserted = heap.insert(owner, tuple);
+        drop(heap);
+        inserted
+    }
+
+    pub fn read(&self, tuple_id: TupleId) -> Result<Vec<u8>, String> {
+        let mut heap = self.heap.lock().unwrap();
+        let tuple = heap.pool.page(tuple_id.page)?.tuple(tuple_id.slot).map(|tuple| tuple.to_vec());
+        drop(heap);
+        tuple.ok_or_else(|| format!("tuple {:?} does not exist", tuple_id))
+    }
+
+    /// Overwrites bytes of the tuple starting at `offset`.
+    pub fn write(&self, tuple_id: TupleId, offset: usize, bytes: &[u8]) -> Result<(), String> {
+        let mut heap = self.heap.lock().unwrap();
+        let written = match heap.pool.page_mut(tuple_id.page)?.tuple_mut(tuple_id.slot) {
+            Some(ref mut tuple) if offset + bytes.len() <= tuple.len() => {
+                tuple[offset..offset + bytes.len()].copy_from_slice(bytes);
+                Ok(())
+            },
+            _ => Err(format!("tuple {:?} does not exist", tuple_id))
+        };
+        drop(heap);
+        written
+    }
+
+    pub fn delete(&self, tuple_id: TupleId) -> Result<(), String> {
+        let mut heap = self.heap.lock().unwrap();
+        let page = heap.pool.page_mut(tuple_id.page)?;
+        page.delete(tuple_id.slot);
+        let free = page.free_space()?;
+        heap.free_space.update(tuple_id.page, free);
+        drop(heap);
+        Ok(())
+    }
+
+    /// Last values of sequences as they were saved.
+    pub fn sequences(&self) -> Vec<(String, i64)> {
+        let heap = self.heap.lock().unwrap();
+        let sequences = heap.sequences.iter().map(|(name, &(_, value))| (name.clone(), value)).collect();
+        drop(heap);
+        sequences
+    }
+
+    pub fn save_sequence(&self, name: &str, last_value: i64) -> Result<(), String> {
+        let mut heap = self.heap.lock().unwrap();
+        let saved = match heap.sequences.get(name).cloned() {
+            Some((_, value)) if value == last_value => Ok(()),
+            Some((tuple_id, _)) => {
+                let written = match heap.pool.page_mut(tuple_id.page)?.tuple_mut(tuple_id.slot) {
+                    Some(tuple) => {
+                        tuple[1..9].copy_from_slice(&last_value.to_le_bytes());
+                        Ok(())
+                    },
+                    None => Err(format!("value of sequence \"{}\" is lost", name))
+                };
+                heap.sequences.insert(name.to_owned(), (tuple_id, last_value));
+                written
+            },
+            None => {
+                let mut entry = vec![SEQUENCE_ENTRY];
+                entry.extend_from_slice(&last_value.to_le_bytes());
+                entry.extend_from_slice(name.as_bytes());
+                let tuple_id = heap.insert(SYSTEM, &entry)?;
+                heap.sequences.insert(name.to_owned(), (tuple_id, last_value));
+                Ok(())
+            }
+        };
+        drop(heap);
+        saved
+    }
+
+    /// Makes changes of the transaction durable, `changed` tells whether it
+    /// changed any tuples.
+    pub fn commit(&self, id: TransactionId, changed: bool) -> Result<(), String> {
+        let mut heap = self.heap.lock().unwrap();
+        let committed = heap.pool.commit(id, changed);
+        drop(heap);
+        committed
+    }
+
+    /// Writes changed pages to the file and empties the log, tuples stamped
+    /// with `active` transactions or ones from `next` on are not committed.
+    pub fn checkpoint(&self, next: TransactionId, active: &[TransactionId]) -> Result<(), String> {
+        let mut heap = self.heap.lock().unwrap();
+        let done = heap.pool.checkpoint(next, active);
+        drop(heap);
+        done
+    }
+
+    /// Bytes logged since the last checkpoint.
+    pub fn wal_size(&self) -> u64 {
+        let heap = self.heap.lock().unwrap();
+        let size = heap.pool.wal_size();
+        drop(heap);
+        size
+    }
+
+    pub fn resize_buffer_pool(&self, pages: usize) -> Result<(), String> {
+        let mut heap = self.heap.lock().unwrap();
+        let resized = heap.pool.resize(pages);
+        drop(heap);
+        resized
+    }
+
+    /// Number of pages in the file and how many of them are in memory.
+    pub fn pages(&self) -> (u32, usize) {
+        let heap = self.heap.lock().unwrap();
+        let pages = (heap.pool.page_count(), heap.pool.cached());
+        drop(heap);
+        pages
+    }
+}
+
+impl Heap {
+
+    /// Reads the directory and builds the free space map.
+    fn load(&mut self) -> Result<(), String> {
+        let magic = self.pool.page(0)?.tuple(0).map(|tuple| tuple.to_vec()).unwrap_or_default();
+        if magic.first() != Some(&MAGIC_ENTRY) || &magic[1..] != MAGIC {
+            return Err("file is not a database file".into());
+        }
+        for page_id in 0..self.pool.page_count() {
+            let page = self.pool.page(page_id)?;
+            let owner = page.owner();
+            let free = page.free_space()?;
+            let entries = if owner == SYSTEM {
+                page.tuples().into_iter().map(|(slot, tuple)| (TupleId { page: page_id, slot }, tuple.to_vec())).collect()
+            } else {
+                vec![]
+            };
+            self.free_space.add(owner, page_id, free);
+            for (tuple_id, entry) in entries {
+                self.load_entry(tuple_id, &entry)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn load_entry(&mut self, tuple_id: TupleId, entry: &[u8]) -> Result<(), String> {
+        let corrupted = || "directory of database file is corrupted".to_owned();
+        match entry.first() {
+            Some(&MAGIC_ENTRY) => {},
+            Some(&TABLE_ENTRY) if entry.len() > 5 => {
+                let mut owner = [0; 4];
+                owner.copy_from_slice(&entry[1..5]);
+                let name = String::from_utf8(entry[5..].to_vec()).map_err(|_| corrupted())?;
+                self.owners.insert(name, u32::from_le_bytes(owner));
+            },
+            Some(&SEQUENCE_ENTRY) if entry.len() > 9 => {
+                let mut value = [0; 8];
+                value.copy_from_slice(&entry[1..9]);
+                let name = String::from_utf8(entry[9..].to_vec()).map_err(|_| corrupted())?;
+                self.sequences.insert(name, (tuple_id, i64::from_le_bytes(value)));
+            },
+            _ => return Err(corrupted())
+        }
+        Ok(())
+    }
+
+    /// Number of pages of the table, a new table gets the next one.
+    fn owner_of(&mut self, table_name: &str) -> Result<u32, String> {
+        if let Some(&owner) = self.owners.get(table_name) {
+            return Ok(owner);
+        }
+        let owner = self.owners.values().max().map_or(SYSTEM + 1, |owner| owner + 1);
+        let mut entry = vec![TABLE_ENTRY];
+        entry.extend_from_slice(&owner.to_le_bytes());
+        entry.extend_from_slice(table_name.as_bytes());
+        self.insert(SYSTEM, &entry)?;
+        self.owners.insert(table_name.to_owned(), owner);
+        Ok(owner)
+    }
+
+    fn insert(&mut self, owner: u32, tuple: &[u8]) -> Result<TupleId, String> {
+        let page_id = match self.free_space.find(owner, tuple.len()) {
+            Some(page_id) => page_id,
+            None => {
+                let page_id = self.pool.allocate(owner)?;
+                self.free_space.add(owner, page_id, 0);
+                page_id
+            }
+        };
+        let page = self.pool.page_mut(page_id)?;
+        let slot = page.insert(tuple).ok_or_else(|| format!("page {} has no space for a tuple of {} bytes", page_id, tuple.len()))?;
+        let free = page.free_space()?;
+        self.free_space.update(page_id, free);
+        Ok(TupleId { page: page_id, slot })
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::{BTreeSet, HashSet};
+use std::mem;
+
+use super::lock_manager::DEADLOCK;
+
+/// Identifies a transaction, rows written outside of any transaction are
+/// stamped with `FROZEN` and are seen by everyone.
+pub type TransactionId = u64;
+
+pub const FROZEN: TransactionId = 0;
+
+const SERIALIZATION_FAILURE: &str = "could not serialize access due to read/write dependencies among transactions";
+pub const CONCURRENT_UPDATE: &str = "could not serialize access due to concurrent update";
+
+/// Transactions of all sessions that are started but not yet finished.
+#[derive(Debug)]
+pub struct Transactions {
+    next: TransactionId,
+    active: BTreeSet<TransactionId>,
+    serializable: HashSet<TransactionId>,
+    /// tables written by transactions that committed while a serializable
+    /// transaction was in progress
+    committed_writes: Vec<(TransactionId, HashSet<String>)>
+}
+
+impl Default for Transactions {
+    fn default() -> Self {
+        Transactions {
+            next: FROZEN + 1,
+            active: BTreeSet::default(),
+            serializable: HashSet::default(),
+            committed_writes: vec![]
+        }
+    }
+}
+
+impl Transactions {
+
+    pub fn start(&mut self, serializable: bool) -> TransactionId {
+        let id = self.next;
+        self.next += 1;
+        self.active.insert(id);
+        if serializable {
+            self.serializable.insert(id);
+        }
+        id
+    }
+
+    /// A serializable transaction that wrote something is serialized at its
+    /// commit, so it fails when a transaction it doesn't see has committed
+    /// changes to a table it read. One that only read is serialized at its
+    /// snapshot and never fails.
+    pub fn validate(&self, snapshot: &Snapshot, reads: &HashSet<String>) -> Result<(), String> {
+        let conflict = self.committed_writes.iter()
+            .any(|(id, writes)| !snapshot.sees(*id) && !writes.is_disjoint(reads));
+        if conflict {
+            Err(SERIALIZATION_FAILURE.into())
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn commit(&mut self, id: TransactionId, writes: HashSet<String>) {
+        if !self.serializable.is_empty() && !writes.is_empty() {
+            self.committed_writes.push((id, writes));
+        }
+        self.finish(id);
+    }
+
+    /// Changes of the transaction become visible to snapshots taken after
+    /// it finished, a rolled back transaction has to undo its changes before.
+    pub fn finish(&mut self, id: TransactionId) {
+        self.active.remove(&id);
+        self.end_serializable(id);
+    }
+
+    /// A rolled back transaction whose changes could not all be undone
+    /// stays in progress, so that snapshots never see its versions and
+    /// checkpoints keep them uncommitted until recovery removes them.
+    pub fn abort(&mut self, id: TransactionId) {
+        self.end_serializable(id);
+    }
+
+    fn end_serializable(&mut self, id: TransactionId) {
+        self.serializable.remove(&id);
+        if self.serializable.is_empty() {
+            self.committed_writes.clear();
+        }
+    }
+
+    /// Id of the next transaction and ids of transactions in progress.
+    pub fn in_progress(&self) -> (TransactionId, Vec<TransactionId>) {
+        (self.next, self.active.iter().cloned().collect())
+    }
+
+    pub fn snapshot(&self, own: TransactionId) -> Snapshot {
+        Snapshot {
+            own,
+            horizon: self.next,
+            active: self.active.iter().cloned().filter(|&id| id != own).collect()
+        }
+    }
+}
+
+/// Transactions whose changes are seen by a reader: its own one and those
+/// that had committed when the snapshot was taken.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    own: TransactionId,
+    /// transactions started after the snapshot was taken are not seen
+    horizon: TransactionId,
+    active: HashSet<TransactionId>
+}
+
+impl Snapshot {
+
+    pub fn own(&self) -> TransactionId {
+        self.own
+    }
+
+    pub fn sees(&self, id: TransactionId) -> bool {
+        id == self.own || (id < self.horizon && !self.active.contains(&id))
+    }
+}
+
+/// Whether a transaction that failed with the error can succeed when it is
+/// run again from the start.
+pub fn is_serialization_failure(error: &str) -> bool {
+    error == SERIALIZATION_FAILURE || error == CONCURRENT_UPDATE || error == DEADLOCK
+}
+
+/// Changes made by an open transaction in the order they were made, they
+/// are undone from the last one when the transaction or a part of it after
+/// a savepoint is rolled back.
+#[derive(Debug)]
+pub struct UndoLog<U> {
+    entries: Vec<U>,
+    /// names of savepoints with the number of entries made before them
+    savepoints: Vec<(String, usize)>
+}
+
+impl <U> Default for UndoLog<U> {
+    fn default() -> Self {
+        UndoLog {
+            entries: vec![],
+            savepoints: vec![]
+        }
+    }
+}
+
+impl <U> UndoLog<U> {
+
+    pub fn record(&mut self, entry: U) {
+        self.entries.push(entry);
+    }
+
+    /// A savepoint hides older savepoints with the same name until it is
+    /// released.
+    pub fn savepoint<I: Into<String>>(&mut self, name: I) {
+        self.savepoints.push((name.into(), self.entries.len()));
+    }
+
+    /// Entries to undo, the latest first, to return to the state of the
+    /// savepoint. The savepoint stays, the ones made after it are destroyed.
+    pub fn rollback_to(&mut self, name: &str) -> Result<Vec<U>, String> {
+        let index = self.find(name)?;
+        let position = self.savepoints[index].1;
+        self.savepoints.truncate(index + 1);
+        let mut undone = self.entries.split_off(position);
+        undone.reverse();
+        Ok(undone)
+    }
+
+    /// Destroys the savepoint and the ones made after it keeping all changes.
+    pub fn release(&mut self, name: &str) -> Result<(), String> {
+        let index = self.find(name)?;
+        self.savepoints.truncate(index);
+        Ok(())
+    }
+
+    /// Entries to undo, the latest first, to return to the state before the
+    /// transaction.
+    pub fn rollback(&mut self) -> Vec<U> {
+        self.savepoints.clear();
+        let mut undone = mem::take(&mut self.entries);
+        undone.reverse();
+        undone
+    }
+
+    fn find(&self, name: &str) -> Result<usize, String> {
+        self.savepoints.iter()
+            .rposition(|(savepoint, _)| savepoint == name)
+            .ok_or_else(|| format!("savepoint \"{}\" does not exist", name))
+    }
+}
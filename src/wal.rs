@@ -0,0 +1,220 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use super::buffer_pool::PageId;
+use super::page::{Page, PAGE_SIZE};
+use super::transaction::{TransactionId, FROZEN};
+
+pub const DEFAULT_WAL_SIZE_LIMIT: u64 = 16 * 1024 * 1024;
+
+const CHECKPOINT: u8 = 0;
+const PAGE: u8 = 1;
+const COMMIT: u8 = 2;
+
+/// kind, length of the payload and checksum of both
+const RECORD_HEADER_SIZE: usize = 9;
+
+/// Log of images of pages written ahead of the pages themselves. A page of
+/// the database file is overwritten only after its image reached the disk
+/// in the log, and a transaction commits when the images of pages it
+/// changed are followed by its commit record on the disk. The log starts
+/// with a checkpoint record, pages written to the file before it need no
+/// images.
+#[derive(Debug)]
+pub struct Wal {
+    path: PathBuf,
+    file: File,
+    size: u64
+}
+
+/// What the log tells about pages and transactions after a crash. Versions
+/// of rows in the pages are stamped with transactions that created or
+/// expired them, only those of committed transactions are kept.
+#[derive(Debug)]
+pub struct Recovery {
+    /// transactions started before the checkpoint are committed unless
+    /// they were in progress at it
+    next: TransactionId,
+    active: HashSet<TransactionId>,
+    committed: HashSet<TransactionId>,
+    /// latest images of pages written after the checkpoint
+    pages: HashMap<PageId, Page>
+}
+
+impl Recovery {
+
+    pub fn is_committed(&self, id: TransactionId) -> bool {
+        id == FROZEN || (id < self.next && !self.active.contains(&id)) || self.committed.contains(&id)
+    }
+
+    /// Images of pages to write to the database file, each one once.
+    pub fn take_pages(&mut self) -> Vec<(PageId, Page)> {
+        let mut pages = mem::take(&mut self.pages).into_iter().collect::<Vec<(PageId, Page)>>();
+        pages.sort_by_key(|&(page_id, _)| page_id);
+        pages
+    }
+}
+
+/// Log of the database file at `path`.
+pub fn wal_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut wal = path.as_ref().as_os_str().to_owned();
+    wal.push("-wal");
+    PathBuf::from(wal)
+}
+
+impl Wal {
+
+    /// Reads records up to the first one that was not written completely
+    /// and drops the rest. Without a log every version in the database file
+    /// is committed.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<(Wal, Recovery), String> {
+        let path = path.as_ref().to_path_buf();
+        let mut bytes = vec![];
+        match File::open(&path) {
+            Ok(mut file) => {
+                file.read_to_end(&mut bytes).map_err(|e| format!("could not read log {}: {}", path.display(), e))?;
+            },
+            Err(ref e) if e.kind() == ErrorKind::NotFound => {
+                let recovery = Recovery { next: TransactionId::MAX, active: HashSet::default(), committed: HashSet::default(), pages: HashMap::default() };
+                return Ok((Wal::create(path, FROZEN + 1, &[])?, recovery));
+            },
+            Err(e) => return Err(format!("could not open log {}: {}", path.display(), e))
+        }
+        let mut recovery = Recovery { next: FROZEN + 1, active: HashSet::default(), committed: HashSet::default(), pages: HashMap::default() };
+        let mut position = 0;
+        while let Some((kind, payload)) = read_record(&bytes[position..]) {
+            match kind {
+                CHECKPOINT if position == 0 && payload.len() >= 8 && payload.len() % 8 == 0 => {
+                    recovery.next = read_u64(&payload[0..8]);
+                    recovery.active = payload[8..].chunks(8).map(read_u64).collect();
+                },
+                PAGE if position > 0 && payload.len() == 4 + PAGE_SIZE => {
+                    let page = Page::from_bytes(payload[4..].to_vec().into_boxed_slice())?;
+                    recovery.pages.insert(read_u32(&payload[0..4]), page);
+                },
+                COMMIT if position > 0 && payload.len() == 8 => {
+                    recovery.committed.insert(read_u64(payload));
+                },
+                _ => break
+            }
+            position += RECORD_HEADER_SIZE + payload.len();
+        }
+        if position == 0 {
+            return Err(format!("log {} does not start with a checkpoint", path.display()));
+        }
+        let file = OpenOptions::new().append(true).open(&path).map_err(|e| format!("could not open log {}: {}", path.display(), e))?;
+        file.set_len(position as u64).map_err(|e| e.to_string())?;
+        Ok((Wal { path, file, size: position as u64 }, recovery))
+    }
+
+    fn create(path: PathBuf, next: TransactionId, active: &[TransactionId]) -> Result<Wal, String> {
+        let mut wal = Wal { file: File::create(&path).map_err(|e| format!("could not create log {}: {}", path.display(), e))?, path, size: 0 };
+        wal.reset(next, active)?;
+        Ok(wal)
+    }
+
+    /// Bytes written to the log since the last checkpoint.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn append_page(&mut self, page_id: PageId, page: &Page) -> Result<(), String> {
+        let mut payload = Vec::with_capacity(4 + PAGE_SIZE);
+        payload.extend_from_slice(&page_id.to_le_bytes());
+        payload.extend_from_slice(page.bytes());
+        self.append(PAGE, &payload)
+    }
+
+    pub fn append_commit(&mut self, id: TransactionId) -> Result<(), String> {
+        self.append(COMMIT, &id.to_le_bytes())
+    }
+
+    /// Waits until the records reach the disk.
+    pub fn sync(&mut self) -> Result<(), String> {
+        self.file.sync_data().map_err(|e| format!("could not write log {}: {}", self.path.display(), e))
+    }
+
+    /// Replaces the log with a single checkpoint record once all pages are
+    /// in the database file. The new log is renamed over the old one, so a
+    /// crash leaves one of them in place.
+    pub fn reset(&mut self, next: TransactionId, active: &[TransactionId]) -> Result<(), String> {
+        let mut payload = next.to_le_bytes().to_vec();
+        for id in active {
+            payload.extend_from_slice(&id.to_le_bytes());
+        }
+        let record = record(CHECKPOINT, &payload);
+        let mut temporary = self.path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        let temporary = PathBuf::from(temporary);
+        let error = |e: ::std::io::Error| format!("could not write log {}: {}", self.path.display(), e);
+        let mut file = File::create(&temporary).map_err(error)?;
+        file.write_all(&record).map_err(error)?;
+        file.sync_data().map_err(error)?;
+        fs::rename(&temporary, &self.path).map_err(error)?;
+        if let Some(directory) = self.path.parent().filter(|directory| !directory.as_os_str().is_empty()) {
+            File::open(directory).and_then(|directory| directory.sync_all()).map_err(error)?;
+        }
+        self.file = OpenOptions::new().append(true).open(&self.path).map_err(error)?;
+        self.size = record.len() as u64;
+        Ok(())
+    }
+
+    fn append(&mut self, kind: u8, payload: &[u8]) -> Result<(), String> {
+        let record = record(kind, payload);
+        self.file.write_all(&record).map_err(|e| format!("could not write log {}: {}", self.path.display(), e))?;
+        self.size += record.len() as u64;
+        Ok(())
+    }
+}
+
+fn record(kind: u8, payload: &[u8]) -> Vec<u8> {
+    let mut record = Vec::with_capacity(RECORD_HEADER_SIZE + payload.len());
+    record.push(kind);
+    record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    record.extend_from_slice(&checksum(kind, payload).to_le_bytes());
+    record.extend_from_slice(payload);
+    record
+}
+
+/// Kind and payload of the record at the start of `bytes`, `None` when it
+/// is cut off or its checksum doesn't match.
+fn read_record(bytes: &[u8]) -> Option<(u8, &[u8])> {
+    if bytes.len() < RECORD_HEADER_SIZE {
+        return None;
+    }
+    let kind = bytes[0];
+    let length = read_u32(&bytes[1..5]) as usize;
+    let payload = bytes.get(RECORD_HEADER_SIZE..RECORD_HEADER_SIZE + length)?;
+    if read_u32(&bytes[5..9]) == checksum(kind, payload) {
+        Some((kind, payload))
+    } else {
+        None
+    }
+}
+
+/// CRC-32 of the kind and payload of a record.
+fn checksum(kind: u8, payload: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in [kind].iter().chain(payload) {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    let mut value = [0; 8];
+    value.copy_from_slice(bytes);
+    u64::from_le_bytes(value)
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    let mut value = [0; 4];
+    value.copy_from_slice(bytes);
+    u32::from_le_bytes(value)
+}
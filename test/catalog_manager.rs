@@ -1,14 +1,15 @@
-use expectest::prelude::{be_true, be_false, be_some, be_equal_to};
+use expectest::prelude::{be_true, be_false, be_some, be_equal_to, be_ok, be_err};
 
 use sql::ast::Type;
 use sql::catalog_manager::CatalogManager;
-use sql::catalog::ColumnMetadata;
+use sql::catalog::{ColumnMetadata, UniqueKey};
+use sql::data::Datum;
 
 #[test]
 fn adds_table_to_catalog_manger() {
     let catalog_manager = CatalogManager::default();
 
-    catalog_manager.add_table("table_name");
+    drop(catalog_manager.add_table("table_name"));
 
     expect!(catalog_manager.contains_table("table_name"))
         .to(be_true());
@@ -26,7 +27,7 @@ fn does_not_contain_table_that_was_not_add() {
 fn adds_column_to_table() {
     let catalog_manager = CatalogManager::default();
 
-    catalog_manager.add_table("table");
+    drop(catalog_manager.add_table("table"));
 
     catalog_manager.add_column_to("table", ("col", Type::Integer, None));
 
@@ -38,7 +39,7 @@ fn adds_column_to_table() {
 fn does_not_contain_column_that_was_not_add() {
     let catalog_manager = CatalogManager::default();
 
-    catalog_manager.add_table("table");
+    drop(catalog_manager.add_table("table"));
 
     expect!(catalog_manager.contains_column_in("table", "col"))
         .to(be_false());
@@ -48,7 +49,7 @@ fn does_not_contain_column_that_was_not_add() {
 fn column_matches_type() {
     let catalog_manager = CatalogManager::default();
 
-    catalog_manager.add_table("table");
+    drop(catalog_manager.add_table("table"));
 
     catalog_manager.add_column_to("table", ("col", Type::Integer, None));
 
@@ -60,7 +61,7 @@ fn column_matches_type() {
 fn column_does_not_match_type() {
     let catalog_manager = CatalogManager::default();
 
-    catalog_manager.add_table("table");
+    drop(catalog_manager.add_table("table"));
 
     catalog_manager.add_column_to("table", ("col", Type::Character(Option::from(10)), None));
 
@@ -72,7 +73,7 @@ fn column_does_not_match_type() {
 fn get_table_columns() {
     let catalog_manager = CatalogManager::default();
 
-    catalog_manager.add_table("table");
+    drop(catalog_manager.add_table("table"));
 
     catalog_manager.add_column_to("table", ("col_1", Type::Integer, None));
     catalog_manager.add_column_to("table", ("col_2", Type::Integer, None));
@@ -82,9 +83,9 @@ fn get_table_columns() {
         .to(
             be_equal_to(
                 vec![
-                    ColumnMetadata { name: "col_1".to_owned(), col_type: Type::Integer, default_val: None },
-                    ColumnMetadata { name: "col_2".to_owned(), col_type: Type::Integer, default_val: None },
-                    ColumnMetadata { name: "col_3".to_owned(), col_type: Type::Integer, default_val: None }
+                    ColumnMetadata { name: "col_1".to_owned(), col_type: Type::Integer, default_val: None, identity: None, generated: None },
+                    ColumnMetadata { name: "col_2".to_owned(), col_type: Type::Integer, default_val: None, identity: None, generated: None },
+                    ColumnMetadata { name: "col_3".to_owned(), col_type: Type::Integer, default_val: None, identity: None, generated: None }
                 ]
             )
         );
@@ -94,7 +95,7 @@ fn get_table_columns() {
 fn gets_column_index_by_name() {
     let catalog_manager = CatalogManager::default();
 
-    catalog_manager.add_table("table");
+    drop(catalog_manager.add_table("table"));
 
     catalog_manager.add_column_to("table", ("col_1", Type::Integer, None));
     catalog_manager.add_column_to("table", ("col_2", Type::Integer, None));
@@ -104,3 +105,30 @@ fn gets_column_index_by_name() {
     expect!(catalog_manager.get_column_index("table", "col_2")).to(be_some().value(1));
     expect!(catalog_manager.get_column_index("table", "col_3")).to(be_some().value(2));
 }
+
+#[test]
+fn keeps_unique_keys_of_table() {
+    let catalog_manager = CatalogManager::default();
+
+    drop(catalog_manager.add_table("table"));
+    catalog_manager.add_unique_key("table", UniqueKey::new("table_pkey", vec!["col_1".to_owned()]));
+    catalog_manager.add_unique_key("table", UniqueKey::new("table_col_2_col_3_key", vec!["col_2".to_owned(), "col_3".to_owned()]));
+
+    let keys = catalog_manager.get_unique_keys("table");
+    expect!(keys.len()).to(be_equal_to(2));
+    expect!(keys[1].matches(&["col_3".to_owned(), "col_2".to_owned()])).to(be_true());
+    expect!(keys[1].matches(&["col_2".to_owned()])).to(be_false());
+    expect!(catalog_manager.get_unique_keys("other")).to(be_equal_to(vec![]));
+}
+
+#[test]
+fn does_not_register_function_twice() {
+    let catalog_manager = CatalogManager::default();
+
+    expect!(catalog_manager.register_function("Hash", vec![Type::Integer], Type::Integer, |args: &[Datum]| Ok(args[0].clone())))
+        .to(be_ok());
+    expect!(catalog_manager.register_function("hash", vec![Type::Integer], Type::Integer, |args: &[Datum]| Ok(args[0].clone())))
+        .to(be_err().value("function \"hash\" already exists".to_owned()));
+    expect!(catalog_manager.register_function("upper", vec![Type::Integer], Type::Integer, |args: &[Datum]| Ok(args[0].clone())))
+        .to(be_err().value("function \"upper\" already exists".to_owned()));
+}
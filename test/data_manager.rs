@@ -1,5 +1,6 @@
-use expectest::prelude::be_equal_to;
+use expectest::prelude::be_ok;
 
+use sql::data::Datum;
 use sql::data_manager::DataManager;
 
 #[test]
@@ -9,7 +10,7 @@ fn saves_to_one_row_table() {
     drop(data_manger.save_to("table_name", vec!["1".to_owned()]));
 
     expect!(data_manger.get_range_till_end("table_name", 0))
-        .to(be_equal_to(vec![vec!["1"]]));
+        .to(be_ok().value(vec![vec!["1"]]));
 }
 
 #[test]
@@ -20,9 +21,9 @@ fn retrievs_data_from_table() {
     drop(data_manager.save_to("table_name", vec!["3".to_owned(), "4".to_owned()]));
 
     expect!(data_manager.get_row_from("table_name", 0))
-        .to(be_equal_to(vec!["1", "2"]));
+        .to(be_ok().value(vec!["1", "2"]));
     expect!(data_manager.get_row_from("table_name", 1))
-        .to(be_equal_to(vec!["3", "4"]));
+        .to(be_ok().value(vec!["3", "4"]));
 }
 
 #[test]
@@ -36,7 +37,7 @@ fn retrievs_range_of_rows_from_table() {
     drop(data_manager.save_to("table_name", vec!["13".to_owned(), "14".to_owned(), "15".to_owned()]));
 
     expect!(data_manager.get_range("table_name", 1, 3))
-        .to(be_equal_to(
+        .to(be_ok().value(
             vec![
                 vec!["4", "5", "6"],
                 vec!["7", "8", "9"],
@@ -45,7 +46,7 @@ fn retrievs_range_of_rows_from_table() {
         ));
 
     expect!(data_manager.get_range("table_name", 2, 2))
-        .to(be_equal_to(
+        .to(be_ok().value(
             vec![
                 vec!["7", "8", "9"],
                 vec!["10", "11", "12"]
@@ -64,7 +65,7 @@ fn retrievs_range_from_index_till_end() {
     drop(data_manager.save_to("table_name", vec!["13".to_owned(), "14".to_owned(), "15".to_owned()]));
 
     expect!(data_manager.get_range_till_end("table_name", 2))
-        .to(be_equal_to(
+        .to(be_ok().value(
             vec![
                 vec!["7", "8", "9"],
                 vec!["10", "11", "12"],
@@ -82,7 +83,7 @@ fn retrievs_by_not_equal_predicate_on_column() {
     drop(data_manager.save_to("table_name", vec!["7".to_owned(), "8".to_owned(), "9".to_owned()]));
 
     expect!(data_manager.get_not_equal("table_name", 0, &("1".to_owned())))
-        .to(be_equal_to(
+        .to(be_ok().value(
             vec![
                 vec!["10", "11", "12"],
                 vec!["7", "8", "9"]
@@ -99,7 +100,7 @@ fn retrives_by_column_index() {
     drop(data_manager.save_to("table_name", vec!["7".to_owned(), "8".to_owned(), "9".to_owned()]));
 
     expect!(data_manager.get_range_till_end_for_column("table_name", 0, 1))
-        .to(be_equal_to(
+        .to(be_ok().value(
             vec![
                 vec!["10"],
                 vec!["1"],
@@ -107,3 +108,21 @@ fn retrives_by_column_index() {
             ]
         ));
 }
+
+#[test]
+fn finds_rows_by_current_values_of_key() {
+    let data_manager = DataManager::default();
+
+    drop(data_manager.save_to("table_name", vec!["1".to_owned(), "a".to_owned()]));
+    drop(data_manager.save_to("table_name", vec!["2".to_owned(), "b  ".to_owned()]));
+
+    expect!(data_manager.find_key("table_name", "key", &[1], &[Datum::from("b".to_owned())]))
+        .to(be_ok().value(Some((1, vec![Datum::from("2".to_owned()), Datum::from("b  ".to_owned())]))));
+
+    drop(data_manager.update_rows_by_id("table_name", vec![(0, vec![Datum::from("1".to_owned()), Datum::from("c".to_owned())])]));
+
+    expect!(data_manager.find_key("table_name", "key", &[1], &[Datum::from("a".to_owned())]))
+        .to(be_ok().value(None));
+    expect!(data_manager.find_key("table_name", "key", &[1], &[Datum::from("c".to_owned())]))
+        .to(be_ok().value(Some((0, vec![Datum::from("1".to_owned()), Datum::from("c".to_owned())]))));
+}
@@ -0,0 +1,70 @@
+use expectest::prelude::{be_equal_to, be_ok};
+
+use sql::data::Datum;
+use sql::external_sort::ExternalSorter;
+
+type KeyOf = fn(&[Datum]) -> Result<Datum, String>;
+
+fn first_column(row: &[Datum]) -> Result<Datum, String> {
+    Ok(row[0].clone())
+}
+
+fn sorted(sorter: ExternalSorter<Datum, KeyOf>) -> Result<Vec<Vec<Datum>>, String> {
+    sorter.finish().and_then(|rows| rows.collect())
+}
+
+#[test]
+fn sorts_rows_in_memory_without_limit() {
+    let mut sorter = ExternalSorter::new(first_column as KeyOf, None);
+
+    for v in &[3, 1, 2] {
+        expect!(sorter.push(vec![Datum::Integer(*v)])).to(be_ok());
+    }
+
+    expect!(sorter.spilled_runs()).to(be_equal_to(0));
+    expect!(sorted(sorter))
+        .to(be_ok().value(vec![vec!["1"], vec!["2"], vec!["3"]]));
+}
+
+#[test]
+fn spills_runs_when_memory_limit_exceeded() {
+    let mut sorter = ExternalSorter::new(first_column as KeyOf, Some(1));
+
+    for v in &[5, 3, 4, 1, 2] {
+        expect!(sorter.push(vec![Datum::Integer(*v), Datum::Character(format!("row\t{}\n", v))])).to(be_ok());
+    }
+
+    expect!(sorter.spilled_runs()).to(be_equal_to(5));
+    expect!(sorted(sorter))
+        .to(be_ok().value(
+            vec![
+                vec![Datum::Integer(1), Datum::Character("row\t1\n".into())],
+                vec![Datum::Integer(2), Datum::Character("row\t2\n".into())],
+                vec![Datum::Integer(3), Datum::Character("row\t3\n".into())],
+                vec![Datum::Integer(4), Datum::Character("row\t4\n".into())],
+                vec![Datum::Integer(5), Datum::Character("row\t5\n".into())]
+            ]
+        ));
+}
+
+#[test]
+fn keeps_nulls_and_order_of_equal_keys_across_runs() {
+    let mut sorter = ExternalSorter::new(first_column as KeyOf, Some(100));
+
+    for &(k, v) in &[(1, "a"), (0, "b"), (1, "c"), (0, "d"), (1, "e"), (0, "f")] {
+        expect!(sorter.push(vec![Datum::Integer(k), Datum::Character(v.into()), Datum::Null])).to(be_ok());
+    }
+
+    expect!(sorter.spilled_runs() > 1).to(be_equal_to(true));
+    expect!(sorted(sorter))
+        .to(be_ok().value(
+            vec![
+                vec![Datum::Integer(0), Datum::Character("b".into()), Datum::Null],
+                vec![Datum::Integer(0), Datum::Character("d".into()), Datum::Null],
+                vec![Datum::Integer(0), Datum::Character("f".into()), Datum::Null],
+                vec![Datum::Integer(1), Datum::Character("a".into()), Datum::Null],
+                vec![Datum::Integer(1), Datum::Character("c".into()), Datum::Null],
+                vec![Datum::Integer(1), Datum::Character("e".into()), Datum::Null]
+            ]
+        ));
+}
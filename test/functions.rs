@@ -0,0 +1,99 @@
+use expectest::prelude::{be_equal_to, be_ok, be_err};
+
+use sql::ast::Type;
+use sql::ast::function::{Function, TrimSide};
+use sql::data::Datum;
+use sql::functions::{call, like, timestamp, AggregateFunction, UserFunctions, AGGREGATE_BATCH_SIZE};
+
+fn text(s: &str) -> Datum {
+    Datum::Character(s.into())
+}
+
+#[test]
+fn like_matches_any_sequence_and_single_character() {
+    expect!(like("abcde", "a%e", None, false)).to(be_ok().value(true));
+    expect!(like("abcde", "a_c%", None, false)).to(be_ok().value(true));
+    expect!(like("abcde", "%c_", None, false)).to(be_ok().value(false));
+    expect!(like("", "%", None, false)).to(be_ok().value(true));
+    expect!(like("aXbXc", "%x%x%", None, true)).to(be_ok().value(true));
+}
+
+#[test]
+fn like_escapes_wildcards() {
+    expect!(like("50%", "50\\%", None, false)).to(be_ok().value(true));
+    expect!(like("500", "50#%", Some("#"), false)).to(be_ok().value(false));
+    expect!(like("a\\b", "a\\b", Some(""), false)).to(be_ok().value(true));
+    expect!(like("abc", "abc#", Some("#"), false)).to(be_err().value("LIKE pattern must not end with escape character".to_owned()));
+    expect!(like("abc", "abc", Some("##"), false)).to(be_err().value("invalid escape string".to_owned()));
+}
+
+#[test]
+fn timestamp_is_formatted_in_utc() {
+    expect!(timestamp(0)).to(be_equal_to("1970-01-01 00:00:00".to_owned()));
+    expect!(timestamp(951_829_509)).to(be_equal_to("2000-02-29 13:05:09".to_owned()));
+}
+
+#[test]
+fn substring_counts_positions_before_the_string() {
+    expect!(call(Function::Substring, &[text("hello"), Datum::Integer(-1), Datum::Integer(4)])).to(be_ok().value(text("he")));
+    expect!(call(Function::Substring, &[text("hello"), Datum::Integer(4)])).to(be_ok().value(text("lo")));
+    expect!(call(Function::Substring, &[text("hello"), Datum::Integer(1), Datum::Integer(-1)]))
+        .to(be_err().value("negative substring length not allowed".to_owned()));
+}
+
+#[test]
+fn string_functions_return_null_on_null_argument() {
+    expect!(call(Function::Trim(TrimSide::Both), &[Datum::Null])).to(be_ok().value(Datum::Null));
+    expect!(call(Function::Position, &[text("l"), text("hello")])).to(be_ok().value(Datum::Integer(3)));
+    expect!(call(Function::Replace, &[text("hello"), text("l"), Datum::Null])).to(be_equal_to(Ok(Datum::Null)));
+}
+
+struct Count;
+
+impl AggregateFunction for Count {
+    type State = (i64, usize);
+
+    fn init(&self) -> (i64, usize) {
+        (0, 0)
+    }
+
+    fn accumulate(&self, state: &mut (i64, usize), _args: &[Datum]) -> Result<(), String> {
+        state.0 += 1;
+        Ok(())
+    }
+
+    fn merge(&self, state: &mut (i64, usize), other: (i64, usize)) -> Result<(), String> {
+        state.0 += other.0;
+        state.1 += other.1 + 1;
+        Ok(())
+    }
+
+    fn finalize(&self, state: (i64, usize)) -> Result<Datum, String> {
+        Ok(Datum::Character(format!("{} rows in {} batches", state.0, state.1)))
+    }
+}
+
+#[test]
+fn aggregate_merges_states_of_row_batches() {
+    let mut functions = UserFunctions::default();
+    expect!(functions.add_aggregate("count_rows", vec![Type::Integer], Type::Character(None), Count)).to(be_ok());
+
+    let rows = (0..AGGREGATE_BATCH_SIZE * 2 + 1).map(|v| vec![Datum::Integer(v as i64)]).collect::<Vec<Vec<Datum>>>();
+
+    expect!(functions.aggregate("count_rows", &rows)).to(be_ok().value(text("2049 rows in 3 batches")));
+    expect!(functions.aggregate("count_rows", &[])).to(be_ok().value(text("0 rows in 0 batches")));
+}
+
+#[test]
+fn accumulator_takes_rows_one_by_one() {
+    let mut functions = UserFunctions::default();
+    expect!(functions.add_aggregate("count_rows", vec![Type::Integer], Type::Character(None), Count)).to(be_ok());
+
+    let mut accumulator = functions.accumulator("count_rows").unwrap();
+    for v in 0..AGGREGATE_BATCH_SIZE + 1 {
+        expect!(accumulator.push(&[Datum::Integer(v as i64)])).to(be_ok());
+    }
+
+    expect!(accumulator.finish()).to(be_ok().value(text("1025 rows in 2 batches")));
+    expect!(functions.accumulator("missing").map(|_| ())).to(be_err().value("aggregate function missing() does not exist".to_owned()));
+}
@@ -8,8 +8,19 @@ pub mod parser;
 pub mod query_typer;
 pub mod query_validator;
 pub mod query_executer;
+pub mod session;
+pub mod transaction;
 pub mod catalog_manager;
 pub mod data_manager;
+pub mod external_sort;
+pub mod functions;
+pub mod storage;
+pub mod wal;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
 
 use sql::lexer::tokenize;
 use sql::parser::parse;
@@ -18,6 +29,8 @@ use sql::query_validator::validate_old;
 use sql::query_executer::{execute, ExecutionResult};
 use sql::data_manager::DataManager;
 use sql::catalog_manager::CatalogManager;
+use sql::session::Session;
+use sql::wal::wal_path;
 
 pub fn evaluate_query(query: &str, data_manager: &DataManager, catalog_manager: &CatalogManager) -> Result<ExecutionResult, String> {
     tokenize(query)
@@ -26,3 +39,22 @@ pub fn evaluate_query(query: &str, data_manager: &DataManager, catalog_manager:
         .and_then(|statement| validate_old(catalog_manager, statement))
         .and_then(|statement| execute(catalog_manager, data_manager, statement))
 }
+
+pub fn assert_that_session_returns_data(session: &Session, src_query: &str, expected_data: &str) {
+    match session.execute(src_query) {
+        Ok(ExecutionResult::Data(data)) => assert_eq!(format!("{:?}", data), expected_data),
+        res => panic!("unexpected query evaluation result {:?}", res)
+    }
+}
+
+/// Path of a database file in the temporary directory, removed with its log.
+pub fn database_file(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("sql-{}-{}.db", process::id(), name));
+    remove_database(&path);
+    path
+}
+
+pub fn remove_database(path: &PathBuf) {
+    drop(fs::remove_file(path));
+    drop(fs::remove_file(wal_path(path)));
+}
@@ -1,5 +1,5 @@
 use sql::lexer::tokenize;
-use sql::parser::parse;
+use sql::parser::{parse, parse_script};
 
 fn assert_that_statement_parsed_into(src: &str, expected: &str) {
     match tokenize(src).and_then(parse) {
@@ -37,6 +37,14 @@ mod should_parse {
             );
         }
 
+        #[test]
+        fn with_unique_columns_and_table_keys() {
+            assert_that_statement_parsed_into(
+                "create table table_1 (col_1 integer unique, col_2 integer, col_3 integer, primary key (col_2, col_3), unique (col_3));",
+                "statement: 'create table', table name: 'table_1', columns: [<name: 'col_1', type: 'integer', primary key: No, foreign key: No, nullable: Yes, default value: NULL, unique: Yes>, <name: 'col_2', type: 'integer', primary key: No, foreign key: No, nullable: Yes, default value: NULL>, <name: 'col_3', type: 'integer', primary key: No, foreign key: No, nullable: Yes, default value: NULL>], keys: [<primary key: ['col_2', 'col_3']>, <unique: ['col_3']>]"
+            );
+        }
+
         #[test]
         fn with_default_value_constraint() {
             assert_that_statement_parsed_into(
@@ -45,6 +53,14 @@ mod should_parse {
             );
         }
 
+        #[test]
+        fn with_default_expressions() {
+            assert_that_statement_parsed_into(
+                "create table table1 (col1 char(10) default 'a' || 'b' not null, col2 integer default nextval('seq'), col3 char(19) default current_timestamp);",
+                "statement: 'create table', table name: 'table1', columns: [<name: 'col1', type: 'character[10]', primary key: No, foreign key: No, nullable: No, default value: 'a' || 'b'>, <name: 'col2', type: 'integer', primary key: No, foreign key: No, nullable: Yes, default value: nextval('seq')>, <name: 'col3', type: 'character[19]', primary key: No, foreign key: No, nullable: Yes, default value: current_timestamp>]"
+            );
+        }
+
         #[test]
         fn infer_type_for_primary_key_column() {
             assert_that_statement_parsed_into(
@@ -92,6 +108,111 @@ mod should_parse {
                 "statement: 'create table', table name: 'tab1', columns: [<name: 'col2', type: 'character', primary key: No, foreign key: No, nullable: Yes, default value: NULL>]"
             );
         }
+
+        #[test]
+        fn with_serial_and_identity_columns() {
+            assert_that_statement_parsed_into(
+                "create table tab1 (col1 serial, col2 integer generated always as identity, col3 integer generated by default as identity);",
+                "statement: 'create table', table name: 'tab1', columns: [<name: 'col1', type: 'integer', primary key: No, foreign key: No, nullable: Yes, default value: NULL, identity: by default>, <name: 'col2', type: 'integer', primary key: No, foreign key: No, nullable: Yes, default value: NULL, identity: always>, <name: 'col3', type: 'integer', primary key: No, foreign key: No, nullable: Yes, default value: NULL, identity: by default>]"
+            );
+        }
+
+        #[test]
+        fn temporary_table() {
+            assert_that_statement_parsed_into(
+                "create temporary table tab1 (col1 integer);",
+                "statement: 'create temporary table', table name: 'tab1', columns: [<name: 'col1', type: 'integer', primary key: No, foreign key: No, nullable: Yes, default value: NULL>]"
+            );
+            assert_that_statement_parsed_into(
+                "create temp table tab1 (col1 integer);",
+                "statement: 'create temporary table', table name: 'tab1', columns: [<name: 'col1', type: 'integer', primary key: No, foreign key: No, nullable: Yes, default value: NULL>]"
+            );
+        }
+
+        #[test]
+        fn with_generated_columns() {
+            assert_that_statement_parsed_into(
+                "create table tab1 (col1 char(5), col2 char(5) generated always as (upper(col1)) stored, col3 integer generated always as (length(col1)));",
+                "statement: 'create table', table name: 'tab1', columns: [<name: 'col1', type: 'character[5]', primary key: No, foreign key: No, nullable: Yes, default value: NULL>, <name: 'col2', type: 'character[5]', primary key: No, foreign key: No, nullable: Yes, default value: NULL, generated: upper(col1) stored>, <name: 'col3', type: 'integer', primary key: No, foreign key: No, nullable: Yes, default value: NULL, generated: length(col1) virtual>]"
+            );
+        }
+
+        #[test]
+        fn create_sequence() {
+            assert_that_statement_parsed_into(
+                "create sequence seq_1;",
+                "statement: 'create sequence', name: 'seq_1', start: 1, increment: 1"
+            );
+            assert_that_statement_parsed_into(
+                "create sequence seq_2 start with 10 increment by -2;",
+                "statement: 'create sequence', name: 'seq_2', start: 10, increment: -2"
+            );
+        }
+
+        #[test]
+        fn create_and_drop_views() {
+            assert_that_statement_parsed_into(
+                "create view view_1 (name) as select col1 from tab1 where col2 > 0;",
+                "statement: 'create view', name: 'view_1', columns: ['name'], query: <statement: 'select', tables: [<name: 'tab1'>], columns: [<name: 'col1'>], where: predicate <col2 greater than 0>>"
+            );
+            assert_that_statement_parsed_into(
+                "create materialized view view_2 as select col1 from tab1;",
+                "statement: 'create materialized view', name: 'view_2', columns: [], query: <statement: 'select', tables: [<name: 'tab1'>], columns: [<name: 'col1'>], where: no predicate>"
+            );
+            assert_that_statement_parsed_into(
+                "refresh materialized view view_2;",
+                "statement: 'refresh materialized view', name: 'view_2'"
+            );
+            assert_that_statement_parsed_into(
+                "drop view view_1;",
+                "statement: 'drop view', name: 'view_1'"
+            );
+            assert_that_statement_parsed_into(
+                "drop materialized view view_2;",
+                "statement: 'drop materialized view', name: 'view_2'"
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod transaction_statements {
+        use super::super::assert_that_statement_parsed_into;
+
+        #[test]
+        fn begin_commit_and_rollback() {
+            assert_that_statement_parsed_into("begin;", "statement: 'begin'");
+            assert_that_statement_parsed_into("start transaction;", "statement: 'begin'");
+            assert_that_statement_parsed_into("commit work;", "statement: 'commit'");
+            assert_that_statement_parsed_into("rollback transaction;", "statement: 'rollback'");
+        }
+
+        #[test]
+        fn isolation_levels() {
+            assert_that_statement_parsed_into(
+                "begin isolation level read committed;",
+                "statement: 'begin', isolation level: 'read committed'"
+            );
+            assert_that_statement_parsed_into(
+                "begin transaction isolation level repeatable read;",
+                "statement: 'begin', isolation level: 'repeatable read'"
+            );
+            assert_that_statement_parsed_into(
+                "start transaction isolation level snapshot;",
+                "statement: 'begin', isolation level: 'snapshot'"
+            );
+            assert_that_statement_parsed_into(
+                "begin isolation level serializable;",
+                "statement: 'begin', isolation level: 'serializable'"
+            );
+        }
+
+        #[test]
+        fn savepoints() {
+            assert_that_statement_parsed_into("savepoint sp_1;", "statement: 'savepoint', name: 'sp_1'");
+            assert_that_statement_parsed_into("rollback to savepoint sp_1;", "statement: 'rollback to savepoint', name: 'sp_1'");
+            assert_that_statement_parsed_into("rollback to sp_1;", "statement: 'rollback to savepoint', name: 'sp_1'");
+            assert_that_statement_parsed_into("release sp_1;", "statement: 'release savepoint', name: 'sp_1'");
+        }
     }
 
     #[cfg(test)]
@@ -121,6 +242,35 @@ mod should_parse {
                 "statement: 'delete', table name: 'table_name_3', where: predicate <'str' equals to col_2>"
             );
         }
+
+        #[test]
+        fn with_returning() {
+            assert_that_statement_parsed_into(
+                "delete from table_name_4 where col_1 = 5 returning col_1, col_2 as c;",
+                "statement: 'delete', table name: 'table_name_4', where: predicate <col_1 equals to 5>, returning: [<name: 'col_1'>, <name: 'col_2'> as 'c']"
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod update_statements {
+        use super::super::assert_that_statement_parsed_into;
+
+        #[test]
+        fn with_many_assignments() {
+            assert_that_statement_parsed_into(
+                "update table_name_1 set col_1 = 5, col_2 = col_2 || 'x';",
+                "statement: 'update', table name: 'table_name_1', set: [<name: 'col_1'> = 5, <name: 'col_2'> = col_2 || 'x'], where: no predicate"
+            );
+        }
+
+        #[test]
+        fn with_predicate_and_returning() {
+            assert_that_statement_parsed_into(
+                "update table_name_2 set col_1 = 'str' where col_2 = 1 returning col_1;",
+                "statement: 'update', table name: 'table_name_2', set: [<name: 'col_1'> = 'str'], where: predicate <col_2 equals to 1>, returning: [<name: 'col_1'>]"
+            );
+        }
     }
 
     #[cfg(test)]
@@ -158,6 +308,38 @@ mod should_parse {
                 "statement: 'insert', table name: 'table_1', columns: [<name: 'col_1'>, <name: 'col_2'>], values: <substatement: 'select', tables: [<name: 'table_1'>], columns: [<name: 'col_1'>, <name: 'col_2'>], where: no predicate>"
             );
         }
+
+        #[test]
+        fn with_many_rows_of_values() {
+            assert_that_statement_parsed_into(
+                "insert into table_1 values (1, 'a'), (2, 'b');",
+                "statement: 'insert', table name: 'table_1', columns: [], values: [<value: 1, type: integer>, <value: a, type: character[1]>], [<value: 2, type: integer>, <value: b, type: character[1]>]"
+            );
+        }
+
+        #[test]
+        fn with_returning() {
+            assert_that_statement_parsed_into(
+                "insert into table_1 (col_2) values ('a') returning col_1, col_2;",
+                "statement: 'insert', table name: 'table_1', columns: [<name: 'col_2'>], values: [<value: a, type: character[1]>], returning: [<name: 'col_1'>, <name: 'col_2'>]"
+            );
+        }
+
+        #[test]
+        fn with_on_conflict_do_nothing() {
+            assert_that_statement_parsed_into(
+                "insert into table_1 values (1) on conflict do nothing;",
+                "statement: 'insert', table name: 'table_1', columns: [], values: [<value: 1, type: integer>], on conflict: <target: [], do nothing>"
+            );
+        }
+
+        #[test]
+        fn with_on_conflict_do_update() {
+            assert_that_statement_parsed_into(
+                "insert into table_1 values (1, 'a') on conflict (col_1) do update set col_2 = excluded.col_2 where col_2 <> 'b' returning col_2;",
+                "statement: 'insert', table name: 'table_1', columns: [], values: [<value: 1, type: integer>, <value: a, type: character[1]>], on conflict: <target: ['col_1'], do update set: [<name: 'col_2'> = excluded.col_2], where: predicate <col_2 not equals to 'b'>>, returning: [<name: 'col_2'>]"
+            );
+        }
     }
 
     #[cfg(test)]
@@ -195,6 +377,148 @@ mod should_parse {
                 "statement: 'select', tables: [<name: 'table_1'>], columns: [<name: 'col_2'>], where: predicate <col_1 not equals to 'a'>"
             );
         }
+
+        #[test]
+        fn with_order_by() {
+            assert_that_statement_parsed_into(
+                "select col_1 from table_1 order by col_1;",
+                "statement: 'select', tables: [<name: 'table_1'>], columns: [<name: 'col_1'>], where: no predicate, order by: [<key: <name: 'col_1'>, order: asc, nulls: last>]"
+            );
+        }
+
+        #[test]
+        fn with_order_by_list_of_keys() {
+            assert_that_statement_parsed_into(
+                "select col_1, col_2 from table_1 order by col_1 desc, 2 asc nulls first;",
+                "statement: 'select', tables: [<name: 'table_1'>], columns: [<name: 'col_1'>, <name: 'col_2'>], where: no predicate, order by: [<key: <name: 'col_1'>, order: desc, nulls: first>, <key: <value: 2, type: integer>, order: asc, nulls: first>]"
+            );
+        }
+
+        #[test]
+        fn with_column_alias_and_limit() {
+            assert_that_statement_parsed_into(
+                "select col_1 as c from table_1 order by c nulls last limit 5;",
+                "statement: 'select', tables: [<name: 'table_1'>], columns: [<name: 'col_1'> as 'c'], where: no predicate, order by: [<key: <name: 'c'>, order: asc, nulls: last>], limit: 5"
+            );
+        }
+
+        #[test]
+        fn with_row_locking() {
+            assert_that_statement_parsed_into(
+                "select id from jobs order by id limit 1 for update skip locked;",
+                "statement: 'select', tables: [<name: 'jobs'>], columns: [<name: 'id'>], where: no predicate, order by: [<key: <name: 'id'>, order: asc, nulls: last>], limit: 1, for update skip locked"
+            );
+            assert_that_statement_parsed_into(
+                "select id from jobs for share nowait;",
+                "statement: 'select', tables: [<name: 'jobs'>], columns: [<name: 'id'>], where: no predicate, for share nowait"
+            );
+        }
+
+        #[test]
+        fn with_distinct() {
+            assert_that_statement_parsed_into(
+                "select distinct col_1 from table_1;",
+                "statement: 'select distinct', tables: [<name: 'table_1'>], columns: [<name: 'col_1'>], where: no predicate"
+            );
+        }
+
+        #[test]
+        fn with_union_all_and_order_by() {
+            assert_that_statement_parsed_into(
+                "select col_1 from table_1 union all select col_2 from table_2 order by 1;",
+                "statement: 'select', tables: [<name: 'table_1'>], columns: [<name: 'col_1'>], where: no predicate, union all: <substatement: 'select', tables: [<name: 'table_2'>], columns: [<name: 'col_2'>], where: no predicate>, order by: [<key: <value: 1, type: integer>, order: asc, nulls: last>]"
+            );
+        }
+
+        #[test]
+        fn with_intersect_taking_precedence_over_except() {
+            assert_that_statement_parsed_into(
+                "select col from tab_1 except select col from tab_2 intersect select col from tab_3;",
+                "statement: 'select', tables: [<name: 'tab_1'>], columns: [<name: 'col'>], where: no predicate, except: <substatement: 'select', tables: [<name: 'tab_2'>], columns: [<name: 'col'>], where: no predicate, intersect: <substatement: 'select', tables: [<name: 'tab_3'>], columns: [<name: 'col'>], where: no predicate>>"
+            );
+        }
+
+        #[test]
+        fn with_in_and_not_in_sub_queries() {
+            assert_that_statement_parsed_into(
+                "select col from tab_1 where col in (select col from tab_2) or col not in (select col from tab_3);",
+                "statement: 'select', tables: [<name: 'tab_1'>], columns: [<name: 'col'>], where: predicate <col in <substatement: 'select', tables: [<name: 'tab_2'>], columns: [<name: 'col'>], where: no predicate> or not col in <substatement: 'select', tables: [<name: 'tab_3'>], columns: [<name: 'col'>], where: no predicate>>"
+            );
+        }
+
+        #[test]
+        fn with_recursive_common_table_expression() {
+            assert_that_statement_parsed_into(
+                "with recursive cte (c) as (select col from tab_1 union all select c from cte) select c from cte;",
+                "statement: 'select', tables: [<name: 'cte'>], columns: [<name: 'c'>], where: no predicate, with recursive: [<name: 'cte', columns: ['c'], query: <substatement: 'select', tables: [<name: 'tab_1'>], columns: [<name: 'col'>], where: no predicate, union all: <substatement: 'select', tables: [<name: 'cte'>], columns: [<name: 'c'>], where: no predicate>>>]"
+            );
+        }
+
+        #[test]
+        fn with_window_function() {
+            assert_that_statement_parsed_into(
+                "select sum(col_2) over (partition by col_1 order by col_2 desc rows between 2 preceding and current row) from tab_1;",
+                "statement: 'select', tables: [<name: 'tab_1'>], columns: [<expression: sum(col_2) over (partition by col_1 order by [<key: <name: 'col_2'>, order: desc, nulls: first>] rows between 2 preceding and current row)>], where: no predicate"
+            );
+        }
+
+        #[test]
+        fn with_case_and_casts() {
+            assert_that_statement_parsed_into(
+                "select case col_1 when 1 then 'a' else cast(col_2 as char(1)) end, coalesce(col_2, nullif(col_1, 0))::boolean from tab_1;",
+                "statement: 'select', tables: [<name: 'tab_1'>], columns: [<expression: case col_1 when 1 then 'a' else cast(col_2 as character[1]) end>, <expression: cast(coalesce(col_2, nullif(col_1, 0)) as boolean)>], where: no predicate"
+            );
+        }
+
+        #[test]
+        fn with_null_literal() {
+            assert_that_statement_parsed_into(
+                "select coalesce(null, col_1), case when col_1 > 1 then null end from tab_1;",
+                "statement: 'select', tables: [<name: 'tab_1'>], columns: [<expression: coalesce(null, col_1)>, <expression: case when col_1 greater than 1 then null end>], where: no predicate"
+            );
+        }
+
+        #[test]
+        fn with_exists_and_scalar_sub_query() {
+            assert_that_statement_parsed_into(
+                "select col from tab_1 where exists (select col from tab_2 where tab_2.col = tab_1.col) and col > (select col from tab_3);",
+                "statement: 'select', tables: [<name: 'tab_1'>], columns: [<name: 'col'>], where: predicate <exists <substatement: 'select', tables: [<name: 'tab_2'>], columns: [<name: 'col'>], where: predicate <tab_2.col equals to tab_1.col>> and col greater than <substatement: 'select', tables: [<name: 'tab_3'>], columns: [<name: 'col'>], where: no predicate>>"
+            );
+        }
+
+        #[test]
+        fn with_string_functions_and_pattern_matching() {
+            assert_that_statement_parsed_into(
+                "select col_1 || '!', substring(col_1 from 2 for 3), trim(leading 'x' from col_1), position('a' in col_1) from tab_1 where col_1 not ilike 'a#_%' escape '#';",
+                "statement: 'select', tables: [<name: 'tab_1'>], columns: [<expression: col_1 || '!'>, <expression: substring(col_1, 2, 3)>, <expression: ltrim(col_1, 'x')>, <expression: position('a', col_1)>], where: predicate <not col_1 ilike 'a#_%' escape '#'>"
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod scripts {
+        use sql::lexer::tokenize;
+
+        use super::super::parse_script;
+
+        #[test]
+        fn statements_separated_by_semicolons() {
+            let statements = tokenize("create table tab1 (col1 integer);\n-- seed\ninsert into tab1 values (1);; delete from tab1")
+                .and_then(parse_script)
+                .unwrap()
+                .iter()
+                .map(|statement| format!("{:?}", statement))
+                .collect::<Vec<String>>();
+
+            assert_eq!(
+                statements,
+                vec![
+                    "statement: 'create table', table name: 'tab1', columns: [<name: 'col1', type: 'integer', primary key: No, foreign key: No, nullable: Yes, default value: NULL>]",
+                    "statement: 'insert', table name: 'tab1', columns: [], values: [<value: 1, type: integer>]",
+                    "statement: 'delete', table name: 'tab1', where: no predicate"
+                ]
+            );
+        }
     }
 }
 
@@ -235,4 +559,19 @@ mod should_not_parse_when {
             );
         }
     }
+
+    #[cfg(test)]
+    mod script {
+        use sql::lexer::tokenize;
+
+        use super::super::parse_script;
+
+        #[test]
+        fn names_statement_with_error() {
+            assert_eq!(
+                tokenize("create sequence seq_1; create sequence seq_2 start 1 2;").and_then(parse_script),
+                Err("statement 2: unexpected token Some(NumericConstant(2))".into())
+            );
+        }
+    }
 }
@@ -91,6 +91,31 @@ mod data_manipulation_language {
         use super::super::assert_that_query_evaluation_return_message;
         use super::super::assert_that_query_evaluation_return_data;
 
+        #[test]
+        fn shorter_string_into_character_column() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            drop(evaluate_query("create table words (id integer, name character(5));", &data_manager, &catalog_manager));
+
+            assert_that_query_evaluation_return_message(
+                "insert into words (name) values ('zz');",
+                "row was inserted",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_that_query_evaluation_return_data(
+                "select name || '|' from words;",
+                "[[\"zz|\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_eq!(
+                evaluate_query("insert into words (name) values (1);", &data_manager, &catalog_manager),
+                Err("column \"name\" is of type character[5] but expression is of type integer".into())
+            );
+        }
+
         #[test]
         #[ignore]
         fn row_in_created_table() {
@@ -170,6 +195,119 @@ mod data_manipulation_language {
                 &catalog_manager
             );
         }
+
+        #[test]
+        fn many_rows_of_values() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            drop(evaluate_query("create table table1 (col1 integer, col2 integer default 1);", &data_manager, &catalog_manager));
+
+            assert_that_query_evaluation_return_message(
+                "insert into table1 (col1) values (1), (2), (3);",
+                "3 rows were inserted",
+                &data_manager,
+                &catalog_manager
+            );
+
+            assert_that_query_evaluation_return_data(
+                "select col1, col2 from table1;",
+                "[[\"1\", \"1\"], [\"2\", \"1\"], [\"3\", \"1\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn values_into_reordered_and_skipped_columns() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            drop(evaluate_query("create table table1 (col1 integer, col2 integer default 5, col3 integer);", &data_manager, &catalog_manager));
+
+            assert_that_query_evaluation_return_message(
+                "insert into table1 (col3, col1) values (3, 1), (6, 4);",
+                "2 rows were inserted",
+                &data_manager,
+                &catalog_manager
+            );
+            drop(evaluate_query("insert into table1 values (7);", &data_manager, &catalog_manager));
+
+            assert_that_query_evaluation_return_data(
+                "select col1, col2, col3 from table1;",
+                "[[\"1\", \"5\", \"3\"], [\"4\", \"5\", \"6\"], [\"7\", \"5\", NULL]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_eq!(
+                evaluate_query("insert into table1 (col1, col3) values (1);", &data_manager, &catalog_manager),
+                Err("INSERT has more target columns than expressions".into())
+            );
+        }
+
+        #[test]
+        fn select_into_listed_columns_with_defaults() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            drop(evaluate_query("create table source (a integer, b integer);", &data_manager, &catalog_manager));
+            drop(evaluate_query("insert into source values (1, 2), (3, 4);", &data_manager, &catalog_manager));
+            drop(evaluate_query("create table target (x integer default 7, y integer, z integer);", &data_manager, &catalog_manager));
+
+            assert_that_query_evaluation_return_message(
+                "insert into target (z, y) select a, b from source;",
+                "2 rows were inserted",
+                &data_manager,
+                &catalog_manager
+            );
+
+            assert_that_query_evaluation_return_data(
+                "select x, y, z from target;",
+                "[[\"7\", \"2\", \"1\"], [\"7\", \"4\", \"3\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn default_expressions_are_evaluated_for_every_row() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            drop(evaluate_query("create sequence note_ids start with 10;", &data_manager, &catalog_manager));
+            drop(evaluate_query("create table notes (id integer default nextval('note_ids'), tag character(5) default 'a' || 'b', created character(19) default current_timestamp, body integer);", &data_manager, &catalog_manager));
+
+            assert_that_query_evaluation_return_message(
+                "insert into notes (body) values (1), (2);",
+                "2 rows were inserted",
+                &data_manager,
+                &catalog_manager
+            );
+
+            assert_that_query_evaluation_return_data(
+                "select id, tag, length(created), body from notes;",
+                "[[\"10\", \"ab   \", \"19\", \"1\"], [\"11\", \"ab   \", \"19\", \"2\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn defaulted_and_written_values_match_the_same_predicate() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            drop(evaluate_query("create table tags (id integer, tag character(5) default 'ab');", &data_manager, &catalog_manager));
+            drop(evaluate_query("insert into tags (id) values (1);", &data_manager, &catalog_manager));
+            drop(evaluate_query("insert into tags values (2, 'ab');", &data_manager, &catalog_manager));
+
+            assert_that_query_evaluation_return_data(
+                "select id, tag from tags where tag = 'ab';",
+                "[[\"1\", \"ab   \"], [\"2\", \"ab   \"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
     }
 
     #[cfg(test)]
@@ -280,4 +418,1592 @@ mod data_manipulation_language {
             );
         }
     }
+
+    #[cfg(test)]
+    mod ordering {
+        use expectest::prelude::be_true;
+
+        use sql::catalog_manager::CatalogManager;
+        use sql::data_manager::DataManager;
+
+        use super::super::super::evaluate_query;
+        use super::super::assert_that_query_evaluation_return_data;
+
+        fn populated_table(data_manager: &DataManager, catalog_manager: &CatalogManager) {
+            drop(evaluate_query("create table tab1 (col_1 integer, col_2 integer);", data_manager, catalog_manager));
+            drop(evaluate_query("insert into tab1 values(10, 1);", data_manager, catalog_manager));
+            drop(evaluate_query("insert into tab1 values(9, 2);", data_manager, catalog_manager));
+            drop(evaluate_query("insert into tab1 values(100, 1);", data_manager, catalog_manager));
+            drop(evaluate_query("insert into tab1 values(2);", data_manager, catalog_manager));
+        }
+
+        #[test]
+        fn compares_integers_by_value() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select col_1 from tab1 order by col_1;",
+                "[[\"2\"], [\"9\"], [\"10\"], [\"100\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn descending_with_nulls_first_by_default() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select col_2, col_1 from tab1 order by col_2 desc;",
+                "[[NULL, \"2\"], [\"2\", \"9\"], [\"1\", \"10\"], [\"1\", \"100\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn by_several_keys_with_explicit_nulls_order() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select col_1 from tab1 order by col_2 asc nulls first, col_1 desc;",
+                "[[\"2\"], [\"100\"], [\"10\"], [\"9\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn by_output_column_position_and_alias() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select col_2 as second, col_1 from tab1 order by second nulls last, 2 desc;",
+                "[[\"1\", \"100\"], [\"1\", \"10\"], [\"2\", \"9\"], [NULL, \"2\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn top_rows_with_limit() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select col_1 from tab1 order by col_1 desc limit 2;",
+                "[[\"100\"], [\"10\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn spilling_to_disk_under_memory_limit() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default().with_query_memory_limit(64);
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select col_1, col_2 from tab1 order by col_2 nulls first, col_1;",
+                "[[\"2\", NULL], [\"10\", \"1\"], [\"100\", \"1\"], [\"9\", \"2\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn sorting_more_rows_than_memory_limit_spills_runs() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default().with_query_memory_limit(64);
+
+            drop(evaluate_query("create table numbers (n integer);", &data_manager, &catalog_manager));
+            for n in 0..50 {
+                drop(evaluate_query(format!("insert into numbers values({});", n).as_str(), &data_manager, &catalog_manager));
+            }
+
+            let sorted = (0..50).rev().map(|n| format!("[\"{}\"]", n)).collect::<Vec<String>>();
+            assert_that_query_evaluation_return_data(
+                "select n from numbers order by n desc;",
+                format!("[{}]", sorted.join(", ")).as_str(),
+                &data_manager,
+                &catalog_manager
+            );
+            expect!(data_manager.spilled_runs() > 1).to(be_true());
+        }
+    }
+
+    #[cfg(test)]
+    mod set_operations {
+        use sql::catalog_manager::CatalogManager;
+        use sql::data_manager::DataManager;
+
+        use super::super::super::evaluate_query;
+        use super::super::assert_that_query_evaluation_return_data;
+
+        fn populated_tables(data_manager: &DataManager, catalog_manager: &CatalogManager) {
+            drop(evaluate_query("create table tab1 (col integer);", data_manager, catalog_manager));
+            drop(evaluate_query("create table tab2 (col integer);", data_manager, catalog_manager));
+            for v in &[3, 1, 2, 3, 1] {
+                drop(evaluate_query(format!("insert into tab1 values({});", v).as_str(), data_manager, catalog_manager));
+            }
+            for v in &[4, 3, 3] {
+                drop(evaluate_query(format!("insert into tab2 values({});", v).as_str(), data_manager, catalog_manager));
+            }
+        }
+
+        #[test]
+        fn distinct_values() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default().with_query_memory_limit(64);
+
+            populated_tables(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select distinct col from tab1 order by col desc;",
+                "[[\"3\"], [\"2\"], [\"1\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn union_removes_duplicates() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_tables(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select col from tab1 union select col from tab2 order by col;",
+                "[[\"1\"], [\"2\"], [\"3\"], [\"4\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn union_all_keeps_duplicates_and_applies_limit_to_result() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_tables(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select col as c from tab1 union all select col from tab2 order by c desc limit 4;",
+                "[[\"4\"], [\"3\"], [\"3\"], [\"3\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn intersect_and_except() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_tables(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select col from tab1 intersect select col from tab2;",
+                "[[\"3\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+
+            assert_that_query_evaluation_return_data(
+                "select col from tab1 except select col from tab2 order by 1;",
+                "[[\"1\"], [\"2\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn intersect_and_except_under_memory_limit() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default().with_query_memory_limit(64);
+
+            populated_tables(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select col from tab2 intersect select col from tab1;",
+                "[[\"3\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+
+            assert_that_query_evaluation_return_data(
+                "select col from tab1 except select col from tab2 except select col from tab1 where col = 2;",
+                "[[\"1\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod subqueries {
+        use sql::catalog_manager::CatalogManager;
+        use sql::data_manager::DataManager;
+
+        use super::super::super::evaluate_query;
+        use super::super::assert_that_query_evaluation_return_data;
+
+        fn populated_tables(data_manager: &DataManager, catalog_manager: &CatalogManager) {
+            drop(evaluate_query("create table departments (id integer, floor integer);", data_manager, catalog_manager));
+            drop(evaluate_query("create table employees (id integer, dep_id integer, salary integer);", data_manager, catalog_manager));
+            drop(evaluate_query("insert into departments values(1, 3);", data_manager, catalog_manager));
+            drop(evaluate_query("insert into departments values(2, 1);", data_manager, catalog_manager));
+            drop(evaluate_query("insert into departments values(3, 2);", data_manager, catalog_manager));
+            drop(evaluate_query("insert into employees values(10, 1, 300);", data_manager, catalog_manager));
+            drop(evaluate_query("insert into employees values(20, 1, 100);", data_manager, catalog_manager));
+            drop(evaluate_query("insert into employees values(30, 2, 200);", data_manager, catalog_manager));
+        }
+
+        #[test]
+        fn in_and_not_in() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_tables(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select floor from departments where id in (select dep_id from employees) order by floor;",
+                "[[\"1\"], [\"3\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+
+            assert_that_query_evaluation_return_data(
+                "select floor from departments where id not in (select dep_id from employees);",
+                "[[\"2\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn correlated_exists() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_tables(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select id from departments where exists (select id from employees where dep_id = departments.id and salary > 250);",
+                "[[\"1\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn scalar_subquery_in_comparison() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_tables(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select id from employees where dep_id = (select id from departments where floor = 1) or salary < 150 order by id;",
+                "[[\"20\"], [\"30\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn scalar_subquery_returning_more_than_one_row() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_tables(&data_manager, &catalog_manager);
+
+            assert_eq!(
+                evaluate_query("select floor from departments where id = (select dep_id from employees);", &data_manager, &catalog_manager),
+                Err("more than one row returned by a subquery used as an expression".into())
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod common_table_expressions {
+        use sql::catalog_manager::CatalogManager;
+        use sql::data_manager::DataManager;
+
+        use super::super::super::evaluate_query;
+        use super::super::assert_that_query_evaluation_return_data;
+
+        fn populated_tree(data_manager: &DataManager, catalog_manager: &CatalogManager) {
+            drop(evaluate_query("create table nodes (id integer primary key, parent_id integer foreign key references nodes (id));", data_manager, catalog_manager));
+            for &(id, parent_id) in &[(1, 0), (2, 1), (3, 2), (4, 2), (5, 3), (6, 1)] {
+                drop(evaluate_query(format!("insert into nodes values({}, {});", id, parent_id).as_str(), data_manager, catalog_manager));
+            }
+        }
+
+        #[test]
+        fn chained_with_queries() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_tree(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "with deep as (select id, parent_id as parent from nodes where id > 2), children(id) as (select id from deep where parent = 2) select id from children order by id;",
+                "[[\"3\"], [\"4\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn recursive_walk_over_hierarchy() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_tree(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "with recursive subtree(id) as (select id from nodes where id = 2 union all select id from nodes where parent_id in (select id from subtree)) select id from subtree order by id;",
+                "[[\"2\"], [\"3\"], [\"4\"], [\"5\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn recursion_without_fixpoint_is_stopped() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_tree(&data_manager, &catalog_manager);
+
+            assert_eq!(
+                evaluate_query("with recursive cycle(id) as (select id from nodes where id = 1 union all select id from cycle) select id from cycle;", &data_manager, &catalog_manager),
+                Err("recursive query \"cycle\" exceeded maximum recursion depth of 1000".into())
+            );
+
+            assert_that_query_evaluation_return_data(
+                "with recursive cycle(id) as (select id from nodes where id = 1 union select id from cycle) select id from cycle;",
+                "[[\"1\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod window_functions {
+        use sql::catalog_manager::CatalogManager;
+        use sql::data_manager::DataManager;
+
+        use super::super::super::evaluate_query;
+        use super::super::assert_that_query_evaluation_return_data;
+
+        fn populated_scores(data_manager: &DataManager, catalog_manager: &CatalogManager) {
+            drop(evaluate_query("create table scores (team integer, player integer, points integer);", data_manager, catalog_manager));
+            for &(team, player, points) in &[(1, 10, 30), (1, 11, 20), (1, 12, 20), (1, 13, 10), (2, 20, 50), (2, 21, 40)] {
+                drop(evaluate_query(format!("insert into scores values({}, {}, {});", team, player, points).as_str(), data_manager, catalog_manager));
+            }
+        }
+
+        #[test]
+        fn ranking_within_partitions() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_scores(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select player, row_number() over (partition by team order by points desc), rank() over (partition by team order by points desc), dense_rank() over (partition by team order by points desc) from scores order by player;",
+                "[[\"10\", \"1\", \"1\", \"1\"], [\"11\", \"2\", \"2\", \"2\"], [\"12\", \"3\", \"2\", \"2\"], [\"13\", \"4\", \"4\", \"3\"], [\"20\", \"1\", \"1\", \"1\"], [\"21\", \"2\", \"2\", \"2\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn lag_lead_and_first_value() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_scores(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select player, lag(points) over (order by player), lead(points, 2, 0) over (order by player), first_value(player) over (partition by team order by points desc) from scores order by player;",
+                "[[\"10\", NULL, \"20\", \"10\"], [\"11\", \"30\", \"10\", \"10\"], [\"12\", \"20\", \"50\", \"10\"], [\"13\", \"20\", \"40\", \"10\"], [\"20\", \"10\", \"0\", \"20\"], [\"21\", \"50\", \"0\", \"20\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn running_sum_includes_peers_of_current_row() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_scores(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select player, sum(points) over (partition by team order by points) from scores order by player;",
+                "[[\"10\", \"80\"], [\"11\", \"50\"], [\"12\", \"50\"], [\"13\", \"10\"], [\"20\", \"90\"], [\"21\", \"40\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn sum_and_avg_over_rows_frames() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_scores(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select player, sum(points) over (order by player rows between 1 preceding and 1 following), avg(points) over (order by player rows 2 preceding) from scores order by player;",
+                "[[\"10\", \"50\", \"30.0000000000000000\"], [\"11\", \"70\", \"25.0000000000000000\"], [\"12\", \"50\", \"23.3333333333333333\"], [\"13\", \"80\", \"16.6666666666666667\"], [\"20\", \"100\", \"26.6666666666666667\"], [\"21\", \"90\", \"33.3333333333333333\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn avg_keeps_fraction() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            drop(evaluate_query("create table pairs (id integer, grp integer, v integer);", &data_manager, &catalog_manager));
+            for (id, grp, v) in &[(1, 1, 1), (2, 1, 2), (3, 2, 1), (4, 2, 2), (5, 2, 2)] {
+                drop(evaluate_query(format!("insert into pairs values({}, {}, {});", id, grp, v).as_str(), &data_manager, &catalog_manager));
+            }
+
+            assert_that_query_evaluation_return_data(
+                "select id, avg(v) over (partition by grp) from pairs order by id;",
+                "[[\"1\", \"1.5000000000000000\"], [\"2\", \"1.5000000000000000\"], [\"3\", \"1.6666666666666667\"], [\"4\", \"1.6666666666666667\"], [\"5\", \"1.6666666666666667\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn sum_over_range_frame_with_offset() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_scores(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select player, sum(points) over (order by points range between 10 preceding and current row) from scores order by player;",
+                "[[\"10\", \"70\"], [\"11\", \"50\"], [\"12\", \"50\"], [\"13\", \"10\"], [\"20\", \"90\"], [\"21\", \"70\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod conditional_expressions {
+        use sql::catalog_manager::CatalogManager;
+        use sql::data_manager::DataManager;
+
+        use super::super::super::evaluate_query;
+        use super::super::assert_that_query_evaluation_return_data;
+
+        fn populated_table(data_manager: &DataManager, catalog_manager: &CatalogManager) {
+            drop(evaluate_query("create table tab1 (col integer);", data_manager, catalog_manager));
+            for v in &[1, 2, 3] {
+                drop(evaluate_query(format!("insert into tab1 values({});", v).as_str(), data_manager, catalog_manager));
+            }
+        }
+
+        #[test]
+        fn case_coalesce_and_nullif() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select case when col > 1 then 'big' else 'small' end, case col when 1 then 'one' end, coalesce(nullif(col, 2), 0) from tab1 order by col;",
+                "[[\"small\", \"one\", \"1\"], [\"big\", NULL, \"0\"], [\"big\", NULL, \"3\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn null_literal() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select coalesce(null, col), case when col > 1 then null else col end, nullif(col, null) from tab1 order by col;",
+                "[[\"1\", \"1\", \"1\"], [\"2\", NULL, \"2\"], [\"3\", NULL, \"3\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn casts_between_types() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select cast(col as character(5)), col::boolean, ' 42 '::integer, 'yes'::bool::int from tab1 where col = 3;",
+                "[[\"3\", \"true\", \"42\", \"1\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn invalid_cast_is_reported() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_eq!(
+                evaluate_query("select 'abc'::integer from tab1;", &data_manager, &catalog_manager),
+                Err("invalid input syntax for type integer: 'abc'".into())
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod string_functions {
+        use sql::catalog_manager::CatalogManager;
+        use sql::data_manager::DataManager;
+
+        use super::super::super::evaluate_query;
+        use super::super::assert_that_query_evaluation_return_data;
+
+        fn populated_table(data_manager: &DataManager, catalog_manager: &CatalogManager) {
+            drop(evaluate_query("create table words (word character(5));", data_manager, catalog_manager));
+            for word in &["Hello", "ab   ", "a%b  "] {
+                drop(evaluate_query(format!("insert into words values('{}');", word).as_str(), data_manager, catalog_manager));
+            }
+        }
+
+        #[test]
+        fn padding_of_fixed_width_values_is_ignored() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select word || '!', length(word), upper(word), lower(word) from words order by word;",
+                "[[\"Hello!\", \"5\", \"HELLO\", \"hello\"], [\"a%b!\", \"3\", \"A%B\", \"a%b\"], [\"ab!\", \"2\", \"AB\", \"ab\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn substring_trim_replace_and_position() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select substring(word from 2 for 3), substring(word, 0, 2), trim(both 'H' from word), trim(leading from '  x  ') || '|', replace(word, 'l', 'L'), position('l' in word) from words where word = 'Hello';",
+                "[[\"ell\", \"H\", \"ello\", \"x  |\", \"HeLLo\", \"3\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn like_and_ilike_with_escape() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select word from words where word like 'a%' and word not like '%#%%' escape '#' or word ilike 'h_LLO' order by word;",
+                "[[\"Hello\"], [\"ab   \"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_that_query_evaluation_return_data(
+                "select word from words where word not like '%b';",
+                "[[\"Hello\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn pattern_ending_with_escape_character_is_reported() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_eq!(
+                evaluate_query("select word from words where word like 'ab#' escape '#';", &data_manager, &catalog_manager),
+                Err("LIKE pattern must not end with escape character".into())
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod user_defined_functions {
+        use sql::ast::Type;
+        use sql::catalog_manager::CatalogManager;
+        use sql::data::Datum;
+        use sql::data_manager::DataManager;
+        use sql::functions::AggregateFunction;
+
+        use super::super::super::evaluate_query;
+        use super::super::assert_that_query_evaluation_return_data;
+
+        struct Product;
+
+        impl AggregateFunction for Product {
+            type State = Option<i64>;
+
+            fn init(&self) -> Option<i64> {
+                None
+            }
+
+            fn accumulate(&self, state: &mut Option<i64>, args: &[Datum]) -> Result<(), String> {
+                if let Datum::Integer(v) = args[0] {
+                    *state = Some(state.unwrap_or(1) * v);
+                }
+                Ok(())
+            }
+
+            fn merge(&self, state: &mut Option<i64>, other: Option<i64>) -> Result<(), String> {
+                if let Some(other) = other {
+                    *state = Some(state.unwrap_or(1) * other);
+                }
+                Ok(())
+            }
+
+            fn finalize(&self, state: Option<i64>) -> Result<Datum, String> {
+                Ok(state.map_or(Datum::Null, Datum::Integer))
+            }
+        }
+
+        fn populated_table(data_manager: &DataManager, catalog_manager: &CatalogManager) {
+            drop(catalog_manager.register_function(
+                "manhattan",
+                vec![Type::Integer, Type::Integer, Type::Integer, Type::Integer],
+                Type::Integer,
+                |args: &[Datum]| match *args {
+                    [Datum::Integer(x1), Datum::Integer(y1), Datum::Integer(x2), Datum::Integer(y2)] =>
+                        Ok(Datum::Integer((x1 - x2).abs() + (y1 - y2).abs())),
+                    _ => Ok(Datum::Null)
+                }
+            ));
+            drop(catalog_manager.register_aggregate("product", vec![Type::Integer], Type::Integer, Product));
+            drop(evaluate_query("create table points (x integer, y integer);", data_manager, catalog_manager));
+            for &(x, y) in &[(1, 2), (3, 4), (6, 5)] {
+                drop(evaluate_query(format!("insert into points values({}, {});", x, y).as_str(), data_manager, catalog_manager));
+            }
+        }
+
+        #[test]
+        fn scalar_function_in_select_list_and_where() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select x, manhattan(x, y, 0, 0) from points where manhattan(x, y, 1, 2) < 5 order by x;",
+                "[[\"1\", \"3\"], [\"3\", \"7\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn aggregate_function_over_filtered_rows() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select product(x), product(y) = 20 from points where y > 2;",
+                "[[\"18\", \"true\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_that_query_evaluation_return_data(
+                "select product(x) from points where y > 5;",
+                "[[NULL]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn arguments_are_checked_against_declared_types() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_eq!(
+                evaluate_query("select manhattan(x, y, 'a', 0) from points;", &data_manager, &catalog_manager),
+                Err("function manhattan(integer, integer, character[1], integer) does not exist".into())
+            );
+            assert_eq!(
+                evaluate_query("select x, product(y) from points;", &data_manager, &catalog_manager),
+                Err("column \"x\" must appear in the GROUP BY clause or be used in an aggregate function".into())
+            );
+            assert_eq!(
+                evaluate_query("select x from points where product(y) > 1;", &data_manager, &catalog_manager),
+                Err("aggregate functions are not allowed in WHERE".into())
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod updates_and_deletes {
+        use sql::catalog_manager::CatalogManager;
+        use sql::data_manager::DataManager;
+
+        use super::super::super::evaluate_query;
+        use super::super::assert_that_query_evaluation_return_message;
+        use super::super::assert_that_query_evaluation_return_data;
+
+        fn populated_table(data_manager: &DataManager, catalog_manager: &CatalogManager) {
+            drop(evaluate_query("create table items (id integer, name character(5));", data_manager, catalog_manager));
+            drop(evaluate_query("insert into items values (1, 'pen  '), (2, 'ink  '), (3, 'cap  ');", data_manager, catalog_manager));
+        }
+
+        #[test]
+        fn update_rows_that_match_predicate() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_message(
+                "update items set name = name || 's' where id > 1;",
+                "2 rows were updated",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_that_query_evaluation_return_data(
+                "select id, name from items;",
+                "[[\"1\", \"pen  \"], [\"2\", \"inks \"], [\"3\", \"caps \"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_eq!(
+                evaluate_query("update items set name = name || 'ss' where id = 2;", &data_manager, &catalog_manager),
+                Err("value too long for type character[5]".into())
+            );
+        }
+
+        #[test]
+        fn delete_rows_that_match_predicate() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_message(
+                "delete from items where name like '%n%';",
+                "2 rows were deleted",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_that_query_evaluation_return_data(
+                "select id, name from items;",
+                "[[\"3\", \"cap  \"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_eq!(
+                evaluate_query("delete from missing;", &data_manager, &catalog_manager),
+                Err("[ERR 100] table 'missing' does not exist".into())
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod returning {
+        use sql::catalog_manager::CatalogManager;
+        use sql::data_manager::DataManager;
+
+        use super::super::super::evaluate_query;
+        use super::super::assert_that_query_evaluation_return_data;
+
+        fn created_table(data_manager: &DataManager, catalog_manager: &CatalogManager) {
+            drop(evaluate_query("create table items (id integer default 7, name character(5));", data_manager, catalog_manager));
+        }
+
+        #[test]
+        fn inserted_rows_with_defaults() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            created_table(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "insert into items (name) values ('pen  '), ('ink  ') returning id, upper(name) as name;",
+                "[[\"7\", \"PEN\"], [\"7\", \"INK\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_that_query_evaluation_return_data(
+                "insert into items select id, name from items where name = 'pen  ' returning name;",
+                "[[\"pen  \"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn updated_rows_with_new_values() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            created_table(&data_manager, &catalog_manager);
+            drop(evaluate_query("insert into items values (1, 'pen  '), (2, 'ink  ');", &data_manager, &catalog_manager));
+
+            assert_that_query_evaluation_return_data(
+                "update items set id = 3, name = 'cap  ' where id = 2 returning id, name;",
+                "[[\"3\", \"cap  \"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_that_query_evaluation_return_data(
+                "update items set name = 'box  ' where id = 5 returning id;",
+                "[]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn deleted_rows() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            created_table(&data_manager, &catalog_manager);
+            drop(evaluate_query("insert into items values (1, 'pen  '), (2, 'ink  ');", &data_manager, &catalog_manager));
+
+            assert_that_query_evaluation_return_data(
+                "delete from items where id = 1 returning id, name;",
+                "[[\"1\", \"pen  \"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_that_query_evaluation_return_data(
+                "select id from items;",
+                "[[\"2\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod on_conflict {
+        use sql::catalog_manager::CatalogManager;
+        use sql::data_manager::DataManager;
+
+        use super::super::super::evaluate_query;
+        use super::super::assert_that_query_evaluation_return_message;
+        use super::super::assert_that_query_evaluation_return_data;
+
+        fn populated_table(data_manager: &DataManager, catalog_manager: &CatalogManager) {
+            drop(evaluate_query("create table stock (id integer primary key, name character(5) unique, qty integer);", data_manager, catalog_manager));
+            drop(evaluate_query("insert into stock values (1, 'pen  ', 10), (2, 'ink  ', 20);", data_manager, catalog_manager));
+        }
+
+        #[test]
+        fn duplicate_keys_are_rejected() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_eq!(
+                evaluate_query("insert into stock values (1, 'cap  ', 5);", &data_manager, &catalog_manager),
+                Err("duplicate key value violates unique constraint \"stock_pkey\"".into())
+            );
+            assert_eq!(
+                evaluate_query("insert into stock values (3, 'cap  ', 5), (4, 'cap  ', 6);", &data_manager, &catalog_manager),
+                Err("duplicate key value violates unique constraint \"stock_name_key\"".into())
+            );
+            assert_eq!(
+                evaluate_query("update stock set id = 2 where id = 1;", &data_manager, &catalog_manager),
+                Err("duplicate key value violates unique constraint \"stock_pkey\"".into())
+            );
+            assert_that_query_evaluation_return_data(
+                "select id, name, qty from stock;",
+                "[[\"1\", \"pen  \", \"10\"], [\"2\", \"ink  \", \"20\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn do_nothing_skips_conflicting_rows() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_message(
+                "insert into stock values (1, 'cap  ', 5), (3, 'box  ', 7) on conflict do nothing;",
+                "row was inserted",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_that_query_evaluation_return_data(
+                "insert into stock values (4, 'ink  ', 1), (5, 'pad  ', 2) on conflict (name) do nothing returning id;",
+                "[[\"5\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_eq!(
+                evaluate_query("insert into stock values (4, 'ink  ', 1) on conflict (id) do nothing;", &data_manager, &catalog_manager),
+                Err("duplicate key value violates unique constraint \"stock_name_key\"".into())
+            );
+        }
+
+        #[test]
+        fn do_update_with_excluded_row() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "insert into stock values (1, 'pen  ', 15), (3, 'cap  ', 5) on conflict (id) do update set qty = excluded.qty returning id, qty;",
+                "[[\"1\", \"15\"], [\"3\", \"5\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_that_query_evaluation_return_message(
+                "insert into stock values (2, 'ink  ', 30) on conflict (id) do update set qty = excluded.qty where stock.qty > 25;",
+                "0 rows were inserted",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_eq!(
+                evaluate_query("insert into stock values (2, 'ink  ', 1), (2, 'ink  ', 2) on conflict (id) do update set qty = excluded.qty;", &data_manager, &catalog_manager),
+                Err("ON CONFLICT DO UPDATE command cannot affect row a second time".into())
+            );
+            assert_that_query_evaluation_return_data(
+                "select id, name, qty from stock;",
+                "[[\"1\", \"pen  \", \"15\"], [\"2\", \"ink  \", \"20\"], [\"3\", \"cap  \", \"5\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn stored_rows_conflict_by_their_current_keys() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+            drop(evaluate_query("update stock set id = 5 where id = 1;", &data_manager, &catalog_manager));
+            drop(evaluate_query("delete from stock where id = 2;", &data_manager, &catalog_manager));
+
+            assert_that_query_evaluation_return_message(
+                "insert into stock values (1, 'cap  ', 1), (2, 'ink  ', 2);",
+                "2 rows were inserted",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_that_query_evaluation_return_data(
+                "insert into stock values (5, 'box  ', 3) on conflict (id) do update set qty = excluded.qty returning id, name, qty;",
+                "[[\"5\", \"pen  \", \"3\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_eq!(
+                evaluate_query("insert into stock values (6, 'pen', 1);", &data_manager, &catalog_manager),
+                Err("duplicate key value violates unique constraint \"stock_name_key\"".into())
+            );
+            assert_that_query_evaluation_return_data(
+                "select id, name, qty from stock;",
+                "[[\"5\", \"pen  \", \"3\"], [\"1\", \"cap  \", \"1\"], [\"2\", \"ink  \", \"2\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn conflict_target_has_to_be_unique_key() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            populated_table(&data_manager, &catalog_manager);
+
+            assert_eq!(
+                evaluate_query("insert into stock values (1, 'pen  ', 1) on conflict (qty) do nothing;", &data_manager, &catalog_manager),
+                Err("there is no unique or exclusion constraint matching the ON CONFLICT specification".into())
+            );
+            assert_eq!(
+                evaluate_query("insert into stock values (1, 'pen  ', 1) on conflict do update set qty = 1;", &data_manager, &catalog_manager),
+                Err("ON CONFLICT DO UPDATE requires inference specification or constraint name".into())
+            );
+        }
+
+        #[test]
+        fn composite_table_key() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            drop(evaluate_query("create table visits (day integer, page integer, hits integer, primary key (day, page));", &data_manager, &catalog_manager));
+            drop(evaluate_query("insert into visits values (1, 1, 1), (1, 2, 1);", &data_manager, &catalog_manager));
+
+            assert_that_query_evaluation_return_data(
+                "insert into visits values (1, 2, 5), (2, 2, 3) on conflict (page, day) do update set hits = excluded.hits returning day, page, hits;",
+                "[[\"1\", \"2\", \"5\"], [\"2\", \"2\", \"3\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_eq!(
+                evaluate_query("create table wrong (a integer primary key, b integer, primary key (b));", &data_manager, &catalog_manager),
+                Err("multiple primary keys for table \"wrong\" are not allowed".into())
+            );
+        }
+    }
+
+    mod sequences {
+        use sql::catalog_manager::CatalogManager;
+        use sql::data_manager::DataManager;
+
+        use super::super::super::evaluate_query;
+        use super::super::assert_that_query_evaluation_return_message;
+        use super::super::assert_that_query_evaluation_return_data;
+
+        #[test]
+        fn serial_column_takes_next_value() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            drop(evaluate_query("create table items (id serial primary key, name character(5));", &data_manager, &catalog_manager));
+
+            assert_that_query_evaluation_return_data(
+                "insert into items (name) values ('pen  '), ('ink  ') returning id;",
+                "[[\"1\"], [\"2\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_that_query_evaluation_return_message(
+                "insert into items values (10, 'cap  ');",
+                "row was inserted",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_that_query_evaluation_return_data(
+                "select id, name from items;",
+                "[[\"1\", \"pen  \"], [\"2\", \"ink  \"], [\"10\", \"cap  \"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_that_query_evaluation_return_data(
+                "select currval('items_id_seq') from items where id = 1;",
+                "[[\"2\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn generated_always_column_rejects_explicit_values() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            drop(evaluate_query("create table items (id integer generated always as identity, qty integer);", &data_manager, &catalog_manager));
+
+            assert_eq!(
+                evaluate_query("insert into items values (1, 5);", &data_manager, &catalog_manager),
+                Err("cannot insert a non-DEFAULT value into column \"id\"".into())
+            );
+            assert_eq!(
+                evaluate_query("update items set id = 2;", &data_manager, &catalog_manager),
+                Err("column \"id\" can only be updated to DEFAULT".into())
+            );
+            assert_that_query_evaluation_return_data(
+                "insert into items (qty) values (5) returning id, qty;",
+                "[[\"1\", \"5\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn nextval_and_currval() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            drop(evaluate_query("create table dual (x integer);", &data_manager, &catalog_manager));
+            drop(evaluate_query("insert into dual values (1);", &data_manager, &catalog_manager));
+
+            assert_that_query_evaluation_return_message(
+                "create sequence counter start with 5 increment by 5;",
+                "'counter' was created",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_eq!(
+                evaluate_query("select currval('counter') from dual;", &data_manager, &catalog_manager),
+                Err("currval of sequence \"counter\" is not yet defined".into())
+            );
+            assert_that_query_evaluation_return_data(
+                "select nextval('counter') from dual;",
+                "[[\"5\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_that_query_evaluation_return_data(
+                "select nextval('counter'), currval('counter') from dual;",
+                "[[\"10\", \"10\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_eq!(
+                evaluate_query("create sequence counter;", &data_manager, &catalog_manager),
+                Err("relation \"counter\" already exists".into())
+            );
+            assert_eq!(
+                evaluate_query("select nextval('missing') from dual;", &data_manager, &catalog_manager),
+                Err("relation \"missing\" does not exist".into())
+            );
+        }
+    }
+
+    mod generated_columns {
+        use sql::catalog_manager::CatalogManager;
+        use sql::data::Datum;
+        use sql::data_manager::DataManager;
+
+        use super::super::super::evaluate_query;
+        use super::super::assert_that_query_evaluation_return_message;
+        use super::super::assert_that_query_evaluation_return_data;
+
+        fn people(data_manager: &DataManager, catalog_manager: &CatalogManager) {
+            drop(evaluate_query("create table people (given character(5), family character(5), full_name character(11) generated always as (trim(given) || ' ' || trim(family)) stored, initial character(1) generated always as (substring(given from 1 for 1)) virtual);", data_manager, catalog_manager));
+            drop(evaluate_query("insert into people (given, family) values ('ann  ', 'lee  ');", data_manager, catalog_manager));
+        }
+
+        #[test]
+        fn stored_columns_are_computed_on_write() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            people(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_data(
+                "select full_name from people;",
+                "[[\"ann lee    \"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_that_query_evaluation_return_data(
+                "select given from people where full_name = 'ann lee' and initial = 'a';",
+                "[[\"ann  \"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_that_query_evaluation_return_data(
+                "update people set given = 'bob  ' returning full_name, initial;",
+                "[[\"bob lee    \", \"b\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_eq!(
+                data_manager.get_row_from("people", 0).unwrap()[2..].to_vec(),
+                vec![Datum::Character("bob lee    ".into()), Datum::Null]
+            );
+        }
+
+        #[test]
+        fn virtual_columns_are_computed_on_read() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            people(&data_manager, &catalog_manager);
+            drop(evaluate_query("insert into people (given, family) values ('cid  ', 'ray  ');", &data_manager, &catalog_manager));
+
+            assert_that_query_evaluation_return_data(
+                "select given, initial from people where initial = 'c';",
+                "[[\"cid  \", \"c\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_that_query_evaluation_return_message(
+                "delete from people where initial = 'a';",
+                "1 rows were deleted",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn direct_writes_are_rejected() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            people(&data_manager, &catalog_manager);
+
+            assert_eq!(
+                evaluate_query("insert into people (given, family, full_name) values ('ann  ', 'lee  ', 'someone    ');", &data_manager, &catalog_manager),
+                Err("cannot insert a non-DEFAULT value into column \"full_name\"".into())
+            );
+            assert_eq!(
+                evaluate_query("insert into people values ('ann  ', 'lee  ', 'someone    ');", &data_manager, &catalog_manager),
+                Err("cannot insert a non-DEFAULT value into column \"full_name\"".into())
+            );
+            assert_eq!(
+                evaluate_query("update people set initial = 'x';", &data_manager, &catalog_manager),
+                Err("column \"initial\" can only be updated to DEFAULT".into())
+            );
+        }
+
+        #[test]
+        fn generation_expression_uses_only_plain_columns() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            assert_eq!(
+                evaluate_query("create table t (a integer, b integer generated always as (a) stored, c integer generated always as (b));", &data_manager, &catalog_manager),
+                Err("cannot use generated column \"b\" in column generation expression".into())
+            );
+            assert_eq!(
+                evaluate_query("create table t (a integer, b integer default 1 generated always as (a) stored);", &data_manager, &catalog_manager),
+                Err("both default and generation expression specified for column \"b\"".into())
+            );
+            assert_that_query_evaluation_return_message(
+                "create table t (a integer, b integer not null generated always as (a) stored);",
+                "'t' was created",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod views {
+        use sql::catalog_manager::CatalogManager;
+        use sql::data_manager::DataManager;
+
+        use super::super::super::evaluate_query;
+        use super::super::assert_that_query_evaluation_return_message;
+        use super::super::assert_that_query_evaluation_return_data;
+
+        fn stock(data_manager: &DataManager, catalog_manager: &CatalogManager) {
+            drop(evaluate_query("create table items (id integer primary key, name character(5), qty integer);", data_manager, catalog_manager));
+            drop(evaluate_query("insert into items values (1, 'pen  ', 3), (2, 'ink  ', 0), (3, 'cap  ', 7);", data_manager, catalog_manager));
+        }
+
+        #[test]
+        fn view_reads_current_rows_of_its_tables() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            stock(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_message(
+                "create view stocked (item, amount) as select name, qty from items where qty > 0;",
+                "'stocked' was created",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_that_query_evaluation_return_data(
+                "select item from stocked where amount > 5;",
+                "[[\"cap  \"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            drop(evaluate_query("update items set qty = 9 where id = 2;", &data_manager, &catalog_manager));
+            assert_that_query_evaluation_return_data(
+                "select id from items where name in (select item from stocked) order by id;",
+                "[[\"1\"], [\"2\"], [\"3\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn views_over_views() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            stock(&data_manager, &catalog_manager);
+            drop(evaluate_query("create view stocked as select id, name from items where qty > 0;", &data_manager, &catalog_manager));
+            drop(evaluate_query("create view first_stocked as select name from stocked where id < 3;", &data_manager, &catalog_manager));
+
+            assert_that_query_evaluation_return_data(
+                "select name from first_stocked;",
+                "[[\"pen  \"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_eq!(
+                evaluate_query("drop view stocked;", &data_manager, &catalog_manager),
+                Err("cannot drop view stocked because other objects depend on it".into())
+            );
+            assert_that_query_evaluation_return_message(
+                "drop view first_stocked;",
+                "'first_stocked' was dropped",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_that_query_evaluation_return_message(
+                "drop view stocked;",
+                "'stocked' was dropped",
+                &data_manager,
+                &catalog_manager
+            );
+        }
+
+        #[test]
+        fn views_are_read_only() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            stock(&data_manager, &catalog_manager);
+            drop(evaluate_query("create view stocked as select id, name from items where qty > 0;", &data_manager, &catalog_manager));
+            drop(evaluate_query("create materialized view names as select name from items;", &data_manager, &catalog_manager));
+
+            assert_eq!(
+                evaluate_query("insert into stocked values (4, 'cup  ');", &data_manager, &catalog_manager),
+                Err("cannot insert into view \"stocked\"".into())
+            );
+            assert_eq!(
+                evaluate_query("delete from names;", &data_manager, &catalog_manager),
+                Err("cannot change materialized view \"names\"".into())
+            );
+            assert_eq!(
+                evaluate_query("create view items as select id from stocked;", &data_manager, &catalog_manager),
+                Err("relation \"items\" already exists".into())
+            );
+        }
+
+        #[test]
+        fn materialized_view_keeps_rows_until_refreshed() {
+            let catalog_manager = CatalogManager::default();
+            let data_manager = DataManager::default();
+
+            stock(&data_manager, &catalog_manager);
+
+            assert_that_query_evaluation_return_message(
+                "create materialized view empty as select id from items where qty = 0;",
+                "'empty' was created",
+                &data_manager,
+                &catalog_manager
+            );
+            drop(evaluate_query("update items set qty = 0 where id = 3;", &data_manager, &catalog_manager));
+            assert_that_query_evaluation_return_data(
+                "select id from empty;",
+                "[[\"2\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_that_query_evaluation_return_message(
+                "refresh materialized view empty;",
+                "'empty' was refreshed",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_that_query_evaluation_return_data(
+                "select id from empty order by id;",
+                "[[\"2\"], [\"3\"]]",
+                &data_manager,
+                &catalog_manager
+            );
+            assert_eq!(
+                evaluate_query("drop view empty;", &data_manager, &catalog_manager),
+                Err("\"empty\" is not a view".into())
+            );
+            assert_that_query_evaluation_return_message(
+                "drop materialized view empty;",
+                "'empty' was dropped",
+                &data_manager,
+                &catalog_manager
+            );
+            assert!(!catalog_manager.contains_table("empty"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod transactions {
+    use sql::catalog_manager::CatalogManager;
+    use sql::data_manager::DataManager;
+
+    use super::super::evaluate_query;
+    use super::assert_that_query_evaluation_return_message;
+    use super::assert_that_query_evaluation_return_data;
+
+    fn accounts(data_manager: &DataManager, catalog_manager: &CatalogManager) {
+        drop(evaluate_query("create table accounts (id integer primary key, balance integer);", data_manager, catalog_manager));
+        drop(evaluate_query("insert into accounts values (1, 100), (2, 50);", data_manager, catalog_manager));
+    }
+
+    #[test]
+    fn rollback_undoes_changes_of_rows() {
+        let catalog_manager = CatalogManager::default();
+        let data_manager = DataManager::default();
+
+        accounts(&data_manager, &catalog_manager);
+
+        assert_that_query_evaluation_return_message("begin;", "transaction was started", &data_manager, &catalog_manager);
+        drop(evaluate_query("update accounts set balance = 0 where id = 1;", &data_manager, &catalog_manager));
+        drop(evaluate_query("delete from accounts where id = 2;", &data_manager, &catalog_manager));
+        drop(evaluate_query("insert into accounts values (3, 10);", &data_manager, &catalog_manager));
+        assert_that_query_evaluation_return_data(
+            "select id, balance from accounts;",
+            "[[\"1\", \"0\"], [\"3\", \"10\"]]",
+            &data_manager,
+            &catalog_manager
+        );
+        assert_that_query_evaluation_return_message("rollback;", "transaction was rolled back", &data_manager, &catalog_manager);
+
+        assert_that_query_evaluation_return_data(
+            "select id, balance from accounts;",
+            "[[\"1\", \"100\"], [\"2\", \"50\"]]",
+            &data_manager,
+            &catalog_manager
+        );
+    }
+
+    #[test]
+    fn rollback_undoes_changes_of_catalog() {
+        let catalog_manager = CatalogManager::default();
+        let data_manager = DataManager::default();
+
+        accounts(&data_manager, &catalog_manager);
+
+        drop(evaluate_query("begin;", &data_manager, &catalog_manager));
+        drop(evaluate_query("create table audit (id integer generated always as identity, note character(10));", &data_manager, &catalog_manager));
+        drop(evaluate_query("create view rich as select id from accounts where balance > 75;", &data_manager, &catalog_manager));
+        drop(evaluate_query("rollback;", &data_manager, &catalog_manager));
+
+        assert!(!catalog_manager.contains_table("audit"));
+        assert!(!catalog_manager.contains_sequence("audit_id_seq"));
+        assert!(!catalog_manager.contains_view("rich"));
+    }
+
+    #[test]
+    fn commit_keeps_changes() {
+        let catalog_manager = CatalogManager::default();
+        let data_manager = DataManager::default();
+
+        accounts(&data_manager, &catalog_manager);
+
+        drop(evaluate_query("begin;", &data_manager, &catalog_manager));
+        drop(evaluate_query("update accounts set balance = 70 where id = 2;", &data_manager, &catalog_manager));
+        assert_that_query_evaluation_return_message("commit;", "transaction was committed", &data_manager, &catalog_manager);
+
+        assert_eq!(
+            evaluate_query("rollback;", &data_manager, &catalog_manager),
+            Err("there is no transaction in progress".into())
+        );
+        assert_that_query_evaluation_return_data(
+            "select balance from accounts;",
+            "[[\"100\"], [\"70\"]]",
+            &data_manager,
+            &catalog_manager
+        );
+    }
+
+    #[test]
+    fn rollback_to_savepoint_keeps_changes_made_before_it() {
+        let catalog_manager = CatalogManager::default();
+        let data_manager = DataManager::default();
+
+        accounts(&data_manager, &catalog_manager);
+
+        drop(evaluate_query("begin;", &data_manager, &catalog_manager));
+        drop(evaluate_query("insert into accounts values (3, 10);", &data_manager, &catalog_manager));
+        assert_that_query_evaluation_return_message("savepoint before_delete;", "savepoint 'before_delete' was created", &data_manager, &catalog_manager);
+        drop(evaluate_query("delete from accounts where id < 3;", &data_manager, &catalog_manager));
+        assert_that_query_evaluation_return_message(
+            "rollback to savepoint before_delete;",
+            "transaction was rolled back to savepoint 'before_delete'",
+            &data_manager,
+            &catalog_manager
+        );
+        assert_that_query_evaluation_return_message("release before_delete;", "savepoint 'before_delete' was released", &data_manager, &catalog_manager);
+        assert_eq!(
+            evaluate_query("rollback to before_delete;", &data_manager, &catalog_manager),
+            Err("savepoint \"before_delete\" does not exist".into())
+        );
+        drop(evaluate_query("commit;", &data_manager, &catalog_manager));
+
+        assert_that_query_evaluation_return_data(
+            "select id from accounts;",
+            "[[\"1\"], [\"2\"], [\"3\"]]",
+            &data_manager,
+            &catalog_manager
+        );
+    }
+
+    #[test]
+    fn failed_statement_leaves_no_changes() {
+        let catalog_manager = CatalogManager::default();
+        let data_manager = DataManager::default();
+
+        accounts(&data_manager, &catalog_manager);
+
+        assert!(evaluate_query("insert into accounts values (3, 10), (1, 10);", &data_manager, &catalog_manager).is_err());
+        drop(evaluate_query("begin;", &data_manager, &catalog_manager));
+        drop(evaluate_query("insert into accounts values (4, 10);", &data_manager, &catalog_manager));
+        assert!(evaluate_query("insert into accounts values (5, 10), (2, 10);", &data_manager, &catalog_manager).is_err());
+        drop(evaluate_query("commit;", &data_manager, &catalog_manager));
+
+        assert_that_query_evaluation_return_data(
+            "select id from accounts;",
+            "[[\"1\"], [\"2\"], [\"4\"]]",
+            &data_manager,
+            &catalog_manager
+        );
+    }
+
+    #[test]
+    fn savepoints_need_a_transaction() {
+        let catalog_manager = CatalogManager::default();
+        let data_manager = DataManager::default();
+
+        assert_eq!(
+            evaluate_query("savepoint sp;", &data_manager, &catalog_manager),
+            Err("SAVEPOINT can only be used in transaction blocks".into())
+        );
+        drop(evaluate_query("begin;", &data_manager, &catalog_manager));
+        assert_eq!(
+            evaluate_query("begin;", &data_manager, &catalog_manager),
+            Err("there is already a transaction in progress".into())
+        );
+    }
 }
@@ -14,9 +14,18 @@ fn assert_that_types_will_be_inferred(sql: &str, expected_dsl: &str, tables_set:
     }
 }
 
+fn assert_that_types_inferring_failed(sql: &str, expected_error: &str, tables_set: &HashMap<String, Vec<ColumnMetadata>>) {
+    match tokenize(sql)
+            .and_then(parse)
+            .and_then(|parsed| type_inferring(tables_set, parsed)) {
+        Ok(typed) => panic!("An unexpected type inferring success. {:?}", typed),
+        Err(e) => assert_eq!(e, expected_error)
+    }
+}
+
 #[cfg(test)]
 mod create_table_query {
-    use super::assert_that_types_will_be_inferred;
+    use super::{assert_that_types_will_be_inferred, assert_that_types_inferring_failed};
 
     use std::collections::HashMap;
 
@@ -55,17 +64,44 @@ mod create_table_query {
             &HashMap::new()
         )
     }
+
+    #[test]
+    fn default_expressions() {
+        assert_that_types_will_be_inferred(
+            "create table tab1 (col1 char(5) default 'a' || 'b', col2 int default length('abc'));",
+            "statement: 'create table', table name: 'tab1', columns: [<name: 'col1', type: 'character[5]', primary key: No, foreign key: No, nullable: Yes, default value: 'a' || 'b'>, <name: 'col2', type: 'integer', primary key: No, foreign key: No, nullable: Yes, default value: length('abc')>]",
+            &HashMap::new()
+        );
+    }
+
+    #[test]
+    fn default_of_another_type() {
+        assert_that_types_inferring_failed(
+            "create table tab1 (col1 int default 'abc');",
+            "column \"col1\" is of type integer but default expression is of type character[3]",
+            &HashMap::new()
+        );
+    }
+
+    #[test]
+    fn default_referencing_column() {
+        assert_that_types_inferring_failed(
+            "create table tab1 (col1 int, col2 int default col1);",
+            "cannot use column reference in DEFAULT expression",
+            &HashMap::new()
+        );
+    }
 }
 
 #[cfg(test)]
 mod insert_query {
-    use super::assert_that_types_will_be_inferred;
+    use super::{assert_that_types_will_be_inferred, assert_that_types_inferring_failed};
 
     use std::collections::HashMap;
 
     use sql::catalog::ColumnMetadata;
-    use sql::ast::Type;
-
+    use sql::ast::{Type, Expression};
+    use sql::ast::insert_query::Value;
 
     #[test]
     fn populates_columns_for_insert_query() {
@@ -85,58 +121,198 @@ mod insert_query {
     }
 
     #[test]
-    fn populates_only_missed_column() {
+    fn leaves_columns_with_defaults_to_executor() {
         let mut table = HashMap::new();
         let columns = vec![
-            ColumnMetadata::new("col1", Type::Integer, Some("1")),
+            ColumnMetadata::new("col1", Type::Integer, Some(Expression::Const(Value::new("1", Type::Integer)))),
             ColumnMetadata::new("col2", Type::Integer, None)
         ];
         table.insert("table_1".into(), columns);
 
         assert_that_types_will_be_inferred(
             "insert into table_1 (col2) values (2);",
-            "statement: 'insert', table name: 'table_1', columns: [<name: 'col2', type: 'integer'>, <name: 'col1', type: 'integer'>], values: [<value: 2, type: integer>, <value: 1, type: integer>]",
+            "statement: 'insert', table name: 'table_1', columns: [<name: 'col2', type: 'integer'>], values: [<value: 2, type: integer>]",
             &table
         );
     }
 
     #[test]
-    fn populates_default_value_for_different_types() {
+    fn populates_types_of_columns_in_select_sub_query() {
         let mut table = HashMap::new();
         let columns = vec![
-            ColumnMetadata::new("col1", Type::Integer, Some("1")),
-            ColumnMetadata::new("col2", Type::Integer, None),
-            ColumnMetadata::new("col3", Type::Character(Some(3)), Some("str"))
+            ColumnMetadata::new("col1", Type::Integer, None),
+            ColumnMetadata::new("col2", Type::Integer, None)
         ];
-        table.insert("table_2".into(), columns);
+        table.insert("table_1".into(), columns);
 
         assert_that_types_will_be_inferred(
-            "insert into table_2 (col2) values (2);",
-            "statement: 'insert', table name: 'table_2', columns: [<name: 'col2', type: 'integer'>, <name: 'col1', type: 'integer'>, <name: 'col3', type: 'character[3]'>], values: [<value: 2, type: integer>, <value: 1, type: integer>, <value: str, type: character[3]>]",
+            "insert into table_1 (col1, col2) select col1, col2 from table_1;",
+            "statement: 'insert', table name: 'table_1', columns: [<name: 'col1', type: 'integer'>, <name: 'col2', type: 'integer'>], values: <substatement: 'select', tables: [<name: 'table_1'>], columns: [<name: 'col1', type: 'integer'>, <name: 'col2', type: 'integer'>], where: no predicate>",
             &table
         );
     }
 
     #[test]
-    fn populates_types_of_columns_in_select_sub_query() {
+    fn sub_query_column_of_another_type() {
+        let mut table = HashMap::new();
+        let columns = vec![
+            ColumnMetadata::new("col1", Type::Integer, None),
+            ColumnMetadata::new("col2", Type::Character(Some(3)), None)
+        ];
+        table.insert("table_1".into(), columns);
+
+        assert_that_types_inferring_failed(
+            "insert into table_1 (col2) select col1 from table_1;",
+            "column \"col2\" is of type character[3] but expression is of type integer",
+            &table
+        );
+    }
+
+    #[test]
+    fn sub_query_with_less_columns_than_listed() {
         let mut table = HashMap::new();
         let columns = vec![
-            ColumnMetadata::new("col1", Type::Integer, Some("1")),
-            ColumnMetadata::new("col2", Type::Integer, Some("2"))
+            ColumnMetadata::new("col1", Type::Integer, None),
+            ColumnMetadata::new("col2", Type::Integer, None)
+        ];
+        table.insert("table_1".into(), columns);
+
+        assert_that_types_inferring_failed(
+            "insert into table_1 (col1, col2) select col1 from table_1;",
+            "INSERT has more target columns than expressions",
+            &table
+        );
+    }
+
+    #[test]
+    fn keeps_order_of_listed_columns() {
+        let mut table = HashMap::new();
+        let columns = vec![
+            ColumnMetadata::new("col1", Type::Integer, None),
+            ColumnMetadata::new("col2", Type::Character(Some(3)), None),
+            ColumnMetadata::new("col3", Type::Integer, None)
         ];
         table.insert("table_1".into(), columns);
 
         assert_that_types_will_be_inferred(
-            "insert into table_1 (col1, col2) select col1, col2 from table_1;",
-            "statement: 'insert', table name: 'table_1', columns: [<name: 'col1', type: 'integer'>, <name: 'col2', type: 'integer'>], values: <substatement: 'select', tables: [<name: 'table_1'>], columns: [<name: 'col1', type: 'integer'>, <name: 'col2', type: 'integer'>], where: no predicate>",
+            "insert into table_1 (col2, col1) values ('abc', 1);",
+            "statement: 'insert', table name: 'table_1', columns: [<name: 'col2', type: 'character[3]'>, <name: 'col1', type: 'integer'>], values: [<value: abc, type: character[3]>, <value: 1, type: integer>]",
+            &table
+        );
+    }
+
+    #[test]
+    fn listed_column_that_does_not_exist() {
+        let mut table = HashMap::new();
+        table.insert("table_1".into(), vec![ColumnMetadata::new("col1", Type::Integer, None)]);
+
+        assert_that_types_inferring_failed(
+            "insert into table_1 (col1, col9) values (1, 2);",
+            "column \"col9\" of relation \"table_1\" does not exist",
+            &table
+        );
+    }
+
+    #[test]
+    fn more_values_than_columns() {
+        let mut table = HashMap::new();
+        table.insert("table_1".into(), vec![ColumnMetadata::new("col1", Type::Integer, None)]);
+
+        assert_that_types_inferring_failed(
+            "insert into table_1 values (1, 2);",
+            "INSERT has more expressions than target columns",
             &table
         );
     }
+
+    #[test]
+    fn returning_columns_of_inserted_table() {
+        let mut table = HashMap::new();
+        let columns = vec![
+            ColumnMetadata::new("id", Type::Integer, None),
+            ColumnMetadata::new("name", Type::Character(Option::from(5)), None)
+        ];
+        table.insert("table_1".into(), columns);
+
+        assert_that_types_will_be_inferred(
+            "insert into table_1 (name) values ('abc') returning id, name;",
+            "statement: 'insert', table name: 'table_1', columns: [<name: 'name', type: 'character[5]'>], values: [<value: abc, type: character[3]>], returning: [<name: 'id', type: 'integer'>, <name: 'name', type: 'character[5]'>]",
+            &table
+        );
+    }
+
+    #[test]
+    fn on_conflict_update_sees_excluded_row() {
+        let mut table = HashMap::new();
+        let columns = vec![
+            ColumnMetadata::new("id", Type::Integer, None),
+            ColumnMetadata::new("name", Type::Character(Option::from(5)), None)
+        ];
+        table.insert("table_1".into(), columns);
+
+        assert_that_types_will_be_inferred(
+            "insert into table_1 values (1, 'abcde') on conflict (id) do update set name = excluded.name where table_1.name <> excluded.name;",
+            "statement: 'insert', table name: 'table_1', columns: [<name: 'id', type: 'integer'>, <name: 'name', type: 'character[5]'>], values: [<value: 1, type: integer>, <value: abcde, type: character[5]>], on conflict: <target: ['id'], do update set: [<name: 'name', type: 'character[5]'> = excluded.name], where: predicate <table_1.name not equals to excluded.name>>",
+            &table
+        );
+        assert_that_types_inferring_failed(
+            "insert into table_1 values (1, 'abcde') on conflict (code) do nothing;",
+            "column \"code\" of relation \"table_1\" does not exist",
+            &table
+        );
+    }
+}
+
+#[cfg(test)]
+mod update_query {
+    use super::{assert_that_types_will_be_inferred, assert_that_types_inferring_failed};
+
+    use std::collections::HashMap;
+
+    use sql::catalog::ColumnMetadata;
+    use sql::ast::Type;
+
+    fn table() -> HashMap<String, Vec<ColumnMetadata>> {
+        let mut table = HashMap::new();
+        let columns = vec![
+            ColumnMetadata::new("col1", Type::Integer, None),
+            ColumnMetadata::new("col2", Type::Character(Option::from(3)), None)
+        ];
+        table.insert("table_1".into(), columns);
+        table
+    }
+
+    #[test]
+    fn populates_types_of_assigned_columns() {
+        assert_that_types_will_be_inferred(
+            "update table_1 set col2 = 'abc' where col1 = 1 returning col1;",
+            "statement: 'update', table name: 'table_1', set: [<name: 'col2', type: 'character[3]'> = 'abc'], where: predicate <col1 equals to 1>, returning: [<name: 'col1', type: 'integer'>]",
+            &table()
+        );
+    }
+
+    #[test]
+    fn value_of_another_type() {
+        assert_that_types_inferring_failed(
+            "update table_1 set col1 = 'abc';",
+            "column \"col1\" is of type integer but expression is of type character[3]",
+            &table()
+        );
+    }
+
+    #[test]
+    fn column_assigned_twice() {
+        assert_that_types_inferring_failed(
+            "update table_1 set col1 = 1, col1 = 2;",
+            "multiple assignments to same column \"col1\"",
+            &table()
+        );
+    }
 }
 
 #[cfg(test)]
 mod select_query {
-    use super::assert_that_types_will_be_inferred;
+    use super::{assert_that_types_will_be_inferred, assert_that_types_inferring_failed};
 
     use std::collections::HashMap;
 
@@ -191,4 +367,261 @@ mod select_query {
             &table
         );
     }
+
+    #[test]
+    fn order_by_position_and_alias() {
+        let mut table = HashMap::new();
+        let columns = vec![
+            ColumnMetadata::new("col1", Type::Integer, None),
+            ColumnMetadata::new("col2", Type::Integer, None)
+        ];
+        table.insert("table_1".into(), columns);
+
+        assert_that_types_will_be_inferred(
+            "select col1 as c, col2 from table_1 order by c desc, 2;",
+            "statement: 'select', tables: [<name: 'table_1'>], columns: [<name: 'col1', type: 'integer'> as 'c', <name: 'col2', type: 'integer'>], where: no predicate, order by: [<key: <name: 'col1', type: 'integer'>, order: desc, nulls: first>, <key: <name: 'col2', type: 'integer'>, order: asc, nulls: last>]",
+            &table
+        );
+    }
+
+    #[test]
+    fn order_by_position_out_of_select_list() {
+        let mut table = HashMap::new();
+        let columns = vec![
+            ColumnMetadata::new("col1", Type::Integer, None)
+        ];
+        table.insert("table_1".into(), columns);
+
+        assert_that_types_inferring_failed(
+            "select col1 from table_1 order by 2;",
+            "ORDER BY position 2 is not in select list",
+            &table
+        );
+    }
+
+    #[test]
+    fn union_of_queries_with_different_number_of_columns() {
+        let mut table = HashMap::new();
+        let columns = vec![
+            ColumnMetadata::new("col1", Type::Integer, None),
+            ColumnMetadata::new("col2", Type::Character(Some(10)), None)
+        ];
+        table.insert("table_1".into(), columns);
+
+        assert_that_types_inferring_failed(
+            "select col1, col2 from table_1 union select col1 from table_1;",
+            "each UNION query must have the same number of columns",
+            &table
+        );
+    }
+
+    #[test]
+    fn union_of_character_columns_of_different_size() {
+        let mut table = HashMap::new();
+        table.insert("table_1".into(), vec![ColumnMetadata::new("name", Type::Character(Some(3)), None)]);
+        table.insert("table_2".into(), vec![ColumnMetadata::new("name", Type::Character(Some(5)), None)]);
+
+        assert_that_types_will_be_inferred(
+            "select name, 'ab' from table_1 union select name, 'abcd' from table_2;",
+            "statement: 'select', tables: [<name: 'table_1'>], columns: [<name: 'name', type: 'character[5]'>, <value: ab, type: character[4]>], where: no predicate, union: <substatement: 'select', tables: [<name: 'table_2'>], columns: [<name: 'name', type: 'character[5]'>, <value: abcd, type: character[4]>], where: no predicate>",
+            &table
+        );
+    }
+
+    #[test]
+    fn except_of_queries_with_mismatched_column_types() {
+        let mut table = HashMap::new();
+        let columns = vec![
+            ColumnMetadata::new("col1", Type::Integer, None),
+            ColumnMetadata::new("col2", Type::Character(Some(10)), None)
+        ];
+        table.insert("table_1".into(), columns);
+
+        assert_that_types_inferring_failed(
+            "select col1 from table_1 except select col2 from table_1;",
+            "EXCEPT types integer and character[10] cannot be matched",
+            &table
+        );
+    }
+
+    #[test]
+    fn order_by_column_out_of_distinct_select_list() {
+        let mut table = HashMap::new();
+        let columns = vec![
+            ColumnMetadata::new("col1", Type::Integer, None),
+            ColumnMetadata::new("col2", Type::Integer, None)
+        ];
+        table.insert("table_1".into(), columns);
+
+        assert_that_types_inferring_failed(
+            "select distinct col1 from table_1 order by col2;",
+            "for SELECT DISTINCT, ORDER BY expressions must appear in select list",
+            &table
+        );
+    }
+
+    #[test]
+    fn correlated_sub_query_sees_outer_columns() {
+        let mut table = HashMap::new();
+        table.insert("table_1".into(), vec![ColumnMetadata::new("col1", Type::Integer, None)]);
+        table.insert("table_2".into(), vec![ColumnMetadata::new("col2", Type::Integer, None)]);
+
+        assert_that_types_will_be_inferred(
+            "select col1 from table_1 where exists (select col2 from table_2 where col2 = table_1.col1);",
+            "statement: 'select', tables: [<name: 'table_1'>], columns: [<name: 'col1', type: 'integer'>], where: predicate <exists <substatement: 'select', tables: [<name: 'table_2'>], columns: [<name: 'col2', type: 'integer'>], where: predicate <col2 equals to table_1.col1>>>",
+            &table
+        );
+    }
+
+    #[test]
+    fn scalar_sub_query_with_multiple_columns() {
+        let mut table = HashMap::new();
+        let columns = vec![
+            ColumnMetadata::new("col1", Type::Integer, None),
+            ColumnMetadata::new("col2", Type::Integer, None)
+        ];
+        table.insert("table_1".into(), columns);
+
+        assert_that_types_inferring_failed(
+            "select col1 from table_1 where col1 = (select col1, col2 from table_1);",
+            "subquery must return only one column",
+            &table
+        );
+    }
+
+    #[test]
+    fn in_sub_query_with_mismatched_type() {
+        let mut table = HashMap::new();
+        let columns = vec![
+            ColumnMetadata::new("col1", Type::Integer, None),
+            ColumnMetadata::new("col2", Type::Character(Some(10)), None)
+        ];
+        table.insert("table_1".into(), columns);
+
+        assert_that_types_inferring_failed(
+            "select col1 from table_1 where col1 in (select col2 from table_1);",
+            "can't compare integer with character[10]",
+            &table
+        );
+    }
+
+    #[test]
+    fn with_query_columns_are_visible_to_main_query() {
+        let mut table = HashMap::new();
+        table.insert("table_1".into(), vec![ColumnMetadata::new("col1", Type::Integer, None)]);
+
+        assert_that_types_will_be_inferred(
+            "with cte (c) as (select col1 from table_1) select c from cte;",
+            "statement: 'select', tables: [<name: 'cte'>], columns: [<name: 'c', type: 'integer'>], where: no predicate, with: [<name: 'cte', columns: ['c'], query: <substatement: 'select', tables: [<name: 'table_1'>], columns: [<name: 'col1', type: 'integer'>], where: no predicate>>]",
+            &table
+        );
+    }
+
+    #[test]
+    fn with_query_having_more_column_names_than_columns() {
+        let mut table = HashMap::new();
+        table.insert("table_1".into(), vec![ColumnMetadata::new("col1", Type::Integer, None)]);
+
+        assert_that_types_inferring_failed(
+            "with cte (c1, c2) as (select col1 from table_1) select c1 from cte;",
+            "WITH query \"cte\" has 1 columns available but 2 columns specified",
+            &table
+        );
+    }
+
+    #[test]
+    fn recursive_query_without_non_recursive_term() {
+        let mut table = HashMap::new();
+        table.insert("table_1".into(), vec![ColumnMetadata::new("col1", Type::Integer, None)]);
+
+        assert_that_types_inferring_failed(
+            "with recursive cte (c) as (select c from cte) select c from cte;",
+            "recursive query \"cte\" must have the form non-recursive-term UNION [ALL] recursive-term",
+            &table
+        );
+    }
+
+    #[test]
+    fn window_function_with_wrong_argument_type() {
+        let mut table = HashMap::new();
+        table.insert("table_1".into(), vec![ColumnMetadata::new("col1", Type::Character(Some(10)), None)]);
+
+        assert_that_types_inferring_failed(
+            "select sum(col1) over () from table_1;",
+            "function sum(character[10]) does not exist",
+            &table
+        );
+    }
+
+    #[test]
+    fn range_frame_with_offset_over_character_column() {
+        let mut table = HashMap::new();
+        table.insert("table_1".into(), vec![ColumnMetadata::new("col1", Type::Character(Some(10)), None)]);
+
+        assert_that_types_inferring_failed(
+            "select first_value(col1) over (order by col1 range 1 preceding) from table_1;",
+            "RANGE with offset PRECEDING/FOLLOWING requires exactly one ORDER BY column of type integer",
+            &table
+        );
+    }
+
+    #[test]
+    fn case_with_branches_of_different_types() {
+        let mut table = HashMap::new();
+        table.insert("table_1".into(), vec![ColumnMetadata::new("col1", Type::Integer, None)]);
+
+        assert_that_types_inferring_failed(
+            "select case when col1 = 1 then col1 else 'one' end from table_1;",
+            "CASE types integer and character[3] cannot be matched",
+            &table
+        );
+    }
+
+    #[test]
+    fn cast_changes_type_of_expression() {
+        let mut table = HashMap::new();
+        table.insert("table_1".into(), vec![ColumnMetadata::new("col1", Type::Integer, None)]);
+
+        assert_that_types_will_be_inferred(
+            "select case when col1 = 1 then col1::char(3) else 'one' end from table_1;",
+            "statement: 'select', tables: [<name: 'table_1'>], columns: [<expression: case when col1 equals to 1 then cast(col1 as character[3]) else 'one' end>], where: no predicate",
+            &table
+        );
+    }
+
+    #[test]
+    fn string_functions_return_text_and_positions() {
+        let mut table = HashMap::new();
+        table.insert("table_1".into(), vec![ColumnMetadata::new("col1", Type::Character(Some(5)), None)]);
+
+        assert_that_types_will_be_inferred(
+            "select length(col1), upper(col1) || 'x' from table_1 where col1 like 'a%';",
+            "statement: 'select', tables: [<name: 'table_1'>], columns: [<expression: length(col1)>, <expression: upper(col1) || 'x'>], where: predicate <col1 like 'a%'>",
+            &table
+        );
+    }
+
+    #[test]
+    fn string_function_with_wrong_argument_type() {
+        let mut table = HashMap::new();
+        table.insert("table_1".into(), vec![ColumnMetadata::new("col1", Type::Integer, None)]);
+
+        assert_that_types_inferring_failed(
+            "select substring(col1 from 1) from table_1;",
+            "function substring(integer, integer) does not exist",
+            &table
+        );
+    }
+
+    #[test]
+    fn like_over_integer_column() {
+        let mut table = HashMap::new();
+        table.insert("table_1".into(), vec![ColumnMetadata::new("col1", Type::Integer, None)]);
+
+        assert_that_types_inferring_failed(
+            "select col1 from table_1 where col1 like '1%';",
+            "operator does not exist: integer LIKE character[2]",
+            &table
+        );
+    }
 }
@@ -65,3 +65,39 @@ fn validate_insertion_when_column_type_does_not_match() {
         &table
     );
 }
+
+#[test]
+fn validate_window_function_in_where_clause() {
+    let mut table = HashMap::new();
+    table.insert("table_1".into(), vec![ColumnMetadata::new("col1", Type::Integer, None)]);
+
+    assert_that_query_verified_with_error_message(
+        "select col1 from table_1 where row_number() over (order by col1) = 1;",
+        "window functions are not allowed in WHERE",
+        &table
+    );
+}
+
+#[test]
+fn validate_nested_window_function_calls() {
+    let mut table = HashMap::new();
+    table.insert("table_1".into(), vec![ColumnMetadata::new("col1", Type::Integer, None)]);
+
+    assert_that_query_verified_with_error_message(
+        "select sum(rank() over (order by col1)) over () from table_1;",
+        "window function calls cannot be nested",
+        &table
+    );
+}
+
+#[test]
+fn validate_row_locking_of_distinct_rows() {
+    let mut table = HashMap::new();
+    table.insert("table_1".into(), vec![ColumnMetadata::new("col1", Type::Integer, None)]);
+
+    assert_that_query_verified_with_error_message(
+        "select distinct col1 from table_1 for update;",
+        "FOR UPDATE is not allowed with DISTINCT clause",
+        &table
+    );
+}
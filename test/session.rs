@@ -0,0 +1,287 @@
+use std::thread;
+use std::time::Duration;
+
+use expectest::prelude::{be_true, be_false, be_ok, be_err, be_some};
+
+use sql::catalog_manager::CatalogManager;
+use sql::data::Datum;
+use sql::data_manager::DataManager;
+use sql::query_executer::ExecutionResult;
+use sql::session::{Session, ScriptError};
+
+use super::assert_that_session_returns_data;
+
+#[test]
+fn temporary_table_is_visible_only_to_its_session() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    let etl = Session::new(&catalog_manager, &data_manager);
+    let other = Session::new(&catalog_manager, &data_manager);
+
+    expect!(etl.execute("create temporary table scratch (id integer);")).to(be_ok());
+    expect!(etl.execute("insert into scratch values (1);")).to(be_ok());
+
+    assert_that_session_returns_data(&etl, "select id from scratch;", "[[\"1\"]]");
+    expect!(other.catalog_manager().contains_table("scratch")).to(be_false());
+    expect!(other.execute("insert into scratch values (2);")).to(be_err());
+    expect!(other.execute("create temporary table scratch (id integer, note character(5));")).to(be_ok());
+}
+
+#[test]
+fn temporary_table_hides_permanent_table() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    let etl = Session::new(&catalog_manager, &data_manager);
+    let other = Session::new(&catalog_manager, &data_manager);
+
+    expect!(etl.execute("create table items (id serial, qty integer);")).to(be_ok());
+    expect!(etl.execute("insert into items (qty) values (5);")).to(be_ok());
+    expect!(etl.execute("create temporary table items (id serial, qty integer);")).to(be_ok());
+    expect!(etl.execute("insert into items (qty) values (7), (8);")).to(be_ok());
+
+    assert_that_session_returns_data(&etl, "select id, qty from items;", "[[\"1\", \"7\"], [\"2\", \"8\"]]");
+    assert_that_session_returns_data(&other, "select id, qty from items;", "[[\"1\", \"5\"]]");
+}
+
+#[test]
+fn temporary_tables_are_dropped_with_session() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    let etl = Session::new(&catalog_manager, &data_manager);
+
+    expect!(etl.execute("create temporary table scratch (id integer);")).to(be_ok());
+    expect!(etl.catalog_manager().contains_table("scratch")).to(be_true());
+    drop(etl);
+
+    let next = Session::new(&catalog_manager, &data_manager);
+    expect!(next.catalog_manager().contains_table("scratch")).to(be_false());
+    expect!(next.execute("create temporary table scratch (id integer);")).to(be_ok());
+}
+
+#[test]
+fn script_stops_on_first_failed_statement() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    let migration = Session::new(&catalog_manager, &data_manager);
+
+    expect!(migration.execute_script("create table items (id integer primary key); insert into items values (1); insert into items values (1); insert into items values (2);"))
+        .to(be_err().value(ScriptError { statement: 3, message: "duplicate key value violates unique constraint \"items_pkey\"".into() }));
+
+    assert_that_session_returns_data(&migration, "select id from items;", "[[\"1\"]]");
+}
+
+#[test]
+fn script_returns_result_of_every_statement() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    let migration = Session::new(&catalog_manager, &data_manager);
+
+    expect!(migration.execute_script("create table items (id integer);\n-- seed data\ninsert into items values (1), (2);\nselect id from items;"))
+        .to(be_ok().value(vec![
+            ExecutionResult::Message("'items' was created".into()),
+            ExecutionResult::Message("2 rows were inserted".into()),
+            ExecutionResult::Data(vec![vec![Datum::Integer(1)], vec![Datum::Integer(2)]])
+        ]));
+}
+
+#[test]
+fn query_memory_limit_is_changed_for_one_session() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default().with_query_memory_limit(1024 * 1024);
+    let report = Session::new(&catalog_manager, &data_manager);
+    let other = Session::new(&catalog_manager, &data_manager);
+    expect!(report.execute_script("create table items (id integer); insert into items values (3), (1), (2), (3);")).to(be_ok());
+
+    report.data_manager().set_query_memory_limit(Some(16));
+
+    expect!(other.data_manager().query_memory_limit()).to(be_some().value(1024 * 1024));
+    assert_that_session_returns_data(&report, "select distinct id from items order by id desc;", "[[\"3\"], [\"2\"], [\"1\"]]");
+    report.data_manager().set_query_memory_limit(None);
+    assert_that_session_returns_data(&report, "select id from items order by id;", "[[\"1\"], [\"2\"], [\"3\"], [\"3\"]]");
+}
+
+#[test]
+fn uncommitted_table_is_not_visible_to_other_sessions() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    let first = Session::new(&catalog_manager, &data_manager);
+    let second = Session::new(&catalog_manager, &data_manager);
+
+    expect!(first.execute_script("begin; create table t (id integer); insert into t values (1);")).to(be_ok());
+
+    expect!(second.execute("insert into t values (2);")).to(be_err().value("[ERR 100] table 'table_name' does not exist".to_owned()));
+    assert_that_session_returns_data(&first, "select id from t;", "[[\"1\"]]");
+
+    expect!(first.execute("rollback;")).to(be_ok());
+    expect!(first.execute("select id from t;")).to(be_err());
+    expect!(second.execute("create table t (name character(10));")).to(be_ok());
+}
+
+#[test]
+fn committed_table_is_visible_to_other_sessions() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    let first = Session::new(&catalog_manager, &data_manager);
+    let second = Session::new(&catalog_manager, &data_manager);
+
+    expect!(first.execute_script("begin; create table t (id integer); insert into t values (1);")).to(be_ok());
+    expect!(second.execute("select id from t;")).to(be_err());
+
+    expect!(first.execute("commit;")).to(be_ok());
+    assert_that_session_returns_data(&second, "select id from t;", "[[\"1\"]]");
+}
+
+#[test]
+fn concurrent_create_of_the_same_table_is_aborted() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    let first = Session::new(&catalog_manager, &data_manager);
+    let second = Session::new(&catalog_manager, &data_manager);
+
+    expect!(first.execute_script("begin; create table t (a integer, b integer);")).to(be_ok());
+    expect!(second.execute("begin;")).to(be_ok());
+
+    expect!(second.execute("create table t (b character(10), c integer);"))
+        .to(be_err().value("could not serialize access due to concurrent update".to_owned()));
+    expect!(second.execute("commit;")).to(be_ok().value(ExecutionResult::Message("transaction was rolled back".into())));
+
+    expect!(first.execute_script("insert into t values (1, 2); commit;")).to(be_ok());
+    assert_that_session_returns_data(&second, "select a, b from t;", "[[\"1\", \"2\"]]");
+    expect!(second.execute("create table t (c integer);")).to(be_err());
+}
+
+fn accounts(catalog_manager: &CatalogManager, data_manager: &DataManager) {
+    let setup = Session::new(catalog_manager, data_manager);
+    expect!(setup.execute_script("create table accounts (id integer primary key, balance integer); insert into accounts values (1, 100), (2, 50);"))
+        .to(be_ok());
+}
+
+#[test]
+fn uncommitted_rows_are_not_visible_to_other_sessions() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    accounts(&catalog_manager, &data_manager);
+    let writer = Session::new(&catalog_manager, &data_manager);
+    let reader = Session::new(&catalog_manager, &data_manager);
+
+    expect!(writer.execute_script("begin; insert into accounts values (3, 10); update accounts set balance = 0 where id = 1;")).to(be_ok());
+
+    assert_that_session_returns_data(&writer, "select id, balance from accounts;", "[[\"1\", \"0\"], [\"2\", \"50\"], [\"3\", \"10\"]]");
+    assert_that_session_returns_data(&reader, "select id, balance from accounts;", "[[\"1\", \"100\"], [\"2\", \"50\"]]");
+    expect!(writer.execute("commit;")).to(be_ok());
+    assert_that_session_returns_data(&reader, "select id, balance from accounts;", "[[\"1\", \"0\"], [\"2\", \"50\"], [\"3\", \"10\"]]");
+}
+
+#[test]
+fn read_committed_sees_rows_committed_before_each_statement() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    accounts(&catalog_manager, &data_manager);
+    let writer = Session::new(&catalog_manager, &data_manager);
+    let reader = Session::new(&catalog_manager, &data_manager);
+
+    expect!(reader.execute("begin isolation level read committed;")).to(be_ok());
+    assert_that_session_returns_data(&reader, "select balance from accounts where id = 2;", "[[\"50\"]]");
+    expect!(writer.execute("update accounts set balance = 70 where id = 2;")).to(be_ok());
+    assert_that_session_returns_data(&reader, "select balance from accounts where id = 2;", "[[\"70\"]]");
+}
+
+#[test]
+fn repeatable_read_keeps_snapshot_of_its_first_statement() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    accounts(&catalog_manager, &data_manager);
+    let writer = Session::new(&catalog_manager, &data_manager);
+    let reader = Session::new(&catalog_manager, &data_manager);
+
+    expect!(reader.execute("begin isolation level repeatable read;")).to(be_ok());
+    expect!(writer.execute("delete from accounts where id = 1;")).to(be_ok());
+    assert_that_session_returns_data(&reader, "select id from accounts;", "[[\"2\"]]");
+    expect!(writer.execute("insert into accounts values (3, 10);")).to(be_ok());
+    assert_that_session_returns_data(&reader, "select id from accounts;", "[[\"2\"]]");
+    expect!(reader.execute("commit;")).to(be_ok());
+    assert_that_session_returns_data(&reader, "select id from accounts;", "[[\"2\"], [\"3\"]]");
+}
+
+#[test]
+fn snapshot_isolation_reads_rows_committed_before_transaction_began() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    accounts(&catalog_manager, &data_manager);
+    let writer = Session::new(&catalog_manager, &data_manager);
+    let reader = Session::new(&catalog_manager, &data_manager);
+
+    expect!(reader.execute("begin isolation level snapshot;")).to(be_ok());
+    expect!(writer.execute("update accounts set balance = 0;")).to(be_ok());
+    assert_that_session_returns_data(&reader, "select balance from accounts;", "[[\"100\"], [\"50\"]]");
+}
+
+#[test]
+fn concurrent_writer_of_the_same_row_is_aborted() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    accounts(&catalog_manager, &data_manager);
+    let first = Session::new(&catalog_manager, &data_manager);
+    let second = Session::new(&catalog_manager, &data_manager);
+
+    expect!(first.execute_script("begin isolation level repeatable read; update accounts set balance = 90 where id = 1;")).to(be_ok());
+    expect!(second.execute_script("begin isolation level repeatable read; update accounts set balance = 40 where id = 2;")).to(be_ok());
+
+    thread::scope(|scope| {
+        let deleted = scope.spawn(|| second.execute("delete from accounts where id = 1;"));
+        thread::sleep(Duration::from_millis(50));
+        expect!(first.execute("commit;")).to(be_ok());
+        expect!(deleted.join().unwrap())
+            .to(be_err().value("could not serialize access due to concurrent update".to_owned()));
+    });
+    expect!(second.execute("select id from accounts;"))
+        .to(be_err().value("current transaction is aborted, commands ignored until end of transaction block".to_owned()));
+    expect!(second.execute("commit;")).to(be_ok().value(ExecutionResult::Message("transaction was rolled back".into())));
+
+    assert_that_session_returns_data(&second, "select id, balance from accounts;", "[[\"1\", \"90\"], [\"2\", \"50\"]]");
+}
+
+#[test]
+fn concurrent_insert_of_the_same_key_waits_for_the_first_writer() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    accounts(&catalog_manager, &data_manager);
+    let first = Session::new(&catalog_manager, &data_manager);
+    let second = Session::new(&catalog_manager, &data_manager);
+
+    expect!(first.execute_script("begin; insert into accounts values (3, 1);")).to(be_ok());
+    thread::scope(|scope| {
+        let inserted = scope.spawn(|| second.execute("insert into accounts values (3, 2);"));
+        thread::sleep(Duration::from_millis(50));
+        expect!(first.execute("commit;")).to(be_ok());
+        expect!(inserted.join().unwrap())
+            .to(be_err().value("could not serialize access due to concurrent update".to_owned()));
+    });
+
+    expect!(first.execute_script("begin; insert into accounts values (4, 1);")).to(be_ok());
+    thread::scope(|scope| {
+        let inserted = scope.spawn(|| second.execute("insert into accounts values (4, 2);"));
+        thread::sleep(Duration::from_millis(50));
+        expect!(first.execute("rollback;")).to(be_ok());
+        expect!(inserted.join().unwrap()).to(be_ok());
+    });
+
+    assert_that_session_returns_data(&second, "select id, balance from accounts order by id;", "[[\"1\", \"100\"], [\"2\", \"50\"], [\"3\", \"1\"], [\"4\", \"2\"]]");
+}
+
+#[test]
+fn insert_of_a_key_committed_after_snapshot_is_aborted() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    accounts(&catalog_manager, &data_manager);
+    let first = Session::new(&catalog_manager, &data_manager);
+    let second = Session::new(&catalog_manager, &data_manager);
+
+    expect!(first.execute_script("begin isolation level repeatable read; select id from accounts;")).to(be_ok());
+    expect!(second.execute("insert into accounts values (3, 2);")).to(be_ok());
+
+    expect!(first.execute("insert into accounts values (3, 1);"))
+        .to(be_err().value("could not serialize access due to concurrent update".to_owned()));
+    expect!(first.execute("commit;")).to(be_ok().value(ExecutionResult::Message("transaction was rolled back".into())));
+    assert_that_session_returns_data(&first, "select id, balance from accounts where id = 3;", "[[\"3\", \"2\"]]");
+}
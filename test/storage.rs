@@ -0,0 +1,149 @@
+use std::fs::OpenOptions;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use expectest::prelude::{be_equal_to, be_some, be_none, be_true, be_ok, be_err};
+
+use sql::database;
+use sql::page::{Page, MAX_TUPLE_SIZE, PAGE_SIZE};
+use sql::session::Session;
+use sql::storage::Storage;
+
+use super::{assert_that_session_returns_data, database_file, remove_database};
+
+fn reopened(path: &PathBuf) -> Session {
+    let (catalog_manager, data_manager) = database::open(path, 16).unwrap();
+    Session::new(&catalog_manager, &data_manager)
+}
+
+#[test]
+fn page_reuses_space_of_deleted_tuples() {
+    let mut page = Page::new(1);
+    let first = page.insert(&[1; 3000]).unwrap();
+    let second = page.insert(&[2; 3000]).unwrap();
+
+    expect!(page.insert(&[3; 3000])).to(be_none());
+    expect!(page.delete(first)).to(be_true());
+
+    let third = page.insert(&[3; 3000]);
+    expect!(third).to(be_some().value(first));
+    expect!(page.tuple(second)).to(be_some().value(&[2; 3000][..]));
+    expect!(page.tuples().len()).to(be_equal_to(2));
+}
+
+#[test]
+fn page_compacts_tuples_to_fit_a_bigger_one() {
+    let mut page = Page::new(1);
+    let slots = (0..4).map(|i| page.insert(&[i; 2000]).unwrap()).collect::<Vec<u16>>();
+    page.delete(slots[0]);
+    page.delete(slots[2]);
+
+    expect!(page.insert(&[9; 3900])).to(be_some());
+    expect!(page.tuple(slots[1])).to(be_some().value(&[1; 2000][..]));
+    expect!(page.tuple(slots[3])).to(be_some().value(&[3; 2000][..]));
+}
+
+#[test]
+fn page_with_tuples_bigger_than_it_is_corrupted() {
+    let mut page = Page::new(1);
+    page.insert(&[1; 100]).unwrap();
+    page.insert(&[2; 100]).unwrap();
+    let mut bytes = page.bytes().to_vec();
+    for slot in 0..2 {
+        bytes[8 + slot * 4..8 + slot * 4 + 4].copy_from_slice(&[0xb8, 0x0b, 0x88, 0x13]);
+    }
+
+    expect!(Page::from_bytes(bytes.clone().into_boxed_slice())).to(be_err());
+    bytes[8..12].copy_from_slice(&[0x00, 0x1f, 0xff, 0xff]);
+    expect!(Page::from_bytes(bytes.into_boxed_slice())).to(be_err());
+}
+
+#[test]
+fn corrupted_page_is_read_as_an_error() {
+    let path = database_file("corrupted");
+    let storage = Storage::open(&path, 2).unwrap();
+    let ids = (0..40u8).map(|i| storage.insert("t", &[i; 1000]).unwrap()).collect::<Vec<_>>();
+    let mut file = OpenOptions::new().write(true).open(&path).unwrap();
+    file.seek(SeekFrom::Start(ids[0].page as u64 * PAGE_SIZE as u64 + 8 + ids[0].slot as u64 * 4 + 2)).unwrap();
+    file.write_all(&[0xff, 0xff]).unwrap();
+    drop(file);
+
+    expect!(storage.read(ids[0])).to(be_err());
+    drop(storage);
+    remove_database(&path);
+}
+
+#[test]
+fn buffer_pool_evicts_pages_it_has_no_frames_for() {
+    let path = database_file("eviction");
+    let storage = Storage::open(&path, 2).unwrap();
+    let ids = (0..40u8).map(|i| storage.insert("t", &[i; 1000]).unwrap()).collect::<Vec<_>>();
+
+    let (pages, cached) = storage.pages();
+    expect!(pages > 5).to(be_true());
+    expect!(cached).to(be_equal_to(2));
+    for (i, id) in ids.into_iter().enumerate() {
+        expect!(storage.read(id)).to(be_ok().value(vec![i as u8; 1000]));
+    }
+    drop(storage);
+
+    let storage = Storage::open(&path, 2).unwrap();
+    expect!(storage.tuples("t").map(|tuples| tuples.len())).to(be_ok().value(40));
+    remove_database(&path);
+}
+
+#[test]
+fn reopened_database_keeps_committed_rows() {
+    let path = database_file("rows");
+    {
+        let session = reopened(&path);
+        session.execute("create table t (id integer primary key, name character(5));").unwrap();
+        session.execute("insert into t values (1, 'one  '), (2, 'two  '), (3, 'it''s ');").unwrap();
+        session.execute("update t set name = 'TWO  ' where id = 2;").unwrap();
+        session.execute("delete from t where id = 1;").unwrap();
+        session.execute("begin;").unwrap();
+        session.execute("insert into t values (4, 'four ');").unwrap();
+        session.execute("rollback;").unwrap();
+    }
+
+    let session = reopened(&path);
+    assert_that_session_returns_data(&session, "select id, name from t order by id;", "[[\"2\", \"TWO  \"], [\"3\", \"it's \"]]");
+    expect!(session.execute("insert into t values (2, 'again');")).to(be_err());
+    remove_database(&path);
+}
+
+#[test]
+fn reopened_database_keeps_views_and_sequences() {
+    let path = database_file("catalog");
+    {
+        let session = reopened(&path);
+        session.execute("create table t (id integer generated always as identity, v integer);").unwrap();
+        session.execute("insert into t (v) values (10), (20);").unwrap();
+        session.execute("create sequence s;").unwrap();
+        session.execute("select nextval('s') from t where v = 10;").unwrap();
+        session.execute("create materialized view big as select v from t where v > 15;").unwrap();
+        session.execute("create view small as select v from t where v < 15;").unwrap();
+        session.execute("create temporary table scratch (id integer);").unwrap();
+    }
+
+    let session = reopened(&path);
+    session.execute("insert into t (v) values (30);").unwrap();
+    assert_that_session_returns_data(&session, "select id, v from t;", "[[\"1\", \"10\"], [\"2\", \"20\"], [\"3\", \"30\"]]");
+    assert_that_session_returns_data(&session, "select nextval('s') from t where v = 10;", "[[\"2\"]]");
+    assert_that_session_returns_data(&session, "select v from big;", "[[\"20\"]]");
+    assert_that_session_returns_data(&session, "select v from small;", "[[\"10\"]]");
+    expect!(session.catalog_manager().contains_table("scratch")).to(be_equal_to(false));
+    remove_database(&path);
+}
+
+#[test]
+fn row_bigger_than_a_page_is_not_stored() {
+    let path = database_file("big-row");
+    let session = reopened(&path);
+    let values = (0..=MAX_TUPLE_SIZE / 255).map(|_| format!("'{}'", "x".repeat(255))).collect::<Vec<String>>().join(", ");
+    session.execute(format!("create table wide ({});", (0..=MAX_TUPLE_SIZE / 255).map(|i| format!("c{} character(255)", i)).collect::<Vec<String>>().join(", ")).as_str()).unwrap();
+
+    expect!(session.execute(format!("insert into wide values ({});", values).as_str())).to(be_err());
+    assert_that_session_returns_data(&session, "select c0 from wide;", "[]");
+    remove_database(&path);
+}
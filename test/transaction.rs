@@ -0,0 +1,223 @@
+use std::thread;
+use std::time::Duration;
+
+use expectest::prelude::{be_ok, be_err, be_true, be_equal_to};
+
+use sql::catalog_manager::CatalogManager;
+use sql::data_manager::DataManager;
+use sql::session::Session;
+use sql::lock_manager::DEADLOCK;
+use sql::transaction::is_serialization_failure;
+
+use super::assert_that_session_returns_data;
+
+fn doctors(catalog_manager: &CatalogManager, data_manager: &DataManager) {
+    let setup = Session::new(catalog_manager, data_manager);
+    expect!(setup.execute_script("create table doctors (id integer primary key, on_call integer); insert into doctors values (1, 1), (2, 1);"))
+        .to(be_ok());
+}
+
+#[test]
+fn write_skew_fails_serializable_transaction_committed_last() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    doctors(&catalog_manager, &data_manager);
+    let alice = Session::new(&catalog_manager, &data_manager);
+    let bob = Session::new(&catalog_manager, &data_manager);
+
+    expect!(alice.execute("begin isolation level serializable;")).to(be_ok());
+    expect!(bob.execute("begin isolation level serializable;")).to(be_ok());
+    assert_that_session_returns_data(&alice, "select id from doctors where on_call = 1;", "[[\"1\"], [\"2\"]]");
+    assert_that_session_returns_data(&bob, "select id from doctors where on_call = 1;", "[[\"1\"], [\"2\"]]");
+    expect!(alice.execute("update doctors set on_call = 0 where id = 1;")).to(be_ok());
+    expect!(bob.execute("update doctors set on_call = 0 where id = 2;")).to(be_ok());
+    expect!(alice.execute("commit;")).to(be_ok());
+
+    let failure = bob.execute("commit;").unwrap_err();
+    expect!(failure.as_str()).to(be_equal_to("could not serialize access due to read/write dependencies among transactions"));
+    expect!(is_serialization_failure(failure.as_str())).to(be_true());
+    assert_that_session_returns_data(&bob, "select id from doctors where on_call = 1;", "[[\"2\"]]");
+}
+
+#[test]
+fn write_skew_is_allowed_by_snapshot_isolation() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    doctors(&catalog_manager, &data_manager);
+    let alice = Session::new(&catalog_manager, &data_manager);
+    let bob = Session::new(&catalog_manager, &data_manager);
+
+    expect!(alice.execute_script("begin isolation level snapshot; select id from doctors where on_call = 1;")).to(be_ok());
+    expect!(bob.execute_script("begin isolation level snapshot; select id from doctors where on_call = 1;")).to(be_ok());
+    expect!(alice.execute_script("update doctors set on_call = 0 where id = 1; commit;")).to(be_ok());
+    expect!(bob.execute_script("update doctors set on_call = 0 where id = 2; commit;")).to(be_ok());
+
+    assert_that_session_returns_data(&bob, "select id from doctors where on_call = 1;", "[]");
+}
+
+#[test]
+fn lost_update_fails_the_second_writer_until_it_retries() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    let setup = Session::new(&catalog_manager, &data_manager);
+    expect!(setup.execute_script("create table accounts (id integer primary key, balance integer); insert into accounts values (1, 100);")).to(be_ok());
+    let deposit = Session::new(&catalog_manager, &data_manager);
+    let withdrawal = Session::new(&catalog_manager, &data_manager);
+
+    expect!(deposit.execute("begin isolation level serializable;")).to(be_ok());
+    expect!(withdrawal.execute("begin isolation level serializable;")).to(be_ok());
+    assert_that_session_returns_data(&deposit, "select balance from accounts where id = 1;", "[[\"100\"]]");
+    assert_that_session_returns_data(&withdrawal, "select balance from accounts where id = 1;", "[[\"100\"]]");
+    expect!(deposit.execute_script("update accounts set balance = 150 where id = 1; commit;")).to(be_ok());
+
+    let failure = withdrawal.execute("update accounts set balance = 70 where id = 1;").unwrap_err();
+    expect!(failure.as_str()).to(be_equal_to("could not serialize access due to concurrent update"));
+    expect!(is_serialization_failure(failure.as_str())).to(be_true());
+    expect!(withdrawal.execute("rollback;")).to(be_ok());
+
+    expect!(withdrawal.execute("begin isolation level serializable;")).to(be_ok());
+    assert_that_session_returns_data(&withdrawal, "select balance from accounts where id = 1;", "[[\"150\"]]");
+    expect!(withdrawal.execute_script("update accounts set balance = 120 where id = 1; commit;")).to(be_ok());
+    assert_that_session_returns_data(&deposit, "select balance from accounts;", "[[\"120\"]]");
+}
+
+#[test]
+fn phantom_inserts_fail_serializable_transaction_committed_last() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    let setup = Session::new(&catalog_manager, &data_manager);
+    expect!(setup.execute("create table bookings (room integer, guest integer);")).to(be_ok());
+    let first = Session::new(&catalog_manager, &data_manager);
+    let second = Session::new(&catalog_manager, &data_manager);
+
+    expect!(first.execute("begin isolation level serializable;")).to(be_ok());
+    expect!(second.execute("begin isolation level serializable;")).to(be_ok());
+    assert_that_session_returns_data(&first, "select guest from bookings where room = 7;", "[]");
+    assert_that_session_returns_data(&second, "select guest from bookings where room = 7;", "[]");
+    expect!(first.execute_script("insert into bookings values (7, 1); commit;")).to(be_ok());
+    expect!(second.execute("insert into bookings values (7, 2);")).to(be_ok());
+
+    expect!(second.execute("commit;")).to(be_err());
+    assert_that_session_returns_data(&second, "select guest from bookings;", "[[\"1\"]]");
+}
+
+#[test]
+fn read_only_serializable_transaction_commits() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    doctors(&catalog_manager, &data_manager);
+    let report = Session::new(&catalog_manager, &data_manager);
+    let alice = Session::new(&catalog_manager, &data_manager);
+
+    expect!(report.execute("begin isolation level serializable;")).to(be_ok());
+    assert_that_session_returns_data(&report, "select id from doctors where on_call = 1;", "[[\"1\"], [\"2\"]]");
+    expect!(alice.execute("update doctors set on_call = 0 where id = 1;")).to(be_ok());
+    assert_that_session_returns_data(&report, "select id from doctors where on_call = 1;", "[[\"1\"], [\"2\"]]");
+    expect!(report.execute("commit;")).to(be_ok());
+}
+
+fn jobs(catalog_manager: &CatalogManager, data_manager: &DataManager) {
+    let setup = Session::new(catalog_manager, data_manager);
+    expect!(setup.execute_script("create table jobs (id integer primary key, done integer); insert into jobs values (1, 0), (2, 0), (3, 0);"))
+        .to(be_ok());
+}
+
+#[test]
+fn skip_locked_hands_out_every_job_to_one_worker() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    jobs(&catalog_manager, &data_manager);
+    let first = Session::new(&catalog_manager, &data_manager);
+    let second = Session::new(&catalog_manager, &data_manager);
+    let next_job = "select id from jobs where done = 0 order by id limit 1 for update skip locked;";
+
+    expect!(first.execute("begin;")).to(be_ok());
+    expect!(second.execute("begin;")).to(be_ok());
+    assert_that_session_returns_data(&first, next_job, "[[\"1\"]]");
+    assert_that_session_returns_data(&second, next_job, "[[\"2\"]]");
+    expect!(first.execute_script("update jobs set done = 1 where id = 1; commit;")).to(be_ok());
+    expect!(second.execute_script("update jobs set done = 1 where id = 2; commit;")).to(be_ok());
+
+    assert_that_session_returns_data(&first, "select id from jobs where done = 0;", "[[\"3\"]]");
+}
+
+#[test]
+fn nowait_fails_on_locked_row() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    jobs(&catalog_manager, &data_manager);
+    let first = Session::new(&catalog_manager, &data_manager);
+    let second = Session::new(&catalog_manager, &data_manager);
+
+    expect!(first.execute_script("begin; select id from jobs where id = 1 for update;")).to(be_ok());
+    expect!(second.execute("begin;")).to(be_ok());
+    expect!(second.execute("select id from jobs where id = 1 for update nowait;"))
+        .to(be_err().value("could not obtain lock on row in relation \"jobs\"".to_owned()));
+    assert_that_session_returns_data(&second, "select id from jobs where id = 2 for update nowait;", "[[\"2\"]]");
+}
+
+#[test]
+fn shared_locks_block_writers_until_lock_timeout() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default().with_lock_timeout(Duration::from_millis(20));
+    jobs(&catalog_manager, &data_manager);
+    let first = Session::new(&catalog_manager, &data_manager);
+    let second = Session::new(&catalog_manager, &data_manager);
+
+    expect!(first.execute_script("begin; select id from jobs where id = 1 for share;")).to(be_ok());
+    expect!(second.execute("begin;")).to(be_ok());
+    assert_that_session_returns_data(&second, "select id from jobs where id = 1 for share nowait;", "[[\"1\"]]");
+    expect!(second.execute("update jobs set done = 1 where id = 1;"))
+        .to(be_err().value("canceling statement due to lock timeout".to_owned()));
+    expect!(first.execute("commit;")).to(be_ok());
+    expect!(second.execute_script("update jobs set done = 1 where id = 1; commit;")).to(be_ok());
+}
+
+#[test]
+fn writer_waits_for_row_locked_for_update() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    jobs(&catalog_manager, &data_manager);
+    let first = Session::new(&catalog_manager, &data_manager);
+    let second = Session::new(&catalog_manager, &data_manager);
+
+    expect!(first.execute_script("begin; select id from jobs where id = 1 for update;")).to(be_ok());
+    thread::scope(|scope| {
+        let updated = scope.spawn(|| second.execute("update jobs set done = 1 where id = 1;"));
+        thread::sleep(Duration::from_millis(20));
+        expect!(first.execute("commit;")).to(be_ok());
+        expect!(updated.join().unwrap()).to(be_ok());
+    });
+
+    assert_that_session_returns_data(&first, "select done from jobs where id = 1;", "[[\"1\"]]");
+}
+
+#[test]
+fn deadlock_fails_one_of_the_transactions() {
+    let catalog_manager = CatalogManager::default();
+    let data_manager = DataManager::default();
+    jobs(&catalog_manager, &data_manager);
+    let first = Session::new(&catalog_manager, &data_manager);
+    let second = Session::new(&catalog_manager, &data_manager);
+
+    expect!(first.execute_script("begin; update jobs set done = 1 where id = 1;")).to(be_ok());
+    expect!(second.execute_script("begin; update jobs set done = 2 where id = 2;")).to(be_ok());
+    let update = |session: &Session, id: i64| {
+        let result = session.execute(format!("update jobs set done = 3 where id = {};", id).as_str());
+        if result.is_err() {
+            expect!(session.execute("rollback;")).to(be_ok());
+        }
+        result
+    };
+    let (first_result, second_result) = thread::scope(|scope| {
+        let first_update = scope.spawn(|| update(&first, 2));
+        let second_update = scope.spawn(|| update(&second, 1));
+        (first_update.join().unwrap(), second_update.join().unwrap())
+    });
+
+    let failures = [&first_result, &second_result].iter().filter_map(|result| result.as_ref().err().cloned()).collect::<Vec<String>>();
+    expect!(failures).to(be_equal_to(vec![DEADLOCK.to_owned()]));
+    expect!(is_serialization_failure(DEADLOCK)).to(be_true());
+    let winner = if first_result.is_ok() { &first } else { &second };
+    expect!(winner.execute("commit;")).to(be_ok());
+}
@@ -0,0 +1,261 @@
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use expectest::prelude::{be_equal_to, be_true, be_ok};
+
+use sql::catalog_manager::CatalogManager;
+use sql::data_manager::DataManager;
+use sql::database;
+use sql::page::PAGE_SIZE;
+use sql::query_executer::ExecutionResult;
+use sql::session::Session;
+use sql::wal::wal_path;
+
+use super::{assert_that_session_returns_data, database_file, remove_database};
+
+const ACCOUNTS: i64 = 10;
+const BALANCE: i64 = 100;
+/// transfers of transactions whose rollback failed are numbered from it
+const FAILED_TRANSFER: i64 = 1_000_000;
+
+fn opened(path: &PathBuf, buffer_pool_pages: usize) -> (CatalogManager, DataManager) {
+    database::open(path, buffer_pool_pages).unwrap()
+}
+
+fn integers(session: &Session, src_query: &str) -> Vec<i64> {
+    match session.execute(src_query) {
+        Ok(ExecutionResult::Data(data)) => data.into_iter().map(|row| format!("{:?}", row[0]).trim_matches('"').parse().unwrap()).collect(),
+        res => panic!("unexpected query evaluation result {:?}", res)
+    }
+}
+
+#[test]
+fn committed_rows_are_replayed_from_log_after_crash() {
+    let path = database_file("replay");
+    {
+        let (catalog_manager, data_manager) = opened(&path, 16);
+        let session = Session::new(&catalog_manager, &data_manager);
+        session.execute("create table t (id integer primary key, v integer);").unwrap();
+        session.execute("insert into t values (1, 10), (2, 20);").unwrap();
+        session.execute("update t set v = 21 where id = 2;").unwrap();
+        expect!(fs::metadata(wal_path(&path)).map(|file| file.len() > 0)).to(be_ok().value(true));
+    }
+
+    let (catalog_manager, data_manager) = opened(&path, 16);
+    let session = Session::new(&catalog_manager, &data_manager);
+    assert_that_session_returns_data(&session, "select id, v from t order by id;", "[[\"1\", \"10\"], [\"2\", \"21\"]]");
+    remove_database(&path);
+}
+
+#[test]
+fn changes_of_transaction_in_progress_are_lost_in_crash() {
+    let path = database_file("in-progress");
+    {
+        let (catalog_manager, data_manager) = opened(&path, 16);
+        let writer = Session::new(&catalog_manager, &data_manager);
+        let other = Session::new(&catalog_manager, &data_manager);
+        writer.execute("create table t (id integer, v integer);").unwrap();
+        writer.execute("insert into t values (1, 10), (2, 20);").unwrap();
+        writer.execute("begin;").unwrap();
+        writer.execute("insert into t values (3, 30);").unwrap();
+        writer.execute("update t set v = 11 where id = 1;").unwrap();
+        writer.execute("delete from t where id = 2;").unwrap();
+        other.execute("insert into t values (4, 40);").unwrap();
+        data_manager.checkpoint().unwrap();
+        other.execute("insert into t values (5, 50);").unwrap();
+    }
+
+    let (catalog_manager, data_manager) = opened(&path, 16);
+    let session = Session::new(&catalog_manager, &data_manager);
+    assert_that_session_returns_data(&session, "select id, v from t order by id;", "[[\"1\", \"10\"], [\"2\", \"20\"], [\"4\", \"40\"], [\"5\", \"50\"]]");
+    remove_database(&path);
+}
+
+#[test]
+fn torn_record_at_end_of_log_is_ignored() {
+    let path = database_file("torn");
+    {
+        let (catalog_manager, data_manager) = opened(&path, 16);
+        let session = Session::new(&catalog_manager, &data_manager);
+        session.execute("create table t (id integer);").unwrap();
+        session.execute("insert into t values (1);").unwrap();
+    }
+    let mut log = OpenOptions::new().append(true).open(wal_path(&path)).unwrap();
+    log.write_all(&[1, 4, 32, 0, 0, 7, 7]).unwrap();
+    drop(log);
+
+    let (catalog_manager, data_manager) = opened(&path, 16);
+    let session = Session::new(&catalog_manager, &data_manager);
+    session.execute("insert into t values (2);").unwrap();
+    assert_that_session_returns_data(&session, "select id from t order by id;", "[[\"1\"], [\"2\"]]");
+    remove_database(&path);
+}
+
+#[test]
+fn checkpoint_keeps_log_small() {
+    let path = database_file("checkpoint");
+    let (catalog_manager, data_manager) = opened(&path, 4);
+    let data_manager = data_manager.with_wal_size_limit(64 * 1024);
+    let session = Session::new(&catalog_manager, &data_manager);
+    session.execute("create table t (id integer);").unwrap();
+    for id in 0..200 {
+        session.execute(format!("insert into t values ({});", id).as_str()).unwrap();
+    }
+
+    expect!(fs::metadata(wal_path(&path)).unwrap().len() < 128 * 1024).to(be_true());
+    drop(session);
+    drop(data_manager);
+    let (catalog_manager, data_manager) = opened(&path, 4);
+    let session = Session::new(&catalog_manager, &data_manager);
+    expect!(integers(&session, "select id from t;").len()).to(be_equal_to(200));
+    remove_database(&path);
+}
+
+/// Moves money between accounts and records every transfer until it is
+/// killed, the number of every committed transfer is printed. With
+/// `SQL_CRASH_FAILED_ROLLBACK` set it first rolls back a transfer that
+/// can't be undone in the database file.
+#[test]
+#[ignore]
+fn crash_writer() {
+    let path = match env::var("SQL_CRASH_DATABASE") {
+        Ok(path) => path,
+        Err(_) => return
+    };
+    let mut transfer = env::var("SQL_CRASH_TRANSFER").unwrap().parse::<i64>().unwrap();
+    let (catalog_manager, data_manager) = database::open(&path, 4).unwrap();
+    let data_manager = data_manager.with_wal_size_limit(32 * 1024);
+    let session = Session::new(&catalog_manager, &data_manager);
+    if env::var("SQL_CRASH_FAILED_ROLLBACK").is_ok() {
+        session.execute("begin;").unwrap();
+        session.execute("update accounts set balance = 0 where id = 0;").unwrap();
+        session.execute(format!("insert into transfers values ({}, 0, 0);", FAILED_TRANSFER + transfer).as_str()).unwrap();
+        data_manager.resize_buffer_pool(1).unwrap();
+        let headers = corrupt_pages(&path);
+        expect!(session.execute("rollback;").is_err()).to(be_true());
+        restore_pages(&path, headers);
+        data_manager.resize_buffer_pool(4).unwrap();
+        data_manager.checkpoint().unwrap();
+        println!("rollback failed");
+    }
+    loop {
+        let from = transfer % ACCOUNTS;
+        let to = (transfer * 7 + 3) % ACCOUNTS;
+        session.execute("begin;").unwrap();
+        for &(id, amount) in &[(from, -(transfer % 9)), (to, transfer % 9)] {
+            let balance = integers(&session, format!("select balance from accounts where id = {};", id).as_str())[0];
+            session.execute(format!("update accounts set balance = {} where id = {};", balance + amount, id).as_str()).unwrap();
+        }
+        session.execute(format!("insert into transfers values ({}, {}, {});", transfer, from, to).as_str()).unwrap();
+        session.execute("commit;").unwrap();
+        println!("committed {}", transfer);
+        transfer += 1;
+    }
+}
+
+/// Breaks the slot directory of every page in the database file so that
+/// pages can't be read from it, their headers are returned.
+fn corrupt_pages(path: &str) -> Vec<[u8; 2]> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path).unwrap();
+    let pages = file.metadata().unwrap().len() / PAGE_SIZE as u64;
+    let mut headers = vec![];
+    for page in 0..pages {
+        let mut header = [0; 2];
+        file.seek(SeekFrom::Start(page * PAGE_SIZE as u64 + 4)).unwrap();
+        file.read_exact(&mut header).unwrap();
+        file.seek(SeekFrom::Start(page * PAGE_SIZE as u64 + 4)).unwrap();
+        file.write_all(&[0xff, 0xff]).unwrap();
+        headers.push(header);
+    }
+    headers
+}
+
+/// Brings back headers of pages that were not written since they were
+/// broken.
+fn restore_pages(path: &str, headers: Vec<[u8; 2]>) {
+    let mut file = OpenOptions::new().read(true).write(true).open(path).unwrap();
+    for (page, header) in headers.into_iter().enumerate() {
+        let mut current = [0; 2];
+        file.seek(SeekFrom::Start(page as u64 * PAGE_SIZE as u64 + 4)).unwrap();
+        file.read_exact(&mut current).unwrap();
+        if current == [0xff, 0xff] {
+            file.seek(SeekFrom::Start(page as u64 * PAGE_SIZE as u64 + 4)).unwrap();
+            file.write_all(&header).unwrap();
+        }
+    }
+}
+
+#[test]
+fn database_recovers_after_writer_is_killed_at_random_points() {
+    recovers_after_writer_is_killed("killed", false);
+}
+
+#[test]
+fn changes_of_failed_rollback_are_lost_after_checkpoint_and_crash() {
+    expect!(recovers_after_writer_is_killed("failed-rollback", true)).to(be_true());
+}
+
+/// Kills the writer a few times and checks that only transfers it
+/// committed are in the database, tells whether a rollback of the writer
+/// failed before it was killed.
+fn recovers_after_writer_is_killed(name: &str, failed_rollback: bool) -> bool {
+    let path = database_file(name);
+    {
+        let (catalog_manager, data_manager) = opened(&path, 16);
+        let session = Session::new(&catalog_manager, &data_manager);
+        session.execute("create table accounts (id integer primary key, balance integer);").unwrap();
+        for id in 0..ACCOUNTS {
+            session.execute(format!("insert into accounts values ({}, {});", id, BALANCE).as_str()).unwrap();
+        }
+        session.execute("create table transfers (n integer primary key, source integer, target integer);").unwrap();
+    }
+    let mut random = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().subsec_nanos() as u64 | 1;
+    let mut acknowledged = -1;
+    let mut rolled_back = false;
+    for _ in 0..5 {
+        random ^= random << 13;
+        random ^= random >> 7;
+        random ^= random << 17;
+        let transfers = {
+            let (catalog_manager, data_manager) = opened(&path, 16);
+            let session = Session::new(&catalog_manager, &data_manager);
+            integers(&session, "select n from transfers order by n;")
+        };
+        let mut writer = Command::new(env::current_exe().unwrap());
+        writer.args(["wal::crash_writer", "--exact", "--ignored", "--nocapture"])
+            .env("SQL_CRASH_DATABASE", &path)
+            .env("SQL_CRASH_TRANSFER", transfers.len().to_string())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+        if failed_rollback {
+            writer.env("SQL_CRASH_FAILED_ROLLBACK", "1");
+        }
+        let mut writer = writer.spawn().unwrap();
+        thread::sleep(Duration::from_millis(50 + random % 250));
+        writer.kill().unwrap();
+        writer.wait().unwrap();
+        let mut output = String::new();
+        writer.stdout.take().unwrap().read_to_string(&mut output).unwrap();
+        for line in output.lines().filter(|line| line.starts_with("committed ")) {
+            acknowledged = line["committed ".len()..].parse::<i64>().unwrap();
+        }
+        rolled_back |= output.contains("rollback failed");
+
+        let (catalog_manager, data_manager) = opened(&path, 16);
+        let session = Session::new(&catalog_manager, &data_manager);
+        let balances = integers(&session, "select balance from accounts;");
+        let transfers = integers(&session, "select n from transfers order by n;");
+        expect!(balances.len() as i64).to(be_equal_to(ACCOUNTS));
+        expect!(balances.iter().sum::<i64>()).to(be_equal_to(ACCOUNTS * BALANCE));
+        expect!(transfers.clone()).to(be_equal_to((0..transfers.len() as i64).collect::<Vec<i64>>()));
+        expect!(transfers.len() as i64 > acknowledged).to(be_true());
+    }
+    expect!(acknowledged >= 0).to(be_true());
+    remove_database(&path);
+    rolled_back
+}